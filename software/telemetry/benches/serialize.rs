@@ -0,0 +1,55 @@
+// Per-frame serialization cost: building a packet and turning it into wire bytes, as the
+// main loop does every iteration. Run with `cargo bench --bench serialize`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use balloon_software::checksum::Checksum;
+use balloon_software::fields::{Field, FieldMask};
+use balloon_software::frame::{self, Endianness};
+use balloon_software::packet::TelemetryPacket;
+
+fn serialize(c: &mut Criterion) {
+    c.bench_function("packet_new", |b| b.iter(|| TelemetryPacket::new(black_box(0))));
+
+    let packet = TelemetryPacket::new(0);
+    c.bench_function("to_le_bytes", |b| b.iter(|| black_box(&packet).to_le_bytes().len()));
+    c.bench_function("to_bytes", |b| b.iter(|| black_box(&packet).to_bytes(Endianness::Little)));
+
+    // What the loop does: one scratch buffer for the lifetime of the process
+    let mut wire = Vec::new();
+    c.bench_function("write_bytes_reused", |b| {
+        b.iter(|| {
+            wire.clear();
+            black_box(&packet).write_bytes(Endianness::Little, &mut wire);
+            wire.len()
+        })
+    });
+
+    let mask: FieldMask = [Field::Temperature, Field::Altitude, Field::Latitude, Field::Longitude].into_iter().collect();
+    c.bench_function("masked_encode", |b| b.iter(|| mask.encode(black_box(&packet))));
+    c.bench_function("masked_encode_reused", |b| {
+        b.iter(|| {
+            wire.clear();
+            mask.encode_into(black_box(&packet), &mut wire);
+            wire.len()
+        })
+    });
+
+    let payload = packet.to_bytes(Endianness::Little);
+    c.bench_function("frame_encode", |b| b.iter(|| frame::encode(black_box(&payload), Endianness::Little, Checksum::Crc32)));
+    c.bench_function("frame_encode_reused", |b| {
+        b.iter(|| {
+            frame::encode_into(black_box(&payload), Endianness::Little, Checksum::Crc32, &mut wire).unwrap();
+            wire.len()
+        })
+    });
+    c.bench_function("frame_encode_crc16_reused", |b| {
+        b.iter(|| {
+            frame::encode_into(black_box(&payload), Endianness::Little, Checksum::Crc16Ccitt, &mut wire).unwrap();
+            wire.len()
+        })
+    });
+}
+
+criterion_group!(benches, serialize);
+criterion_main!(benches);
@@ -0,0 +1,303 @@
+// Barometric altitude relative to a configurable sea-level reference (QNH), its fusion
+// with GPS altitude, and a Kalman filter combining it with vertical acceleration
+
+use tracing::warn;
+
+// ICAO standard atmosphere sea-level pressure
+pub const STANDARD_SEA_LEVEL_HPA: f32 = 1013.25;
+
+// Accepted range for an operator-supplied sea-level reference
+pub const SEA_LEVEL_MIN_HPA: f32 = 950.0;
+pub const SEA_LEVEL_MAX_HPA: f32 = 1050.0;
+
+// Returns the value if it is a plausible sea-level pressure
+pub fn validate_sea_level(hpa: f32) -> Result<f32, String> {
+    if (SEA_LEVEL_MIN_HPA..=SEA_LEVEL_MAX_HPA).contains(&hpa) {
+        Ok(hpa)
+    } else {
+        Err(format!("Sea-level pressure {} hPa outside {}-{} hPa", hpa, SEA_LEVEL_MIN_HPA, SEA_LEVEL_MAX_HPA))
+    }
+}
+
+// Altitude in meters from the international barometric formula
+pub fn pressure_to_altitude(pressure_hpa: f32, sea_level_hpa: f32) -> f32 {
+    44_330.0 * (1.0 - (pressure_hpa / sea_level_hpa).powf(1.0 / 5.255))
+}
+
+// Inverse of pressure_to_altitude(): the sea-level reference that puts `pressure_hpa`
+// at `altitude_m`
+pub fn sea_level_from_altitude(pressure_hpa: f32, altitude_m: f32) -> f32 {
+    pressure_hpa / (1.0 - altitude_m / 44_330.0).powf(5.255)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GpsAltitude {
+    pub altitude: f32, // m above mean sea level
+    pub hdop: f32,     // Horizontal dilution of precision of the fix
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FusionConfig {
+    // GPS samples further than base_margin + margin_per_hdop * HDOP from the barometric
+    // altitude are rejected, so poor fix geometry is given more room before the baro wins
+    pub base_margin_m: f32,
+    pub margin_per_hdop_m: f32,
+    // 1σ errors used to weight accepted samples against each other
+    pub baro_sigma_m: f32,
+    pub gps_sigma_per_hdop_m: f32,
+}
+
+impl Default for FusionConfig {
+    fn default() -> Self {
+        Self {
+            base_margin_m: 50.0,
+            margin_per_hdop_m: 25.0,
+            baro_sigma_m: 10.0,
+            gps_sigma_per_hdop_m: 5.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AltitudeSource {
+    Fused,
+    Barometric, // GPS absent or rejected
+    Gps,        // No barometer
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FusedAltitude {
+    pub altitude: f32,
+    pub source: AltitudeSource,
+}
+
+// Combines barometric and GPS altitude, gating out GPS jumps that disagree with the
+// barometer. With only one source there is nothing to check against, so it is used as is.
+#[derive(Debug, Clone, Default)]
+pub struct AltitudeFusion {
+    config: FusionConfig,
+    rejected: u64,
+}
+
+impl AltitudeFusion {
+    pub fn new(config: FusionConfig) -> Self {
+        Self { config, rejected: 0 }
+    }
+
+    pub fn fuse(&mut self, baro: Option<f32>, gps: Option<GpsAltitude>) -> Option<FusedAltitude> {
+        let baro = baro.filter(|altitude| altitude.is_finite());
+        let gps = gps.filter(|fix| fix.altitude.is_finite());
+
+        match (baro, gps) {
+            (Some(baro), Some(fix)) => {
+                let c = &self.config;
+                // An unknown HDOP is treated as worse than any the gate would accept
+                let hdop = if fix.hdop.is_finite() && fix.hdop > 0.0 { fix.hdop } else { f32::INFINITY };
+                let margin = c.base_margin_m + c.margin_per_hdop_m * hdop;
+                if (fix.altitude - baro).abs() > margin || hdop.is_infinite() {
+                    self.rejected += 1;
+                    warn!("Rejected GPS altitude {:.1} m (HDOP {:.1}): {:.1} m from barometric {:.1} m, margin {:.1} m",
+                          fix.altitude, fix.hdop, (fix.altitude - baro).abs(), baro, margin);
+                    return Some(FusedAltitude { altitude: baro, source: AltitudeSource::Barometric });
+                }
+
+                // Inverse-variance weighting
+                let baro_weight = 1.0 / (c.baro_sigma_m * c.baro_sigma_m);
+                let gps_sigma = c.gps_sigma_per_hdop_m * hdop;
+                let gps_weight = 1.0 / (gps_sigma * gps_sigma);
+                let altitude = (baro * baro_weight + fix.altitude * gps_weight) / (baro_weight + gps_weight);
+                Some(FusedAltitude { altitude, source: AltitudeSource::Fused })
+            }
+            (Some(baro), None) => Some(FusedAltitude { altitude: baro, source: AltitudeSource::Barometric }),
+            (None, Some(fix)) => Some(FusedAltitude { altitude: fix.altitude, source: AltitudeSource::Gps }),
+            (None, None) => None,
+        }
+    }
+
+    // GPS samples rejected by the plausibility gate so far
+    pub fn rejected(&self) -> u64 {
+        self.rejected
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KalmanConfig {
+    pub accel_sigma: f32,  // Process noise: unmodelled vertical acceleration, m/s² (1σ)
+    pub baro_sigma_m: f32, // Measurement noise of the barometric altitude, m (1σ)
+}
+
+impl Default for KalmanConfig {
+    fn default() -> Self {
+        Self { accel_sigma: 0.5, baro_sigma_m: 2.0 }
+    }
+}
+
+// 2-state (altitude, vertical velocity) filter: predict() integrates the measured
+// vertical acceleration, update() corrects with a barometric altitude. Nothing is
+// estimated until the first update().
+#[derive(Debug, Clone, Default)]
+pub struct AltitudeKalman {
+    config: KalmanConfig,
+    state: Option<[f32; 2]>, // Altitude m, climb rate m/s
+    covariance: [[f32; 2]; 2],
+}
+
+impl AltitudeKalman {
+    // Initial climb rate uncertainty, m/s (1σ); large, so the first few updates set it
+    const INITIAL_VELOCITY_SIGMA: f32 = 10.0;
+
+    pub fn new(config: KalmanConfig) -> Self {
+        Self { config, ..Self::default() }
+    }
+
+    // `accel_up` is the vertical acceleration with gravity removed (m/s², up positive);
+    // pass 0 without an accelerometer to run as a constant-velocity model
+    pub fn predict(&mut self, accel_up: f32, dt: f32) {
+        let Some([altitude, velocity]) = self.state else { return };
+        if !dt.is_finite() || dt <= 0.0 || !accel_up.is_finite() {
+            return;
+        }
+        self.state = Some([altitude + velocity * dt + 0.5 * accel_up * dt * dt, velocity + accel_up * dt]);
+
+        // P = F P Fᵀ + G Gᵀ σa², with F = [[1, dt], [0, 1]] and G = [dt²/2, dt]
+        let [[p00, p01], [p10, p11]] = self.covariance;
+        let q = self.config.accel_sigma * self.config.accel_sigma;
+        let (g0, g1) = (0.5 * dt * dt, dt);
+        self.covariance = [
+            [p00 + dt * (p10 + p01) + dt * dt * p11 + g0 * g0 * q, p01 + dt * p11 + g0 * g1 * q],
+            [p10 + dt * p11 + g1 * g0 * q, p11 + g1 * g1 * q],
+        ];
+    }
+
+    pub fn update(&mut self, baro_altitude: f32) {
+        if !baro_altitude.is_finite() {
+            return;
+        }
+        let r = self.config.baro_sigma_m * self.config.baro_sigma_m;
+        let Some([altitude, velocity]) = self.state else {
+            self.state = Some([baro_altitude, 0.0]);
+            let v = Self::INITIAL_VELOCITY_SIGMA * Self::INITIAL_VELOCITY_SIGMA;
+            self.covariance = [[r, 0.0], [0.0, v]];
+            return;
+        };
+
+        // Only altitude is observed: H = [1, 0]
+        let [[p00, p01], [p10, p11]] = self.covariance;
+        let innovation = baro_altitude - altitude;
+        let s = p00 + r;
+        let (k0, k1) = (p00 / s, p10 / s);
+        self.state = Some([altitude + k0 * innovation, velocity + k1 * innovation]);
+        self.covariance = [
+            [(1.0 - k0) * p00, (1.0 - k0) * p01],
+            [p10 - k1 * p00, p11 - k1 * p01],
+        ];
+    }
+
+    pub fn altitude(&self) -> Option<f32> {
+        self.state.map(|[altitude, _]| altitude)
+    }
+
+    pub fn climb_rate(&self) -> Option<f32> {
+        self.state.map(|[_, velocity]| velocity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn altitude_follows_reference() {
+        assert_eq!(pressure_to_altitude(STANDARD_SEA_LEVEL_HPA, STANDARD_SEA_LEVEL_HPA), 0.0);
+
+        let standard = pressure_to_altitude(900.0, STANDARD_SEA_LEVEL_HPA);
+        assert!((standard - 988.5).abs() < 1.0, "altitude {}", standard);
+
+        // A higher QNH means the same static pressure reads higher
+        assert!(pressure_to_altitude(900.0, 1020.0) > standard);
+    }
+
+    #[test]
+    fn sea_level_range_is_validated() {
+        assert_eq!(validate_sea_level(1001.5), Ok(1001.5));
+        assert!(validate_sea_level(949.9).is_err());
+        assert!(validate_sea_level(1050.1).is_err());
+        assert!(validate_sea_level(f32::NAN).is_err());
+    }
+
+    #[test]
+    fn gps_jumps_are_gated_by_hdop() {
+        let mut fusion = AltitudeFusion::new(FusionConfig::default());
+        let gps = |altitude, hdop| Some(GpsAltitude { altitude, hdop });
+
+        // Good fix, 2 m apart: equal 10 m sigmas at HDOP 2 average the two
+        let fused = fusion.fuse(Some(1000.0), gps(1002.0, 2.0)).unwrap();
+        assert_eq!(fused.source, AltitudeSource::Fused);
+        assert!((fused.altitude - 1001.0).abs() < 1e-3);
+
+        // 150 m jump: rejected at HDOP 1 (margin 75 m), accepted at HDOP 5 (175 m)
+        assert_eq!(fusion.fuse(Some(1000.0), gps(1150.0, 1.0)),
+                   Some(FusedAltitude { altitude: 1000.0, source: AltitudeSource::Barometric }));
+        assert_eq!(fusion.fuse(Some(1000.0), gps(1150.0, 5.0)).unwrap().source, AltitudeSource::Fused);
+        assert_eq!(fusion.fuse(Some(1000.0), gps(1001.0, f32::NAN)).unwrap().source, AltitudeSource::Barometric);
+        assert_eq!(fusion.rejected(), 2);
+    }
+
+    #[test]
+    fn fusion_degrades_to_the_available_source() {
+        let mut fusion = AltitudeFusion::default();
+        assert_eq!(fusion.fuse(None, Some(GpsAltitude { altitude: 5000.0, hdop: 9.0 })),
+                   Some(FusedAltitude { altitude: 5000.0, source: AltitudeSource::Gps }));
+        assert_eq!(fusion.fuse(Some(f32::NAN), None), None);
+        assert_eq!(fusion.fuse(Some(12.0), None).unwrap().source, AltitudeSource::Barometric);
+        assert_eq!(fusion.rejected(), 0);
+    }
+
+    // Deterministic stand-in for barometer noise, roughly uniform in ±amplitude
+    fn noise(i: u32, amplitude: f32) -> f32 {
+        let hashed = i.wrapping_mul(2_654_435_761) >> 16;
+        (hashed as f32 / 65_535.0 * 2.0 - 1.0) * amplitude
+    }
+
+    #[test]
+    fn kalman_converges_on_a_steady_climb() {
+        let mut kalman = AltitudeKalman::new(KalmanConfig::default());
+        assert_eq!(kalman.altitude(), None);
+
+        // 5 m/s ascent from 100 m, sampled at 10 Hz with ±3 m barometer noise
+        let dt = 0.1;
+        for i in 0..600 {
+            let truth = 100.0 + 5.0 * i as f32 * dt;
+            kalman.predict(0.0, dt);
+            kalman.update(truth + noise(i, 3.0));
+        }
+        let truth = 100.0 + 5.0 * 599.0 * dt;
+        let climb = kalman.climb_rate().unwrap();
+        assert!((climb - 5.0).abs() < 0.5, "climb rate {}", climb);
+        let altitude = kalman.altitude().unwrap();
+        assert!((altitude - truth).abs() < 2.0, "altitude {} vs {}", altitude, truth);
+    }
+
+    #[test]
+    fn kalman_tracks_acceleration_input() {
+        // Burst: 5 m/s up, then 9 m/s² down for 2 s; the accel input should keep the
+        // estimate on the trajectory through the turn
+        let mut kalman = AltitudeKalman::new(KalmanConfig { accel_sigma: 0.2, baro_sigma_m: 5.0 });
+        let dt = 0.05;
+        let (mut altitude, mut velocity) = (30_000.0f32, 5.0f32);
+        for i in 0..400 {
+            let accel = if (200..240).contains(&i) { -9.0 } else { 0.0 };
+            altitude += velocity * dt + 0.5 * accel * dt * dt;
+            velocity += accel * dt;
+            kalman.predict(accel, dt);
+            kalman.update(altitude + noise(i, 5.0));
+        }
+        let climb = kalman.climb_rate().unwrap();
+        assert!((climb - velocity).abs() < 0.5, "climb rate {} vs {}", climb, velocity);
+
+        // Non-finite input is ignored
+        kalman.update(f32::NAN);
+        kalman.predict(f32::INFINITY, dt);
+        assert!(kalman.altitude().unwrap().is_finite());
+    }
+}
@@ -0,0 +1,573 @@
+// State carried between main loop iterations, one iteration of the loop (commands,
+// sensors, flight tracking and the downlink), and the fixed-rate loop that runs them
+// until shutdown.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use tracing::{debug, info, warn};
+
+use balloon_software::attitude::ComplementaryFilter;
+use balloon_software::blackbox;
+use balloon_software::cadence::{LoopTimer, TransmitSchedule};
+use balloon_software::clock::{Clock, ClockSource};
+use balloon_software::command::{Ack, Command, CommandListener, StatsReply};
+use balloon_software::coords::PositionGuard;
+use balloon_software::downlink::PacketSender;
+use balloon_software::fields::FieldMask;
+use balloon_software::flight::{AlarmChange, ApogeeDetector, ClimbRateEstimator, DescentAlarm, FlightPhase, FlightPhaseTracker};
+use balloon_software::fragment::{ExtendedSender, MessageType};
+use balloon_software::frame::Endianness;
+use balloon_software::heading::HeadingTracker;
+#[cfg(feature = "influx")]
+use balloon_software::influx::InfluxSink;
+#[cfg(feature = "mqtt")]
+use balloon_software::mqtt::MqttSink;
+use balloon_software::led::{LinkState, StatusLed};
+use balloon_software::on_change::ChangeGate;
+use balloon_software::packet::{StatusFlags, TelemetryPacket};
+use balloon_software::sensors::Sensors;
+use balloon_software::session::SessionHeader;
+use balloon_software::shutdown::Shutdown;
+use balloon_software::spin;
+use balloon_software::stats::FlightStats;
+use balloon_software::transport::Transport;
+use balloon_software::trend::TemperatureRate;
+
+use crate::cli::Args;
+use crate::LogControl;
+
+// Iterations between loop timing reports (~10 s at the default send interval)
+const JITTER_REPORT_ITERATIONS: u64 = 100;
+
+pub struct AppContext {
+    pub args: Args,
+    pub stats: FlightStats,
+    pub sensors: Sensors,
+    logging: LogControl,
+    transport: Box<dyn Transport>, // Already wrapped in the frame envelope
+    sender: PacketSender,
+    field_mask: FieldMask,
+    byte_order: Endianness,
+    change_gate: Option<ChangeGate>,
+    schedule: TransmitSchedule,
+    sequence: u32, // Of the next packet; advances with every transmission attempt
+    clock: Clock,  // Stamps each packet
+    commands: Option<CommandListener>,
+    last_header: Option<SessionHeader>,
+    climb: ClimbRateEstimator,
+    phases: FlightPhaseTracker,
+    descent_alarm: DescentAlarm,
+    apogee: ApogeeDetector,
+    was_freefall: bool,
+    black_box_dumped: bool,
+    temperature_rate: TemperatureRate,
+    heading: HeadingTracker,
+    attitude: ComplementaryFilter,
+    last_motion: Option<Instant>, // When `attitude` last took a sample
+    position: PositionGuard,
+    led: StatusLed,
+    extended: ExtendedSender, // Diagnostic blobs, one fragment per iteration between telemetry packets
+    #[cfg(feature = "influx")]
+    influx: Option<InfluxSink>,
+    #[cfg(feature = "mqtt")]
+    mqtt: Option<MqttSink>,
+}
+
+impl AppContext {
+    pub fn new(args: Args, transport: Box<dyn Transport>, logging: LogControl) -> Result<Self, Box<dyn std::error::Error>> {
+        let byte_order = Endianness::from(args.byte_order);
+        let field_mask = args.field_mask();
+        if field_mask != FieldMask::ALL {
+            let names: Vec<&str> = field_mask.fields().map(|field| field.name()).collect();
+            info!("Trimmed frames with fields: {}", names.join(","));
+        }
+
+        let change_gate = if args.on_change {
+            info!("Transmit-on-change enabled (heartbeat every {} ms)", args.heartbeat_ms);
+            Some(ChangeGate::new(args.change_thresholds(), args.heartbeat_interval()))
+        } else {
+            None
+        };
+
+        let commands = match &args.command_bind {
+            Some(addr) => {
+                let listener = CommandListener::bind(addr)?;
+                info!("Listening for uplink commands on {}", listener.local_addr()?);
+                Some(listener)
+            }
+            None => None,
+        };
+
+        #[cfg(feature = "influx")]
+        let influx = args.influx_url.clone().map(|url| {
+            info!("Streaming line protocol to InfluxDB at {}", url);
+            InfluxSink::spawn(url, args.influx_token.clone(), args.influx_measurement.clone())
+        });
+
+        #[cfg(feature = "mqtt")]
+        let mqtt = match args.mqtt_config() {
+            Some(config) => {
+                info!("Publishing packets to MQTT broker {} on {}", config.broker, config.topic);
+                Some(MqttSink::spawn(config)?)
+            }
+            None => None,
+        };
+
+        let mut sensors = Sensors::init(args.sensor_config());
+        let mut extended = ExtendedSender::new();
+        if let Some(dump) = sensors.motion_register_dump() {
+            if let Err(e) = extended.queue_message(MessageType::RegisterDump, &dump) {
+                warn!("Failed to queue register dump: {}", e);
+            }
+        }
+
+        if args.clock() == ClockSource::Monotonic && !args.schedule.is_empty() {
+            warn!("With --clock monotonic, --schedule hours count from startup rather than UTC midnight");
+        }
+
+        Ok(Self {
+            stats: FlightStats::new(Instant::now()),
+            logging,
+            transport,
+            sender: PacketSender::new(field_mask, byte_order),
+            field_mask,
+            byte_order,
+            change_gate,
+            schedule: TransmitSchedule::new(args.schedule.clone()),
+            sequence: 0,
+            clock: Clock::new(args.clock()),
+            commands,
+            last_header: None,
+            climb: ClimbRateEstimator::new(),
+            phases: FlightPhaseTracker::new(args.phase_thresholds()),
+            descent_alarm: DescentAlarm::new(args.descent_alarm_rate, args.phase_hold_samples),
+            apogee: ApogeeDetector::new(args.burst_drop_m, args.phase_hold_samples),
+            was_freefall: false,
+            black_box_dumped: false,
+            temperature_rate: TemperatureRate::new(args.temperature_rate_window),
+            heading: HeadingTracker::new(args.gyro_z_bias),
+            attitude: ComplementaryFilter::default(),
+            last_motion: None,
+            position: PositionGuard::new(),
+            led: StatusLed::new(args.led_pin),
+            extended,
+            #[cfg(feature = "influx")]
+            influx,
+            #[cfg(feature = "mqtt")]
+            mqtt,
+            sensors,
+            args,
+        })
+    }
+}
+
+// Runs an iteration every `send_interval` until shutdown is requested, returning how
+// many ran. A request mid-iteration lets that iteration finish, so its packet still
+// goes out; one while waiting for the next deadline ends the wait.
+pub async fn run(ctx: &mut AppContext, send_interval: Duration, shutdown: &Shutdown) -> u64 {
+    let mut timer = LoopTimer::new(send_interval, Instant::now());
+    let mut iterations = 0;
+    while !shutdown.is_requested() {
+        if let Some(jitter) = timer.start_iteration(Instant::now()) {
+            ctx.stats.loop_jitter.update(jitter.abs());
+        }
+        if let Some(report) = timer.take_report(JITTER_REPORT_ITERATIONS) {
+            info!("Loop timing over {} iterations: mean interval {:.1} ms (target {} ms), jitter mean {:.1} ms, max {:.1} ms",
+                     report.iterations, report.mean_interval_ms, send_interval.as_millis(),
+                     report.mean_jitter_ms, report.max_jitter_ms);
+        }
+
+        run_iteration(ctx).await;
+        iterations += 1;
+
+        let deadline = tokio::time::Instant::from_std(timer.next_deadline(Instant::now()));
+        tokio::select! {
+            _ = tokio::time::sleep_until(deadline) => {}
+            _ = shutdown.requested() => {}
+        }
+    }
+    iterations
+}
+
+// One pass of the main loop; returns what happened to this iteration's packet
+pub async fn run_iteration(ctx: &mut AppContext) -> LinkState {
+    while let Some((command, from)) = ctx.commands.as_ref().and_then(CommandListener::poll) {
+        info!("Received command from {}: {:?}", from, command);
+        let ack = handle_command(ctx, command);
+        if let Some(commands) = &ctx.commands {
+            commands.reply(&ack, from);
+        }
+    }
+
+    // Announce the sensor configuration at startup and whenever it changes
+    let header = ctx.sensors.session_header().with_field_mask(ctx.field_mask).with_checksum(ctx.args.checksum.into());
+    if ctx.last_header != Some(header) {
+        send_session_header(ctx.transport.as_mut(), &header, ctx.byte_order);
+        ctx.last_header = Some(header);
+    }
+
+    let readings = ctx.sensors.read().await;
+    let sensor_fault = readings.motion.is_none();
+    if let Some(motion) = &readings.motion {
+        let rate = spin::spin_rate(motion);
+        let axis = if rate.about_vertical { "about vertical" } else { "total, tumbling" };
+        debug!("Spin rate: {:+.1} RPM ({})", rate.rpm, axis);
+    }
+    let mut packet = readings.to_packet(ctx.sequence);
+    packet.timestamp_ms = ctx.clock.now_ms();
+    let now = Instant::now();
+    packet.heading = ctx.heading.update(packet.gyro_z, now);
+    // The compass is absolute; the gyro carries on from its last heading if it drops out
+    if let Some(compass) = readings.compass_heading {
+        ctx.heading.align(compass);
+        packet.heading = ctx.heading.heading();
+    }
+    if let Some(motion) = &readings.motion {
+        let dt = ctx.last_motion.map_or(0.0, |last| now.saturating_duration_since(last).as_secs_f32());
+        ctx.attitude.update(motion, dt);
+        ctx.last_motion = Some(now);
+        packet.roll = ctx.attitude.roll();
+        packet.pitch = ctx.attitude.pitch();
+    }
+    ctx.position.apply(&mut packet);
+
+    let previous_phase = ctx.phases.phase();
+    let climb_rate = update_flight_phase(&mut ctx.climb, &mut ctx.phases, &mut packet, readings.altitude);
+    match ctx.descent_alarm.update(climb_rate, ctx.phases.phase()) {
+        Some(AlarmChange::Raised) => ctx.logging.set_full_detail(true),
+        Some(AlarmChange::Cleared) => ctx.logging.set_full_detail(false),
+        None => {}
+    }
+    // Only a measured altitude: the simulated one is random from packet to packet
+    let burst_detected = match readings.altitude {
+        Some(altitude) => ctx.apogee.update(altitude),
+        None => ctx.apogee.apogee().is_some(),
+    };
+    if burst_detected {
+        packet.status |= StatusFlags::BURST_DETECTED.bits();
+        ctx.stats.apogee = ctx.apogee.apogee();
+    }
+    // Free fall shows up samples before the climb rate confirms burst; whichever
+    // comes first dumps the black box, once per flight
+    let freefall_onset = readings.freefall && !ctx.was_freefall
+        && matches!(ctx.phases.phase(), FlightPhase::Ascent | FlightPhase::Float);
+    ctx.was_freefall = readings.freefall;
+    let burst_onset = ctx.phases.phase() == FlightPhase::Burst && previous_phase != FlightPhase::Burst;
+    if (freefall_onset || burst_onset) && !ctx.black_box_dumped {
+        let trigger = if freefall_onset { "Free fall" } else { "Burst" };
+        let downlink = ctx.args.black_box_downlink.then_some(&mut ctx.extended);
+        dump_black_box(&ctx.sensors, trigger, &ctx.args.black_box_path, downlink);
+        ctx.black_box_dumped = true;
+    }
+    // Complete from here on
+    packet.finalize();
+    ctx.stats.record_packet(&packet, climb_rate);
+
+    #[cfg(feature = "influx")]
+    if let Some(influx) = &ctx.influx {
+        influx.submit(&packet);
+    }
+    #[cfg(feature = "mqtt")]
+    if let Some(mqtt) = &ctx.mqtt {
+        mqtt.submit(&packet);
+    }
+    if let Some(rate) = ctx.temperature_rate.update(&packet) {
+        ctx.stats.temperature_rate.update(rate);
+        debug!("Temperature rate of change: {:+.4} °C/s", rate);
+    }
+
+    let mut link = LinkState::Skipped;
+    // The descent alarm overrides the schedule and transmit-on-change: every frame goes out
+    let now = Instant::now();
+    let scheduled = ctx.schedule.is_due(packet.timestamp_ms / 1000, now);
+    if ctx.descent_alarm.is_active() || (scheduled && should_transmit(&mut ctx.change_gate, &packet)) {
+        ctx.schedule.record_sent(now);
+        // A failed send is a loss the ground should see as a gap
+        ctx.sequence = ctx.sequence.wrapping_add(1);
+        match ctx.sender.send(ctx.transport.as_mut(), &packet) {
+            Ok(bytes_sent) => {
+                ctx.stats.packets_sent += 1;
+                link = LinkState::Sent;
+                ctx.sensors.reset_peak();
+                info!("Sent telemetry packet ({} bytes): {:?}", bytes_sent, packet);
+            }
+            Err(e) => {
+                ctx.stats.send_errors += 1;
+                link = LinkState::SendFailed;
+                warn!("Failed to send packet: {}", e);
+            }
+        }
+    }
+
+    ctx.led.update(link, sensor_fault);
+
+    if let Some(fragment) = ctx.extended.next_fragment() {
+        if let Err(e) = ctx.transport.send(&fragment.to_bytes()) {
+            ctx.stats.send_errors += 1;
+            warn!("Failed to send extended packet fragment: {}", e);
+        }
+    }
+    link
+}
+
+fn send_session_header(transport: &mut dyn Transport, header: &SessionHeader, order: Endianness) {
+    match transport.send(&header.to_bytes(order)) {
+        Ok(_) => info!("Sent session header: {:?}", header),
+        Err(e) => warn!("Failed to send session header: {}", e),
+    }
+}
+
+fn handle_command(ctx: &mut AppContext, command: Command) -> Ack {
+    let opcode = command.opcode();
+    match command {
+        Command::SetSeaLevelPressure { hpa } => match ctx.sensors.set_sea_level_pressure(hpa) {
+            Ok(()) => info!("Sea-level reference set to {:.2} hPa", hpa),
+            Err(e) => {
+                warn!("Rejected sea-level pressure command: {}", e);
+                return Ack::rejected(opcode);
+            }
+        },
+        Command::SetTransmitInterval { interval } => {
+            match interval {
+                Some(interval) => info!("Transmit interval commanded to {:?}", interval),
+                None => info!("Commanded transmit interval cleared, following the schedule"),
+            }
+            ctx.schedule.set_commanded(interval);
+        }
+        Command::QueryStats => {
+            let reply = StatsReply {
+                packets_sent: ctx.stats.packets_sent,
+                send_errors: ctx.stats.send_errors,
+                uptime_s: ctx.stats.uptime(Instant::now()).as_secs() as u32,
+            };
+            return Ack { payload: reply.to_bytes(), ..Ack::ok(opcode) };
+        }
+        // Sent further down this iteration
+        Command::ResendHeader => ctx.last_header = None,
+    }
+    Ack::ok(opcode)
+}
+
+// Returns the climb rate used for the phase decision. Without a measured altitude there
+// is none: the phase holds and the packet reports no vertical speed.
+fn update_flight_phase(climb: &mut ClimbRateEstimator, phases: &mut FlightPhaseTracker, packet: &mut TelemetryPacket,
+                       measured_altitude: Option<f32>) -> Option<f32> {
+    let Some(altitude) = measured_altitude else {
+        packet.vertical_speed = 0.0;
+        packet.flight_phase = phases.phase() as u8;
+        return None;
+    };
+    let climb_rate = climb.update(altitude, Instant::now());
+    packet.vertical_speed = climb.smoothed();
+    packet.flight_phase = phases.update(altitude, climb_rate, packet.accel_magnitude()) as u8;
+    Some(climb_rate)
+}
+
+// Writes the full-rate window around burst to `path` and optionally queues it for downlink.
+// `trigger` names what detected the burst, for the log.
+fn dump_black_box(sensors: &Sensors, trigger: &str, path: &Path, downlink: Option<&mut ExtendedSender>) {
+    let samples = sensors.black_box_snapshot();
+    if samples.is_empty() {
+        info!("{} detected, but the black box is empty (no motion sampler running)", trigger);
+        return;
+    }
+    let now = Instant::now();
+    info!("{} detected - dumping {} black box samples", trigger, samples.len());
+
+    let written = File::create(path).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        blackbox::write_csv(&samples, now, &mut writer)?;
+        writer.flush()
+    });
+    match written {
+        Ok(()) => info!("Black box written to {}", path.display()),
+        Err(e) => warn!("Failed to write black box to {}: {}", path.display(), e),
+    }
+
+    if let Some(extended) = downlink {
+        let dump_unix_ms = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |t| t.as_millis() as u64);
+        for chunk in blackbox::encode_chunks(&samples, now, dump_unix_ms) {
+            if let Err(e) = extended.queue_message(MessageType::BlackBox, &chunk) {
+                warn!("Failed to queue black box chunk: {}", e);
+            }
+        }
+    }
+}
+
+// In on-change mode, skip packets the gate considers redundant
+fn should_transmit(gate: &mut Option<ChangeGate>, packet: &TelemetryPacket) -> bool {
+    match gate {
+        Some(gate) => {
+            let now = Instant::now();
+            if gate.should_send(packet, now) {
+                gate.record_sent(*packet, now);
+                true
+            } else {
+                false
+            }
+        }
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::{reload, EnvFilter};
+
+    use balloon_software::downlink;
+    use balloon_software::frame::{self, Framed};
+    use balloon_software::packet::PACKET_SYNC;
+
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<Vec<u8>>>>);
+
+    impl Transport for Capture {
+        fn send(&mut self, frame: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().push(frame.to_vec());
+            Ok(frame.len())
+        }
+    }
+
+    // Requests shutdown as the first telemetry packet goes out, partway through an iteration
+    struct StopOnPacket {
+        capture: Capture,
+        shutdown: Shutdown,
+    }
+
+    impl Transport for StopOnPacket {
+        fn send(&mut self, frame: &[u8]) -> io::Result<usize> {
+            if downlink::decode_packet(frame, FieldMask::ALL).is_some() {
+                self.shutdown.request();
+            }
+            self.capture.send(frame)
+        }
+    }
+
+    fn context(extra_args: &[&str]) -> (AppContext, Capture) {
+        let capture = Capture::default();
+        (context_with(extra_args, Box::new(capture.clone())), capture)
+    }
+
+    fn context_with(extra_args: &[&str], downlink: Box<dyn Transport>) -> AppContext {
+        let args = Args::parse_from(["balloon_software"].iter().chain(extra_args));
+        let transport = Framed::new(downlink, Endianness::from(args.byte_order)).with_checksum(args.checksum.into());
+        let transport = Box::new(transport);
+        // No subscriber is installed, so filter switches are logged as failures and ignored
+        let (_, handle) = reload::Layer::new(EnvFilter::new("info"));
+        let logging = LogControl { handle, normal: "info".to_string() };
+        AppContext::new(args, transport, logging).unwrap()
+    }
+
+    #[tokio::test]
+    async fn sends_header_once_then_a_packet_per_iteration() {
+        let (mut ctx, capture) = context(&[]);
+        for _ in 0..3 {
+            assert_eq!(run_iteration(&mut ctx).await, LinkState::Sent);
+        }
+        assert_eq!(ctx.stats.packets_sent, 3);
+
+        let frames = capture.0.lock().unwrap();
+        assert_eq!(frames.len(), 4);
+        let (_, header) = frame::decode(&frames[0]).unwrap();
+        assert_ne!(header[..8], PACKET_SYNC.to_le_bytes());
+        for frame in &frames[1..] {
+            let packet = downlink::decode_packet(frame, FieldMask::ALL).unwrap();
+            assert_eq!({ packet.sync }, PACKET_SYNC);
+        }
+        let sequences: Vec<u32> = frames[1..].iter().map(|f| downlink::decode_packet(f, FieldMask::ALL).unwrap().sequence).collect();
+        assert_eq!(sequences, [0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn simulated_altitude_never_detects_burst() {
+        // Every host sensor is simulated, so no iteration has a measured altitude
+        let (mut ctx, capture) = context(&[]);
+        for _ in 0..60 {
+            run_iteration(&mut ctx).await;
+        }
+        assert_eq!(ctx.apogee.apogee(), None);
+        let frames = capture.0.lock().unwrap();
+        assert!(frames.iter().filter_map(|f| downlink::decode_packet(f, FieldMask::ALL))
+            .all(|packet| packet.status & StatusFlags::BURST_DETECTED.bits() == 0));
+    }
+
+    #[tokio::test]
+    async fn simulated_altitude_holds_the_phase_without_a_climb_rate() {
+        let (mut ctx, capture) = context(&[]);
+        for _ in 0..20 {
+            run_iteration(&mut ctx).await;
+        }
+        assert_eq!(ctx.phases.phase(), FlightPhase::Pad);
+        assert_eq!(ctx.stats.climb_rate.count(), 0);
+        let frames = capture.0.lock().unwrap();
+        let packets: Vec<_> = frames.iter().filter_map(|f| downlink::decode_packet(f, FieldMask::ALL)).collect();
+        assert_eq!(packets.len(), 20);
+        assert!(packets.iter().all(|packet| { packet.vertical_speed } == 0.0 && packet.flight_phase == FlightPhase::Pad as u8));
+
+        // A measured altitude updates the climb rate again
+        let mut packet = TelemetryPacket::new(0);
+        assert_eq!(update_flight_phase(&mut ctx.climb, &mut ctx.phases, &mut packet, Some(300.0)), Some(0.0));
+    }
+
+    #[tokio::test]
+    async fn loop_stops_after_the_iteration_in_progress() {
+        let shutdown = Shutdown::new();
+        let capture = Capture::default();
+        let mut ctx = context_with(&[], Box::new(StopOnPacket { capture: capture.clone(), shutdown: shutdown.clone() }));
+
+        // Requested during the send: the iteration's bookkeeping still completes
+        let timeout = std::time::Duration::from_secs(5);
+        let iterations = tokio::time::timeout(timeout, run(&mut ctx, Duration::from_millis(10), &shutdown)).await.unwrap();
+        assert_eq!(iterations, 1);
+        assert_eq!(ctx.stats.packets_sent, 1);
+        assert_eq!(capture.0.lock().unwrap().len(), 2); // Header and packet
+
+        // Requested while waiting out a long interval: the wait ends early
+        let shutdown = Shutdown::new();
+        let (mut ctx, capture) = context(&[]);
+        tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                shutdown.request();
+            }
+        });
+        let iterations = tokio::time::timeout(timeout, run(&mut ctx, Duration::from_secs(3600), &shutdown)).await.unwrap();
+        assert_eq!(iterations, 1);
+        assert_eq!(capture.0.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn commands_are_acked_and_applied() {
+        let (mut ctx, capture) = context(&["--command-bind", "127.0.0.1:0"]);
+        run_iteration(&mut ctx).await;
+
+        let ground = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        ground.set_read_timeout(Some(std::time::Duration::from_secs(2))).unwrap();
+        let target = ctx.commands.as_ref().unwrap().local_addr().unwrap();
+        ground.send_to(&Command::QueryStats.to_bytes(), target).unwrap();
+        ground.send_to(&Command::ResendHeader.to_bytes(), target).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        run_iteration(&mut ctx).await;
+
+        let mut buf = [0u8; 64];
+        let len = ground.recv(&mut buf).unwrap();
+        let ack = Ack::parse(&buf[..len]).unwrap();
+        let reply = StatsReply::from_bytes(&ack.payload).unwrap();
+        assert_eq!((reply.packets_sent, reply.send_errors), (1, 0));
+        let len = ground.recv(&mut buf).unwrap();
+        assert_eq!(Ack::parse(&buf[..len]), Ok(Ack::ok(Command::ResendHeader.opcode())));
+
+        // Header, packet, then the resent header ahead of the second packet
+        let frames = capture.0.lock().unwrap();
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[2], frames[0]);
+    }
+}
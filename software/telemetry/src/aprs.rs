@@ -0,0 +1,199 @@
+// APRS position reports for amateur-radio tracking. Only the information field (and its
+// TNC2 text form) is produced; the TNC or radio does the AX.25 framing.
+
+use std::fmt;
+
+use crate::packet::TelemetryPacket;
+
+const FEET_PER_METER: f32 = 3.28084;
+
+// Base-91 comment telemetry: each value is two characters, 0-8280
+const TELEMETRY_MAX: u32 = 91 * 91 - 1;
+
+// Primary symbol table, 'O' = balloon
+pub const BALLOON_SYMBOL: (char, char) = ('/', 'O');
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AprsStation {
+    callsign: String,
+    ssid: u8,
+}
+
+impl AprsStation {
+    // Callsigns are 1-6 letters and digits; the SSID is 0-15 (11 is customary for balloons)
+    pub fn new(callsign: &str, ssid: u8) -> Result<Self, String> {
+        let callsign = callsign.to_ascii_uppercase();
+        if callsign.is_empty() || callsign.len() > 6 || !callsign.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(format!("invalid callsign {:?}: expected 1-6 letters and digits", callsign));
+        }
+        if ssid > 15 {
+            return Err(format!("invalid SSID {}: expected 0-15", ssid));
+        }
+        Ok(Self { callsign, ssid })
+    }
+
+    // "CALL>DEST,PATH:info", the line format TNCs and APRS-IS accept
+    pub fn tnc2(&self, destination: &str, path: &[&str], info: &str) -> String {
+        let mut line = format!("{}>{}", self, destination);
+        for hop in path {
+            line.push(',');
+            line.push_str(hop);
+        }
+        line.push(':');
+        line.push_str(info);
+        line
+    }
+}
+
+impl fmt::Display for AprsStation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.ssid == 0 {
+            write!(f, "{}", self.callsign)
+        } else {
+            write!(f, "{}-{}", self.callsign, self.ssid)
+        }
+    }
+}
+
+// Position with timestamp (HHMMSS UTC), balloon symbol, the altitude comment and a
+// base-91 telemetry extension, e.g.
+//   /123456h4903.50N/07201.75WO/A=012345|!!..|
+// `sequence` numbers the telemetry (it wraps at 8281). None without a valid position.
+//
+// Telemetry channels:
+//   1: temperature, (°C + 100) × 10
+//   2: battery voltage, V × 1000
+//   3: humidity, % × 10
+//   4: heading, ° × 10
+//   5: peak acceleration, m/s² × 100
+pub fn position_report(packet: &TelemetryPacket, sequence: u16) -> Option<String> {
+    let latitude = format_latitude(packet.latitude)?;
+    let longitude = format_longitude(packet.longitude)?;
+    let seconds_of_day = { packet.timestamp_ms } / 1000 % 86_400;
+    let (symbol_table, symbol_code) = BALLOON_SYMBOL;
+
+    let mut info = format!("/{:02}{:02}{:02}h{}{}{}{}", seconds_of_day / 3600, seconds_of_day / 60 % 60, seconds_of_day % 60,
+                           latitude, symbol_table, longitude, symbol_code);
+    if { packet.altitude }.is_finite() {
+        info.push_str(&altitude_comment(packet.altitude));
+    }
+
+    let channels = [
+        scale({ packet.temperature } + 100.0, 10.0),
+        scale(packet.battery_voltage, 1000.0),
+        scale(packet.humidity, 10.0),
+        scale(packet.heading, 10.0),
+        scale(packet.peak_accel, 100.0),
+    ];
+    info.push_str(&telemetry_extension(sequence as u32 % (TELEMETRY_MAX + 1), &channels));
+    Some(info)
+}
+
+// "/A=nnnnnn" in feet, as the APRS spec asks for altitude in comments
+pub fn altitude_comment(altitude_m: f32) -> String {
+    let feet = (altitude_m * FEET_PER_METER).round().clamp(-99_999.0, 999_999.0) as i32;
+    format!("/A={:06}", feet)
+}
+
+// ddmm.hhN: degrees and hundredths of minutes
+pub fn format_latitude(deg: f32) -> Option<String> {
+    let (degrees, hundredths) = degrees_and_hundredths(deg, 90.0)?;
+    Some(format!("{:02}{:02}.{:02}{}", degrees, hundredths / 100, hundredths % 100, if deg < 0.0 { 'S' } else { 'N' }))
+}
+
+// dddmm.hhE
+pub fn format_longitude(deg: f32) -> Option<String> {
+    let (degrees, hundredths) = degrees_and_hundredths(deg, 180.0)?;
+    Some(format!("{:03}{:02}.{:02}{}", degrees, hundredths / 100, hundredths % 100, if deg < 0.0 { 'W' } else { 'E' }))
+}
+
+// Rounded to the nearest 0.01 minute, carrying into the degrees
+fn degrees_and_hundredths(deg: f32, limit: f32) -> Option<(u32, u32)> {
+    if !deg.is_finite() || deg.abs() > limit {
+        return None;
+    }
+    let total = ((deg as f64).abs() * 6000.0).round() as u32;
+    Some((total / 6000, total % 6000))
+}
+
+fn scale(value: f32, factor: f32) -> u32 {
+    if value.is_finite() {
+        (value * factor).round().clamp(0.0, TELEMETRY_MAX as f32) as u32
+    } else {
+        0
+    }
+}
+
+// "|ss1122...|": sequence and channel values, two base-91 characters each
+fn telemetry_extension(sequence: u32, channels: &[u32]) -> String {
+    let mut out = String::from("|");
+    for value in std::iter::once(sequence).chain(channels.iter().copied()) {
+        out.push((b'!' + (value / 91) as u8) as char);
+        out.push((b'!' + (value % 91) as u8) as char);
+    }
+    out.push('|');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_coordinates_like_the_spec() {
+        // Examples from the APRS 1.01 specification
+        assert_eq!(format_latitude(49.058333).unwrap(), "4903.50N");
+        assert_eq!(format_longitude(-72.02917).unwrap(), "07201.75W");
+        assert_eq!(format_latitude(-33.8568).unwrap(), "3351.41S");
+        // 0.999999° rounds to a whole degree rather than 60 minutes
+        assert_eq!(format_longitude(0.999_999).unwrap(), "00100.00E");
+        assert!(format_latitude(91.0).is_none());
+        assert!(format_longitude(f32::NAN).is_none());
+    }
+
+    #[test]
+    fn altitude_is_in_feet() {
+        assert_eq!(altitude_comment(1000.0), "/A=003281");
+        assert_eq!(altitude_comment(30_480.0), "/A=100000");
+        assert_eq!(altitude_comment(-3.0), "/A=-00010");
+    }
+
+    #[test]
+    fn telemetry_uses_base91_pairs() {
+        assert_eq!(telemetry_extension(0, &[1, 90, 91, 8280]), "|!!!\"!{\"!{{|");
+    }
+
+    #[test]
+    fn builds_a_full_report() {
+        let mut packet = TelemetryPacket::new(0);
+        packet.timestamp_ms = 1_700_000_000_999; // 22:13:20.999 UTC
+        packet.latitude = 49.058333;
+        packet.longitude = -72.02917;
+        packet.altitude = 1000.0;
+        packet.temperature = -20.0;
+        packet.battery_voltage = 3.7;
+        packet.humidity = 50.0;
+        packet.heading = 90.0;
+        packet.peak_accel = 9.81;
+
+        let info = position_report(&packet, 8282).unwrap();
+        // Channels: 800, 3700, 500, 900, 981
+        assert_eq!(info, "/221320h4903.50N/07201.75WO/A=003281|!\")iI]&N*r+h|");
+
+        packet.altitude = f32::NAN;
+        assert!(!position_report(&packet, 0).unwrap().contains("/A="));
+        packet.latitude = f32::NAN;
+        assert!(position_report(&packet, 0).is_none());
+    }
+
+    #[test]
+    fn station_validates_and_formats_tnc2() {
+        let station = AprsStation::new("n0call", 11).unwrap();
+        assert_eq!(station.to_string(), "N0CALL-11");
+        assert_eq!(station.tnc2("APRS", &["WIDE2-1"], "/000000h"), "N0CALL-11>APRS,WIDE2-1:/000000h");
+        assert_eq!(AprsStation::new("N0CALL", 0).unwrap().to_string(), "N0CALL");
+        assert!(AprsStation::new("TOOLONGCALL", 1).is_err());
+        assert!(AprsStation::new("N0-CALL", 1).is_err());
+        assert!(AprsStation::new("N0CALL", 16).is_err());
+    }
+}
@@ -0,0 +1,142 @@
+// Roll and pitch from fusing the MPU6050's accelerometer and gyroscope. The gyro is
+// smooth but drifts; the accelerometer's view of gravity doesn't drift but picks up
+// every swing and jolt of the payload. A complementary filter follows the integrated
+// gyro over short timescales and is pulled slowly toward the accelerometer angles.
+//
+// Roll is about X (right wing down positive), pitch about Y (nose up positive), both in
+// degrees; yaw has no gravity reference, see heading.rs. The accelerometer angles
+// assume the only sustained acceleration is gravity, so they mislead during balloon
+// burst and under the parachute's first swings.
+
+use crate::i2c::MPU6050::MotionReading;
+
+// Weight of the gyro path per update; 0.98 at 10 Hz trusts the accelerometer with a
+// time constant of about 5 s
+pub const DEFAULT_ALPHA: f32 = 0.98;
+
+// Longer gaps between samples (a stalled loop) restart from the accelerometer, since
+// the rotation in between is unknown
+const MAX_STEP_SECS: f32 = 1.0;
+
+#[derive(Debug, Clone)]
+pub struct ComplementaryFilter {
+    alpha: f32,
+    angles: Option<(f32, f32)>, // Roll and pitch, None until the first usable sample
+}
+
+impl ComplementaryFilter {
+    pub fn new(alpha: f32) -> Self {
+        Self { alpha: alpha.clamp(0.0, 1.0), angles: None }
+    }
+
+    // Blends one sample taken `dt` seconds after the previous one. Non-finite readings
+    // are skipped.
+    pub fn update(&mut self, reading: &MotionReading, dt: f32) {
+        let Some((accel_roll, accel_pitch)) = accel_angles(reading) else {
+            return;
+        };
+        let gyro = &reading.gyroscope;
+        self.angles = match self.angles {
+            Some((roll, pitch)) if (0.0..=MAX_STEP_SECS).contains(&dt) && gyro.x.is_finite() && gyro.y.is_finite() => {
+                let roll = roll + gyro.x * dt;
+                let pitch = pitch + gyro.y * dt;
+                // Blend the difference so roll doesn't jump across the ±180° seam
+                let roll = wrap_degrees(roll + (1.0 - self.alpha) * wrap_degrees(accel_roll - roll));
+                let pitch = pitch + (1.0 - self.alpha) * (accel_pitch - pitch);
+                Some((roll, pitch.clamp(-90.0, 90.0)))
+            }
+            _ => Some((accel_roll, accel_pitch)),
+        };
+    }
+
+    // Degrees in (-180, 180]; 0 before the first sample
+    pub fn roll(&self) -> f32 {
+        self.angles.map_or(0.0, |(roll, _)| roll)
+    }
+
+    // Degrees in [-90, 90]; 0 before the first sample
+    pub fn pitch(&self) -> f32 {
+        self.angles.map_or(0.0, |(_, pitch)| pitch)
+    }
+
+    // Forget the estimate; the next sample starts from the accelerometer again
+    pub fn reset(&mut self) {
+        self.angles = None;
+    }
+}
+
+impl Default for ComplementaryFilter {
+    fn default() -> Self {
+        Self::new(DEFAULT_ALPHA)
+    }
+}
+
+// Roll and pitch of the gravity vector alone; works in m/s² or g alike
+fn accel_angles(reading: &MotionReading) -> Option<(f32, f32)> {
+    let a = &reading.accelerometer;
+    if !(a.x.is_finite() && a.y.is_finite() && a.z.is_finite()) || (a.x, a.y, a.z) == (0.0, 0.0, 0.0) {
+        return None;
+    }
+    let roll = a.y.atan2(a.z).to_degrees();
+    let pitch = (-a.x).atan2(a.y.hypot(a.z)).to_degrees();
+    Some((roll, pitch))
+}
+
+fn wrap_degrees(angle: f32) -> f32 {
+    let wrapped = (angle + 180.0).rem_euclid(360.0) - 180.0;
+    if wrapped == -180.0 { 180.0 } else { wrapped }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i2c::MPU6050::{AccelerometerReading, GyroscopeReading};
+
+    const DT: f32 = 0.1;
+
+    fn reading(accel: (f32, f32, f32), gyro: (f32, f32, f32)) -> MotionReading {
+        MotionReading {
+            accelerometer: AccelerometerReading { x: accel.0, y: accel.1, z: accel.2 },
+            gyroscope: GyroscopeReading { x: gyro.0, y: gyro.1, z: gyro.2 },
+            temperature: 25.0,
+        }
+    }
+
+    #[test]
+    fn stationary_gravity_converges_to_level() {
+        let mut filter = ComplementaryFilter::default();
+        // Start from a bad estimate, then sit level and still
+        filter.update(&reading((4.9, 4.9, 7.0), (0.0, 0.0, 0.0)), DT);
+        assert!(filter.roll().abs() > 20.0 && filter.pitch().abs() > 20.0);
+
+        let level = reading((0.0, 0.0, 9.81), (0.0, 0.0, 0.0));
+        for _ in 0..500 {
+            filter.update(&level, DT);
+        }
+        assert!(filter.roll().abs() < 0.1, "roll {}", filter.roll());
+        assert!(filter.pitch().abs() < 0.1, "pitch {}", filter.pitch());
+    }
+
+    #[test]
+    fn follows_gyro_short_term_and_wraps_roll() {
+        let mut filter = ComplementaryFilter::new(1.0); // Gyro only once started
+        filter.update(&reading((0.0, 0.0, 9.81), (0.0, 0.0, 0.0)), DT);
+        for _ in 0..10 {
+            filter.update(&reading((0.0, 0.0, 9.81), (90.0, -45.0, 0.0)), DT);
+        }
+        assert!((filter.roll() - 90.0).abs() < 0.01);
+        assert!((filter.pitch() + 45.0).abs() < 0.01);
+
+        // Upside down, ±180° is one orientation
+        let mut inverted = ComplementaryFilter::new(0.5);
+        inverted.update(&reading((0.0, 0.1, -9.81), (0.0, 0.0, 0.0)), DT);
+        inverted.update(&reading((0.0, -0.1, -9.81), (0.0, 0.0, 0.0)), DT);
+        assert!(inverted.roll().abs() > 179.0, "roll {}", inverted.roll());
+
+        // NaN is skipped, a long gap restarts from the accelerometer
+        filter.update(&reading((f32::NAN, 0.0, 9.81), (0.0, 0.0, 0.0)), DT);
+        assert!((filter.roll() - 90.0).abs() < 0.01);
+        filter.update(&reading((0.0, 0.0, 9.81), (0.0, 0.0, 0.0)), 5.0);
+        assert_eq!((filter.roll(), filter.pitch()), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,459 @@
+// Ground-station receiver: listens for downlink frames, decodes them and prints one table
+// row per data packet, with running totals of valid, malformed and lost packets. Every
+// packet is also appended to a CSV log for post-flight analysis. Extended packets are
+// reassembled into their messages (register dumps, event logs, black box chunks), each
+// saved to its own file. Frames from a sender running --reliable are acked back to it.
+// Each sender's session header is cached, for the mask its trimmed frames need and the
+// IMU ranges its rows are flagged as saturated against.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, LineWriter, Write};
+use std::net::{SocketAddr, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+
+use balloon_software::clock::unix_time_ms;
+use balloon_software::cobs::StreamDecoder;
+use balloon_software::downlink;
+use balloon_software::fields::MASKED_PACKET_SYNC;
+use balloon_software::flight::FlightPhase;
+use balloon_software::fragment::{ExtendedPacket, MessageType, Reassembler, EXTENDED_SYNC};
+use balloon_software::frame::{self, ByteReader, Endianness, DEFAULT_PREAMBLE_PATTERN};
+use balloon_software::packet::{TelemetryPacket, CSV_HEADER, PACKET_SYNC};
+use balloon_software::reliable::{self, Deduplicator};
+use balloon_software::session::{SessionCache, SessionHeader, SESSION_HEADER_SYNC};
+
+#[derive(Debug, Parser)]
+#[command(name = "receiver", about = "Balloon telemetry ground-station receiver")]
+struct Args {
+    /// Address to listen on for downlink frames
+    #[arg(long, default_value = "0.0.0.0:3000")]
+    bind: String,
+
+    /// Print running totals after every N data packets (0 disables)
+    #[arg(long, default_value_t = 10)]
+    summary_every: u64,
+
+    /// Preamble byte to strip ahead of each frame (see the sender's --preamble-pattern)
+    #[arg(long, default_value_t = DEFAULT_PREAMBLE_PATTERN)]
+    preamble_pattern: u8,
+
+    /// CSV log of every packet received [default: telemetry_<unix time at launch>.csv]
+    #[arg(long)]
+    csv: Option<PathBuf>,
+
+    /// Frames are COBS-encoded and 0x00-delimited (see the sender's --cobs)
+    #[arg(long)]
+    cobs: bool,
+
+    /// Directory reassembled extended messages are saved to, one file each
+    /// [default: messages_<unix time at launch>]
+    #[arg(long)]
+    messages_dir: Option<PathBuf>,
+
+    /// Seconds an incomplete extended message waits for its missing fragments
+    #[arg(long, default_value_t = 60)]
+    fragment_timeout_s: u64,
+}
+
+// One row per packet: receive time, then every packet field. Rows are flushed as they
+// are written so a crash loses nothing already received.
+struct CsvLog<W: Write> {
+    writer: W,
+}
+
+impl CsvLog<LineWriter<File>> {
+    fn create(path: &Path) -> io::Result<Self> {
+        Self::new(LineWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> CsvLog<W> {
+    fn new(mut writer: W) -> io::Result<Self> {
+        writeln!(writer, "received_unix_ms,{}", CSV_HEADER)?;
+        writer.flush()?;
+        Ok(Self { writer })
+    }
+
+    fn append(&mut self, received_unix_ms: u64, packet: &TelemetryPacket) -> io::Result<()> {
+        writeln!(self.writer, "{},{}", received_unix_ms, packet.to_csv_row())?;
+        self.writer.flush()
+    }
+}
+
+// Packet timestamps below this (2001-09-09) come from a sender's monotonic clock
+const WALL_CLOCK_MIN_MS: u64 = 1_000_000_000_000;
+
+// Time of day (UTC) for a wall-clock timestamp, else time since the sender started
+fn format_timestamp(timestamp_ms: u64) -> String {
+    let (secs, ms) = (timestamp_ms / 1000, timestamp_ms % 1000);
+    if timestamp_ms >= WALL_CLOCK_MIN_MS {
+        let seconds_of_day = secs % 86_400;
+        format!("{:02}:{:02}:{:02}.{:03}", seconds_of_day / 3600, seconds_of_day / 60 % 60, seconds_of_day % 60, ms)
+    } else {
+        format!("+{}.{:03}s", secs, ms)
+    }
+}
+
+#[derive(Debug)]
+enum Datagram {
+    Packet(TelemetryPacket),
+    Header(SessionHeader),
+    Fragment,                                   // Part of an extended message still arriving
+    Message { message_type: u8, data: Vec<u8> }, // The fragment that completed one
+    Malformed(String),
+}
+
+struct Receiver {
+    sessions: HashMap<SocketAddr, SessionCache>, // Each sender's latest session header
+    last_sequence: Option<u32>,
+    fragments: Reassembler,
+    valid: u64,
+    malformed: u64,
+    missed: u64,
+}
+
+impl Receiver {
+    fn new(fragment_timeout: Duration) -> Self {
+        Self {
+            sessions: HashMap::new(),
+            last_sequence: None,
+            fragments: Reassembler::new(fragment_timeout),
+            valid: 0,
+            malformed: 0,
+            missed: 0,
+        }
+    }
+
+    fn session(&self, from: SocketAddr) -> Option<&SessionCache> {
+        self.sessions.get(&from)
+    }
+
+    fn decode(&mut self, from: SocketAddr, datagram: &[u8], preamble_pattern: u8) -> Datagram {
+        let (header, payload) = match frame::decode(frame::skip_preamble(datagram, preamble_pattern)) {
+            Ok(frame) => frame,
+            Err(e) => return self.malformed(e.to_string()),
+        };
+        let order = header.endianness();
+        // Trimmed frames and fragments are little-endian whatever the envelope says
+        let sync = ByteReader::new(payload, order).u64().zip(ByteReader::new(payload, Endianness::Little).u64());
+        let Some((sync, sync_le)) = sync else {
+            return self.malformed(format!("{}-byte payload has no sync word", payload.len()));
+        };
+
+        let packet = match (sync, sync_le) {
+            (PACKET_SYNC, _) => match TelemetryPacket::from_bytes_in(payload, order) {
+                Ok(packet) if packet.verify_crc() => packet,
+                Ok(packet) => return self.malformed(format!("CRC mismatch in packet {}", { packet.sequence })),
+                Err(e) => return self.malformed(e.to_string()),
+            },
+            (_, MASKED_PACKET_SYNC) => match self.sessions.get(&from).and_then(SessionCache::fields).and_then(|mask| mask.decode(payload)) {
+                Some(packet) => packet,
+                None if self.sessions.get(&from).and_then(SessionCache::current).is_none() => return self.malformed("trimmed frame before any session header".to_string()),
+                None => return self.malformed("trimmed frame doesn't match the session's field mask".to_string()),
+            },
+            (SESSION_HEADER_SYNC, _) => match SessionHeader::from_bytes_in(payload, order) {
+                Some(header) => {
+                    self.sessions.entry(from).or_default().store(header);
+                    return Datagram::Header(header);
+                }
+                None => return self.malformed("bad session header".to_string()),
+            },
+            (_, EXTENDED_SYNC) => {
+                let Some(fragment) = ExtendedPacket::from_bytes(payload) else {
+                    return self.malformed("bad extended packet".to_string());
+                };
+                return match self.fragments.accept(fragment, Instant::now()) {
+                    Some((message_type, data)) => Datagram::Message { message_type, data },
+                    None => Datagram::Fragment,
+                };
+            }
+            _ => return self.malformed(format!("unknown sync word 0x{:016X}", sync)),
+        };
+
+        self.valid += 1;
+        if let Some(last) = self.last_sequence {
+            self.missed += downlink::packets_missed(last, packet.sequence) as u64;
+        }
+        self.last_sequence = Some(packet.sequence);
+        Datagram::Packet(packet)
+    }
+
+    fn malformed(&mut self, reason: String) -> Datagram {
+        self.malformed += 1;
+        Datagram::Malformed(reason)
+    }
+}
+
+// Reliable-delivery duplicates tracked per sender, so two payloads' sequence numbers
+// don't collide
+#[derive(Debug, Default)]
+struct Deliveries {
+    senders: HashMap<SocketAddr, Deduplicator>,
+}
+
+impl Deliveries {
+    // True the first time `from` sends `sequence` in its current session. A sender starts
+    // each session (and numbering from 0) with its session header.
+    fn first_delivery(&mut self, from: SocketAddr, sequence: u32, starts_session: bool) -> bool {
+        let seen = self.senders.entry(from).or_default();
+        if starts_session {
+            seen.reset();
+        }
+        seen.first_delivery(sequence)
+    }
+}
+
+// Whether a frame carries a session header
+fn is_session_header(frame: &[u8], preamble_pattern: u8) -> bool {
+    frame::decode(frame::skip_preamble(frame, preamble_pattern))
+        .is_ok_and(|(header, payload)| ByteReader::new(payload, header.endianness()).u64() == Some(SESSION_HEADER_SYNC))
+}
+
+// Writes a reassembled message to `dir` (created if need be) as <count>_<type>.bin
+fn save_message(dir: &Path, count: u64, message_type: u8, data: &[u8]) -> io::Result<PathBuf> {
+    let name = MessageType::from_u8(message_type).map_or_else(|| format!("type{}", message_type), |t| t.name().to_string());
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{:04}_{}.bin", count, name));
+    fs::write(&path, data)?;
+    Ok(path)
+}
+
+fn print_table_header() {
+    println!(
+        "{:>10} {:>12} {:>8} {:>9} {:>7} {:>6} {:>8} {:>10} {:>11} {:>6} {:>6}",
+        "seq", "time", "phase", "alt m", "temp C", "imu C", "hPa", "lat", "lon", "batt V", "status"
+    );
+}
+
+// `saturated` marks a row whose IMU reading clipped at the session's full-scale range
+fn print_packet(packet: &TelemetryPacket, saturated: bool) {
+    let phase = FlightPhase::from_u8(packet.flight_phase).map_or_else(|| "?".to_string(), |phase| format!("{:?}", phase));
+    println!(
+        "{:>10} {:>12} {:>8} {:>9.1} {:>7.1} {:>6.1} {:>8.2} {:>10.5} {:>11.5} {:>6.2} {:>#6x} {}{}",
+        { packet.sequence }, format_timestamp(packet.timestamp_ms), phase, { packet.altitude }, { packet.temperature },
+        { packet.imu_temperature }, { packet.pressure_hpa }, { packet.latitude }, { packet.longitude }, { packet.battery_voltage }, { packet.status },
+        packet.status_flags(),
+        if saturated { " IMU saturated" } else { "" }
+    );
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let socket = UdpSocket::bind(&args.bind)?;
+    println!("Listening for telemetry on {}", socket.local_addr()?);
+    let csv_path = args.csv.clone().unwrap_or_else(|| format!("telemetry_{}.csv", unix_time_ms() / 1000).into());
+    let mut csv = CsvLog::create(&csv_path)?;
+    println!("Logging packets to {}", csv_path.display());
+    let messages_dir = args.messages_dir.clone().unwrap_or_else(|| format!("messages_{}", unix_time_ms() / 1000).into());
+    let mut messages_saved = 0;
+    print_table_header();
+
+    let mut receiver = Receiver::new(Duration::from_secs(args.fragment_timeout_s));
+    let mut cobs = StreamDecoder::new();
+    let mut delivered = Deliveries::default();
+    let mut buf = [0u8; 2048];
+    loop {
+        let (len, from) = socket.recv_from(&mut buf)?;
+        // A sender running --reliable wants every datagram acked, retransmits included
+        let datagram = match reliable::unwrap(&buf[..len]) {
+            Some((sequence, datagram)) => {
+                if let Err(e) = socket.send_to(&reliable::ack(sequence), from) {
+                    eprintln!("Failed to ack frame {} to {}: {}", sequence, from, e);
+                }
+                // A COBS piece can't be told apart as a header until the stream is decoded;
+                // the sequence jumping back still catches a restart
+                let starts_session = !args.cobs && is_session_header(datagram, args.preamble_pattern);
+                if !delivered.first_delivery(from, sequence, starts_session) {
+                    continue;
+                }
+                datagram
+            }
+            None => &buf[..len],
+        };
+        // With --cobs a datagram is a piece of the stream, holding any number of frames
+        let frames = if args.cobs {
+            cobs.push(datagram)
+        } else {
+            vec![Ok(datagram.to_vec())]
+        };
+        for frame in frames {
+            let decoded = match frame {
+                Ok(frame) => receiver.decode(from, &frame, args.preamble_pattern),
+                Err(e) => receiver.malformed(e.to_string()),
+            };
+            match decoded {
+                Datagram::Packet(packet) => {
+                    print_packet(&packet, receiver.session(from).is_some_and(|session| session.saturated(&packet)));
+                    if let Err(e) = csv.append(unix_time_ms(), &packet) {
+                        eprintln!("Failed to write {}: {}", csv_path.display(), e);
+                    }
+                    if args.summary_every > 0 && receiver.valid % args.summary_every == 0 {
+                        println!(
+                            "-- {} valid, {} malformed, {} lost --",
+                            receiver.valid, receiver.malformed, receiver.missed
+                        );
+                        print_table_header();
+                    }
+                }
+                Datagram::Header(header) => println!("Session header from {}: {:?}", from, header),
+                Datagram::Fragment => {}
+                Datagram::Message { message_type, data } => {
+                    messages_saved += 1;
+                    match save_message(&messages_dir, messages_saved, message_type, &data) {
+                        Ok(path) => println!("Extended message from {} ({} bytes) saved to {}", from, data.len(), path.display()),
+                        Err(e) => eprintln!("Failed to save extended message from {}: {}", from, e),
+                    }
+                }
+                Datagram::Malformed(reason) => eprintln!("Malformed datagram from {}: {}", from, reason),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use balloon_software::checksum::Checksum;
+    use balloon_software::fields::{Field, FieldMask};
+    use balloon_software::fragment::ExtendedSender;
+
+    fn framed(payload: &[u8], order: Endianness) -> Vec<u8> {
+        frame::encode(payload, order, Checksum::Crc32).unwrap()
+    }
+
+    fn payload_addr() -> SocketAddr {
+        "10.0.0.2:4000".parse().unwrap()
+    }
+
+    fn packet(sequence: u32) -> TelemetryPacket {
+        let mut packet = TelemetryPacket::new(sequence);
+        packet.finalize();
+        packet
+    }
+
+    #[test]
+    fn decodes_and_counts_what_arrives() {
+        let mut receiver = Receiver::new(Duration::from_secs(60));
+        let decode = |receiver: &mut Receiver, datagram: &[u8]| receiver.decode(payload_addr(), datagram, DEFAULT_PREAMBLE_PATTERN);
+
+        let full = framed(&packet(0).to_bytes(Endianness::Big), Endianness::Big);
+        assert!(matches!(decode(&mut receiver, &full), Datagram::Packet(p) if { p.sequence } == 0));
+
+        // Trimmed frames need the session header's mask first
+        let mask = FieldMask::empty().with(Field::Altitude).with(Field::Sequence);
+        let trimmed = framed(&mask.encode(&packet(4)), Endianness::Big);
+        assert!(matches!(decode(&mut receiver, &trimmed), Datagram::Malformed(_)));
+        let header = SessionHeader::simulated().with_field_mask(mask);
+        let header_frame = framed(&header.to_bytes(Endianness::Big), Endianness::Big);
+        assert!(matches!(decode(&mut receiver, &header_frame), Datagram::Header(h) if h == header));
+        assert!(matches!(decode(&mut receiver, &trimmed), Datagram::Packet(p) if { p.sequence } == 4));
+
+        let mut corrupted = framed(&packet(5).to_le_bytes(), Endianness::Little);
+        corrupted[20] ^= 0x01;
+        assert!(matches!(decode(&mut receiver, &corrupted), Datagram::Malformed(_)));
+        assert!(matches!(decode(&mut receiver, b"garbage"), Datagram::Malformed(_)));
+
+        assert_eq!((receiver.valid, receiver.malformed, receiver.missed), (2, 3, 3));
+    }
+
+    #[test]
+    fn sessions_are_kept_per_sender() {
+        let (base, chase): (SocketAddr, SocketAddr) = (payload_addr(), "10.0.0.3:4000".parse().unwrap());
+        let mask = FieldMask::empty().with(Field::AccelZ).with(Field::Sequence);
+        let header = SessionHeader::new(2, 250, 10).with_field_mask(mask);
+        let mut receiver = Receiver::new(Duration::from_secs(60));
+        let header_frame = framed(&header.to_bytes(Endianness::Little), Endianness::Little);
+        assert!(matches!(receiver.decode(base, &header_frame, DEFAULT_PREAMBLE_PATTERN), Datagram::Header(_)));
+
+        // The base payload's mask doesn't decode the chase payload's trimmed frames
+        let mut clipped = packet(1);
+        clipped.accel_z = -19.6;
+        let trimmed = framed(&mask.encode(&clipped), Endianness::Little);
+        assert!(matches!(receiver.decode(chase, &trimmed, DEFAULT_PREAMBLE_PATTERN), Datagram::Malformed(_)));
+        let Datagram::Packet(decoded) = receiver.decode(base, &trimmed, DEFAULT_PREAMBLE_PATTERN) else {
+            panic!("trimmed frame not decoded with the base payload's mask");
+        };
+
+        // Saturation is judged against the sender's own ±2 g / ±250 °/s ranges
+        let session = receiver.session(base).unwrap();
+        assert_eq!((session.accel_range_g(), session.gyro_range_dps()), (Some(2), Some(250)));
+        assert!(session.saturated(&decoded));
+        let mut level = decoded;
+        level.accel_z = 9.8;
+        assert!(!session.saturated(&level));
+        assert!(receiver.session(chase).is_none());
+    }
+
+    #[test]
+    fn extended_messages_are_reassembled_and_saved() {
+        let dump: Vec<u8> = (0..450).map(|i| (i % 251) as u8).collect();
+        let mut sender = ExtendedSender::new();
+        sender.queue_message(MessageType::RegisterDump, &dump).unwrap();
+        sender.queue_message(MessageType::EventLog, b"burst").unwrap();
+
+        // Sent as the payload does: one framed fragment at a time, amid the telemetry
+        let mut receiver = Receiver::new(Duration::from_secs(60));
+        let mut messages = Vec::new();
+        while let Some(fragment) = sender.next_fragment() {
+            let data = framed(&packet(receiver.valid as u32).to_le_bytes(), Endianness::Little);
+            assert!(matches!(receiver.decode(payload_addr(), &data, DEFAULT_PREAMBLE_PATTERN), Datagram::Packet(_)));
+            match receiver.decode(payload_addr(), &framed(&fragment.to_bytes(), Endianness::Big), DEFAULT_PREAMBLE_PATTERN) {
+                Datagram::Message { message_type, data } => messages.push((message_type, data)),
+                Datagram::Fragment => {}
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        assert_eq!(messages, [(MessageType::RegisterDump as u8, dump.clone()), (MessageType::EventLog as u8, b"burst".to_vec())]);
+        assert_eq!((receiver.valid, receiver.malformed, receiver.missed), (4, 0, 0));
+
+        let dir = std::env::temp_dir().join(format!("balloon-receiver-messages-{}", std::process::id()));
+        let path = save_message(&dir, 1, MessageType::RegisterDump as u8, &dump).unwrap();
+        assert_eq!(path.file_name().unwrap(), "0001_register_dump.bin");
+        assert_eq!(fs::read(&path).unwrap(), dump);
+        assert!(save_message(&dir, 2, 99, b"?").unwrap().ends_with("0002_type99.bin"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn csv_log_writes_the_header_once() {
+        let mut csv = CsvLog::new(Vec::new()).unwrap();
+        csv.append(1_700_000_000_123, &packet(7)).unwrap();
+        csv.append(1_700_000_000_223, &packet(8)).unwrap();
+
+        let text = String::from_utf8(csv.writer).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("received_unix_ms,sync,version,timestamp_ms,"));
+        assert!(lines[2].starts_with("1700000000223,"));
+        assert_eq!(lines[1].split(',').count(), lines[0].split(',').count());
+    }
+
+    #[test]
+    fn deliveries_are_per_sender_and_session() {
+        let header = framed(&SessionHeader::simulated().to_bytes(Endianness::Little), Endianness::Little);
+        let data = framed(&packet(0).to_le_bytes(), Endianness::Little);
+        assert!(is_session_header(&header, DEFAULT_PREAMBLE_PATTERN));
+        assert!(!is_session_header(&data, DEFAULT_PREAMBLE_PATTERN));
+
+        let (base, chase): (SocketAddr, SocketAddr) = ("10.0.0.2:4000".parse().unwrap(), "10.0.0.3:4000".parse().unwrap());
+        let mut delivered = Deliveries::default();
+        assert!(delivered.first_delivery(base, 0, true));
+        assert!(delivered.first_delivery(base, 1, false));
+        assert!(!delivered.first_delivery(base, 1, false)); // Retransmit
+        assert!(delivered.first_delivery(chase, 1, false)); // Another payload's numbering
+
+        // The base payload reboots and numbers from 0 again, header first
+        assert!(delivered.first_delivery(base, 0, true));
+        assert!(delivered.first_delivery(base, 1, false));
+    }
+
+    #[test]
+    fn timestamps_format_by_clock() {
+        assert_eq!(format_timestamp(1_700_000_000_042), "22:13:20.042");
+        assert_eq!(format_timestamp(3_723_005), "+3723.005s");
+    }
+}
@@ -0,0 +1,178 @@
+// "Black box" of the last few seconds of full-rate motion samples, kept in RAM so the
+// window around burst can be written out or downlinked at full fidelity after the fact.
+// The 10Hz telemetry stream is far too coarse to show the transient itself.
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::fragment::MAX_MESSAGE_LEN;
+use crate::i2c::MPU6050::MotionReading;
+
+// Downlink chunk layout: dump time (u64 unix ms), sample count (u16), then per sample
+// its age at the dump (u32 ms) and accel x/y/z, gyro x/y/z, temperature as f32, all
+// little-endian
+const CHUNK_HEADER_LEN: usize = 8 + 2;
+const ENCODED_SAMPLE_LEN: usize = 4 + 7 * 4;
+pub const SAMPLES_PER_CHUNK: usize = (MAX_MESSAGE_LEN - CHUNK_HEADER_LEN) / ENCODED_SAMPLE_LEN;
+
+#[derive(Debug, Clone)]
+pub struct BlackBoxSample {
+    pub at: Instant,
+    pub reading: MotionReading,
+}
+
+// Fixed-capacity ring: once full, each new sample overwrites the oldest
+#[derive(Debug)]
+pub struct BlackBox {
+    samples: VecDeque<BlackBoxSample>,
+    capacity: usize,
+}
+
+impl BlackBox {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self { samples: VecDeque::with_capacity(capacity), capacity }
+    }
+
+    // Enough room for `window` of samples taken every `interval`
+    pub fn with_window(window: Duration, interval: Duration) -> Self {
+        let capacity = window.as_nanos() / interval.as_nanos().max(1);
+        Self::new(capacity.try_into().unwrap_or(usize::MAX))
+    }
+
+    pub fn record(&mut self, reading: MotionReading, at: Instant) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(BlackBoxSample { at, reading });
+    }
+
+    // Buffered window, oldest first
+    pub fn snapshot(&self) -> Vec<BlackBoxSample> {
+        self.samples.iter().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+// Black box shared between the sampler thread and the telemetry loop
+#[derive(Debug, Clone)]
+pub struct SharedBlackBox(Arc<Mutex<BlackBox>>);
+
+impl SharedBlackBox {
+    pub fn new(black_box: BlackBox) -> Self {
+        Self(Arc::new(Mutex::new(black_box)))
+    }
+
+    pub fn record(&self, reading: MotionReading, at: Instant) {
+        self.lock().record(reading, at);
+    }
+
+    pub fn snapshot(&self) -> Vec<BlackBoxSample> {
+        self.lock().snapshot()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BlackBox> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn age_ms(sample: &BlackBoxSample, now: Instant) -> u32 {
+    now.saturating_duration_since(sample.at).as_millis().try_into().unwrap_or(u32::MAX)
+}
+
+// One row per sample, oldest first, timed by age at `now` in ms
+pub fn write_csv(samples: &[BlackBoxSample], now: Instant, mut writer: impl Write) -> io::Result<()> {
+    writeln!(writer, "age_ms,accel_x,accel_y,accel_z,gyro_x,gyro_y,gyro_z,temperature")?;
+    for sample in samples {
+        let (accel, gyro) = (&sample.reading.accelerometer, &sample.reading.gyroscope);
+        writeln!(writer, "{},{},{},{},{},{},{},{}", age_ms(sample, now),
+                 accel.x, accel.y, accel.z, gyro.x, gyro.y, gyro.z, sample.reading.temperature)?;
+    }
+    Ok(())
+}
+
+// Splits the window into payloads that each fit one extended message
+pub fn encode_chunks(samples: &[BlackBoxSample], now: Instant, dump_unix_ms: u64) -> Vec<Vec<u8>> {
+    samples
+        .chunks(SAMPLES_PER_CHUNK)
+        .map(|chunk| {
+            let mut buf = Vec::with_capacity(CHUNK_HEADER_LEN + chunk.len() * ENCODED_SAMPLE_LEN);
+            buf.extend_from_slice(&dump_unix_ms.to_le_bytes());
+            buf.extend_from_slice(&(chunk.len() as u16).to_le_bytes());
+            for sample in chunk {
+                let (accel, gyro) = (&sample.reading.accelerometer, &sample.reading.gyroscope);
+                buf.extend_from_slice(&age_ms(sample, now).to_le_bytes());
+                for value in [accel.x, accel.y, accel.z, gyro.x, gyro.y, gyro.z, sample.reading.temperature] {
+                    buf.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+            buf
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i2c::MPU6050::{AccelerometerReading, GyroscopeReading};
+
+    fn reading(x: f32) -> MotionReading {
+        MotionReading {
+            accelerometer: AccelerometerReading { x, y: 0.0, z: 9.8 },
+            gyroscope: GyroscopeReading { x: 0.0, y: 0.0, z: 1.0 },
+            temperature: 20.0,
+        }
+    }
+
+    #[test]
+    fn keeps_only_the_newest_window() {
+        let start = Instant::now();
+        let mut black_box = BlackBox::with_window(Duration::from_millis(40), Duration::from_millis(10));
+        assert_eq!(black_box.capacity(), 4);
+
+        for i in 0..6 {
+            black_box.record(reading(i as f32), start + Duration::from_millis(10 * i));
+        }
+
+        let snapshot = black_box.snapshot();
+        assert_eq!(black_box.len(), 4);
+        let xs: Vec<f32> = snapshot.iter().map(|sample| sample.reading.accelerometer.x).collect();
+        assert_eq!(xs, vec![2.0, 3.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn csv_and_chunks_carry_every_sample() {
+        let start = Instant::now();
+        let samples: Vec<BlackBoxSample> = (0..SAMPLES_PER_CHUNK + 3)
+            .map(|i| BlackBoxSample { at: start + Duration::from_millis(i as u64), reading: reading(i as f32) })
+            .collect();
+        let now = start + Duration::from_millis(samples.len() as u64);
+
+        let mut csv = Vec::new();
+        write_csv(&samples[..2], now, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1], format!("{},0,0,9.8,0,0,1,20", samples.len()));
+
+        let chunks = encode_chunks(&samples, now, 1_700_000_000_000);
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= MAX_MESSAGE_LEN));
+        assert_eq!(u64::from_le_bytes(chunks[1][..8].try_into().unwrap()), 1_700_000_000_000);
+        assert_eq!(u16::from_le_bytes([chunks[1][8], chunks[1][9]]), 3);
+        assert_eq!(chunks[1].len(), CHUNK_HEADER_LEN + 3 * ENCODED_SAMPLE_LEN);
+    }
+}
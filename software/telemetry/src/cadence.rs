@@ -0,0 +1,249 @@
+// Fixed-rate loop timing. Sleeping to absolute deadlines keeps the average rate on
+// target however long the sensor reads and sends take; the jitter that remains is
+// measured per iteration.
+//
+// TransmitSchedule thins the downlink by time of day (e.g. a slow beacon overnight on
+// a multi-day float) without changing the sampling rate.
+
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+use crate::stats::RunningStat;
+
+const SECONDS_PER_HOUR: u64 = 3600;
+
+pub struct LoopTimer {
+    target: Duration,
+    next_deadline: Instant,
+    last_start: Option<Instant>,
+    interval_ms: RunningStat, // Since the last report
+    jitter_ms: RunningStat,   // |actual - target| since the last report
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JitterReport {
+    pub iterations: u64,
+    pub mean_interval_ms: f32,
+    pub mean_jitter_ms: f32,
+    pub max_jitter_ms: f32,
+}
+
+impl LoopTimer {
+    pub fn new(target: Duration, now: Instant) -> Self {
+        Self {
+            target,
+            next_deadline: now,
+            last_start: None,
+            interval_ms: RunningStat::default(),
+            jitter_ms: RunningStat::default(),
+        }
+    }
+
+    // Call at the top of each iteration; returns the signed deviation of the interval
+    // since the previous iteration from the target, in ms
+    pub fn start_iteration(&mut self, now: Instant) -> Option<f32> {
+        let jitter = self.last_start.map(|last| {
+            let interval_ms = now.duration_since(last).as_secs_f32() * 1000.0;
+            let jitter_ms = interval_ms - self.target.as_secs_f32() * 1000.0;
+            self.interval_ms.update(interval_ms);
+            self.jitter_ms.update(jitter_ms.abs());
+            jitter_ms
+        });
+        self.last_start = Some(now);
+        jitter
+    }
+
+    // When to start the next iteration. After an overrun the schedule restarts from
+    // `now` rather than firing several iterations back to back to catch up.
+    pub fn next_deadline(&mut self, now: Instant) -> Instant {
+        self.next_deadline += self.target;
+        if self.next_deadline < now {
+            self.next_deadline = now;
+        }
+        self.next_deadline
+    }
+
+    // Summary of the iterations since the last report, once there are `min_iterations`
+    pub fn take_report(&mut self, min_iterations: u64) -> Option<JitterReport> {
+        if self.jitter_ms.count() < min_iterations.max(1) {
+            return None;
+        }
+
+        let report = JitterReport {
+            iterations: self.jitter_ms.count(),
+            mean_interval_ms: self.interval_ms.mean()?,
+            mean_jitter_ms: self.jitter_ms.mean()?,
+            max_jitter_ms: self.jitter_ms.max()?,
+        };
+        self.interval_ms = RunningStat::default();
+        self.jitter_ms = RunningStat::default();
+        Some(report)
+    }
+}
+
+// From `start_hour` up to `end_hour` UTC, at most one packet per `interval`. Windows
+// may wrap past midnight (20-6). Written START-END=SECONDS on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduleWindow {
+    pub start_hour: u8, // 0-23
+    pub end_hour: u8,   // 0-24, exclusive
+    pub interval: Duration,
+}
+
+impl ScheduleWindow {
+    fn contains(&self, hour: u8) -> bool {
+        if self.start_hour < self.end_hour {
+            (self.start_hour..self.end_hour).contains(&hour)
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        }
+    }
+}
+
+impl FromStr for ScheduleWindow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("'{}' is not START-END=SECONDS (UTC hours, e.g. 20-6=60)", s);
+        let (hours, seconds) = s.split_once('=').ok_or_else(invalid)?;
+        let (start, end) = hours.split_once('-').ok_or_else(invalid)?;
+        let start_hour: u8 = start.trim().parse().map_err(|_| invalid())?;
+        let end_hour: u8 = end.trim().parse().map_err(|_| invalid())?;
+        let seconds: f32 = seconds.trim().parse().map_err(|_| invalid())?;
+
+        if start_hour > 23 || end_hour > 24 || start_hour == end_hour {
+            return Err(format!("'{}': hours must be 0-23 to 0-24 and not the same", s));
+        }
+        // Rejects negative, non-finite and too-large intervals
+        let interval = Duration::try_from_secs_f32(seconds)
+            .map_err(|_| format!("'{}': the interval must be zero or more seconds, within a Duration", s))?;
+        Ok(Self { start_hour, end_hour, interval })
+    }
+}
+
+// Every packet goes out outside the windows; the first window containing the hour wins
+#[derive(Debug, Clone, Default)]
+pub struct TransmitSchedule {
+    windows: Vec<ScheduleWindow>,
+    last_sent: Option<Instant>,
+    current: Option<Duration>, // Interval in force, for logging changes
+    commanded: Option<Duration>, // From the uplink; overrides the windows
+}
+
+impl TransmitSchedule {
+    pub fn new(windows: Vec<ScheduleWindow>) -> Self {
+        Self { windows, ..Self::default() }
+    }
+
+    // None hands control back to the windows
+    pub fn set_commanded(&mut self, interval: Option<Duration>) {
+        self.commanded = interval;
+    }
+
+    // Minimum spacing of packets at `unix_secs`, zero outside every window
+    pub fn interval_at(&self, unix_secs: u64) -> Duration {
+        if let Some(interval) = self.commanded {
+            return interval;
+        }
+        let hour = (unix_secs / SECONDS_PER_HOUR % 24) as u8;
+        self.windows
+            .iter()
+            .find(|window| window.contains(hour))
+            .map_or(Duration::ZERO, |window| window.interval)
+    }
+
+    pub fn is_due(&mut self, unix_secs: u64, now: Instant) -> bool {
+        let interval = self.interval_at(unix_secs);
+        if self.current != Some(interval) {
+            if !self.windows.is_empty() || self.commanded.is_some() {
+                info!("Transmit interval now {:?} ({:02}:00 UTC)", interval, unix_secs / SECONDS_PER_HOUR % 24);
+            }
+            self.current = Some(interval);
+        }
+        self.last_sent.is_none_or(|last| now.duration_since(last) >= interval)
+    }
+
+    pub fn record_sent(&mut self, now: Instant) {
+        self.last_sent = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET: Duration = Duration::from_millis(100);
+
+    #[test]
+    fn deadlines_absorb_work_time() {
+        let start = Instant::now();
+        let mut timer = LoopTimer::new(TARGET, start);
+
+        // 30ms of work still wakes at the 100ms mark, not 130ms
+        assert_eq!(timer.next_deadline(start + Duration::from_millis(30)), start + TARGET);
+        assert_eq!(timer.next_deadline(start + Duration::from_millis(180)), start + 2 * TARGET);
+
+        // A 250ms overrun restarts the schedule instead of bursting to catch up
+        let late = start + Duration::from_millis(450);
+        assert_eq!(timer.next_deadline(late), late);
+        assert_eq!(timer.next_deadline(late + Duration::from_millis(10)), late + TARGET);
+    }
+
+    #[test]
+    fn reports_mean_and_max_jitter() {
+        let start = Instant::now();
+        let mut timer = LoopTimer::new(TARGET, start);
+        assert_eq!(timer.start_iteration(start), None);
+
+        let mut now = start;
+        for interval_ms in [100, 104, 96, 112] {
+            now += Duration::from_millis(interval_ms);
+            timer.start_iteration(now);
+        }
+        assert_eq!(timer.take_report(5), None);
+
+        let report = timer.take_report(4).unwrap();
+        assert_eq!(report.iterations, 4);
+        assert!((report.mean_interval_ms - 103.0).abs() < 0.01);
+        assert!((report.mean_jitter_ms - 5.0).abs() < 0.01);
+        assert!((report.max_jitter_ms - 12.0).abs() < 0.01);
+
+        // The window restarts after each report
+        assert_eq!(timer.take_report(1), None);
+    }
+
+    #[test]
+    fn schedule_picks_the_window_for_the_hour() {
+        let windows: Vec<ScheduleWindow> = ["20-6=60", "12-14=5"].iter().map(|w| w.parse().unwrap()).collect();
+        let mut schedule = TransmitSchedule::new(windows);
+        let at = |hour: u64| 1_700_006_400 / 86_400 * 86_400 + hour * SECONDS_PER_HOUR;
+
+        assert_eq!(schedule.interval_at(at(22)), Duration::from_secs(60));
+        assert_eq!(schedule.interval_at(at(3)), Duration::from_secs(60));
+        assert_eq!(schedule.interval_at(at(6)), Duration::ZERO);
+        assert_eq!(schedule.interval_at(at(13)), Duration::from_secs(5));
+
+        let start = Instant::now();
+        assert!(schedule.is_due(at(23), start));
+        schedule.record_sent(start);
+        assert!(!schedule.is_due(at(23), start + Duration::from_secs(59)));
+        assert!(schedule.is_due(at(23), start + Duration::from_secs(60)));
+        // Daylight: every iteration
+        assert!(schedule.is_due(at(9), start + Duration::from_millis(100)));
+
+        // A commanded interval applies at every hour until cleared
+        schedule.set_commanded(Some(Duration::from_secs(20)));
+        assert_eq!(schedule.interval_at(at(9)), Duration::from_secs(20));
+        assert_eq!(schedule.interval_at(at(22)), Duration::from_secs(20));
+        schedule.set_commanded(None);
+        assert_eq!(schedule.interval_at(at(22)), Duration::from_secs(60));
+
+        assert!("6-6=10".parse::<ScheduleWindow>().is_err());
+        assert!("0-25=10".parse::<ScheduleWindow>().is_err());
+        assert!("6-20".parse::<ScheduleWindow>().is_err());
+        assert!("0-1=1e30".parse::<ScheduleWindow>().is_err());
+        assert!("0-1=-5".parse::<ScheduleWindow>().is_err());
+        assert_eq!("0-24=1".parse::<ScheduleWindow>().map(|w| w.contains(23)), Ok(true));
+    }
+}
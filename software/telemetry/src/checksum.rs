@@ -0,0 +1,132 @@
+// Frame checksums, pure Rust so the algorithms match what common radio modems and HAB
+// ground software compute. The checksum trails the frame it covers, most significant
+// byte first.
+//
+//   Crc16Ccitt: CRC-16/CCITT-FALSE (poly 0x1021, init 0xFFFF, not reflected, no xorout)
+//   Crc32:      CRC-32/ISO-HDLC, as in zlib and Ethernet (poly 0x04C11DB7 reflected,
+//               init and xorout 0xFFFFFFFF)
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Checksum {
+    None,
+    Crc16Ccitt,
+    #[default]
+    Crc32,
+}
+
+impl Checksum {
+    // Identifier carried in the frame flags and the session header
+    pub fn id(self) -> u8 {
+        match self {
+            Checksum::None => 0,
+            Checksum::Crc16Ccitt => 1,
+            Checksum::Crc32 => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Checksum::None),
+            1 => Some(Checksum::Crc16Ccitt),
+            2 => Some(Checksum::Crc32),
+            _ => None,
+        }
+    }
+
+    // Bytes appended to each frame
+    pub fn trailer_len(self) -> usize {
+        match self {
+            Checksum::None => 0,
+            Checksum::Crc16Ccitt => 2,
+            Checksum::Crc32 => 4,
+        }
+    }
+
+    pub fn compute(self, data: &[u8]) -> u32 {
+        match self {
+            Checksum::None => 0,
+            Checksum::Crc16Ccitt => crc16_ccitt(data) as u32,
+            Checksum::Crc32 => crc32(data),
+        }
+    }
+
+    // Appends the checksum of everything already in `frame`
+    pub fn finalize(self, frame: &mut Vec<u8>) {
+        let value = self.compute(frame).to_be_bytes();
+        frame.extend_from_slice(&value[4 - self.trailer_len()..]);
+    }
+
+    // Inverse of finalize(): the frame without its trailer, or the (received,
+    // computed) checksums when they differ
+    pub fn verify(self, frame: &[u8]) -> Result<&[u8], (u32, u32)> {
+        let split = frame.len().checked_sub(self.trailer_len()).ok_or((0, 0))?;
+        let (data, trailer) = frame.split_at(split);
+        let received = trailer.iter().fold(0, |value, &b| (value << 8) | b as u32);
+        let computed = self.compute(data);
+        if received == computed {
+            Ok(data)
+        } else {
+            Err((received, computed))
+        }
+    }
+}
+
+pub fn crc16_ccitt(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Check values from the CRC catalogue (reveng.sourceforge.io/crc-catalogue)
+    #[test]
+    fn matches_reference_values() {
+        assert_eq!(crc16_ccitt(b"123456789"), 0x29B1);
+        assert_eq!(crc16_ccitt(b""), 0xFFFF);
+        assert_eq!(crc16_ccitt(b"A"), 0xB915);
+
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"The quick brown fox jumps over the lazy dog"), 0x414F_A339);
+    }
+
+    #[test]
+    fn finalize_and_verify_round_trip() {
+        for checksum in [Checksum::None, Checksum::Crc16Ccitt, Checksum::Crc32] {
+            let mut frame = b"123456789".to_vec();
+            checksum.finalize(&mut frame);
+            assert_eq!(frame.len(), 9 + checksum.trailer_len());
+            assert_eq!(checksum.verify(&frame), Ok(&b"123456789"[..]));
+            assert_eq!(Checksum::from_id(checksum.id()), Some(checksum));
+
+            if checksum != Checksum::None {
+                frame[3] ^= 0x10;
+                assert!(checksum.verify(&frame).is_err());
+            }
+        }
+
+        let mut frame = b"123456789".to_vec();
+        Checksum::Crc16Ccitt.finalize(&mut frame);
+        assert_eq!(&frame[9..], &[0x29, 0xB1]);
+        assert!(Checksum::Crc32.verify(&[1, 2]).is_err());
+    }
+}
@@ -0,0 +1,623 @@
+// Command-line options for the telemetry sender
+
+use clap::{Parser, ValueEnum};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use balloon_software::altitude;
+use balloon_software::cadence::ScheduleWindow;
+use balloon_software::checksum::Checksum;
+use balloon_software::clock::ClockSource;
+use balloon_software::config::{Config, DEFAULT_SEND_INTERVAL_MS};
+use balloon_software::fields::{Field, FieldMask};
+use balloon_software::flight::PhaseThresholds;
+use balloon_software::frame::Endianness;
+use balloon_software::on_change::ChangeThresholds;
+use balloon_software::reliable::ReliableConfig;
+use balloon_software::serial::{self, DEFAULT_BAUD_RATE};
+use balloon_software::i2c::ADS1115::Gain;
+use balloon_software::i2c::MPU6050::{AxisMap, DlpfBandwidth};
+#[cfg(feature = "mqtt")]
+use balloon_software::mqtt::{Encoding, MqttConfig};
+use balloon_software::sensors::{BatteryConfig, BatteryMonitor, SensorConfig};
+use balloon_software::transport::EmitFormat;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TransportKind {
+    Udp,
+    Tcp,
+    Serial, // --target is a serial device, e.g. /dev/ttyUSB0
+    File,   // --target is a file to record frames to
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ByteOrder {
+    Little,
+    Big,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ChecksumKind {
+    Crc16,
+    Crc32,
+    None,
+}
+
+impl From<ChecksumKind> for Checksum {
+    fn from(kind: ChecksumKind) -> Self {
+        match kind {
+            ChecksumKind::Crc16 => Checksum::Crc16Ccitt,
+            ChecksumKind::Crc32 => Checksum::Crc32,
+            ChecksumKind::None => Checksum::None,
+        }
+    }
+}
+
+#[cfg(feature = "mqtt")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MqttEncoding {
+    Json,
+    Msgpack,
+}
+
+#[cfg(feature = "mqtt")]
+impl From<MqttEncoding> for Encoding {
+    fn from(encoding: MqttEncoding) -> Self {
+        match encoding {
+            MqttEncoding::Json => Encoding::Json,
+            MqttEncoding::Msgpack => Encoding::MsgPack,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BatteryMonitorKind {
+    Ads1115,
+    Ina219,
+}
+
+impl From<BatteryMonitorKind> for BatteryMonitor {
+    fn from(kind: BatteryMonitorKind) -> Self {
+        match kind {
+            BatteryMonitorKind::Ads1115 => BatteryMonitor::Ads1115,
+            BatteryMonitorKind::Ina219 => BatteryMonitor::Ina219,
+        }
+    }
+}
+
+impl From<ByteOrder> for Endianness {
+    fn from(order: ByteOrder) -> Self {
+        match order {
+            ByteOrder::Little => Endianness::Little,
+            ByteOrder::Big => Endianness::Big,
+        }
+    }
+}
+
+#[derive(Debug, Parser)]
+#[command(name = "balloon-software", about = "Balloon telemetry packet generator")]
+pub struct Args {
+    /// Print the MPU6050 register map and exit (read-only)
+    #[arg(long)]
+    pub dump_registers: bool,
+
+    /// Check every sensor, the output directories and the targets, print a go/no-go table and exit (non-zero on no-go)
+    #[arg(long, conflicts_with = "dump_registers")]
+    pub preflight: bool,
+
+    /// Log filter directives, e.g. "info" or "warn,balloon_software::sensors=debug" (RUST_LOG overrides)
+    #[arg(long, default_value = "info")]
+    pub log_filter: String,
+
+    /// UDP address to listen on for uplink commands (disabled if unset)
+    #[arg(long)]
+    pub command_bind: Option<String>,
+
+    /// BCM GPIO pin driving the status LED (disabled if unset)
+    #[arg(long)]
+    pub led_pin: Option<u8>,
+
+    /// Budget for a single sensor read before it is abandoned (ms)
+    #[arg(long, default_value_t = SensorConfig::default().read_budget.as_millis() as u64)]
+    pub sensor_timeout_ms: u64,
+
+    /// Wait between starting an MPL115A2 conversion and reading it (ms). Too short returns
+    /// the previous conversion's stale data; the datasheet gives 3ms typical.
+    #[arg(long, default_value_t = SensorConfig::default().baro_conversion_delay.as_millis() as u64)]
+    pub baro_conversion_delay_ms: u64,
+
+    /// MPL115A2 conversions averaged per reading: steadier altitude, but each one adds a
+    /// conversion delay to the read, which must stay inside --sensor-timeout-ms
+    #[arg(long, default_value_t = SensorConfig::default().baro_oversampling, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=32))]
+    pub baro_oversampling: usize,
+
+    /// MPL115A2 readings in the moving average behind altitude (1 disables): less jitter,
+    /// but a climb reads (n - 1) / 2 packets late
+    #[arg(long, default_value_t = SensorConfig::default().baro_filter_window, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=64))]
+    pub baro_filter_window: usize,
+
+    /// Sea-level pressure for barometric altitude (hPa): the local QNH on launch day, or
+    /// the standard atmosphere's. Can be changed in flight by command.
+    #[arg(long, default_value_t = SensorConfig::default().sea_level_hpa, value_parser = parse_sea_level_hpa)]
+    pub sea_level_hpa: f32,
+
+    /// Surveyed elevation of the launch site (m above mean sea level). At startup the
+    /// MPL115A2 is averaged there and --sea-level-hpa replaced by the reference that
+    /// makes it read this altitude.
+    #[arg(long, allow_negative_numbers = true)]
+    pub field_elevation_m: Option<f32>,
+
+    /// Serial port of an NMEA GPS receiver, e.g. /dev/serial0. Without one the position
+    /// is simulated.
+    #[arg(long)]
+    pub gps_device: Option<PathBuf>,
+
+    /// Baud rate of the --gps-device receiver
+    #[arg(long, default_value_t = SensorConfig::default().gps_baud, value_parser = serial::parse_baud_rate)]
+    pub gps_baud: u32,
+
+    /// MPU6050 polling interval for the between-packet peak latch and black box (ms, 0 disables)
+    #[arg(long, default_value_t = SensorConfig::default().peak_sample_interval.map_or(0, |d| d.as_millis() as u64))]
+    pub peak_sample_ms: u64,
+
+    /// Vehicle X,Y,Z in terms of MPU6050 axes, e.g. "-y,x,z" for a board rotated 90° about Z
+    #[arg(long, default_value = "x,y,z")]
+    pub imu_axes: AxisMap,
+
+    /// MPU6050 low-pass filter bandwidth (Hz): 260 (off), 184, 94, 44, 21, 10 or 5. Wider
+    /// keeps the vibration of burst and descent; keep it under half the 125 Hz sample rate.
+    #[arg(long, default_value = "44")]
+    pub imu_dlpf_hz: DlpfBandwidth,
+
+    /// Gyro Z bias subtracted before integrating the relative heading (°/s)
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    pub gyro_z_bias: f32,
+
+    /// MPU6050 initialization attempts before falling back to simulated motion data
+    #[arg(long, default_value_t = SensorConfig::default().motion_init_attempts, value_parser = clap::value_parser!(u32).range(1..))]
+    pub imu_init_attempts: u32,
+
+    /// Wait between MPU6050 initialization attempts (ms)
+    #[arg(long, default_value_t = SensorConfig::default().motion_init_retry_delay.as_millis() as u64)]
+    pub imu_init_retry_ms: u64,
+
+    /// Magnetic declination at the launch site, east positive, turning compass headings
+    /// into true ones (°). Only used with an HMC5883L or QMC5883L magnetometer fitted.
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    pub magnetic_declination: f32,
+
+    /// Acceleration magnitude below which the payload is in free fall: sets the FREEFALL
+    /// status bit and, before burst is recognised, triggers the black box dump (m/s²)
+    #[arg(long, default_value_t = SensorConfig::default().freefall_threshold)]
+    pub freefall_threshold: f32,
+
+    /// Full-rate motion history kept in RAM and dumped on burst (s)
+    #[arg(long, default_value_t = SensorConfig::default().black_box_window.as_secs())]
+    pub black_box_seconds: u64,
+
+    /// Where to write the black box CSV when burst is detected
+    #[arg(long, default_value = "black_box.csv")]
+    pub black_box_path: PathBuf,
+
+    /// Also downlink the black box as extended messages when burst is detected
+    #[arg(long)]
+    pub black_box_downlink: bool,
+
+    /// Battery monitor fitted: an ADS1115 behind a divider, or an INA219 high-side shunt
+    #[arg(long, value_enum, default_value_t = BatteryMonitorKind::Ads1115)]
+    pub battery_monitor: BatteryMonitorKind,
+
+    /// ADS1115 input the battery divider is wired to
+    #[arg(long, default_value_t = BatteryConfig::default().channel, value_parser = clap::value_parser!(u8).range(0..=3))]
+    pub battery_channel: u8,
+
+    /// ADS1115 full-scale range (mV): 6144, 4096, 2048, 1024, 512 or 256
+    #[arg(long, default_value_t = BatteryConfig::default().gain.full_scale_millivolts(), value_parser = parse_gain_mv)]
+    pub battery_range_mv: u16,
+
+    /// Battery volts per volt at the ADC input
+    #[arg(long, default_value_t = BatteryConfig::default().divider_ratio)]
+    pub battery_divider: f32,
+
+    /// INA219 shunt resistance (Ω)
+    #[arg(long, default_value_t = BatteryConfig::default().shunt_ohms)]
+    pub battery_shunt_ohms: f32,
+
+    /// Battery voltage below which the LOW_BATTERY status bit is set (V)
+    #[arg(long, default_value_t = BatteryConfig::default().low_voltage)]
+    pub low_battery_volts: f32,
+
+    /// Ground station host:port; repeat or comma-separate to send every frame to several
+    /// (hostnames are resolved at startup and periodically after) [default: target_addr
+    /// from the config file, else 127.0.0.1:3000]
+    #[arg(long, value_delimiter = ',')]
+    pub target: Vec<String>,
+
+    /// Main loop period (ms) [default: send_interval_ms from the config file, else 100]
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub interval_ms: Option<u64>,
+
+    /// Use simulated data for every sensor, even on the flight computer, without
+    /// touching the I2C bus
+    #[arg(long)]
+    pub simulate: bool,
+
+    /// Treat every --target as an IPv4 multicast group (224.0.0.0/4) and send with this
+    /// TTL (1 stays on the local subnet). UDP only.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=255))]
+    pub multicast_ttl: Option<u32>,
+
+    /// Downlink transport for telemetry frames. With serial or file, each --target is a
+    /// device or file path.
+    #[arg(long, value_enum, default_value_t = TransportKind::Udp)]
+    pub transport: TransportKind,
+
+    /// Baud rate of the --transport serial radio modem [default: serial_baud from the
+    /// config file, else 9600]
+    #[arg(long, value_parser = serial::parse_baud_rate)]
+    pub serial_baud: Option<u32>,
+
+    /// Packet timestamp source: wall (Unix time) or monotonic (milliseconds since startup,
+    /// for a payload without an RTC) [default: clock from the config file, else wall]
+    #[arg(long)]
+    pub clock: Option<ClockSource>,
+
+    /// Resend each frame until the ground station acks it, for links where a complete
+    /// record matters more than latency (the receiver acks automatically). UDP only,
+    /// and not to multicast groups.
+    #[arg(long)]
+    pub reliable: bool,
+
+    /// With --reliable: wait for an ack before resending (ms)
+    #[arg(long, default_value_t = ReliableConfig::default().ack_timeout.as_millis() as u64, value_parser = clap::value_parser!(u64).range(1..))]
+    pub ack_timeout_ms: u64,
+
+    /// With --reliable: resends before a frame is given up
+    #[arg(long, default_value_t = ReliableConfig::default().max_retransmits)]
+    pub max_retransmits: u32,
+
+    /// Byte order of data packets and session headers (declared in each frame's envelope)
+    #[arg(long, value_enum, default_value_t = ByteOrder::Little)]
+    pub byte_order: ByteOrder,
+
+    /// Checksum appended to every frame (crc16 is CRC-16-CCITT, for radio modems and
+    /// ground software that expect it)
+    #[arg(long, value_enum, default_value_t = ChecksumKind::Crc32)]
+    pub checksum: ChecksumKind,
+
+    /// Bytes of --preamble-pattern sent ahead of every frame for a radio modem's bit
+    /// sync (unnecessary over UDP or TCP)
+    #[arg(long, default_value_t = 0)]
+    pub preamble_bytes: usize,
+
+    /// Preamble byte, in hex (must differ from the frame magic, 0xB7)
+    #[arg(long, default_value = "0xAA", value_parser = parse_hex_byte)]
+    pub preamble_pattern: u8,
+
+    /// COBS-encode every frame and end it with a 0x00 delimiter, so frames can be
+    /// recovered from a byte-stream link such as a serial radio
+    #[arg(long)]
+    pub cobs: bool,
+
+    /// Write every frame to stdout as a line of hex (logs move to stderr)
+    #[arg(long, group = "emit")]
+    pub emit_hex: bool,
+
+    /// Write every frame's raw bytes to stdout (logs move to stderr)
+    #[arg(long, group = "emit")]
+    pub emit_raw: bool,
+
+    /// Only write frames to stdout, without opening the downlink transport
+    #[arg(long, requires = "emit")]
+    pub emit_only: bool,
+
+    /// Also publish every frame on this Unix domain socket for local consumers
+    #[arg(long)]
+    pub local_socket: Option<PathBuf>,
+
+    /// InfluxDB v2 write URL (including org/bucket/precision=ns) to stream line protocol to
+    #[cfg(feature = "influx")]
+    #[arg(long)]
+    pub influx_url: Option<String>,
+
+    /// InfluxDB API token
+    #[cfg(feature = "influx")]
+    #[arg(long)]
+    pub influx_token: Option<String>,
+
+    /// InfluxDB measurement name
+    #[cfg(feature = "influx")]
+    #[arg(long, default_value = "balloon")]
+    pub influx_measurement: String,
+
+    /// MQTT broker (host[:port]) to publish every packet to
+    #[cfg(feature = "mqtt")]
+    #[arg(long)]
+    pub mqtt_broker: Option<String>,
+
+    /// MQTT topic for the packets
+    #[cfg(feature = "mqtt")]
+    #[arg(long, default_value = "balloon/telemetry")]
+    pub mqtt_topic: String,
+
+    /// MQTT quality of service: 0 (at most once), 1 (at least once) or 2 (exactly once)
+    #[cfg(feature = "mqtt")]
+    #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=2))]
+    pub mqtt_qos: u8,
+
+    /// MQTT payload encoding
+    #[cfg(feature = "mqtt")]
+    #[arg(long, value_enum, default_value_t = MqttEncoding::Json)]
+    pub mqtt_encoding: MqttEncoding,
+
+    /// Where to write the flight summary on shutdown
+    #[arg(long, default_value = "flight_summary.txt")]
+    pub summary_path: PathBuf,
+
+    /// Comma-separated fields to include in each frame (default: all)
+    #[arg(long, value_delimiter = ',')]
+    pub fields: Vec<Field>,
+
+    /// Smoothing window for the temperature rate of change (s)
+    #[arg(long, default_value_t = 30)]
+    pub temperature_rate_window: u64,
+
+    /// Only transmit when a reading changes beyond its threshold or the heartbeat interval elapses
+    #[arg(long)]
+    pub on_change: bool,
+
+    /// Maximum time between packets in on-change mode (ms)
+    #[arg(long, default_value_t = 5000)]
+    pub heartbeat_ms: u64,
+
+    /// On-change threshold for temperature (°C)
+    #[arg(long, default_value_t = ChangeThresholds::default().temperature)]
+    pub temperature_threshold: f32,
+
+    /// On-change threshold for humidity (%)
+    #[arg(long, default_value_t = ChangeThresholds::default().humidity)]
+    pub humidity_threshold: f32,
+
+    /// On-change threshold for altitude (m)
+    #[arg(long, default_value_t = ChangeThresholds::default().altitude)]
+    pub altitude_threshold: f32,
+
+    /// On-change threshold for latitude/longitude (degrees)
+    #[arg(long, default_value_t = ChangeThresholds::default().position)]
+    pub position_threshold: f32,
+
+    /// On-change threshold for any accelerometer axis (m/s²)
+    #[arg(long, default_value_t = ChangeThresholds::default().accel)]
+    pub accel_threshold: f32,
+
+    /// On-change threshold for any gyroscope axis (°/s)
+    #[arg(long, default_value_t = ChangeThresholds::default().gyro)]
+    pub gyro_threshold: f32,
+
+    /// Deviation of |accel| from 1g that counts as a launch spike (m/s²)
+    #[arg(long, default_value_t = PhaseThresholds::default().launch_accel)]
+    pub launch_accel: f32,
+
+    /// Climb rate above which the balloon is ascending (m/s)
+    #[arg(long, default_value_t = PhaseThresholds::default().ascent_rate)]
+    pub ascent_rate: f32,
+
+    /// Climb rate magnitude below which the balloon is floating (m/s)
+    #[arg(long, default_value_t = PhaseThresholds::default().float_rate)]
+    pub float_rate: f32,
+
+    /// Sharp negative climb rate indicating burst (m/s)
+    #[arg(long, default_value_t = PhaseThresholds::default().burst_rate, allow_negative_numbers = true)]
+    pub burst_rate: f32,
+
+    /// Sustained negative climb rate indicating descent (m/s)
+    #[arg(long, default_value_t = PhaseThresholds::default().descent_rate, allow_negative_numbers = true)]
+    pub descent_rate: f32,
+
+    /// Climb rate at or below which a sustained descent raises the descent alarm: every
+    /// frame is transmitted (overriding --on-change) and logging goes to full detail until landing (m/s)
+    #[arg(long, default_value_t = -5.0, allow_negative_numbers = true)]
+    pub descent_alarm_rate: f32,
+
+    /// Time-of-day transmit schedule: comma-separated START-END=SECONDS windows (UTC
+    /// hours, may wrap midnight) sending at most one packet per SECONDS, e.g. 20-6=60 for
+    /// a slow night beacon. Outside every window each packet is sent. The descent alarm
+    /// overrides the schedule.
+    #[arg(long, value_delimiter = ',')]
+    pub schedule: Vec<ScheduleWindow>,
+
+    /// Climb rate magnitude below which a descending payload has landed (m/s)
+    #[arg(long, default_value_t = PhaseThresholds::default().landed_rate)]
+    pub landed_rate: f32,
+
+    /// Height above the pad below which a stalled climb is still ascent, not float (m)
+    #[arg(long, default_value_t = PhaseThresholds::default().float_altitude)]
+    pub float_altitude: f32,
+
+    /// Height above the pad a landing must be within, allowing for higher ground than the pad (m)
+    #[arg(long, default_value_t = PhaseThresholds::default().landed_altitude)]
+    pub landed_altitude: f32,
+
+    /// Deviation of |accel| from 1g within which a descended payload counts as at rest (m/s²)
+    #[arg(long, default_value_t = PhaseThresholds::default().landed_accel)]
+    pub landed_accel: f32,
+
+    /// Consecutive samples a flight phase transition must persist
+    #[arg(long, default_value_t = PhaseThresholds::default().hold_samples)]
+    pub phase_hold_samples: u32,
+
+    /// Consecutive samples needed to declare burst
+    #[arg(long, default_value_t = PhaseThresholds::default().burst_samples)]
+    pub burst_samples: u32,
+
+    /// Fall below the peak altitude that sets BURST_DETECTED once it persists for
+    /// --phase-hold-samples samples (m)
+    #[arg(long, default_value_t = 100.0)]
+    pub burst_drop_m: f32,
+}
+
+impl Args {
+    pub fn change_thresholds(&self) -> ChangeThresholds {
+        ChangeThresholds {
+            temperature: self.temperature_threshold,
+            humidity: self.humidity_threshold,
+            altitude: self.altitude_threshold,
+            position: self.position_threshold,
+            accel: self.accel_threshold,
+            gyro: self.gyro_threshold,
+        }
+    }
+
+    pub fn phase_thresholds(&self) -> PhaseThresholds {
+        PhaseThresholds {
+            launch_accel: self.launch_accel,
+            ascent_rate: self.ascent_rate,
+            float_rate: self.float_rate,
+            burst_rate: self.burst_rate,
+            descent_rate: self.descent_rate,
+            landed_rate: self.landed_rate,
+            float_altitude: self.float_altitude,
+            landed_altitude: self.landed_altitude,
+            landed_accel: self.landed_accel,
+            hold_samples: self.phase_hold_samples,
+            burst_samples: self.burst_samples,
+        }
+    }
+
+    pub fn field_mask(&self) -> FieldMask {
+        if self.fields.is_empty() {
+            FieldMask::ALL
+        } else {
+            self.fields.iter().copied().collect()
+        }
+    }
+
+    // Settings the command line leaves to the config file
+    pub fn apply_config(&mut self, config: &Config) {
+        if self.target.is_empty() {
+            self.target = match self.transport {
+                TransportKind::Serial => vec![config.serial_device.clone()],
+                _ => config.target_addr.clone(),
+            };
+        }
+        self.interval_ms.get_or_insert(config.send_interval_ms);
+        self.serial_baud.get_or_insert(config.serial_baud);
+        self.clock.get_or_insert(config.clock);
+    }
+
+    pub fn reliable_config(&self) -> Option<ReliableConfig> {
+        self.reliable.then(|| ReliableConfig {
+            ack_timeout: Duration::from_millis(self.ack_timeout_ms),
+            max_retransmits: self.max_retransmits,
+        })
+    }
+
+    pub fn serial_baud(&self) -> u32 {
+        self.serial_baud.unwrap_or(DEFAULT_BAUD_RATE)
+    }
+
+    pub fn clock(&self) -> ClockSource {
+        self.clock.unwrap_or_default()
+    }
+
+    pub fn send_interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms.unwrap_or(DEFAULT_SEND_INTERVAL_MS))
+    }
+
+    pub fn sensor_config(&self) -> SensorConfig {
+        SensorConfig {
+            read_budget: Duration::from_millis(self.sensor_timeout_ms),
+            baro_conversion_delay: Duration::from_millis(self.baro_conversion_delay_ms),
+            baro_oversampling: self.baro_oversampling,
+            baro_filter_window: self.baro_filter_window,
+            peak_sample_interval: (self.peak_sample_ms > 0).then(|| Duration::from_millis(self.peak_sample_ms)),
+            black_box_window: Duration::from_secs(self.black_box_seconds),
+            battery: BatteryConfig {
+                monitor: self.battery_monitor.into(),
+                channel: self.battery_channel,
+                gain: Gain::from_millivolts(self.battery_range_mv).expect("validated by parse_gain_mv"),
+                divider_ratio: self.battery_divider,
+                shunt_ohms: self.battery_shunt_ohms,
+                low_voltage: self.low_battery_volts,
+            },
+            axis_map: self.imu_axes,
+            dlpf: self.imu_dlpf_hz,
+            motion_init_attempts: self.imu_init_attempts,
+            motion_init_retry_delay: Duration::from_millis(self.imu_init_retry_ms),
+            freefall_threshold: self.freefall_threshold,
+            magnetic_declination: self.magnetic_declination,
+            sea_level_hpa: self.sea_level_hpa,
+            field_elevation_m: self.field_elevation_m,
+            gps_device: self.gps_device.clone(),
+            gps_baud: self.gps_baud,
+            simulate: self.simulate,
+        }
+    }
+
+    pub fn emit_format(&self) -> Option<EmitFormat> {
+        if self.emit_hex {
+            Some(EmitFormat::Hex)
+        } else if self.emit_raw {
+            Some(EmitFormat::Raw)
+        } else {
+            None
+        }
+    }
+
+    #[cfg(feature = "mqtt")]
+    pub fn mqtt_config(&self) -> Option<MqttConfig> {
+        self.mqtt_broker.clone().map(|broker| MqttConfig {
+            broker,
+            topic: self.mqtt_topic.clone(),
+            qos: self.mqtt_qos,
+            encoding: self.mqtt_encoding.into(),
+        })
+    }
+
+    pub fn heartbeat_interval(&self) -> Duration {
+        Duration::from_millis(self.heartbeat_ms)
+    }
+}
+
+fn parse_hex_byte(value: &str) -> Result<u8, String> {
+    let digits = value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")).unwrap_or(value);
+    u8::from_str_radix(digits, 16).map_err(|_| format!("'{}' is not a hex byte, e.g. 0xAA", value))
+}
+
+fn parse_sea_level_hpa(value: &str) -> Result<f32, String> {
+    let hpa: f32 = value.parse().map_err(|_| format!("'{}' is not a pressure in hPa", value))?;
+    altitude::validate_sea_level(hpa)
+}
+
+fn parse_gain_mv(value: &str) -> Result<u16, String> {
+    let millivolts: u16 = value.parse().map_err(|_| format!("'{}' is not a number of millivolts", value))?;
+    Gain::from_millivolts(millivolts)
+        .map(|_| millivolts)
+        .ok_or_else(|| "expected one of 6144, 4096, 2048, 1024, 512, 256".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(argv: &[&str]) -> Args {
+        let mut args = Args::try_parse_from(["balloon-software"].iter().chain(argv)).unwrap();
+        args.apply_config(&Config { target_addr: vec!["config.local:4000".to_string()], ..Config::default() });
+        args
+    }
+
+    #[test]
+    fn command_line_overrides_the_config_file() {
+        let args = parse(&["--target", "ground.example.org:3000", "--interval-ms", "250", "--simulate"]);
+        assert_eq!(args.target, ["ground.example.org:3000"]);
+        assert_eq!(args.send_interval(), Duration::from_millis(250));
+        assert!(args.sensor_config().simulate);
+
+        let args = parse(&[]);
+        assert_eq!(args.target, ["config.local:4000"]);
+        assert_eq!(args.send_interval(), Duration::from_millis(DEFAULT_SEND_INTERVAL_MS));
+        assert!(!args.sensor_config().simulate);
+
+        assert!(Args::try_parse_from(["balloon-software", "--interval-ms", "0"]).is_err());
+    }
+}
@@ -0,0 +1,84 @@
+// Source of the packet timestamp. Wall-clock time lines up with the ground station's
+// logs, but a Pi without an RTC or network boots at whatever time it last saved and
+// steps when NTP finally syncs. The monotonic clock counts milliseconds from startup
+// instead: meaningless as a date, but it never jumps.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClockSource {
+    #[default]
+    Wall,      // Milliseconds since the Unix epoch
+    Monotonic, // Milliseconds since the clock was created at startup
+}
+
+impl FromStr for ClockSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "wall" => Ok(ClockSource::Wall),
+            "monotonic" => Ok(ClockSource::Monotonic),
+            other => Err(format!("Invalid clock '{}' (wall or monotonic)", other)),
+        }
+    }
+}
+
+impl fmt::Display for ClockSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ClockSource::Wall => "wall",
+            ClockSource::Monotonic => "monotonic",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Clock {
+    source: ClockSource,
+    start: Instant,
+}
+
+impl Clock {
+    pub fn new(source: ClockSource) -> Self {
+        Self { source, start: Instant::now() }
+    }
+
+    pub fn source(&self) -> ClockSource {
+        self.source
+    }
+
+    pub fn now_ms(&self) -> u64 {
+        match self.source {
+            ClockSource::Wall => unix_time_ms(),
+            ClockSource::Monotonic => self.start.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+// Milliseconds since the Unix epoch, 0 if the clock is set before it
+pub fn unix_time_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |t| t.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn monotonic_clock_counts_from_creation() {
+        let clock = Clock::new(ClockSource::Monotonic);
+        assert!(clock.now_ms() < 50);
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(clock.now_ms() >= 20);
+
+        // Well past 2020 in Unix milliseconds
+        assert!(Clock::new(ClockSource::Wall).now_ms() > 1_577_836_800_000);
+        assert_eq!("monotonic".parse(), Ok(ClockSource::Monotonic));
+        assert!("rtc".parse::<ClockSource>().is_err());
+    }
+}
@@ -0,0 +1,170 @@
+// Consistent Overhead Byte Stuffing, for byte-stream links (a UART radio) where a frame
+// boundary has to be recoverable from anywhere in the stream. Encoding removes every
+// 0x00 from the frame at a cost of one byte per 254, so a 0x00 after each frame marks
+// its end unambiguously.
+
+use std::fmt;
+use std::io;
+
+use crate::transport::Transport;
+
+pub const DELIMITER: u8 = 0x00;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CobsError {
+    ZeroByte(usize), // Offset of a 0x00 inside the encoded data
+    Truncated,       // The last block is shorter than its code byte says
+}
+
+impl fmt::Display for CobsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CobsError::ZeroByte(offset) => write!(f, "zero byte at offset {} of a COBS frame", offset),
+            CobsError::Truncated => f.write_str("truncated COBS frame"),
+        }
+    }
+}
+
+impl std::error::Error for CobsError {}
+
+// Without the trailing delimiter
+pub fn encode(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + data.len() / 254 + 2);
+    let mut code_at = 0;
+    let mut code = 1u8;
+    out.push(0); // Code byte of the first block, filled in once its length is known
+    for &byte in data {
+        if byte != 0 {
+            out.push(byte);
+            code += 1;
+        }
+        if byte == 0 || code == 0xFF {
+            out[code_at] = code;
+            code_at = out.len();
+            out.push(0);
+            code = 1;
+        }
+    }
+    out[code_at] = code;
+    out
+}
+
+// One encoded frame, delimiter already removed
+pub fn decode(data: &[u8]) -> Result<Vec<u8>, CobsError> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut at = 0;
+    while at < data.len() {
+        let code = data[at] as usize;
+        if code == 0 {
+            return Err(CobsError::ZeroByte(at));
+        }
+        let block = data.get(at + 1..at + code).ok_or(CobsError::Truncated)?;
+        if let Some(zero) = block.iter().position(|&b| b == 0) {
+            return Err(CobsError::ZeroByte(at + 1 + zero));
+        }
+        out.extend_from_slice(block);
+        at += code;
+        // A full 254-byte block has no implied zero after it, nor does the last block
+        if code < 0xFF && at < data.len() {
+            out.push(0);
+        }
+    }
+    Ok(out)
+}
+
+// Encodes every frame sent through `inner` and ends it with DELIMITER
+pub struct CobsTransport {
+    inner: Box<dyn Transport>,
+}
+
+impl CobsTransport {
+    pub fn new(inner: Box<dyn Transport>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Transport for CobsTransport {
+    // Returns the frame bytes sent, not counting the stuffing
+    fn send(&mut self, frame: &[u8]) -> io::Result<usize> {
+        let mut encoded = encode(frame);
+        encoded.push(DELIMITER);
+        self.inner.send(&encoded)?;
+        Ok(frame.len())
+    }
+}
+
+// Receiver side: reassembles frames from a stream read in arbitrary pieces
+#[derive(Debug, Default)]
+pub struct StreamDecoder {
+    pending: Vec<u8>, // Since the last delimiter
+}
+
+impl StreamDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Every frame completed by `bytes`; empty frames (repeated delimiters) are skipped
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Result<Vec<u8>, CobsError>> {
+        let mut frames = Vec::new();
+        for &byte in bytes {
+            if byte == DELIMITER {
+                if !self.pending.is_empty() {
+                    frames.push(decode(&self.pending));
+                    self.pending.clear();
+                }
+            } else {
+                self.pending.push(byte);
+            }
+        }
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_embedded_zeros_and_empty_input() {
+        assert_eq!(encode(&[]), [0x01]);
+        assert_eq!(decode(&[0x01]), Ok(vec![]));
+        // Reference vectors from the COBS paper / Wikipedia
+        assert_eq!(encode(&[0x00]), [0x01, 0x01]);
+        assert_eq!(encode(&[0x00, 0x00]), [0x01, 0x01, 0x01]);
+        assert_eq!(encode(&[0x11, 0x22, 0x00, 0x33]), [0x03, 0x11, 0x22, 0x02, 0x33]);
+        assert_eq!(encode(&[0x11, 0x00, 0x00, 0x00]), [0x02, 0x11, 0x01, 0x01, 0x01]);
+
+        let packet = crate::packet::TelemetryPacket::new(0); // Mostly zeros
+        let mut long: Vec<u8> = (1..=255).collect();
+        long.extend_from_slice(&[0, 0, 7]);
+        for data in [packet.to_le_bytes().to_vec(), long, vec![0xAB; 254], vec![0; 300]] {
+            let encoded = encode(&data);
+            assert!(!encoded.contains(&0));
+            assert_eq!(decode(&encoded), Ok(data));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_frames() {
+        assert_eq!(decode(&[0x03, 0x11]), Err(CobsError::Truncated));
+        assert_eq!(decode(&[0x03, 0x00, 0x11]), Err(CobsError::ZeroByte(1)));
+        assert_eq!(decode(&[0x02, 0x11, 0x00]), Err(CobsError::ZeroByte(2)));
+    }
+
+    #[test]
+    fn stream_decoder_splits_on_delimiters() {
+        let mut stream = Vec::new();
+        for frame in [&[1u8, 0, 2][..], &[0, 0], &[9; 10]] {
+            stream.extend(encode(frame));
+            stream.push(DELIMITER);
+            stream.push(DELIMITER); // Idle fill between frames
+        }
+
+        let mut decoder = StreamDecoder::new();
+        let (first, rest) = stream.split_at(4); // Mid-frame
+        assert!(decoder.push(first).is_empty());
+        let frames: Vec<Vec<u8>> = decoder.push(rest).into_iter().map(Result::unwrap).collect();
+        assert_eq!(frames, [vec![1, 0, 2], vec![0, 0], vec![9; 10]]);
+    }
+}
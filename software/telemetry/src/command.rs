@@ -0,0 +1,336 @@
+// Uplink commands from the ground station, and the acks sent back for them
+//
+// Command layout (little-endian): COMMAND_SYNC u64 | opcode u8 | opcode-specific payload
+// Ack layout (little-endian):     ACK_SYNC u64 | opcode u8 | AckStatus u8 | reply payload
+//
+// Opcode  Command               Payload                       Reply payload (on Ok)
+// 0x01    SetSeaLevelPressure   f32 hPa                       -
+// 0x02    SetTransmitInterval   u32 ms, 0 = back to schedule  -
+// 0x03    QueryStats            -                             StatsReply
+// 0x04    ResendHeader          -                             -
+//
+// Every command gets exactly one ack, including ones rejected for a bad argument or an
+// unknown opcode. Datagrams without the sync word get none.
+
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::Duration;
+use tracing::warn;
+
+// Sync word identifying an uplink command
+pub const COMMAND_SYNC: u64 = 0xFF_FF_FF_FF_FF_FF_FF_FB;
+
+// Sync word identifying an ack
+pub const ACK_SYNC: u64 = 0xFF_FF_FF_FF_FF_FF_FF_FA;
+
+// Longest interval SetTransmitInterval accepts; beyond this the ground could lose
+// track of the payload entirely
+pub const MAX_TRANSMIT_INTERVAL: Duration = Duration::from_secs(3600);
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    SetSeaLevelPressure = 0x01,
+    SetTransmitInterval = 0x02,
+    QueryStats = 0x03,
+    ResendHeader = 0x04,
+}
+
+impl Opcode {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x01 => Some(Opcode::SetSeaLevelPressure),
+            0x02 => Some(Opcode::SetTransmitInterval),
+            0x03 => Some(Opcode::QueryStats),
+            0x04 => Some(Opcode::ResendHeader),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    SetSeaLevelPressure { hpa: f32 },
+    SetTransmitInterval { interval: Option<Duration> }, // None returns to the --schedule
+    QueryStats,
+    ResendHeader,
+}
+
+impl Command {
+    pub fn opcode(&self) -> Opcode {
+        match self {
+            Command::SetSeaLevelPressure { .. } => Opcode::SetSeaLevelPressure,
+            Command::SetTransmitInterval { .. } => Opcode::SetTransmitInterval,
+            Command::QueryStats => Opcode::QueryStats,
+            Command::ResendHeader => Opcode::ResendHeader,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = COMMAND_SYNC.to_le_bytes().to_vec();
+        bytes.push(self.opcode() as u8);
+        match self {
+            Command::SetSeaLevelPressure { hpa } => bytes.extend_from_slice(&hpa.to_le_bytes()),
+            Command::SetTransmitInterval { interval } => {
+                let ms = interval.map_or(0, |interval| interval.as_millis() as u32);
+                bytes.extend_from_slice(&ms.to_le_bytes());
+            }
+            Command::QueryStats | Command::ResendHeader => {}
+        }
+        bytes
+    }
+
+    pub fn parse(buf: &[u8]) -> Result<Self, String> {
+        let opcode = command_opcode(buf).ok_or("Not a command")?;
+        let opcode = Opcode::from_u8(opcode).ok_or_else(|| format!("Unknown opcode 0x{:02X}", opcode))?;
+        let payload = &buf[9..];
+        let expect = |len: usize| {
+            if payload.len() == len {
+                Ok(())
+            } else {
+                Err(format!("{:?} expects {} payload bytes, got {}", opcode, len, payload.len()))
+            }
+        };
+
+        match opcode {
+            Opcode::SetSeaLevelPressure => {
+                expect(4)?;
+                Ok(Command::SetSeaLevelPressure { hpa: f32::from_le_bytes(payload.try_into().unwrap()) })
+            }
+            Opcode::SetTransmitInterval => {
+                expect(4)?;
+                let ms = u32::from_le_bytes(payload.try_into().unwrap());
+                let interval = Duration::from_millis(ms as u64);
+                if interval > MAX_TRANSMIT_INTERVAL {
+                    return Err(format!("transmit interval {} ms is longer than the {:?} maximum", ms, MAX_TRANSMIT_INTERVAL));
+                }
+                Ok(Command::SetTransmitInterval { interval: (ms > 0).then_some(interval) })
+            }
+            Opcode::QueryStats => expect(0).map(|_| Command::QueryStats),
+            Opcode::ResendHeader => expect(0).map(|_| Command::ResendHeader),
+        }
+    }
+}
+
+// The opcode byte of a datagram carrying the command sync word, known or not
+fn command_opcode(buf: &[u8]) -> Option<u8> {
+    (buf.len() >= 9 && buf[..8] == COMMAND_SYNC.to_le_bytes()).then(|| buf[8])
+}
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AckStatus {
+    Ok = 0x00,
+    Rejected = 0x01,      // Bad payload or argument out of range
+    UnknownOpcode = 0x02,
+}
+
+impl AckStatus {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x00 => Some(AckStatus::Ok),
+            0x01 => Some(AckStatus::Rejected),
+            0x02 => Some(AckStatus::UnknownOpcode),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ack {
+    pub opcode: u8, // Raw, so unknown opcodes can be acked too
+    pub status: AckStatus,
+    pub payload: Vec<u8>,
+}
+
+impl Ack {
+    pub fn ok(opcode: Opcode) -> Self {
+        Self { opcode: opcode as u8, status: AckStatus::Ok, payload: Vec::new() }
+    }
+
+    pub fn rejected(opcode: Opcode) -> Self {
+        Self { status: AckStatus::Rejected, ..Self::ok(opcode) }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = ACK_SYNC.to_le_bytes().to_vec();
+        bytes.push(self.opcode);
+        bytes.push(self.status as u8);
+        bytes.extend_from_slice(&self.payload);
+        bytes
+    }
+
+    pub fn parse(buf: &[u8]) -> Result<Self, String> {
+        if buf.len() < 10 || buf[..8] != ACK_SYNC.to_le_bytes() {
+            return Err("Not an ack".to_string());
+        }
+        let status = AckStatus::from_u8(buf[9]).ok_or_else(|| format!("Unknown ack status 0x{:02X}", buf[9]))?;
+        Ok(Self { opcode: buf[8], status, payload: buf[10..].to_vec() })
+    }
+}
+
+// Reply payload for QueryStats. Layout (little-endian):
+// packets_sent u64 | send_errors u64 | uptime_s u32
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatsReply {
+    pub packets_sent: u64,
+    pub send_errors: u64,
+    pub uptime_s: u32,
+}
+
+impl StatsReply {
+    pub const LEN: usize = 20;
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::LEN);
+        bytes.extend_from_slice(&self.packets_sent.to_le_bytes());
+        bytes.extend_from_slice(&self.send_errors.to_le_bytes());
+        bytes.extend_from_slice(&self.uptime_s.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        if buf.len() != Self::LEN {
+            return None;
+        }
+        Some(Self {
+            packets_sent: u64::from_le_bytes(buf[0..8].try_into().ok()?),
+            send_errors: u64::from_le_bytes(buf[8..16].try_into().ok()?),
+            uptime_s: u32::from_le_bytes(buf[16..20].try_into().ok()?),
+        })
+    }
+}
+
+// Non-blocking UDP listener polled once per loop iteration
+pub struct CommandListener {
+    socket: UdpSocket,
+}
+
+impl CommandListener {
+    pub fn bind(addr: &str) -> io::Result<Self> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    // Next well-formed command, if any. Malformed datagrams are logged and skipped;
+    // malformed commands are also acked as rejected.
+    pub fn poll(&self) -> Option<(Command, SocketAddr)> {
+        let mut buf = [0u8; 256];
+        loop {
+            match self.socket.recv_from(&mut buf) {
+                Ok((len, from)) => match Command::parse(&buf[..len]) {
+                    Ok(command) => return Some((command, from)),
+                    Err(e) => {
+                        warn!("Ignoring uplink datagram from {}: {}", from, e);
+                        if let Some(opcode) = command_opcode(&buf[..len]) {
+                            let status = match Opcode::from_u8(opcode) {
+                                Some(_) => AckStatus::Rejected,
+                                None => AckStatus::UnknownOpcode,
+                            };
+                            self.reply(&Ack { opcode, status, payload: Vec::new() }, from);
+                        }
+                    }
+                },
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return None,
+                Err(e) => {
+                    warn!("Failed to receive command: {}", e);
+                    return None;
+                }
+            }
+        }
+    }
+
+    pub fn reply(&self, ack: &Ack, to: SocketAddr) {
+        if let Err(e) = self.socket.send_to(&ack.to_bytes(), to) {
+            warn!("Failed to send ack to {}: {}", to, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_round_trip() {
+        for command in [
+            Command::SetSeaLevelPressure { hpa: 1008.7 },
+            Command::SetTransmitInterval { interval: Some(Duration::from_secs(30)) },
+            Command::SetTransmitInterval { interval: None },
+            Command::QueryStats,
+            Command::ResendHeader,
+        ] {
+            assert_eq!(Command::parse(&command.to_bytes()), Ok(command));
+        }
+
+        let stats = StatsReply { packets_sent: 1200, send_errors: 3, uptime_s: 3600 };
+        let ack = Ack { payload: stats.to_bytes(), ..Ack::ok(Opcode::QueryStats) };
+        let parsed = Ack::parse(&ack.to_bytes()).unwrap();
+        assert_eq!(parsed, ack);
+        assert_eq!(StatsReply::from_bytes(&parsed.payload), Some(stats));
+    }
+
+    #[test]
+    fn rejects_malformed_commands() {
+        let mut bytes = Command::SetSeaLevelPressure { hpa: 1000.0 }.to_bytes();
+        assert!(Command::parse(&bytes[..bytes.len() - 1]).is_err());
+
+        bytes[8] = 0xEE;
+        assert_eq!(Command::parse(&bytes), Err("Unknown opcode 0xEE".to_string()));
+
+        bytes[0] = 0;
+        assert!(Command::parse(&bytes).is_err());
+
+        let mut too_slow = Command::SetTransmitInterval { interval: Some(MAX_TRANSMIT_INTERVAL) }.to_bytes();
+        assert!(Command::parse(&too_slow).is_ok());
+        too_slow[9..].copy_from_slice(&(MAX_TRANSMIT_INTERVAL.as_millis() as u32 + 1).to_le_bytes());
+        assert!(Command::parse(&too_slow).is_err());
+
+        let mut query = Command::QueryStats.to_bytes();
+        query.push(0);
+        assert!(Command::parse(&query).is_err());
+    }
+
+    #[test]
+    fn listener_receives_without_blocking() {
+        let listener = CommandListener::bind("127.0.0.1:0").unwrap();
+        assert!(listener.poll().is_none());
+
+        let ground = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let target = listener.local_addr().unwrap();
+        ground.send_to(b"garbage", target).unwrap();
+        ground.send_to(&Command::SetSeaLevelPressure { hpa: 990.0 }.to_bytes(), target).unwrap();
+
+        // Give the loopback datagrams a moment to land
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let (command, from) = listener.poll().unwrap();
+        assert_eq!(command, Command::SetSeaLevelPressure { hpa: 990.0 });
+        assert_eq!(from, ground.local_addr().unwrap());
+    }
+
+    #[test]
+    fn malformed_commands_are_acked() {
+        let listener = CommandListener::bind("127.0.0.1:0").unwrap();
+        let ground = UdpSocket::bind("127.0.0.1:0").unwrap();
+        ground.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let target = listener.local_addr().unwrap();
+
+        let mut unknown = Command::ResendHeader.to_bytes();
+        unknown[8] = 0xEE;
+        ground.send_to(&unknown, target).unwrap();
+        ground.send_to(&Command::SetTransmitInterval { interval: None }.to_bytes()[..10], target).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert!(listener.poll().is_none());
+
+        let mut buf = [0u8; 64];
+        let len = ground.recv(&mut buf).unwrap();
+        assert_eq!(Ack::parse(&buf[..len]).unwrap(), Ack { opcode: 0xEE, status: AckStatus::UnknownOpcode, payload: Vec::new() });
+        let len = ground.recv(&mut buf).unwrap();
+        assert_eq!(Ack::parse(&buf[..len]).unwrap(), Ack::rejected(Opcode::SetTransmitInterval));
+    }
+}
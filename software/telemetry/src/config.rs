@@ -0,0 +1,164 @@
+// Site settings read from a TOML file at startup, so pointing the payload at a different
+// ground station doesn't need a rebuild or a long command line. The file is found
+// through $BALLOON_CONFIG, else balloon.toml in the working directory; without one the
+// defaults below apply. Every key is optional:
+//
+//   target_addr = "ground.local:3000"  # Ground station host:port (--target overrides),
+//                                      # or a list: ["base.local:3000", "chase.local:3000"]
+//   bind_addr = "0.0.0.0:0"            # Local address the UDP downlink sends from
+//   send_interval_ms = 100             # Main loop period, > 0
+//   serial_device = "/dev/serial0"     # Radio modem for --transport serial (--target overrides)
+//   serial_baud = 9600                 # Its baud rate (--serial-baud overrides)
+//   clock = "wall"                     # Packet timestamps: "wall", or "monotonic" since
+//                                      # startup for a payload without an RTC (--clock overrides)
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::de::{Deserializer, Error as _};
+use serde::Deserialize;
+
+use crate::clock::ClockSource;
+use crate::serial::{self, DEFAULT_BAUD_RATE, DEFAULT_SERIAL_DEVICE};
+
+pub const CONFIG_ENV: &str = "BALLOON_CONFIG";
+pub const DEFAULT_CONFIG_PATH: &str = "balloon.toml";
+
+pub const DEFAULT_TARGET: &str = "127.0.0.1:3000";
+pub const DEFAULT_BIND: &str = "0.0.0.0:0"; // Any interface, ephemeral port
+pub const DEFAULT_SEND_INTERVAL_MS: u64 = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    #[serde(deserialize_with = "one_or_many")]
+    pub target_addr: Vec<String>, // Every frame goes to each
+    pub bind_addr: String,
+    pub send_interval_ms: u64,
+    pub serial_device: String,
+    pub serial_baud: u32,
+    pub clock: ClockSource,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            target_addr: vec![DEFAULT_TARGET.to_string()],
+            bind_addr: DEFAULT_BIND.to_string(),
+            send_interval_ms: DEFAULT_SEND_INTERVAL_MS,
+            serial_device: DEFAULT_SERIAL_DEVICE.to_string(),
+            serial_baud: DEFAULT_BAUD_RATE,
+            clock: ClockSource::Wall,
+        }
+    }
+}
+
+impl Config {
+    // $BALLOON_CONFIG if set, else DEFAULT_CONFIG_PATH
+    pub fn path() -> PathBuf {
+        std::env::var_os(CONFIG_ENV).map_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH), PathBuf::from)
+    }
+
+    // None if the file doesn't exist; any other failure to read or parse it is an error
+    // rather than a silent fallback to the defaults
+    pub fn load(path: &Path) -> Result<Option<Self>, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text).map(Some).map_err(|e| format!("{}: {}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let config: Self = toml::from_str(text).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.send_interval_ms == 0 {
+            return Err("send_interval_ms must be greater than 0".to_string());
+        }
+        if self.target_addr.is_empty() || self.target_addr.iter().any(String::is_empty) {
+            return Err("target_addr must not be empty".to_string());
+        }
+        serial::validate_baud_rate(self.serial_baud)?;
+        Ok(())
+    }
+
+    pub fn send_interval(&self) -> Duration {
+        Duration::from_millis(self.send_interval_ms)
+    }
+}
+
+// target_addr as a single string or a list of them
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Targets {
+        One(String),
+        Many(Vec<String>),
+    }
+    Targets::deserialize(deserializer)
+        .map(|targets| match targets {
+            Targets::One(target) => vec![target],
+            Targets::Many(targets) => targets,
+        })
+        .map_err(|_| D::Error::custom("target_addr must be a string or array of strings"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_sample_config() {
+        let config = Config::parse(r#"
+            # Ground test at the launch site
+            target_addr = "ground.local:3000"   # Laptop in the chase car
+            bind_addr = "192.168.4.2:0"
+            send_interval_ms = 1_000
+            serial_device = "/dev/ttyUSB0"    # RFD900 on the USB hub
+            serial_baud = 57600
+            clock = "monotonic"               # No RTC on this Pi
+        "#).unwrap();
+        assert_eq!(config, Config {
+            target_addr: vec!["ground.local:3000".to_string()],
+            bind_addr: "192.168.4.2:0".to_string(),
+            send_interval_ms: 1000,
+            serial_device: "/dev/ttyUSB0".to_string(),
+            serial_baud: 57600,
+            clock: ClockSource::Monotonic,
+        });
+        assert_eq!(config.send_interval(), Duration::from_secs(1));
+
+        // Keys left out keep their defaults
+        let partial = Config::parse("target_addr = \"10.0.0.5:4000\" # a#b\n").unwrap();
+        assert_eq!(partial, Config { target_addr: vec!["10.0.0.5:4000".to_string()], ..Config::default() });
+
+        // Base station and chase car
+        let both = Config::parse(r#"target_addr = ["base.local:3000", "10.0.0.7:3000",] # "x,y""#).unwrap();
+        assert_eq!(both.target_addr, ["base.local:3000", "10.0.0.7:3000"]);
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn rejects_bad_configs() {
+        assert!(Config::parse("send_interval_ms = 0").unwrap_err().contains("greater than 0"));
+        assert!(Config::parse("send_interval_ms = \"100\"").is_err());
+        assert!(Config::parse("target_addr = ground.local:3000").is_err());
+        assert!(Config::parse("target_addr = []").unwrap_err().contains("must not be empty"));
+        assert!(Config::parse("target_addr = [\"a:1\" \"b:2\"]").is_err());
+        assert!(Config::parse("target = \"ground.local:3000\"").unwrap_err().contains("unknown field"));
+        assert!(Config::parse("bind_addr = \"a\"\nbind_addr = \"b\"").unwrap_err().contains("duplicate"));
+        assert!(Config::parse("[downlink]").is_err());
+        assert!(Config::parse("send_interval_ms = -100").is_err());
+        assert!(Config::parse("serial_baud = 1000").unwrap_err().contains("unsupported baud rate"));
+        assert!(Config::parse("clock = \"rtc\"").is_err());
+
+        let missing = std::env::temp_dir().join("balloon_config_test_missing.toml");
+        assert_eq!(Config::load(&missing), Ok(None));
+    }
+}
@@ -0,0 +1,232 @@
+// Conversions between the packet's decimal degrees and the sexagesimal forms ground
+// trackers and APRS use. The wire format stays decimal; these are for display and for
+// encoders downstream. PositionGuard keeps outgoing coordinates in range.
+
+use std::fmt;
+
+use crate::packet::{StatusFlags, TelemetryPacket};
+
+// Degrees, minutes and seconds. The sign rides on the degrees; a coordinate between 0°
+// and -1° has no negative degree to carry it, so use Dms (or format_latitude/longitude)
+// where that matters.
+pub fn to_dms(deg: f32) -> (i32, u32, f32) {
+    let dms = Dms::from_degrees(deg);
+    let degrees = dms.degrees as i32;
+    (if dms.negative { -degrees } else { degrees }, dms.minutes, dms.seconds)
+}
+
+// Inverse of to_dms(); minutes and seconds take the sign of `degrees`
+pub fn from_dms(degrees: i32, minutes: u32, seconds: f32) -> f32 {
+    let magnitude = degrees.unsigned_abs() as f64 + minutes as f64 / 60.0 + seconds as f64 / 3600.0;
+    (if degrees < 0 { -magnitude } else { magnitude }) as f32
+}
+
+// Degrees and decimal minutes (APRS, NMEA), signed like to_dms()
+pub fn to_degrees_minutes(deg: f32) -> (i32, f32) {
+    let magnitude = (deg as f64).abs();
+    let degrees = magnitude.trunc();
+    let minutes = ((magnitude - degrees) * 60.0) as f32;
+    (if deg < 0.0 { -(degrees as i32) } else { degrees as i32 }, minutes)
+}
+
+pub fn from_degrees_minutes(degrees: i32, minutes: f32) -> f32 {
+    let magnitude = degrees.unsigned_abs() as f64 + minutes as f64 / 60.0;
+    (if degrees < 0 { -magnitude } else { magnitude }) as f32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dms {
+    pub negative: bool, // South or west
+    pub degrees: u32,
+    pub minutes: u32,
+    pub seconds: f32,
+}
+
+impl Dms {
+    pub fn from_degrees(deg: f32) -> Self {
+        // f64 so the seconds of an f32 coordinate aren't eaten by rounding
+        let total_seconds = (deg as f64).abs() * 3600.0;
+        let degrees = (total_seconds / 3600.0).floor();
+        let minutes = ((total_seconds - degrees * 3600.0) / 60.0).floor();
+        let seconds = total_seconds - degrees * 3600.0 - minutes * 60.0;
+        Self { negative: deg < 0.0, degrees: degrees as u32, minutes: minutes as u32, seconds: seconds as f32 }
+    }
+
+    pub fn to_degrees(self) -> f32 {
+        let magnitude = self.degrees as f64 + self.minutes as f64 / 60.0 + self.seconds as f64 / 3600.0;
+        (if self.negative { -magnitude } else { magnitude }) as f32
+    }
+
+    // Rounded to tenths of a second, carrying into minutes and degrees
+    fn rounded(self) -> (u32, u32, f32) {
+        let mut tenths = (self.seconds as f64 * 10.0).round() as u32;
+        let (mut degrees, mut minutes) = (self.degrees, self.minutes);
+        if tenths >= 600 {
+            tenths -= 600;
+            minutes += 1;
+        }
+        if minutes >= 60 {
+            minutes -= 60;
+            degrees += 1;
+        }
+        (degrees, minutes, tenths as f32 / 10.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoordinateFormat {
+    Decimal, // -33.86880 (five places, about the resolution of an f32 longitude)
+    Dms,     // 33°52'07.7"S
+}
+
+struct Coordinate {
+    degrees: f32,
+    format: CoordinateFormat,
+    hemispheres: [char; 2], // Positive, negative
+}
+
+impl fmt::Display for Coordinate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.format {
+            CoordinateFormat::Decimal => write!(f, "{:.5}", self.degrees),
+            CoordinateFormat::Dms => {
+                let dms = Dms::from_degrees(self.degrees);
+                let (degrees, minutes, seconds) = dms.rounded();
+                let hemisphere = self.hemispheres[dms.negative as usize];
+                write!(f, "{}°{:02}'{:04.1}\"{}", degrees, minutes, seconds, hemisphere)
+            }
+        }
+    }
+}
+
+pub fn format_latitude(deg: f32, format: CoordinateFormat) -> String {
+    Coordinate { degrees: deg, format, hemispheres: ['N', 'S'] }.to_string()
+}
+
+pub fn format_longitude(deg: f32, format: CoordinateFormat) -> String {
+    Coordinate { degrees: deg, format, hemispheres: ['E', 'W'] }.to_string()
+}
+
+// Latitude doesn't wrap: past a pole is a glitch, so hold it at the pole
+pub fn clamp_latitude(deg: f32) -> f32 {
+    deg.clamp(-90.0, 90.0)
+}
+
+// Into [-180, 180); longitude is cyclic, so 181° is -179°, not 180°
+pub fn wrap_longitude(deg: f32) -> f32 {
+    let wrapped = ((deg as f64 + 180.0).rem_euclid(360.0) - 180.0) as f32;
+    // rem_euclid of a tiny negative value rounds up to 360
+    if wrapped >= 180.0 { wrapped - 360.0 } else { wrapped }
+}
+
+// Keeps every packet's position in range for map consumers. A NaN or infinite
+// coordinate is replaced by the last valid fix and flagged POSITION_STALE.
+#[derive(Debug, Clone, Default)]
+pub struct PositionGuard {
+    last_fix: Option<(f32, f32)>,
+}
+
+impl PositionGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn apply(&mut self, packet: &mut TelemetryPacket) {
+        let (latitude, longitude) = (packet.latitude, packet.longitude);
+        if latitude.is_finite() && longitude.is_finite() {
+            let fix = (clamp_latitude(latitude), wrap_longitude(longitude));
+            (packet.latitude, packet.longitude) = fix;
+            self.last_fix = Some(fix);
+        } else {
+            // With no fix yet there's nothing better to send than the bad value
+            if let Some((latitude, longitude)) = self.last_fix {
+                (packet.latitude, packet.longitude) = (latitude, longitude);
+            }
+            packet.status |= StatusFlags::POSITION_STALE.bits();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+        assert!((actual - expected).abs() < tolerance, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn converts_reference_coordinates() {
+        // Sydney Opera House, 33°51'24.0"S 151°12'54.0"E. An f32 longitude resolves to
+        // ~0.05", so seconds are compared to 0.1".
+        let (d, m, s) = to_dms(-33.856667);
+        assert_eq!((d, m), (-33, 51));
+        assert_close(s, 24.0, 0.1);
+        let (d, m, s) = to_dms(151.215);
+        assert_eq!((d, m), (151, 12));
+        assert_close(s, 54.0, 0.1);
+
+        // Golden Gate Bridge, 37°49'11.0"N 122°28'43.0"W
+        assert_close(from_dms(37, 49, 11.0), 37.81972, 1e-5);
+        assert_close(from_dms(-122, 28, 43.0), -122.478611, 1e-5);
+
+        let (d, m) = to_degrees_minutes(-122.478611);
+        assert_eq!(d, -122);
+        assert_close(m, 28.7167, 1e-3);
+        assert_close(from_degrees_minutes(d, m), -122.478611, 1e-5);
+    }
+
+    #[test]
+    fn round_trips_and_keeps_the_sign_near_zero() {
+        for deg in [0.0, 45.5, -0.25, 89.999_99, -179.999_9, 12.345_678] {
+            let (d, m, s) = to_dms(deg);
+            if deg > -1.0 && deg < 0.0 {
+                assert_eq!(d, 0); // Documented limitation of the tuple form
+            } else {
+                assert_close(from_dms(d, m, s), deg, 1e-5);
+            }
+            assert_close(Dms::from_degrees(deg).to_degrees(), deg, 1e-5);
+        }
+        assert!(Dms::from_degrees(-0.25).negative);
+    }
+
+    #[test]
+    fn formats_with_hemispheres_and_carries_rounding() {
+        assert_eq!(format_latitude(-33.856667, CoordinateFormat::Dms), "33°51'24.0\"S");
+        assert_eq!(format_longitude(-0.25, CoordinateFormat::Dms), "0°15'00.0\"W");
+        assert_eq!(format_longitude(151.215, CoordinateFormat::Decimal), "151.21500");
+        // 59.99" rounds up to the next minute, and 59' 59.99" to the next degree
+        assert_eq!(format_latitude(from_dms(10, 59, 59.99), CoordinateFormat::Dms), "11°00'00.0\"N");
+    }
+
+    #[test]
+    fn wraps_longitude_at_the_antimeridian() {
+        assert_eq!(wrap_longitude(180.0), -180.0);
+        assert_eq!(wrap_longitude(-180.0), -180.0);
+        assert_close(wrap_longitude(181.5), -178.5, 1e-4);
+        assert_close(wrap_longitude(-181.5), 178.5, 1e-4);
+        assert_close(wrap_longitude(539.0), 179.0, 1e-4);
+        assert_close(wrap_longitude(-1e-9), 0.0, 1e-6);
+        assert!(wrap_longitude(179.999_99) < 180.0);
+        assert_eq!(clamp_latitude(91.0), 90.0);
+        assert_eq!(clamp_latitude(-95.0), -90.0);
+    }
+
+    #[test]
+    fn guard_substitutes_the_last_valid_fix() {
+        let mut guard = PositionGuard::new();
+        let mut packet = TelemetryPacket { latitude: 45.5, longitude: 190.0, status: 0, ..TelemetryPacket::new(0) };
+        guard.apply(&mut packet);
+        assert_eq!(({ packet.latitude }, { packet.longitude }), (45.5, -170.0));
+        assert!(!packet.status_flags().contains(StatusFlags::POSITION_STALE));
+
+        let mut glitch = TelemetryPacket { latitude: f32::NAN, longitude: 10.0, status: 0, ..TelemetryPacket::new(0) };
+        guard.apply(&mut glitch);
+        assert_eq!(({ glitch.latitude }, { glitch.longitude }), (45.5, -170.0));
+        assert_eq!(glitch.status_flags(), StatusFlags::POSITION_STALE);
+
+        let mut no_fix = TelemetryPacket { longitude: f32::INFINITY, status: 0, ..TelemetryPacket::new(0) };
+        PositionGuard::new().apply(&mut no_fix);
+        assert_eq!(no_fix.status_flags(), StatusFlags::POSITION_STALE);
+    }
+}
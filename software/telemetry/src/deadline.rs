@@ -0,0 +1,112 @@
+// Blocking device access with a time budget. A sensor holding the I2C clock line
+// (clock stretching) can block a read far beyond its usual few milliseconds; running
+// reads on the blocking pool lets the loop abandon them and carry on.
+
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+
+pub enum TimedRead<R> {
+    Done(R),
+    // The read exceeded the budget and was abandoned
+    TimedOut,
+    // An abandoned read still holds the device
+    Busy,
+}
+
+pub struct TimedDevice<T> {
+    device: Arc<Mutex<T>>,
+    budget: Duration,
+    in_flight: Option<JoinHandle<()>>,
+}
+
+impl<T: Send + 'static> TimedDevice<T> {
+    pub fn new(device: T, budget: Duration) -> Self {
+        Self {
+            device: Arc::new(Mutex::new(device)),
+            budget,
+            in_flight: None,
+        }
+    }
+
+    pub async fn read<R, F>(&mut self, read: F) -> TimedRead<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut T) -> R + Send + 'static,
+    {
+        if self.in_flight.as_ref().is_some_and(|task| !task.is_finished()) {
+            return TimedRead::Busy;
+        }
+        self.in_flight = None;
+
+        let device = Arc::clone(&self.device);
+        let mut task = tokio::task::spawn_blocking(move || read(&mut lock(&device)));
+
+        match tokio::time::timeout(self.budget, &mut task).await {
+            Ok(Ok(result)) => TimedRead::Done(result),
+            Ok(Err(e)) => std::panic::resume_unwind(e.into_panic()),
+            Err(_) => {
+                // The blocking read can't be cancelled; let it finish in the background
+                // and drop its result
+                self.in_flight = Some(tokio::spawn(async move {
+                    let _ = task.await;
+                }));
+                TimedRead::TimedOut
+            }
+        }
+    }
+
+    // The underlying device, for other users such as a background sampler
+    pub fn shared(&self) -> Arc<Mutex<T>> {
+        Arc::clone(&self.device)
+    }
+
+    // Synchronous access for quick, non-bus operations; None while a read is stuck
+    pub fn try_with<R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        match self.device.try_lock() {
+            Ok(mut device) => Some(f(&mut device)),
+            Err(std::sync::TryLockError::Poisoned(e)) => Some(f(&mut e.into_inner())),
+            Err(std::sync::TryLockError::WouldBlock) => None,
+        }
+    }
+}
+
+fn lock<T>(device: &Mutex<T>) -> MutexGuard<'_, T> {
+    device.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn fast_read_completes() {
+        let mut device = TimedDevice::new(41u32, Duration::from_millis(200));
+        match device.read(|value| *value + 1).await {
+            TimedRead::Done(value) => assert_eq!(value, 42),
+            _ => panic!("expected the read to complete"),
+        }
+    }
+
+    #[tokio::test]
+    async fn stuck_read_is_abandoned_then_device_recovers() {
+        let mut device = TimedDevice::new(0u32, Duration::from_millis(20));
+
+        let stuck = device.read(|value| {
+            std::thread::sleep(Duration::from_millis(150));
+            *value = 7;
+        });
+        assert!(matches!(stuck.await, TimedRead::TimedOut));
+
+        // The abandoned read still holds the device
+        assert!(matches!(device.read(|value| *value).await, TimedRead::Busy));
+        assert!(device.try_with(|value| *value).is_none());
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        match device.read(|value| *value).await {
+            TimedRead::Done(value) => assert_eq!(value, 7),
+            _ => panic!("expected the device to be free again"),
+        }
+    }
+}
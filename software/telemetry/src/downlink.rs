@@ -0,0 +1,137 @@
+// The data-packet send path of the main loop: serialize (whole or trimmed to the field
+// mask) and hand the bytes to the transport as one frame
+
+use std::io;
+
+use crate::fields::FieldMask;
+use crate::frame::{self, Endianness};
+use crate::packet::{TelemetryPacket, PACKET_LEN};
+use crate::transport::Transport;
+
+pub struct PacketSender {
+    mask: FieldMask,
+    order: Endianness, // Full packets only; trimmed frames are always little-endian
+    wire: Vec<u8>,     // Reused so the loop doesn't allocate per frame
+}
+
+impl PacketSender {
+    pub fn new(mask: FieldMask, order: Endianness) -> Self {
+        Self { mask, order, wire: Vec::with_capacity(PACKET_LEN) }
+    }
+
+    pub fn send(&mut self, transport: &mut dyn Transport, packet: &TelemetryPacket) -> io::Result<usize> {
+        self.wire.clear();
+        if self.mask == FieldMask::ALL {
+            packet.write_bytes(self.order, &mut self.wire);
+        } else {
+            self.mask.encode_into(packet, &mut self.wire);
+        }
+        transport.send(&self.wire)
+    }
+}
+
+// Receiver side: a framed data packet back to a TelemetryPacket. None for other
+// payloads (headers, fragments), frames that don't match `mask` and full packets
+// failing their CRC.
+pub fn decode_packet(buf: &[u8], mask: FieldMask) -> Option<TelemetryPacket> {
+    let (header, payload) = frame::decode(buf).ok()?;
+    if mask == FieldMask::ALL {
+        TelemetryPacket::from_bytes_in(payload, header.endianness()).ok().filter(TelemetryPacket::verify_crc)
+    } else {
+        mask.decode(payload)
+    }
+}
+
+// Packets lost between two consecutively received sequence numbers, across the u32 wrap.
+// A repeat or a late (reordered) packet counts as none.
+pub fn packets_missed(previous: u32, current: u32) -> u32 {
+    match current.wrapping_sub(previous) {
+        0 => 0,
+        delta if delta > u32::MAX / 2 => 0,
+        delta => delta - 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::UdpSocket;
+    use std::time::Duration;
+
+    use crate::checksum::Checksum;
+    use crate::fields::Field;
+    use crate::frame::Framed;
+    use crate::transport::UdpTransport;
+
+    fn loopback() -> (UdpSocket, Box<dyn Transport>) {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let udp = UdpTransport::new(&receiver.local_addr().unwrap().to_string()).unwrap();
+        (receiver, Box::new(udp))
+    }
+
+    fn sample(i: u64) -> TelemetryPacket {
+        let mut packet = TelemetryPacket {
+            timestamp_ms: (1_700_000_000 + i) * 1000,
+            temperature: -20.0 - i as f32,
+            altitude: 1000.0 * i as f32,
+            latitude: 45.5,
+            longitude: -122.25,
+            pressure_hpa: 900.0 - 10.0 * i as f32,
+            sequence: i as u32,
+            ..TelemetryPacket::new(0)
+        };
+        packet.finalize();
+        packet
+    }
+
+    #[test]
+    fn full_packets_survive_udp_loopback() {
+        for order in [Endianness::Little, Endianness::Big] {
+            let (receiver, udp) = loopback();
+            let mut transport = Framed::new(udp, order);
+            let mut sender = PacketSender::new(FieldMask::ALL, order);
+            let mut buf = [0u8; 512];
+
+            for i in 0..3 {
+                let packet = sample(i);
+                let sent = sender.send(&mut transport, &packet).unwrap();
+                let received = receiver.recv(&mut buf).unwrap();
+                assert_eq!(received, sent);
+
+                let decoded = decode_packet(&buf[..received], FieldMask::ALL).unwrap();
+                assert_eq!(decoded.to_bytes(Endianness::Little), packet.to_bytes(Endianness::Little));
+            }
+        }
+    }
+
+    #[test]
+    fn trimmed_packets_survive_udp_loopback() {
+        let mask: FieldMask = [Field::Timestamp, Field::Altitude, Field::PressureHpa].into_iter().collect();
+        let (receiver, udp) = loopback();
+        let mut transport = Framed::new(udp, Endianness::Big);
+        let mut sender = PacketSender::new(mask, Endianness::Big);
+        let mut buf = [0u8; 512];
+
+        let packet = sample(2);
+        sender.send(&mut transport, &packet).unwrap();
+        let received = receiver.recv(&mut buf).unwrap();
+        assert_eq!(received, frame::FRAME_HEADER_LEN + mask.frame_len() + Checksum::default().trailer_len());
+
+        let decoded = decode_packet(&buf[..received], mask).unwrap();
+        assert_eq!(({ decoded.timestamp_ms }, { decoded.altitude }, { decoded.pressure_hpa }), (1_700_000_002_000, 2000.0, 880.0));
+        assert!({ decoded.temperature }.is_nan());
+        assert!(decode_packet(&buf[..received], FieldMask::ALL).is_none());
+    }
+
+    #[test]
+    fn counts_missed_packets_across_the_wrap() {
+        assert_eq!(packets_missed(41, 42), 0);
+        assert_eq!(packets_missed(41, 45), 3);
+        assert_eq!(packets_missed(u32::MAX, 0), 0);
+        assert_eq!(packets_missed(u32::MAX - 1, 2), 3);
+        // Duplicates and stragglers
+        assert_eq!(packets_missed(42, 42), 0);
+        assert_eq!(packets_missed(42, 40), 0);
+    }
+}
@@ -0,0 +1,361 @@
+// Per-mission selection of which data fields go into each frame. The session header
+// advertises the mask so the receiver knows the layout of the trimmed frames.
+//
+// Trimmed frame layout (little-endian): MASKED_PACKET_SYNC u64, then each selected
+// field in `Field` order at its native width.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::packet::{TelemetryPacket, PACKET_SYNC, PACKET_VERSION};
+
+// Sync word for trimmed frames (distinct from full packets, headers and extended packets)
+pub const MASKED_PACKET_SYNC: u64 = 0xFF_FF_FF_FF_FF_FF_FF_FC;
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Timestamp = 0,
+    Temperature = 1,
+    Humidity = 2,
+    Altitude = 3,
+    Latitude = 4,
+    Longitude = 5,
+    AccelX = 6,
+    AccelY = 7,
+    AccelZ = 8,
+    GyroX = 9,
+    GyroY = 10,
+    GyroZ = 11,
+    Status = 12,
+    FlightPhase = 13,
+    PeakAccel = 14,
+    PeakAccelAge = 15,
+    BatteryVoltage = 16,
+    Heading = 17,
+    PressureHpa = 18,
+    Sequence = 19,
+    ImuTemperature = 20,
+    Roll = 21,
+    Pitch = 22,
+    VerticalSpeed = 23,
+}
+
+impl Field {
+    pub const ALL: [Field; 24] = [
+        Field::Timestamp, Field::Temperature, Field::Humidity, Field::Altitude,
+        Field::Latitude, Field::Longitude, Field::AccelX, Field::AccelY, Field::AccelZ,
+        Field::GyroX, Field::GyroY, Field::GyroZ, Field::Status, Field::FlightPhase,
+        Field::PeakAccel, Field::PeakAccelAge, Field::BatteryVoltage, Field::Heading,
+        Field::PressureHpa, Field::Sequence, Field::ImuTemperature, Field::Roll, Field::Pitch,
+        Field::VerticalSpeed,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::Timestamp => "timestamp_ms",
+            Field::Temperature => "temperature",
+            Field::Humidity => "humidity",
+            Field::Altitude => "altitude",
+            Field::Latitude => "latitude",
+            Field::Longitude => "longitude",
+            Field::AccelX => "accel_x",
+            Field::AccelY => "accel_y",
+            Field::AccelZ => "accel_z",
+            Field::GyroX => "gyro_x",
+            Field::GyroY => "gyro_y",
+            Field::GyroZ => "gyro_z",
+            Field::Status => "status",
+            Field::FlightPhase => "flight_phase",
+            Field::PeakAccel => "peak_accel",
+            Field::PeakAccelAge => "peak_accel_age_ms",
+            Field::BatteryVoltage => "battery_voltage",
+            Field::Heading => "heading",
+            Field::PressureHpa => "pressure_hpa",
+            Field::Sequence => "sequence",
+            Field::ImuTemperature => "imu_temperature",
+            Field::Roll => "roll",
+            Field::Pitch => "pitch",
+            Field::VerticalSpeed => "vertical_speed",
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Field::Timestamp => 8,
+            Field::FlightPhase => 1,
+            Field::Status | Field::PeakAccelAge => 2,
+            _ => 4,
+        }
+    }
+
+    fn bit(self) -> u32 {
+        1 << self as u8
+    }
+
+    fn write(self, packet: &TelemetryPacket, out: &mut Vec<u8>) {
+        let float = match self {
+            Field::Timestamp => return out.extend_from_slice(&{ packet.timestamp_ms }.to_le_bytes()),
+            Field::Status => return out.extend_from_slice(&{ packet.status }.to_le_bytes()),
+            Field::FlightPhase => return out.push(packet.flight_phase),
+            Field::PeakAccelAge => return out.extend_from_slice(&{ packet.peak_accel_age_ms }.to_le_bytes()),
+            Field::Sequence => return out.extend_from_slice(&{ packet.sequence }.to_le_bytes()),
+            Field::Temperature => packet.temperature,
+            Field::Humidity => packet.humidity,
+            Field::Altitude => packet.altitude,
+            Field::Latitude => packet.latitude,
+            Field::Longitude => packet.longitude,
+            Field::AccelX => packet.accel_x,
+            Field::AccelY => packet.accel_y,
+            Field::AccelZ => packet.accel_z,
+            Field::GyroX => packet.gyro_x,
+            Field::GyroY => packet.gyro_y,
+            Field::GyroZ => packet.gyro_z,
+            Field::PeakAccel => packet.peak_accel,
+            Field::BatteryVoltage => packet.battery_voltage,
+            Field::Heading => packet.heading,
+            Field::PressureHpa => packet.pressure_hpa,
+            Field::ImuTemperature => packet.imu_temperature,
+            Field::Roll => packet.roll,
+            Field::Pitch => packet.pitch,
+            Field::VerticalSpeed => packet.vertical_speed,
+        };
+        out.extend_from_slice(&float.to_le_bytes());
+    }
+
+    fn read(self, bytes: &[u8], packet: &mut TelemetryPacket) {
+        let float = |b: &[u8]| f32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+        match self {
+            Field::Timestamp => packet.timestamp_ms = u64::from_le_bytes(bytes.try_into().unwrap()),
+            Field::Status => packet.status = u16::from_le_bytes([bytes[0], bytes[1]]),
+            Field::FlightPhase => packet.flight_phase = bytes[0],
+            Field::PeakAccelAge => packet.peak_accel_age_ms = u16::from_le_bytes([bytes[0], bytes[1]]),
+            Field::Sequence => packet.sequence = u32::from_le_bytes(bytes.try_into().unwrap()),
+            Field::Temperature => packet.temperature = float(bytes),
+            Field::Humidity => packet.humidity = float(bytes),
+            Field::Altitude => packet.altitude = float(bytes),
+            Field::Latitude => packet.latitude = float(bytes),
+            Field::Longitude => packet.longitude = float(bytes),
+            Field::AccelX => packet.accel_x = float(bytes),
+            Field::AccelY => packet.accel_y = float(bytes),
+            Field::AccelZ => packet.accel_z = float(bytes),
+            Field::GyroX => packet.gyro_x = float(bytes),
+            Field::GyroY => packet.gyro_y = float(bytes),
+            Field::GyroZ => packet.gyro_z = float(bytes),
+            Field::PeakAccel => packet.peak_accel = float(bytes),
+            Field::BatteryVoltage => packet.battery_voltage = float(bytes),
+            Field::Heading => packet.heading = float(bytes),
+            Field::PressureHpa => packet.pressure_hpa = float(bytes),
+            Field::ImuTemperature => packet.imu_temperature = float(bytes),
+            Field::Roll => packet.roll = float(bytes),
+            Field::Pitch => packet.pitch = float(bytes),
+            Field::VerticalSpeed => packet.vertical_speed = float(bytes),
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Field::ALL
+            .into_iter()
+            .find(|field| field.name() == s)
+            .ok_or_else(|| format!("Unknown field '{}'", s))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldMask(u32);
+
+impl FieldMask {
+    // Every field, i.e. the full TelemetryPacket
+    pub const ALL: FieldMask = FieldMask((1 << Field::ALL.len()) - 1);
+
+    pub const fn empty() -> Self {
+        FieldMask(0)
+    }
+
+    pub fn with(self, field: Field) -> Self {
+        FieldMask(self.0 | field.bit())
+    }
+
+    pub fn without(self, field: Field) -> Self {
+        FieldMask(self.0 & !field.bit())
+    }
+
+    pub fn contains(self, field: Field) -> bool {
+        self.0 & field.bit() != 0
+    }
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    // Returns None if any bit doesn't correspond to a known field
+    pub fn from_bits(bits: u32) -> Option<Self> {
+        (bits & !Self::ALL.0 == 0).then_some(FieldMask(bits))
+    }
+
+    pub fn fields(self) -> impl Iterator<Item = Field> {
+        Field::ALL.into_iter().filter(move |&field| self.contains(field))
+    }
+
+    pub fn frame_len(self) -> usize {
+        8 + self.fields().map(Field::size).sum::<usize>()
+    }
+
+    pub fn encode(self, packet: &TelemetryPacket) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(self.frame_len());
+        self.encode_into(packet, &mut frame);
+        frame
+    }
+
+    // encode() appended to `out`, for reusing one buffer across frames
+    pub fn encode_into(self, packet: &TelemetryPacket, out: &mut Vec<u8>) {
+        out.reserve(self.frame_len());
+        out.extend_from_slice(&MASKED_PACKET_SYNC.to_le_bytes());
+        for field in self.fields() {
+            field.write(packet, out);
+        }
+    }
+
+    // Rebuilds a packet from a trimmed frame. Absent float fields are NaN and absent
+    // integer fields are zero.
+    pub fn decode(self, frame: &[u8]) -> Option<TelemetryPacket> {
+        if frame.len() != self.frame_len() || frame[..8] != MASKED_PACKET_SYNC.to_le_bytes() {
+            return None;
+        }
+
+        let mut packet = TelemetryPacket {
+            sync: PACKET_SYNC,
+            version: PACKET_VERSION,
+            timestamp_ms: 0,
+            temperature: f32::NAN,
+            humidity: f32::NAN,
+            altitude: f32::NAN,
+            latitude: f32::NAN,
+            longitude: f32::NAN,
+            accel_x: f32::NAN,
+            accel_y: f32::NAN,
+            accel_z: f32::NAN,
+            gyro_x: f32::NAN,
+            gyro_y: f32::NAN,
+            gyro_z: f32::NAN,
+            status: 0,
+            flight_phase: 0,
+            peak_accel: f32::NAN,
+            peak_accel_age_ms: 0,
+            battery_voltage: f32::NAN,
+            heading: f32::NAN,
+            pressure_hpa: f32::NAN,
+            imu_temperature: f32::NAN,
+            roll: f32::NAN,
+            pitch: f32::NAN,
+            vertical_speed: f32::NAN,
+            sequence: 0,
+            crc: 0, // Trimmed frames rely on the frame checksum alone
+        };
+
+        let mut offset = 8;
+        for field in self.fields() {
+            field.read(&frame[offset..offset + field.size()], &mut packet);
+            offset += field.size();
+        }
+        Some(packet)
+    }
+}
+
+impl FromIterator<Field> for FieldMask {
+    fn from_iter<I: IntoIterator<Item = Field>>(fields: I) -> Self {
+        fields.into_iter().fold(FieldMask::empty(), FieldMask::with)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_packet() -> TelemetryPacket {
+        TelemetryPacket {
+            timestamp_ms: 1_700_000_000_000,
+            temperature: -12.5,
+            altitude: 18_250.0,
+            latitude: 45.5,
+            longitude: -122.25,
+            status: 0x03,
+            flight_phase: 1,
+            ..TelemetryPacket::new(0)
+        }
+    }
+
+    #[test]
+    fn full_mask_matches_packet_size() {
+        // Every field but the packet version and CRC
+        assert_eq!(FieldMask::ALL.frame_len() + 3, std::mem::size_of::<TelemetryPacket>());
+        assert_eq!(FieldMask::from_bits(0xFF_FFFF), Some(FieldMask::ALL));
+        assert_eq!(FieldMask::from_bits(0x100_0000), None);
+    }
+
+    #[test]
+    fn tracker_mask_round_trips() {
+        let mask = FieldMask::empty()
+            .with(Field::Latitude)
+            .with(Field::Longitude)
+            .with(Field::Altitude);
+        let packet = sample_packet();
+
+        let frame = mask.encode(&packet);
+        assert_eq!(frame.len(), 8 + 3 * 4);
+
+        let decoded = mask.decode(&frame).unwrap();
+        assert_eq!({ decoded.latitude }, 45.5);
+        assert_eq!({ decoded.longitude }, -122.25);
+        assert_eq!({ decoded.altitude }, 18_250.0);
+        assert!({ decoded.temperature }.is_nan());
+        assert_eq!({ decoded.timestamp_ms }, 0);
+    }
+
+    #[test]
+    fn several_masks_round_trip() {
+        let packet = sample_packet();
+        let masks = [
+            FieldMask::ALL,
+            FieldMask::empty(),
+            FieldMask::ALL.without(Field::Humidity),
+            [Field::Timestamp, Field::Status, Field::FlightPhase, Field::GyroZ].into_iter().collect(),
+        ];
+
+        for mask in masks {
+            let decoded = mask.decode(&mask.encode(&packet)).unwrap();
+            for field in mask.fields() {
+                let mut expected = Vec::new();
+                let mut actual = Vec::new();
+                field.write(&packet, &mut expected);
+                field.write(&decoded, &mut actual);
+                assert_eq!(expected, actual, "{} with mask {:#010x}", field, mask.bits());
+            }
+        }
+    }
+
+    #[test]
+    fn decode_rejects_wrong_layout() {
+        let mask = FieldMask::empty().with(Field::Temperature);
+        let frame = mask.encode(&sample_packet());
+        assert!(mask.with(Field::Humidity).decode(&frame).is_none());
+        assert!(mask.decode(&sample_packet().to_le_bytes()).is_none());
+    }
+
+    #[test]
+    fn fields_parse_by_name() {
+        assert_eq!("accel_z".parse::<Field>(), Ok(Field::AccelZ));
+        assert_eq!("pressure_hpa".parse::<Field>(), Ok(Field::PressureHpa));
+        assert!("pressure".parse::<Field>().is_err());
+    }
+}
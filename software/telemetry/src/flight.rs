@@ -0,0 +1,540 @@
+// Flight phase detection driven by altitude, climb rate and acceleration magnitude
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use tracing::{info, warn};
+
+use crate::i2c::MPU6050::STANDARD_GRAVITY;
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlightPhase {
+    Pad = 0,
+    Ascent = 1,
+    Float = 2,
+    Burst = 3,
+    Descent = 4,
+    Landed = 5,
+}
+
+impl FlightPhase {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(FlightPhase::Pad),
+            1 => Some(FlightPhase::Ascent),
+            2 => Some(FlightPhase::Float),
+            3 => Some(FlightPhase::Burst),
+            4 => Some(FlightPhase::Descent),
+            5 => Some(FlightPhase::Landed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PhaseThresholds {
+    pub launch_accel: f32,   // Deviation of |accel| from 1g that counts as a launch spike (m/s²)
+    pub ascent_rate: f32,    // Climb rate above which the balloon is ascending (m/s)
+    pub float_rate: f32,     // |climb rate| below which the balloon is floating (m/s)
+    pub burst_rate: f32,     // Sharp negative climb rate indicating burst (m/s)
+    pub descent_rate: f32,   // Sustained negative climb rate indicating descent (m/s)
+    pub landed_rate: f32,    // |climb rate| below which a descending payload has landed (m/s)
+    pub float_altitude: f32, // Height above the pad a float must be at; a stall lower down is still ascent (m)
+    pub landed_altitude: f32, // Height above the pad a landing must be within, allowing for higher ground (m)
+    pub landed_accel: f32,   // Deviation of |accel| from 1g still counted as lying still (m/s²)
+    pub hold_samples: u32,   // Consecutive samples a transition must persist (hysteresis)
+    pub burst_samples: u32,  // Burst is a transient, so it needs fewer samples
+}
+
+impl Default for PhaseThresholds {
+    fn default() -> Self {
+        Self {
+            launch_accel: 3.0,
+            ascent_rate: 1.0,
+            float_rate: 0.5,
+            burst_rate: -10.0,
+            descent_rate: -2.0,
+            landed_rate: 0.3,
+            float_altitude: 5000.0,
+            landed_altitude: 3000.0,
+            landed_accel: 1.0,
+            hold_samples: 10,
+            burst_samples: 2,
+        }
+    }
+}
+
+pub struct FlightPhaseTracker {
+    phase: FlightPhase,
+    thresholds: PhaseThresholds,
+    launch_seen: bool,
+    pad_altitude: Option<f32>, // Latest altitude while on the pad
+    candidate: Option<FlightPhase>,
+    candidate_count: u32,
+}
+
+impl FlightPhaseTracker {
+    pub fn new(thresholds: PhaseThresholds) -> Self {
+        Self {
+            phase: FlightPhase::Pad,
+            thresholds,
+            launch_seen: false,
+            pad_altitude: None,
+            candidate: None,
+            candidate_count: 0,
+        }
+    }
+
+    pub fn phase(&self) -> FlightPhase {
+        self.phase
+    }
+
+    pub fn update(&mut self, altitude: f32, climb_rate: f32, accel_magnitude: f32) -> FlightPhase {
+        let t = self.thresholds;
+        let still = (accel_magnitude - STANDARD_GRAVITY).abs();
+
+        if self.phase == FlightPhase::Pad {
+            if still > t.launch_accel {
+                self.launch_seen = true;
+            }
+            if altitude.is_finite() {
+                self.pad_altitude = Some(altitude);
+            }
+        }
+        // Without a pad altitude, heights can't be judged and don't hold transitions back
+        let height = self.pad_altitude.map(|pad| altitude - pad);
+        let high = height.is_none_or(|height| height > t.float_altitude);
+        let near_ground = height.is_none_or(|height| height < t.landed_altitude);
+
+        let next = match self.phase {
+            FlightPhase::Pad if self.launch_seen && climb_rate > t.ascent_rate => Some(FlightPhase::Ascent),
+            FlightPhase::Ascent | FlightPhase::Float if climb_rate < t.burst_rate => Some(FlightPhase::Burst),
+            FlightPhase::Ascent if high && climb_rate.abs() < t.float_rate => Some(FlightPhase::Float),
+            FlightPhase::Float if climb_rate > t.ascent_rate => Some(FlightPhase::Ascent),
+            // Ascent too: a slow leak or a stuck-open valve can turn the balloon round without a burst
+            FlightPhase::Ascent | FlightPhase::Float | FlightPhase::Burst if climb_rate < t.descent_rate => {
+                Some(FlightPhase::Descent)
+            }
+            FlightPhase::Descent if near_ground && still < t.landed_accel && climb_rate.abs() < t.landed_rate => {
+                Some(FlightPhase::Landed)
+            }
+            _ => None,
+        };
+
+        match next {
+            Some(phase) => {
+                if self.candidate == Some(phase) {
+                    self.candidate_count += 1;
+                } else {
+                    self.candidate = Some(phase);
+                    self.candidate_count = 1;
+                }
+
+                let required = if phase == FlightPhase::Burst { t.burst_samples } else { t.hold_samples };
+                if self.candidate_count >= required {
+                    info!("Flight phase change: {:?} -> {:?}", self.phase, phase);
+                    self.phase = phase;
+                    self.candidate = None;
+                    self.candidate_count = 0;
+                }
+            }
+            None => {
+                self.candidate = None;
+                self.candidate_count = 0;
+            }
+        }
+
+        self.phase
+    }
+}
+
+// Finite differences averaged for the smoothed climb rate: 0.5 s at the default
+// 100 ms loop, enough to take the edge off barometer noise without lagging burst
+pub const CLIMB_RATE_SMOOTHING: usize = 5;
+
+// Vertical speed from successive altitude samples, using the actual elapsed time
+#[derive(Default)]
+pub struct ClimbRateEstimator {
+    last: Option<(f32, Instant)>,
+    climb_rate: f32,
+    recent: VecDeque<f32>, // Last CLIMB_RATE_SMOOTHING finite differences
+}
+
+impl ClimbRateEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Returns the climb rate in m/s; 0 for the first sample
+    pub fn update(&mut self, altitude: f32, now: Instant) -> f32 {
+        if let Some((last_altitude, last_time)) = self.last {
+            let dt = now.duration_since(last_time).as_secs_f32();
+            if dt > 0.0 {
+                self.climb_rate = (altitude - last_altitude) / dt;
+                if self.recent.len() == CLIMB_RATE_SMOOTHING {
+                    self.recent.pop_front();
+                }
+                self.recent.push_back(self.climb_rate);
+            }
+        }
+
+        self.last = Some((altitude, now));
+        self.climb_rate
+    }
+
+    // Mean of the recent climb rates, for the downlink; 0 before there are any
+    pub fn smoothed(&self) -> f32 {
+        if self.recent.is_empty() {
+            return 0.0;
+        }
+        self.recent.iter().sum::<f32>() / self.recent.len() as f32
+    }
+}
+
+// Consecutive altitudes the apogee detector takes the median of, so that a single wild
+// barometer sample moves neither the peak nor the count of samples below it
+const APOGEE_MEDIAN_WINDOW: usize = 3;
+
+// Burst from the altitude profile alone, whatever the climb rate thresholds: once the
+// filtered altitude has stayed more than `drop` below its running peak for
+// `hold_samples` consecutive samples, the peak is taken as apogee. Arms only after
+// climbing `drop` above the first altitude, so pad noise can't trigger it. Latches.
+#[derive(Debug, Clone)]
+pub struct ApogeeDetector {
+    drop: f32,         // Metres below the peak that count as descending
+    hold_samples: u32,
+    recent: VecDeque<f32>,
+    start: Option<f32>, // First filtered altitude
+    peak: f32,
+    below_count: u32,
+    apogee: Option<f32>,
+}
+
+impl ApogeeDetector {
+    pub fn new(drop: f32, hold_samples: u32) -> Self {
+        Self {
+            drop,
+            hold_samples: hold_samples.max(1),
+            recent: VecDeque::with_capacity(APOGEE_MEDIAN_WINDOW),
+            start: None,
+            peak: f32::NEG_INFINITY,
+            below_count: 0,
+            apogee: None,
+        }
+    }
+
+    // Returns whether burst has been detected, on this sample or an earlier one
+    pub fn update(&mut self, altitude: f32) -> bool {
+        if self.apogee.is_some() || !altitude.is_finite() {
+            return self.apogee.is_some();
+        }
+        if self.recent.len() == APOGEE_MEDIAN_WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(altitude);
+        if self.recent.len() < APOGEE_MEDIAN_WINDOW {
+            return false;
+        }
+
+        let mut window: Vec<f32> = self.recent.iter().copied().collect();
+        window.sort_by(f32::total_cmp);
+        let filtered = window[APOGEE_MEDIAN_WINDOW / 2];
+        let start = *self.start.get_or_insert(filtered);
+        self.peak = self.peak.max(filtered);
+
+        let armed = self.peak - start > self.drop;
+        if armed && self.peak - filtered > self.drop {
+            self.below_count += 1;
+        } else {
+            self.below_count = 0;
+        }
+        if self.below_count >= self.hold_samples {
+            self.apogee = Some(self.peak);
+            warn!("Burst detected: apogee {:.0} m, now {:.0} m", self.peak, filtered);
+        }
+        self.apogee.is_some()
+    }
+
+    // Peak altitude (m) once burst has been detected
+    pub fn apogee(&self) -> Option<f32> {
+        self.apogee
+    }
+}
+
+// Sustained rapid descent after burst: the part of the flight recovery depends on, so
+// the loop transmits every frame and logs at full detail while it holds. Cleared only by
+// landing, not by the descent slowing under the parachute. Only measured climb rates in
+// Burst or Descent count, since nothing else can clear a false alarm.
+#[derive(Debug, Clone)]
+pub struct DescentAlarm {
+    rate: f32,          // Climb rate at or below which the descent counts as rapid (m/s, negative)
+    hold_samples: u32,  // Consecutive rapid samples before raising
+    rapid_count: u32,
+    active: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlarmChange {
+    Raised,
+    Cleared,
+}
+
+impl DescentAlarm {
+    pub fn new(rate: f32, hold_samples: u32) -> Self {
+        Self { rate, hold_samples: hold_samples.max(1), rapid_count: 0, active: false }
+    }
+
+    // `climb_rate` is None without a measured altitude
+    pub fn update(&mut self, climb_rate: Option<f32>, phase: FlightPhase) -> Option<AlarmChange> {
+        if self.active {
+            if phase == FlightPhase::Landed {
+                self.active = false;
+                self.rapid_count = 0;
+                info!("Descent alarm cleared: landed");
+                return Some(AlarmChange::Cleared);
+            }
+            return None;
+        }
+
+        let falling = matches!(phase, FlightPhase::Burst | FlightPhase::Descent);
+        if falling && climb_rate.is_some_and(|rate| rate <= self.rate) {
+            self.rapid_count += 1;
+        } else {
+            self.rapid_count = 0;
+        }
+        if self.rapid_count >= self.hold_samples {
+            self.active = true;
+            warn!("Descent alarm: descending at {:.1} m/s (threshold {:.1} m/s)", climb_rate.unwrap_or_default(), self.rate);
+            return Some(AlarmChange::Raised);
+        }
+        None
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn tracker() -> FlightPhaseTracker {
+        FlightPhaseTracker::new(PhaseThresholds { hold_samples: 3, ..PhaseThresholds::default() })
+    }
+
+    #[test]
+    fn ascent_requires_launch_spike_and_sustained_climb() {
+        let mut tracker = tracker();
+        for _ in 0..5 {
+            assert_eq!(tracker.update(200.0, 5.0, STANDARD_GRAVITY), FlightPhase::Pad);
+        }
+
+        tracker.update(200.0, 0.0, 25.0); // Launch spike
+        tracker.update(205.0, 5.0, STANDARD_GRAVITY);
+        tracker.update(210.0, 5.0, STANDARD_GRAVITY);
+        assert_eq!(tracker.update(215.0, 5.0, STANDARD_GRAVITY), FlightPhase::Ascent);
+    }
+
+    #[test]
+    fn hysteresis_ignores_brief_excursions() {
+        let mut tracker = tracker();
+        tracker.update(0.0, 0.0, 25.0);
+        for _ in 0..3 {
+            tracker.update(10.0, 5.0, STANDARD_GRAVITY);
+        }
+        assert_eq!(tracker.phase(), FlightPhase::Ascent);
+
+        // Alternating near-zero climb never persists long enough to count as float
+        for i in 0..20 {
+            let climb = if i % 2 == 0 { 0.0 } else { 5.0 };
+            assert_eq!(tracker.update(20_000.0, climb, STANDARD_GRAVITY), FlightPhase::Ascent);
+        }
+
+        // A sharp drop is detected after the short burst hold
+        tracker.update(20_000.0, -30.0, STANDARD_GRAVITY);
+        assert_eq!(tracker.update(19_970.0, -30.0, STANDARD_GRAVITY), FlightPhase::Burst);
+    }
+
+    // A simulated flight from a 300 m pad, one sample a second
+    struct Flight {
+        tracker: FlightPhaseTracker,
+        climb: ClimbRateEstimator,
+        start: Instant,
+        second: u64,
+        altitude: f32,
+        phases: Vec<FlightPhase>, // Each phase entered, in order
+    }
+
+    impl Flight {
+        fn new() -> Self {
+            Self {
+                tracker: tracker(),
+                climb: ClimbRateEstimator::new(),
+                start: Instant::now(),
+                second: 0,
+                altitude: 300.0,
+                phases: vec![FlightPhase::Pad],
+            }
+        }
+
+        // `seconds` at a steady rate of climb
+        fn step(&mut self, rate: f32, seconds: u64, accel: f32) {
+            for _ in 0..seconds {
+                self.second += 1;
+                self.altitude += rate;
+                let climb_rate = self.climb.update(self.altitude, self.start + Duration::from_secs(self.second));
+                let phase = self.tracker.update(self.altitude, climb_rate, accel);
+                if self.phases.last() != Some(&phase) {
+                    self.phases.push(phase);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn phases_follow_a_full_flight() {
+        let mut flight = Flight::new();
+        flight.step(0.0, 30, STANDARD_GRAVITY);      // Waiting on the pad
+        flight.step(0.0, 1, STANDARD_GRAVITY + 8.0); // Release
+        flight.step(5.0, 600, STANDARD_GRAVITY);     // Climb to 3.3 km
+        flight.step(0.1, 60, STANDARD_GRAVITY);      // Stalls low down: still ascent
+        flight.step(5.0, 3000, STANDARD_GRAVITY);    // On to 18 km
+        flight.step(0.1, 600, STANDARD_GRAVITY);     // Float
+        flight.step(-40.0, 5, STANDARD_GRAVITY * 0.2); // Burst and free fall
+        flight.step(-8.0, 1500, STANDARD_GRAVITY);   // Under the parachute, to ~6 km
+        flight.step(-0.1, 30, STANDARD_GRAVITY);     // Held up in a thermal: far too high to land
+        assert_eq!(flight.tracker.phase(), FlightPhase::Descent);
+        flight.step(-8.0, 500, STANDARD_GRAVITY);    // On down to ~2 km, on a hillside
+        flight.step(0.0, 60, STANDARD_GRAVITY + 4.0); // Dragged along the ground
+        assert_eq!(flight.tracker.phase(), FlightPhase::Descent);
+        flight.step(0.0, 30, STANDARD_GRAVITY);      // At rest
+
+        assert_eq!(flight.phases, [FlightPhase::Pad, FlightPhase::Ascent, FlightPhase::Float, FlightPhase::Burst,
+                                   FlightPhase::Descent, FlightPhase::Landed]);
+    }
+
+    #[test]
+    fn leaking_balloon_descends_without_floating_or_bursting() {
+        let mut flight = Flight::new();
+        flight.step(0.0, 30, STANDARD_GRAVITY);      // Waiting on the pad
+        flight.step(0.0, 1, STANDARD_GRAVITY + 8.0); // Release
+        flight.step(5.0, 600, STANDARD_GRAVITY);     // Climb to 3.3 km, below float_altitude
+        flight.step(-4.0, 10, STANDARD_GRAVITY);     // Leaking: sinking, far slower than burst_rate
+        assert_eq!(flight.tracker.phase(), FlightPhase::Descent);
+        flight.step(-4.0, 740, STANDARD_GRAVITY);    // Down to the ground
+        flight.step(0.0, 30, STANDARD_GRAVITY);      // At rest
+
+        assert_eq!(flight.phases, [FlightPhase::Pad, FlightPhase::Ascent, FlightPhase::Descent, FlightPhase::Landed]);
+    }
+
+    #[test]
+    fn climb_rate_uses_elapsed_time() {
+        let mut climb = ClimbRateEstimator::new();
+        let start = Instant::now();
+        assert_eq!(climb.update(100.0, start), 0.0);
+        assert_eq!(climb.update(105.0, start + Duration::from_millis(500)), 10.0);
+        // A repeated timestamp keeps the previous estimate rather than dividing by zero
+        assert_eq!(climb.update(110.0, start + Duration::from_millis(500)), 10.0);
+    }
+
+    #[test]
+    fn smoothed_climb_rate_averages_recent_differences() {
+        let mut climb = ClimbRateEstimator::new();
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        climb.update(1000.0, start);
+        assert_eq!(climb.smoothed(), 0.0);
+
+        // 5 m/s with ±0.5 m of baro noise on every other sample
+        let altitudes = [1000.5, 1001.0, 1002.0, 1002.0, 1003.0, 1003.0];
+        let rates: Vec<f32> = altitudes.iter().zip(1..).map(|(&altitude, i)| climb.update(altitude, at(i * 200))).collect();
+        assert_eq!(rates, [2.5, 2.5, 5.0, 0.0, 5.0, 0.0]);
+        assert!((climb.smoothed() - 2.5).abs() < 1e-4, "{}", climb.smoothed());
+
+        // Only the last CLIMB_RATE_SMOOTHING differences count: a steady 5 m/s replaces the noise
+        for i in 0..CLIMB_RATE_SMOOTHING as u64 {
+            climb.update(1004.0 + i as f32, at(1400 + i * 200));
+        }
+        assert!((climb.smoothed() - 5.0).abs() < 1e-4, "{}", climb.smoothed());
+    }
+
+    #[test]
+    fn apogee_detected_near_the_peak_despite_noise_spikes() {
+        let mut detector = ApogeeDetector::new(50.0, 3);
+
+        // Pad noise, including drops bigger than the threshold, never arms it
+        for altitude in [120.0, 118.0, 40.0, 121.0, 119.0, 122.0] {
+            assert!(!detector.update(altitude));
+        }
+
+        // Climb at 5 m/sample to 30 km, then burst and fall at 20 m/sample. Single-sample
+        // spikes either way on the ascent are ignored.
+        let ascent = (0..6000).map(|i| 120.0 + 5.0 * i as f32);
+        let descent = (1..200).map(|i| 30_115.0 - 20.0 * i as f32);
+        let mut detected_at = None;
+        for (i, mut altitude) in ascent.chain(descent).enumerate() {
+            if i == 2000 {
+                altitude -= 400.0;
+            }
+            if i == 4000 {
+                altitude += 400.0;
+            }
+            if detector.update(altitude) && detected_at.is_none() {
+                detected_at = Some((i, altitude));
+            }
+        }
+
+        let (i, altitude) = detected_at.expect("burst detected");
+        let apogee = detector.apogee().unwrap();
+        assert!((apogee - 30_115.0).abs() <= 5.0, "apogee {}", apogee);
+        // Within a few samples of the drop passing the threshold
+        assert!((6002..6008).contains(&i), "detected at sample {} ({} m)", i, altitude);
+        assert!(apogee - altitude > 50.0);
+        assert!(detector.update(30_115.0)); // Latched
+    }
+
+    #[test]
+    fn descent_alarm_holds_until_landing() {
+        let mut alarm = DescentAlarm::new(-5.0, 3);
+        assert_eq!(alarm.update(Some(-20.0), FlightPhase::Burst), None);
+        assert_eq!(alarm.update(Some(-20.0), FlightPhase::Burst), None);
+        assert_eq!(alarm.update(Some(-1.0), FlightPhase::Descent), None); // Not sustained
+        for _ in 0..2 {
+            assert_eq!(alarm.update(Some(-20.0), FlightPhase::Descent), None);
+        }
+        assert_eq!(alarm.update(Some(-20.0), FlightPhase::Descent), Some(AlarmChange::Raised));
+        assert!(alarm.is_active());
+
+        // Slowing under the parachute doesn't end it; landing does
+        assert_eq!(alarm.update(Some(-3.0), FlightPhase::Descent), None);
+        assert!(alarm.is_active());
+        assert_eq!(alarm.update(Some(0.0), FlightPhase::Landed), Some(AlarmChange::Cleared));
+        assert!(!alarm.is_active());
+        assert_eq!(alarm.update(Some(-20.0), FlightPhase::Landed), None);
+    }
+
+    #[test]
+    fn descent_alarm_ignores_noise_before_burst() {
+        let mut alarm = DescentAlarm::new(-5.0, 3);
+        // Barometer noise on the pad, a downdraft on the way up
+        for phase in [FlightPhase::Pad, FlightPhase::Ascent, FlightPhase::Float] {
+            for _ in 0..10 {
+                assert_eq!(alarm.update(Some(-30.0), phase), None);
+            }
+        }
+        // No measured altitude, so no climb rate
+        for _ in 0..10 {
+            assert_eq!(alarm.update(None, FlightPhase::Descent), None);
+        }
+        assert!(!alarm.is_active());
+
+        // Unmeasured samples break a run of rapid ones
+        alarm.update(Some(-20.0), FlightPhase::Descent);
+        alarm.update(Some(-20.0), FlightPhase::Descent);
+        alarm.update(None, FlightPhase::Descent);
+        assert_eq!(alarm.update(Some(-20.0), FlightPhase::Descent), None);
+    }
+}
@@ -0,0 +1,288 @@
+// Fragmentation of occasional large diagnostic messages (register dumps, event logs)
+// into extended packets that interleave with regular telemetry frames, plus the
+// receiver-side reassembler.
+//
+// Extended packet layout (little-endian):
+//   sync u64 | message_id u16 | message_type u8 | fragment_index u8 | fragment_count u8 |
+//   payload_len u16 | payload
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+// Sync word identifying an extended packet (distinct from data and session header syncs)
+pub const EXTENDED_SYNC: u64 = 0xFF_FF_FF_FF_FF_FF_FF_FD;
+
+// Keeps each fragment comfortably inside a single UDP datagram / radio frame
+pub const MAX_FRAGMENT_PAYLOAD: usize = 200;
+
+// Largest message that fits in u8::MAX fragments
+pub const MAX_MESSAGE_LEN: usize = MAX_FRAGMENT_PAYLOAD * u8::MAX as usize;
+
+const HEADER_LEN: usize = 8 + 2 + 1 + 1 + 1 + 2;
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageType {
+    RegisterDump = 1,
+    EventLog = 2,
+    BlackBox = 3, // See blackbox::encode_chunks
+}
+
+impl MessageType {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(MessageType::RegisterDump),
+            2 => Some(MessageType::EventLog),
+            3 => Some(MessageType::BlackBox),
+            _ => None,
+        }
+    }
+
+    // For file names and logs
+    pub fn name(self) -> &'static str {
+        match self {
+            MessageType::RegisterDump => "register_dump",
+            MessageType::EventLog => "event_log",
+            MessageType::BlackBox => "black_box",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedPacket {
+    pub message_id: u16,
+    pub message_type: u8,
+    pub fragment_index: u8,
+    pub fragment_count: u8,
+    pub payload: Vec<u8>,
+}
+
+impl ExtendedPacket {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.payload.len());
+        bytes.extend_from_slice(&EXTENDED_SYNC.to_le_bytes());
+        bytes.extend_from_slice(&self.message_id.to_le_bytes());
+        bytes.push(self.message_type);
+        bytes.push(self.fragment_index);
+        bytes.push(self.fragment_count);
+        bytes.extend_from_slice(&(self.payload.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&self.payload);
+        bytes
+    }
+
+    // Returns None unless the buffer is a well-formed extended packet
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        if buf.len() < HEADER_LEN || buf[..8] != EXTENDED_SYNC.to_le_bytes() {
+            return None;
+        }
+
+        let payload_len = u16::from_le_bytes([buf[13], buf[14]]) as usize;
+        if buf.len() != HEADER_LEN + payload_len {
+            return None;
+        }
+
+        let packet = Self {
+            message_id: u16::from_le_bytes([buf[8], buf[9]]),
+            message_type: buf[10],
+            fragment_index: buf[11],
+            fragment_count: buf[12],
+            payload: buf[HEADER_LEN..].to_vec(),
+        };
+
+        (packet.fragment_count > 0 && packet.fragment_index < packet.fragment_count).then_some(packet)
+    }
+}
+
+// Splits a message into fragments of at most MAX_FRAGMENT_PAYLOAD bytes
+pub fn fragment(message_id: u16, message_type: MessageType, data: &[u8]) -> Result<Vec<ExtendedPacket>, String> {
+    let chunks: Vec<&[u8]> = if data.is_empty() {
+        vec![&[]]
+    } else {
+        data.chunks(MAX_FRAGMENT_PAYLOAD).collect()
+    };
+
+    if chunks.len() > u8::MAX as usize {
+        return Err(format!("Message of {} bytes needs more than {} fragments", data.len(), u8::MAX));
+    }
+
+    let fragment_count = chunks.len() as u8;
+    Ok(chunks
+        .into_iter()
+        .enumerate()
+        .map(|(index, chunk)| ExtendedPacket {
+            message_id,
+            message_type: message_type as u8,
+            fragment_index: index as u8,
+            fragment_count,
+            payload: chunk.to_vec(),
+        })
+        .collect())
+}
+
+// Sender-side queue handing out one fragment per loop iteration, so extended messages
+// trickle out between regular telemetry frames
+#[derive(Default)]
+pub struct ExtendedSender {
+    next_message_id: u16,
+    queue: VecDeque<ExtendedPacket>,
+}
+
+impl ExtendedSender {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn queue_message(&mut self, message_type: MessageType, data: &[u8]) -> Result<u16, String> {
+        let message_id = self.next_message_id;
+        self.queue.extend(fragment(message_id, message_type, data)?);
+        self.next_message_id = self.next_message_id.wrapping_add(1);
+        Ok(message_id)
+    }
+
+    pub fn next_fragment(&mut self) -> Option<ExtendedPacket> {
+        self.queue.pop_front()
+    }
+}
+
+struct PendingMessage {
+    message_type: u8,
+    fragments: Vec<Option<Vec<u8>>>,
+    received: usize,
+    first_seen: Instant,
+}
+
+// Receiver-side reassembly keyed on message ID. Fragments may arrive in any order;
+// messages still incomplete after `timeout` are dropped.
+pub struct Reassembler {
+    timeout: Duration,
+    pending: HashMap<u16, PendingMessage>,
+}
+
+impl Reassembler {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            pending: HashMap::new(),
+        }
+    }
+
+    // Returns the message type and data once every fragment of a message has arrived
+    pub fn accept(&mut self, packet: ExtendedPacket, now: Instant) -> Option<(u8, Vec<u8>)> {
+        self.expire(now);
+
+        let pending = self.pending.entry(packet.message_id).or_insert_with(|| PendingMessage {
+            message_type: packet.message_type,
+            fragments: vec![None; packet.fragment_count as usize],
+            received: 0,
+            first_seen: now,
+        });
+
+        // A reused message ID with a different shape means the old message is stale
+        if pending.fragments.len() != packet.fragment_count as usize || pending.message_type != packet.message_type {
+            *pending = PendingMessage {
+                message_type: packet.message_type,
+                fragments: vec![None; packet.fragment_count as usize],
+                received: 0,
+                first_seen: now,
+            };
+        }
+
+        let slot = &mut pending.fragments[packet.fragment_index as usize];
+        if slot.is_none() {
+            *slot = Some(packet.payload);
+            pending.received += 1;
+        }
+
+        if pending.received < pending.fragments.len() {
+            return None;
+        }
+
+        let complete = self.pending.remove(&packet.message_id)?;
+        let data = complete.fragments.into_iter().flatten().flatten().collect();
+        Some((complete.message_type, data))
+    }
+
+    // Drops messages that have been incomplete for longer than the timeout
+    pub fn expire(&mut self, now: Instant) -> usize {
+        let before = self.pending.len();
+        let timeout = self.timeout;
+        self.pending.retain(|_, pending| now.duration_since(pending.first_seen) < timeout);
+        before - self.pending.len()
+    }
+
+    pub fn pending_messages(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn fragments_round_trip_through_bytes() {
+        let fragments = fragment(7, MessageType::EventLog, &message(450)).unwrap();
+        assert_eq!(fragments.len(), 3);
+        assert_eq!(fragments[2].payload.len(), 50);
+
+        for packet in &fragments {
+            assert_eq!(ExtendedPacket::from_bytes(&packet.to_bytes()).as_ref(), Some(packet));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_packets() {
+        let mut bytes = fragment(1, MessageType::RegisterDump, &[1, 2, 3]).unwrap()[0].to_bytes();
+        assert!(ExtendedPacket::from_bytes(&bytes[..bytes.len() - 1]).is_none());
+
+        bytes[11] = 5; // fragment_index beyond fragment_count
+        assert!(ExtendedPacket::from_bytes(&bytes).is_none());
+    }
+
+    #[test]
+    fn reassembles_out_of_order_fragments() {
+        let data = message(650);
+        let mut fragments = fragment(3, MessageType::RegisterDump, &data).unwrap();
+        fragments.reverse();
+
+        let now = Instant::now();
+        let mut reassembler = Reassembler::new(Duration::from_secs(5));
+        let last = fragments.pop().unwrap();
+        for packet in fragments {
+            assert!(reassembler.accept(packet, now).is_none());
+        }
+
+        assert_eq!(reassembler.accept(last, now), Some((MessageType::RegisterDump as u8, data)));
+        assert_eq!(reassembler.pending_messages(), 0);
+    }
+
+    #[test]
+    fn drops_incomplete_messages_after_timeout() {
+        let fragments = fragment(9, MessageType::EventLog, &message(300)).unwrap();
+        let start = Instant::now();
+        let mut reassembler = Reassembler::new(Duration::from_secs(5));
+
+        assert!(reassembler.accept(fragments[0].clone(), start).is_none());
+        assert_eq!(reassembler.expire(start + Duration::from_secs(6)), 1);
+
+        // The late fragment alone can't complete the message
+        assert!(reassembler.accept(fragments[1].clone(), start + Duration::from_secs(7)).is_none());
+        assert_eq!(reassembler.pending_messages(), 1);
+    }
+
+    #[test]
+    fn sender_interleaves_one_fragment_at_a_time() {
+        let mut sender = ExtendedSender::new();
+        assert_eq!(sender.queue_message(MessageType::EventLog, &message(250)).unwrap(), 0);
+        assert_eq!(sender.queue_message(MessageType::EventLog, &[]).unwrap(), 1);
+
+        let ids: Vec<(u16, u8)> = std::iter::from_fn(|| sender.next_fragment())
+            .map(|packet| (packet.message_id, packet.fragment_index))
+            .collect();
+        assert_eq!(ids, vec![(0, 0), (0, 1), (1, 0)]);
+    }
+}
@@ -0,0 +1,422 @@
+// Versioned envelope in front of every downlink frame, so receivers can tell which
+// format a frame uses and skip the ones they don't understand instead of misparsing.
+//
+// Envelope layout: FRAME_MAGIC u8, format version u8, flags u8, payload length u16
+// (little-endian), then the payload (a data packet, session header, trimmed frame or
+// extended fragment, each identified by its own sync word), then a checksum of the
+// envelope and payload in the algorithm the flags name (0, 2 or 4 bytes).
+
+use std::fmt;
+use std::io;
+
+use crate::checksum::Checksum;
+use crate::transport::Transport;
+
+pub const FRAME_MAGIC: u8 = 0xB7;
+
+// Bump whenever any payload layout changes.
+//   1: first versioned format (68-byte data packet with peak acceleration)
+//   2: battery_voltage appended to the data packet (72 bytes)
+//   3: heading appended to the data packet (76 bytes)
+//   4: pressure_hpa appended to the data packet (80 bytes)
+//   5: checksum trailer on every frame, checksum id appended to the session header
+//   6: crc appended to the data packet (82 bytes)
+//   7: sequence inserted ahead of the data packet crc (86 bytes)
+//   8: data packet version byte after the sync word (87 bytes)
+//   9: imu_temperature inserted ahead of the data packet sequence (91 bytes, packet v2)
+//  10: roll and pitch inserted ahead of the data packet sequence (99 bytes, packet v3)
+//  11: vertical_speed inserted ahead of the data packet sequence (103 bytes, packet v4)
+//  12: data packet status widened to u16 (104 bytes, packet v5)
+//  13: data packet timestamp in milliseconds (104 bytes, packet v6)
+pub const FORMAT_VERSION: u8 = 13;
+
+// Versions this build can decode
+pub const SUPPORTED_VERSIONS: &[u8] = &[FORMAT_VERSION];
+
+pub const FRAME_HEADER_LEN: usize = 5;
+
+// Fixed-layout payloads (data packets and session headers) are big-endian. Trimmed
+// frames and extended fragments are always little-endian.
+pub const FLAG_BIG_ENDIAN: u8 = 0x01;
+
+// Checksum::id() of the trailer
+pub const FLAG_CHECKSUM_MASK: u8 = 0x06;
+const FLAG_CHECKSUM_SHIFT: u32 = 1;
+
+// Alternating bits for the radio's bit-timing recovery ahead of each envelope
+pub const DEFAULT_PREAMBLE_PATTERN: u8 = 0xAA;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+impl Endianness {
+    pub fn native() -> Self {
+        if cfg!(target_endian = "big") {
+            Endianness::Big
+        } else {
+            Endianness::Little
+        }
+    }
+
+    pub fn u16_bytes(self, value: u16) -> [u8; 2] {
+        match self {
+            Endianness::Little => value.to_le_bytes(),
+            Endianness::Big => value.to_be_bytes(),
+        }
+    }
+
+    pub fn u32_bytes(self, value: u32) -> [u8; 4] {
+        match self {
+            Endianness::Little => value.to_le_bytes(),
+            Endianness::Big => value.to_be_bytes(),
+        }
+    }
+
+    pub fn u64_bytes(self, value: u64) -> [u8; 8] {
+        match self {
+            Endianness::Little => value.to_le_bytes(),
+            Endianness::Big => value.to_be_bytes(),
+        }
+    }
+
+    pub fn put_u16(self, out: &mut Vec<u8>, value: u16) {
+        out.extend_from_slice(&self.u16_bytes(value));
+    }
+
+    pub fn put_u32(self, out: &mut Vec<u8>, value: u32) {
+        out.extend_from_slice(&self.u32_bytes(value));
+    }
+
+    pub fn put_u64(self, out: &mut Vec<u8>, value: u64) {
+        out.extend_from_slice(&self.u64_bytes(value));
+    }
+
+    pub fn put_f32(self, out: &mut Vec<u8>, value: f32) {
+        self.put_u32(out, value.to_bits());
+    }
+}
+
+// Sequential reads of fixed-width values; every read fails once the buffer runs out
+pub struct ByteReader<'a> {
+    buf: &'a [u8],
+    order: Endianness,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(buf: &'a [u8], order: Endianness) -> Self {
+        Self { buf, order }
+    }
+
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        if self.buf.len() < N {
+            return None;
+        }
+        let (head, rest) = self.buf.split_at(N);
+        self.buf = rest;
+        head.try_into().ok()
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        self.take::<1>().map(|[b]| b)
+    }
+
+    pub fn u16(&mut self) -> Option<u16> {
+        let bytes = self.take()?;
+        Some(match self.order {
+            Endianness::Little => u16::from_le_bytes(bytes),
+            Endianness::Big => u16::from_be_bytes(bytes),
+        })
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        let bytes = self.take()?;
+        Some(match self.order {
+            Endianness::Little => u32::from_le_bytes(bytes),
+            Endianness::Big => u32::from_be_bytes(bytes),
+        })
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        let bytes = self.take()?;
+        Some(match self.order {
+            Endianness::Little => u64::from_le_bytes(bytes),
+            Endianness::Big => u64::from_be_bytes(bytes),
+        })
+    }
+
+    pub fn f32(&mut self) -> Option<f32> {
+        self.u32().map(f32::from_bits)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub version: u8,
+    pub flags: u8,
+    pub length: u16,
+}
+
+impl FrameHeader {
+    pub fn endianness(&self) -> Endianness {
+        if self.flags & FLAG_BIG_ENDIAN != 0 {
+            Endianness::Big
+        } else {
+            Endianness::Little
+        }
+    }
+
+    // Always Some for a header decode() returned
+    pub fn checksum(&self) -> Option<Checksum> {
+        Checksum::from_id((self.flags & FLAG_CHECKSUM_MASK) >> FLAG_CHECKSUM_SHIFT)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameError {
+    Truncated { needed: usize, available: usize },
+    BadMagic(u8),
+    // The envelope is intact, so the `length` payload bytes can be skipped
+    UnsupportedVersion { version: u8, length: u16 },
+    PayloadTooLong(usize),
+    UnknownChecksum(u8), // Checksum id from the flags
+    ChecksumMismatch { received: u32, computed: u32 },
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FrameError::Truncated { needed, available } => {
+                write!(f, "truncated frame: need {} bytes, have {}", needed, available)
+            }
+            FrameError::BadMagic(magic) => {
+                write!(f, "not a telemetry frame (magic 0x{:02X}, expected 0x{:02X})", magic, FRAME_MAGIC)
+            }
+            FrameError::UnsupportedVersion { version, length } => write!(
+                f,
+                "unsupported frame format version {} (this receiver understands {:?}); skipping {}-byte payload",
+                version, SUPPORTED_VERSIONS, length
+            ),
+            FrameError::PayloadTooLong(len) => write!(f, "payload of {} bytes exceeds the frame limit of {}", len, u16::MAX),
+            FrameError::UnknownChecksum(id) => write!(f, "unknown frame checksum algorithm {}", id),
+            FrameError::ChecksumMismatch { received, computed } => {
+                write!(f, "frame checksum mismatch: received 0x{:X}, computed 0x{:X}", received, computed)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+pub fn encode(payload: &[u8], order: Endianness, checksum: Checksum) -> Result<Vec<u8>, FrameError> {
+    let mut frame = Vec::new();
+    encode_into(payload, order, checksum, &mut frame)?;
+    Ok(frame)
+}
+
+// encode() into an emptied `out`; nothing is written on error
+pub fn encode_into(payload: &[u8], order: Endianness, checksum: Checksum, out: &mut Vec<u8>) -> Result<(), FrameError> {
+    let length = u16::try_from(payload.len()).map_err(|_| FrameError::PayloadTooLong(payload.len()))?;
+    let mut flags = checksum.id() << FLAG_CHECKSUM_SHIFT;
+    if order == Endianness::Big {
+        flags |= FLAG_BIG_ENDIAN;
+    }
+
+    out.clear();
+    out.reserve(FRAME_HEADER_LEN + payload.len() + checksum.trailer_len());
+    out.extend_from_slice(&[FRAME_MAGIC, FORMAT_VERSION, flags]);
+    out.extend_from_slice(&length.to_le_bytes());
+    out.extend_from_slice(payload);
+    checksum.finalize(out);
+    Ok(())
+}
+
+// Splits one frame into its envelope and payload, checking the trailer. Bytes beyond it
+// are left for the caller (the next frame on a byte stream).
+pub fn decode(buf: &[u8]) -> Result<(FrameHeader, &[u8]), FrameError> {
+    if buf.len() < FRAME_HEADER_LEN {
+        return Err(FrameError::Truncated { needed: FRAME_HEADER_LEN, available: buf.len() });
+    }
+    if buf[0] != FRAME_MAGIC {
+        return Err(FrameError::BadMagic(buf[0]));
+    }
+
+    let header = FrameHeader {
+        version: buf[1],
+        flags: buf[2],
+        length: u16::from_le_bytes([buf[3], buf[4]]),
+    };
+    if !SUPPORTED_VERSIONS.contains(&header.version) {
+        return Err(FrameError::UnsupportedVersion { version: header.version, length: header.length });
+    }
+
+    let checksum_id = (header.flags & FLAG_CHECKSUM_MASK) >> FLAG_CHECKSUM_SHIFT;
+    let checksum = Checksum::from_id(checksum_id).ok_or(FrameError::UnknownChecksum(checksum_id))?;
+    let end = FRAME_HEADER_LEN + header.length as usize + checksum.trailer_len();
+    let frame = buf.get(..end).ok_or(FrameError::Truncated { needed: end, available: buf.len() })?;
+    let covered = checksum.verify(frame).map_err(|(received, computed)| FrameError::ChecksumMismatch { received, computed })?;
+    Ok((header, &covered[FRAME_HEADER_LEN..]))
+}
+
+// Receiver side: drops the run of `pattern` bytes (of any length, including none) a
+// Preamble transport put ahead of the envelope
+pub fn skip_preamble(buf: &[u8], pattern: u8) -> &[u8] {
+    let start = buf.iter().position(|&b| b != pattern).unwrap_or(buf.len());
+    &buf[start..]
+}
+
+// Prepends `len` bytes of `pattern` to every frame sent through `inner`, for radio
+// modems that need a bit-sync run before the envelope. Wrap it inside Framed so the
+// preamble precedes FRAME_MAGIC.
+pub struct Preamble {
+    inner: Box<dyn Transport>,
+    preamble_len: usize,
+    buf: Vec<u8>, // The preamble, followed by the current frame
+}
+
+impl Preamble {
+    // The pattern can't be FRAME_MAGIC, or the receiver couldn't find where it ends
+    pub fn new(inner: Box<dyn Transport>, pattern: u8, len: usize) -> Result<Self, String> {
+        if pattern == FRAME_MAGIC {
+            return Err(format!("preamble pattern 0x{:02X} is the frame magic", pattern));
+        }
+        Ok(Self { inner, preamble_len: len, buf: vec![pattern; len] })
+    }
+}
+
+impl Transport for Preamble {
+    // Returns the frame bytes sent, not counting the preamble
+    fn send(&mut self, frame: &[u8]) -> io::Result<usize> {
+        self.buf.truncate(self.preamble_len);
+        self.buf.extend_from_slice(frame);
+        let sent = self.inner.send(&self.buf)?;
+        Ok(sent.saturating_sub(self.preamble_len))
+    }
+}
+
+// Wraps every frame sent through `inner` in the versioned envelope
+pub struct Framed {
+    inner: Box<dyn Transport>,
+    order: Endianness,
+    checksum: Checksum,
+    buf: Vec<u8>, // Reused across frames
+}
+
+impl Framed {
+    pub fn new(inner: Box<dyn Transport>, order: Endianness) -> Self {
+        Self { inner, order, checksum: Checksum::default(), buf: Vec::new() }
+    }
+
+    pub fn with_checksum(mut self, checksum: Checksum) -> Self {
+        self.checksum = checksum;
+        self
+    }
+}
+
+impl Transport for Framed {
+    fn send(&mut self, frame: &[u8]) -> io::Result<usize> {
+        encode_into(frame, self.order, self.checksum, &mut self.buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        self.inner.send(&self.buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envelope_round_trips() {
+        let frame = encode(&[1, 2, 3], Endianness::Big, Checksum::None).unwrap();
+        assert_eq!(frame, [FRAME_MAGIC, FORMAT_VERSION, FLAG_BIG_ENDIAN, 3, 0, 1, 2, 3]);
+
+        let (header, payload) = decode(&frame).unwrap();
+        assert_eq!(header.endianness(), Endianness::Big);
+        assert_eq!(payload, [1, 2, 3]);
+    }
+
+    #[test]
+    fn checksum_trailer_is_verified() {
+        for checksum in [Checksum::Crc16Ccitt, Checksum::Crc32] {
+            let mut stream = encode(&[1, 2, 3], Endianness::Little, checksum).unwrap();
+            assert_eq!(stream.len(), FRAME_HEADER_LEN + 3 + checksum.trailer_len());
+            stream.push(FRAME_MAGIC); // Start of the next frame
+
+            let (header, payload) = decode(&stream).unwrap();
+            assert_eq!((header.checksum(), payload), (Some(checksum), &[1, 2, 3][..]));
+            assert!(matches!(decode(&stream[..stream.len() - 2]), Err(FrameError::Truncated { .. })));
+
+            stream[FRAME_HEADER_LEN + 1] ^= 0x01;
+            assert!(matches!(decode(&stream), Err(FrameError::ChecksumMismatch { .. })));
+        }
+
+        let mut frame = encode(&[1], Endianness::Little, Checksum::None).unwrap();
+        frame[2] |= FLAG_CHECKSUM_MASK;
+        assert_eq!(decode(&frame), Err(FrameError::UnknownChecksum(3)));
+    }
+
+    #[test]
+    fn unknown_version_is_rejected_with_skippable_length() {
+        let mut frame = encode(&[0; 80], Endianness::Little, Checksum::None).unwrap();
+        frame[1] = FORMAT_VERSION + 1;
+
+        let err = decode(&frame).unwrap_err();
+        assert_eq!(err, FrameError::UnsupportedVersion { version: FORMAT_VERSION + 1, length: 80 });
+        assert!(err.to_string().contains(&format!("unsupported frame format version {}", FORMAT_VERSION + 1)));
+    }
+
+    #[test]
+    fn rejects_bad_magic_and_truncation() {
+        let frame = encode(&[9; 10], Endianness::Little, Checksum::None).unwrap();
+        assert_eq!(decode(&frame[1..]), Err(FrameError::BadMagic(FORMAT_VERSION)));
+        assert!(matches!(decode(&frame[..8]), Err(FrameError::Truncated { needed: 15, available: 8 })));
+        assert!(matches!(decode(&frame[..2]), Err(FrameError::Truncated { .. })));
+        assert!(encode(&vec![0; 70_000], Endianness::Little, Checksum::None).is_err());
+    }
+
+    #[test]
+    fn preamble_is_prepended_and_skipped() {
+        #[derive(Clone, Default)]
+        struct Capture(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+        impl Transport for Capture {
+            fn send(&mut self, frame: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(frame);
+                Ok(frame.len())
+            }
+        }
+
+        let capture = Capture::default();
+        let preamble = Preamble::new(Box::new(capture.clone()), DEFAULT_PREAMBLE_PATTERN, 4).unwrap();
+        let mut framed = Framed::new(Box::new(preamble), Endianness::Little);
+        assert_eq!(framed.send(&[1, 2]).unwrap(), FRAME_HEADER_LEN + 2 + Checksum::default().trailer_len());
+
+        let wire = capture.0.lock().unwrap().clone();
+        assert_eq!(&wire[..5], &[0xAA, 0xAA, 0xAA, 0xAA, FRAME_MAGIC]);
+        let (_, payload) = decode(skip_preamble(&wire, DEFAULT_PREAMBLE_PATTERN)).unwrap();
+        assert_eq!(payload, [1, 2]);
+
+        // No preamble at all is fine too
+        let plain = encode(&[3], Endianness::Little, Checksum::None).unwrap();
+        assert_eq!(skip_preamble(&plain, DEFAULT_PREAMBLE_PATTERN), &plain[..]);
+        assert!(Preamble::new(Box::new(capture), FRAME_MAGIC, 4).is_err());
+    }
+
+    #[test]
+    fn reader_honours_byte_order() {
+        let mut out = Vec::new();
+        Endianness::Big.put_u16(&mut out, 0x1234);
+        Endianness::Big.put_f32(&mut out, -1.5);
+        assert_eq!(&out[..2], &[0x12, 0x34]);
+
+        let mut reader = ByteReader::new(&out, Endianness::Big);
+        assert_eq!(reader.u16(), Some(0x1234));
+        assert_eq!(reader.f32(), Some(-1.5));
+        assert!(reader.is_empty());
+        assert_eq!(reader.u8(), None);
+    }
+}
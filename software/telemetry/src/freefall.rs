@@ -0,0 +1,36 @@
+// Free-fall detection. An accelerometer measures everything but gravity, so a payload
+// falling freely (just after burst, before the parachute bites) reads close to zero.
+
+use crate::i2c::MPU6050::AccelerometerReading;
+
+// |accel| below this counts as free fall (m/s², about 0.2g)
+pub const DEFAULT_FREEFALL_THRESHOLD: f32 = 2.0;
+
+// Expects accel in m/s²
+pub fn is_freefall(accel: &AccelerometerReading, threshold_ms2: f32) -> bool {
+    (accel.x * accel.x + accel.y * accel.y + accel.z * accel.z).sqrt() < threshold_ms2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i2c::MPU6050::STANDARD_GRAVITY;
+
+    #[test]
+    fn zero_g_is_freefall() {
+        let falling = AccelerometerReading { x: 0.3, y: -0.2, z: 0.5 };
+        assert!(is_freefall(&falling, DEFAULT_FREEFALL_THRESHOLD));
+        assert!(is_freefall(&AccelerometerReading { x: 0.0, y: 0.0, z: 0.0 }, DEFAULT_FREEFALL_THRESHOLD));
+    }
+
+    #[test]
+    fn one_g_is_not_freefall() {
+        let resting = AccelerometerReading { x: 0.0, y: 0.0, z: STANDARD_GRAVITY };
+        assert!(!is_freefall(&resting, DEFAULT_FREEFALL_THRESHOLD));
+        // Magnitude, not a single axis: 1g on its side
+        let tilted = AccelerometerReading { x: STANDARD_GRAVITY * 0.6, y: 0.0, z: STANDARD_GRAVITY * 0.8 };
+        assert!(!is_freefall(&tilted, DEFAULT_FREEFALL_THRESHOLD));
+        // A threshold above 1g would flag the resting payload
+        assert!(is_freefall(&resting, 1.5 * STANDARD_GRAVITY));
+    }
+}
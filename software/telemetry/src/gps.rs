@@ -0,0 +1,501 @@
+// GPS fixes from the NMEA sentences of a receiver on the UART, and the quality gate that
+// decides whether a fix is good enough to report. GGA carries the full fix; RMC updates
+// the position in between and reports when the receiver has lost it.
+//
+// The port is opened raw at the configured baud rate (9600 for most modules), the same
+// way as the radio modem's.
+
+use std::fmt;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::{debug, info, warn};
+
+use crate::altitude::GpsAltitude;
+use crate::coords;
+use crate::serial;
+
+// GGA field 6
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FixQuality {
+    Invalid = 0,
+    Gps = 1,
+    Dgps = 2,
+    Pps = 3,
+    RtkFixed = 4,
+    RtkFloat = 5,
+    Estimated = 6, // Dead reckoning
+}
+
+impl FixQuality {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(FixQuality::Invalid),
+            1 => Some(FixQuality::Gps),
+            2 => Some(FixQuality::Dgps),
+            3 => Some(FixQuality::Pps),
+            4 => Some(FixQuality::RtkFixed),
+            5 => Some(FixQuality::RtkFloat),
+            6 => Some(FixQuality::Estimated),
+            _ => None,
+        }
+    }
+
+    // Estimated and invalid fixes aren't measurements
+    fn is_measured(self) -> bool {
+        !matches!(self, FixQuality::Invalid | FixQuality::Estimated)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GpsFix {
+    pub latitude: f32,  // Degrees, north positive
+    pub longitude: f32, // Degrees, east positive
+    pub altitude: f32,  // m above mean sea level
+    pub quality: FixQuality,
+    pub satellites: u8, // In use for the fix
+    pub hdop: f32,
+}
+
+impl GpsFix {
+    pub fn altitude(&self) -> GpsAltitude {
+        GpsAltitude { altitude: self.altitude, hdop: self.hdop }
+    }
+
+    // A measured position the packet can carry; says nothing about its accuracy, which
+    // is FixGate's call
+    pub fn fix_valid(&self) -> bool {
+        self.quality.is_measured() && self.latitude.is_finite() && self.longitude.is_finite()
+    }
+}
+
+// $GPRMC / $GNRMC: position, speed and course, without altitude or satellite count
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RmcFix {
+    pub valid: bool,    // Status A; V (void) when the receiver has no fix
+    pub latitude: f32,  // NaN when void
+    pub longitude: f32,
+    pub speed_knots: f32,  // Over ground; NaN when not reported
+    pub course: f32,       // Degrees true; NaN when not reported
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sentence {
+    Gga(GpsFix),
+    Rmc(RmcFix),
+}
+
+// A GGA or RMC sentence; Ok(None) for the other sentences receivers send (GSV, VTG...)
+pub fn parse_sentence(sentence: &str) -> Result<Option<Sentence>, String> {
+    let fields = sentence_fields(sentence)?;
+    match fields[0].get(2..) {
+        Some("GGA") => gga_fields(&fields).map(|fix| Some(Sentence::Gga(fix))),
+        Some("RMC") => rmc_fields(&fields).map(|fix| Some(Sentence::Rmc(fix))),
+        _ => Ok(None),
+    }
+}
+
+// $GPGGA / $GNGGA, e.g.
+//   $GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47
+// The checksum is verified when present.
+pub fn parse_gga(sentence: &str) -> Result<GpsFix, String> {
+    gga_fields(&sentence_fields(sentence)?)
+}
+
+// $GPRMC / $GNRMC, e.g.
+//   $GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A
+pub fn parse_rmc(sentence: &str) -> Result<RmcFix, String> {
+    rmc_fields(&sentence_fields(sentence)?)
+}
+
+// Comma-separated fields after the '$', talker and type first, once the checksum matches
+fn sentence_fields(sentence: &str) -> Result<Vec<&str>, String> {
+    let sentence = sentence.trim();
+    let body = sentence.strip_prefix('$').ok_or("NMEA sentence must start with '$'")?;
+    let body = match body.split_once('*') {
+        Some((body, checksum)) => {
+            let expected = u8::from_str_radix(checksum, 16).map_err(|_| format!("bad checksum field {:?}", checksum))?;
+            let actual = body.bytes().fold(0, |sum, b| sum ^ b);
+            if actual != expected {
+                return Err(format!("checksum mismatch: sentence has {:02X}, computed {:02X}", expected, actual));
+            }
+            body
+        }
+        None => body,
+    };
+    Ok(body.split(',').collect())
+}
+
+fn rmc_fields(fields: &[&str]) -> Result<RmcFix, String> {
+    if fields.len() < 10 || !fields[0].ends_with("RMC") {
+        return Err(format!("not an RMC sentence: {}", fields.join(",")));
+    }
+    let optional = |index: usize| fields[index].parse().unwrap_or(f32::NAN);
+    let speed_knots = optional(7);
+    let course = optional(8);
+
+    match fields[2] {
+        "A" => {
+            let number = |index: usize, name: &str| -> Result<f32, String> {
+                fields[index].parse().map_err(|_| format!("bad {} {:?}", name, fields[index]))
+            };
+            Ok(RmcFix {
+                valid: true,
+                latitude: hemisphere(degrees_minutes(number(3, "latitude")?), fields[4], 'N', 'S')?,
+                longitude: hemisphere(degrees_minutes(number(5, "longitude")?), fields[6], 'E', 'W')?,
+                speed_knots,
+                course,
+            })
+        }
+        "V" => Ok(RmcFix { valid: false, latitude: f32::NAN, longitude: f32::NAN, speed_knots, course }),
+        status => Err(format!("bad RMC status {:?}", status)),
+    }
+}
+
+fn gga_fields(fields: &[&str]) -> Result<GpsFix, String> {
+    if fields.len() < 10 || !fields[0].ends_with("GGA") {
+        return Err(format!("not a GGA sentence: {}", fields.join(",")));
+    }
+    let number = |index: usize, name: &str| -> Result<f32, String> {
+        fields[index].parse().map_err(|_| format!("bad {} {:?}", name, fields[index]))
+    };
+
+    let quality = fields[6].parse().ok().and_then(FixQuality::from_u8)
+        .ok_or_else(|| format!("bad fix quality {:?}", fields[6]))?;
+    let satellites = fields[7].parse().map_err(|_| format!("bad satellite count {:?}", fields[7]))?;
+    if quality == FixQuality::Invalid {
+        // Receivers leave the position fields empty without a fix
+        return Ok(GpsFix { latitude: f32::NAN, longitude: f32::NAN, altitude: f32::NAN, quality, satellites, hdop: f32::NAN });
+    }
+
+    Ok(GpsFix {
+        latitude: hemisphere(degrees_minutes(number(2, "latitude")?), fields[3], 'N', 'S')?,
+        longitude: hemisphere(degrees_minutes(number(4, "longitude")?), fields[5], 'E', 'W')?,
+        altitude: number(9, "altitude")?,
+        quality,
+        satellites,
+        hdop: number(8, "HDOP")?,
+    })
+}
+
+// NMEA's (d)ddmm.mmmm
+fn degrees_minutes(value: f32) -> f32 {
+    let degrees = (value / 100.0).trunc();
+    coords::from_degrees_minutes(degrees as i32, value - degrees * 100.0)
+}
+
+fn hemisphere(deg: f32, field: &str, positive: char, negative: char) -> Result<f32, String> {
+    match field.chars().next() {
+        Some(c) if c == positive => Ok(deg),
+        Some(c) if c == negative => Ok(-deg),
+        _ => Err(format!("bad hemisphere {:?}", field)),
+    }
+}
+
+// Folds the sentence stream into one fix. GGA replaces it; a valid RMC moves its position
+// and a void one marks it invalid until the next GGA says otherwise. RMC alone has no
+// altitude, so nothing is reported before the first GGA.
+#[derive(Debug, Clone, Default)]
+pub struct NmeaDecoder {
+    fix: Option<GpsFix>,
+}
+
+impl NmeaDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The fix after this line, if the line changed it
+    pub fn push_line(&mut self, line: &str) -> Result<Option<GpsFix>, String> {
+        match parse_sentence(line)? {
+            Some(Sentence::Gga(fix)) => self.fix = Some(fix),
+            Some(Sentence::Rmc(rmc)) => {
+                let Some(fix) = self.fix.as_mut() else { return Ok(None) };
+                if rmc.valid {
+                    fix.latitude = rmc.latitude;
+                    fix.longitude = rmc.longitude;
+                } else {
+                    fix.quality = FixQuality::Invalid;
+                }
+            }
+            None => return Ok(None),
+        }
+        Ok(self.fix)
+    }
+
+    pub fn fix(&self) -> Option<GpsFix> {
+        self.fix
+    }
+}
+
+// Reads the receiver on a background thread, since the UART read blocks until the next
+// sentence arrives (once a second on most modules)
+pub struct GpsReceiver {
+    latest: Arc<Mutex<Option<(GpsFix, Instant)>>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl GpsReceiver {
+    // e.g. /dev/serial0 for the Pi's GPIO 14/15 UART, /dev/ttyACM0 for a USB receiver
+    pub fn open(device: &Path, baud: u32) -> io::Result<Self> {
+        Self::spawn(BufReader::new(serial::open_port(device, baud)?))
+    }
+
+    pub fn spawn(mut reader: impl BufRead + Send + 'static) -> io::Result<Self> {
+        let latest = Arc::new(Mutex::new(None));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let (thread_latest, thread_stop) = (Arc::clone(&latest), Arc::clone(&stop));
+        thread::Builder::new().name("gps".to_string()).spawn(move || {
+            let mut decoder = NmeaDecoder::new();
+            let mut line = Vec::new();
+            while !thread_stop.load(Ordering::Relaxed) {
+                match reader.read_until(b'\n', &mut line) {
+                    Ok(0) => {
+                        warn!("GPS stream ended");
+                        break;
+                    }
+                    // Line noise at power-up isn't always UTF-8; the checksum rejects it
+                    Ok(_) => match decoder.push_line(&String::from_utf8_lossy(&line)) {
+                        Ok(Some(fix)) => {
+                            *thread_latest.lock().unwrap_or_else(PoisonError::into_inner) = Some((fix, Instant::now()));
+                        }
+                        Ok(None) => {}
+                        Err(e) => debug!("Skipping NMEA line: {}", e),
+                    },
+                    // The port times out while the receiver is quiet; keep any partial line
+                    Err(e) if e.kind() == io::ErrorKind::TimedOut => continue,
+                    Err(e) => {
+                        warn!("Failed to read GPS: {}", e);
+                        break;
+                    }
+                }
+                line.clear();
+            }
+        })?;
+
+        Ok(Self { latest, stop })
+    }
+
+    // Most recent fix, or None if there is none newer than `max_age`
+    pub fn latest(&self, max_age: Duration) -> Option<GpsFix> {
+        let latest = *self.latest.lock().unwrap_or_else(PoisonError::into_inner);
+        latest.filter(|(_, at)| at.elapsed() <= max_age).map(|(fix, _)| fix)
+    }
+}
+
+impl Drop for GpsReceiver {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+// Preflight: waits up to `timeout` for the receiver to report a valid fix
+pub fn check_fix(device: &Path, baud: u32, timeout: Duration) -> Result<String, String> {
+    let receiver = GpsReceiver::open(device, baud).map_err(|e| format!("{}: {}", device.display(), e))?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        match receiver.latest(timeout) {
+            Some(fix) if fix.fix_valid() => {
+                return Ok(format!("{:.5}, {:.5} at {:.0} m, {} satellites, HDOP {:.1}",
+                                  fix.latitude, fix.longitude, fix.altitude, fix.satellites, fix.hdop));
+            }
+            latest if Instant::now() >= deadline => {
+                return Err(match latest {
+                    Some(fix) => format!("no fix after {:?} ({} satellites)", timeout, fix.satellites),
+                    None => format!("no GGA sentence from {} after {:?}", device.display(), timeout),
+                });
+            }
+            _ => thread::sleep(Duration::from_millis(100)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixRequirements {
+    pub min_satellites: u8, // 3-4 satellites can put the fix kilometres out
+    pub min_quality: FixQuality,
+}
+
+impl Default for FixRequirements {
+    fn default() -> Self {
+        Self { min_satellites: 6, min_quality: FixQuality::Gps }
+    }
+}
+
+impl fmt::Display for FixRequirements {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} satellites, quality {:?}", self.min_satellites, self.min_quality)
+    }
+}
+
+// Passes fixes that meet the requirements and holds the last of them through marginal
+// ones, so the ground isn't sent chasing a phantom position
+#[derive(Debug, Clone, Default)]
+pub struct FixGate {
+    requirements: FixRequirements,
+    last_good: Option<GpsFix>,
+    reliable: Option<bool>, // None until the first fix
+}
+
+impl FixGate {
+    pub fn new(requirements: FixRequirements) -> Self {
+        Self { requirements, ..Self::default() }
+    }
+
+    // The fix to report: this one if it is reliable, otherwise the last one that was
+    // (None before any). When is_reliable() is false the packet carries
+    // POSITION_STALE.
+    pub fn apply(&mut self, fix: &GpsFix) -> Option<GpsFix> {
+        let r = &self.requirements;
+        let reliable = fix.quality.is_measured() && fix.quality >= r.min_quality && fix.satellites >= r.min_satellites
+            && fix.latitude.is_finite() && fix.longitude.is_finite();
+
+        if self.reliable != Some(reliable) {
+            if reliable {
+                info!("GPS fix reliable: {} satellites, HDOP {:.1}, quality {:?}", fix.satellites, fix.hdop, fix.quality);
+            } else {
+                warn!("GPS fix unreliable ({} satellites, quality {:?}; need {}) - holding the last good position",
+                      fix.satellites, fix.quality, r);
+            }
+            self.reliable = Some(reliable);
+        }
+
+        if reliable {
+            self.last_good = Some(*fix);
+        }
+        self.last_good
+    }
+
+    pub fn is_reliable(&self) -> bool {
+        self.reliable == Some(true)
+    }
+
+    pub fn last_good(&self) -> Option<GpsFix> {
+        self.last_good
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GGA: &str = "$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47";
+    const RMC: &str = "$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A";
+
+    #[test]
+    fn parses_gga() {
+        let fix = parse_gga(GGA).unwrap();
+        assert!((fix.latitude - 48.1173).abs() < 1e-4);
+        assert!((fix.longitude - 11.516_667).abs() < 1e-4);
+        assert_eq!((fix.altitude, fix.quality, fix.satellites, fix.hdop), (545.4, FixQuality::Gps, 8, 0.9));
+
+        let south_west = parse_gga("$GNGGA,000000,3351.410,S,15112.900,W,2,10,1.1,10.0,M,,M,,").unwrap();
+        assert!(south_west.latitude < 0.0 && south_west.longitude < 0.0);
+
+        let no_fix = parse_gga("$GPGGA,123519,,,,,0,00,,,M,,M,,").unwrap();
+        assert_eq!(no_fix.quality, FixQuality::Invalid);
+        assert!(no_fix.latitude.is_nan());
+
+        assert!(parse_gga(&GGA.replace("*47", "*48")).is_err());
+        assert!(parse_gga(RMC).is_err());
+    }
+
+    #[test]
+    fn parses_rmc_and_degrees_minutes() {
+        // 4807.038 is 48° 07.038' = 48 + 7.038 / 60
+        let fix = parse_rmc(RMC).unwrap();
+        assert!(fix.valid);
+        assert!((fix.latitude - 48.1173).abs() < 1e-4);
+        assert!((fix.longitude - 11.516_667).abs() < 1e-4);
+        assert_eq!((fix.speed_knots, fix.course), (22.4, 84.4));
+        assert!((degrees_minutes(12000.0) - 120.0).abs() < 1e-4);
+        assert!((degrees_minutes(3351.41) - 33.856_83).abs() < 1e-4);
+
+        let void = parse_rmc("$GNRMC,235959.00,V,,,,,,,140126,,,N*62").unwrap();
+        assert!(!void.valid && void.latitude.is_nan());
+        assert_eq!(parse_sentence("$GPGSV,1,1,00*79").unwrap(), None);
+        assert!(parse_rmc(GGA).is_err());
+        assert!(parse_rmc(&RMC.replace(",A,", ",X,")).is_err());
+    }
+
+    #[test]
+    fn decoder_follows_gga_and_rmc() {
+        let mut decoder = NmeaDecoder::new();
+        assert_eq!(decoder.push_line(RMC).unwrap(), None); // No altitude yet
+        let fix = decoder.push_line(GGA).unwrap().unwrap();
+        assert!(fix.fix_valid());
+
+        let moved = decoder.push_line("$GPRMC,123520,A,4808.000,N,01131.000,E,022.4,084.4,230394,003.1,W").unwrap().unwrap();
+        assert!((moved.latitude - 48.133_33).abs() < 1e-4);
+        assert_eq!(moved.altitude, 545.4);
+
+        let lost = decoder.push_line("$GPRMC,123521,V,,,,,,,230394,,").unwrap().unwrap();
+        assert!(!lost.fix_valid());
+        assert!(decoder.push_line("$GPGGA,garbage*00").is_err());
+    }
+
+    // Hands out its chunks with a read timeout between each, like a quiet serial port
+    struct Stalling(Vec<Vec<u8>>, bool);
+
+    impl io::Read for Stalling {
+        fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+            self.1 = !self.1;
+            if self.1 {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            match self.0.first_mut() {
+                Some(chunk) => {
+                    let n = chunk.len().min(buffer.len());
+                    buffer[..n].copy_from_slice(&chunk[..n]);
+                    chunk.drain(..n);
+                    if chunk.is_empty() {
+                        self.0.remove(0);
+                    }
+                    Ok(n)
+                }
+                None => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn receiver_reads_sentences_from_the_port() {
+        let stream = format!("\u{0}\u{ff}noise\r\n$GPGSV,1,1,00*79\r\n{}\r\n{}\r\n", GGA, RMC).into_bytes();
+        // The GGA sentence split by a timeout
+        let (first, rest) = stream.split_at(40);
+        let port = Stalling(vec![first.to_vec(), rest.to_vec()], false);
+        let receiver = GpsReceiver::spawn(BufReader::new(port)).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while receiver.latest(Duration::from_secs(5)).is_none() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        let fix = receiver.latest(Duration::from_secs(5)).unwrap();
+        assert!(fix.fix_valid());
+        assert_eq!(fix.satellites, 8);
+    }
+
+    #[test]
+    fn gate_holds_the_last_good_fix() {
+        let mut gate = FixGate::new(FixRequirements::default());
+        let good = parse_gga(GGA).unwrap();
+        let marginal = GpsFix { latitude: 10.0, satellites: 4, ..good };
+
+        assert_eq!(gate.apply(&marginal), None);
+        assert!(!gate.is_reliable());
+
+        assert_eq!(gate.apply(&good), Some(good));
+        assert!(gate.is_reliable());
+
+        assert_eq!(gate.apply(&marginal), Some(good));
+        assert_eq!(gate.apply(&GpsFix { quality: FixQuality::Estimated, ..good }), Some(good));
+        assert!(!gate.is_reliable());
+    }
+}
@@ -0,0 +1,145 @@
+// Relative heading from integrating the gyroscope's Z axis. Without a magnetometer this
+// is dead reckoning: it starts at 0° wherever the payload points at startup (or the
+// last reset) and drifts by the uncorrected gyro bias, degrees per second of flight.
+// Good for spin rate and rough orientation, not for absolute direction. With one, each
+// compass reading realigns it, and it only bridges the gaps between them.
+//
+// Headings turn clockwise seen from above, like a compass's. The MPU6050's Z axis points
+// up, so a positive gyro Z (counterclockwise) turns the heading down.
+
+use std::time::{Duration, Instant};
+
+// Longer gaps between samples (a stalled loop) aren't integrated, since the rate in
+// between is unknown
+const MAX_STEP: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub struct HeadingTracker {
+    heading_deg: f32,
+    bias_dps: f32, // Subtracted from every gyro Z sample
+    last_sample: Option<Instant>,
+}
+
+impl HeadingTracker {
+    pub fn new(bias_dps: f32) -> Self {
+        Self { heading_deg: 0.0, bias_dps, last_sample: None }
+    }
+
+    // Integrates one gyro Z sample (°/s, counterclockwise positive) and returns the heading in [0, 360)
+    pub fn update(&mut self, gyro_z_dps: f32, now: Instant) -> f32 {
+        if let Some(last) = self.last_sample {
+            let dt = now.saturating_duration_since(last);
+            if gyro_z_dps.is_finite() && dt <= MAX_STEP {
+                let heading = self.heading_deg - (gyro_z_dps - self.bias_dps) * dt.as_secs_f32();
+                self.heading_deg = heading.rem_euclid(360.0);
+            }
+        }
+        self.last_sample = Some(now);
+        self.heading()
+    }
+
+    pub fn heading(&self) -> f32 {
+        // rem_euclid can round up to exactly 360.0 for tiny negative headings
+        if self.heading_deg >= 360.0 { 0.0 } else { self.heading_deg }
+    }
+
+    pub fn set_bias(&mut self, bias_dps: f32) {
+        self.bias_dps = bias_dps;
+    }
+
+    pub fn bias(&self) -> f32 {
+        self.bias_dps
+    }
+
+    // Declare the current orientation 0°
+    pub fn reset(&mut self) {
+        self.heading_deg = 0.0;
+    }
+
+    // Adopt an absolute heading (from the compass); integration carries on from it
+    pub fn align(&mut self, heading_deg: f32) {
+        if heading_deg.is_finite() {
+            self.heading_deg = heading_deg.rem_euclid(360.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i2c::HMC5883L::compass_heading;
+
+    const STEP: Duration = Duration::from_millis(100);
+
+    #[test]
+    fn integrates_and_wraps_both_ways() {
+        let start = Instant::now();
+        let mut tracker = HeadingTracker::new(0.0);
+        assert_eq!(tracker.update(90.0, start), 0.0);
+
+        // 1 s at 90°/s counterclockwise wraps below zero
+        let mut now = start;
+        for _ in 0..10 {
+            now += STEP;
+            tracker.update(90.0, now);
+        }
+        assert!((tracker.heading() - 270.0).abs() < 0.01);
+
+        // 3 s at 90°/s clockwise wraps past 360
+        for _ in 0..30 {
+            now += STEP;
+            tracker.update(-90.0, now);
+        }
+        assert!((tracker.heading() - 180.0).abs() < 0.01);
+
+        tracker.reset();
+        assert_eq!(tracker.heading(), 0.0);
+
+        tracker.align(f32::NAN);
+        assert_eq!(tracker.heading(), 0.0);
+    }
+
+    #[test]
+    fn turns_the_same_way_as_the_compass_after_aligning() {
+        // Horizontal field in sensor axes (X forward, Y left) with the payload facing `heading`
+        let field = |heading: f32| {
+            let north = heading.to_radians(); // Counterclockwise from the nose to north
+            (0.3 * north.cos(), 0.3 * north.sin())
+        };
+        let (x, y) = field(350.0);
+        let compass = compass_heading(x, y, 0.0);
+        assert!((compass - 350.0).abs() < 0.01);
+
+        let start = Instant::now();
+        let mut tracker = HeadingTracker::new(0.0);
+        tracker.update(0.0, start);
+        tracker.align(compass);
+
+        // Turning clockwise at 30°/s (negative gyro Z) with the compass out
+        for i in 1..=10 {
+            let heading = tracker.update(-30.0, start + STEP * i);
+            let (x, y) = field(350.0 + 3.0 * i as f32);
+            let compass = compass_heading(x, y, 0.0);
+            let error = (heading - compass + 180.0).rem_euclid(360.0) - 180.0;
+            assert!(error.abs() < 0.01, "gyro {}°, compass {}°", heading, compass);
+        }
+        assert!((tracker.heading() - 20.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn bias_cancels_drift_and_gaps_are_skipped() {
+        let start = Instant::now();
+        let mut tracker = HeadingTracker::new(1.5);
+        tracker.update(1.5, start);
+        for i in 1..=50 {
+            tracker.update(1.5, start + STEP * i);
+        }
+        assert!(tracker.heading().abs() < 1e-4);
+
+        // A 5 s stall isn't integrated
+        tracker.set_bias(0.0);
+        tracker.update(100.0, start + STEP * 50 + Duration::from_secs(5));
+        assert!(tracker.heading().abs() < 1e-4);
+        assert!(!tracker.update(f32::NAN, start + STEP * 52).is_nan());
+    }
+}
@@ -0,0 +1,202 @@
+// ADS1115 I2C driver for 16-bit single-ended voltage reads (battery monitoring)
+
+use super::sensor::{with_retry, SensorError, READ_ATTEMPTS};
+use super::I2cBus;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+// ADDR pin to GND; VDD, SDA and SCL select 0x49-0x4B
+pub const ADS1115_ADDRESS: u8 = 0x48;
+
+// ADS1115 register pointers (16-bit registers, MSB first)
+const REGISTER_CONVERSION: u8 = 0x00;
+const REGISTER_CONFIG: u8 = 0x01;
+
+// Config register fields
+const CONFIG_OS_SINGLE: u16 = 0x8000;       // Write: start a conversion. Read: 1 = idle
+const CONFIG_MUX_SINGLE_AIN0: u16 = 0x4000; // AINx vs GND, channel in bits 13:12
+const CONFIG_MODE_SINGLE_SHOT: u16 = 0x0100;
+const CONFIG_DR_128SPS: u16 = 0x0080;
+const CONFIG_COMP_QUE_DISABLE: u16 = 0x0003;
+
+// One conversion takes ~7.8ms at 128SPS
+const CONVERSION_TIMEOUT: Duration = Duration::from_millis(20);
+const CONVERSION_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+// Programmable gain: the input range that maps onto the full 16-bit scale. Inputs must
+// still stay within GND..VDD whatever the range.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gain {
+    PGA_6_144V = 0x0000,
+    PGA_4_096V = 0x0200,
+    PGA_2_048V = 0x0400,
+    PGA_1_024V = 0x0600,
+    PGA_0_512V = 0x0800,
+    PGA_0_256V = 0x0A00,
+}
+
+impl Gain {
+    pub fn full_scale_volts(self) -> f32 {
+        self.full_scale_millivolts() as f32 / 1000.0
+    }
+
+    pub fn full_scale_millivolts(self) -> u16 {
+        match self {
+            Gain::PGA_6_144V => 6144,
+            Gain::PGA_4_096V => 4096,
+            Gain::PGA_2_048V => 2048,
+            Gain::PGA_1_024V => 1024,
+            Gain::PGA_0_512V => 512,
+            Gain::PGA_0_256V => 256,
+        }
+    }
+
+    pub fn from_millivolts(millivolts: u16) -> Option<Self> {
+        [Gain::PGA_6_144V, Gain::PGA_4_096V, Gain::PGA_2_048V, Gain::PGA_1_024V, Gain::PGA_0_512V, Gain::PGA_0_256V]
+            .into_iter()
+            .find(|gain| gain.full_scale_millivolts() == millivolts)
+    }
+}
+
+pub struct ADS1115<B: I2cBus> {
+    i2c: B,
+    channel: u8,
+    gain: Gain,
+}
+
+impl<B: I2cBus> ADS1115<B> {
+    pub fn new(mut i2c: B, address: u8, channel: u8, gain: Gain) -> Result<Self, SensorError> {
+        if !(ADS1115_ADDRESS..=ADS1115_ADDRESS + 3).contains(&address) {
+            return Err(SensorError::InvalidConfig(format!("Invalid ADS1115 address 0x{:02X}", address)));
+        }
+        i2c.set_slave_address(address as u16)?;
+
+        let mut sensor = Self { i2c, channel: 0, gain };
+        sensor.set_channel(channel)?;
+
+        // Confirms the device answers; the ADC itself has no identity register
+        let config = sensor.read_register(REGISTER_CONFIG)?;
+        info!("ADS1115 initialized successfully (config 0x{:04X}, channel {}, ±{} V)",
+                 config, channel, gain.full_scale_volts());
+
+        Ok(sensor)
+    }
+
+    pub fn set_channel(&mut self, channel: u8) -> Result<(), SensorError> {
+        if channel > 3 {
+            return Err(SensorError::InvalidConfig(format!("ADS1115 channel must be 0-3, got {}", channel)));
+        }
+        self.channel = channel;
+        Ok(())
+    }
+
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+
+    pub fn set_gain(&mut self, gain: Gain) {
+        self.gain = gain;
+    }
+
+    pub fn gain(&self) -> Gain {
+        self.gain
+    }
+
+    // Single-shot conversion of the selected channel against GND, in volts at the pin
+    pub fn read_voltage(&mut self) -> Result<f32, SensorError> {
+        let config = CONFIG_OS_SINGLE
+            | CONFIG_MUX_SINGLE_AIN0
+            | (self.channel as u16) << 12
+            | self.gain as u16
+            | CONFIG_MODE_SINGLE_SHOT
+            | CONFIG_DR_128SPS
+            | CONFIG_COMP_QUE_DISABLE;
+        let [msb, lsb] = config.to_be_bytes();
+        self.i2c.write(&[REGISTER_CONFIG, msb, lsb])?;
+
+        let deadline = Instant::now() + CONVERSION_TIMEOUT;
+        while self.read_register(REGISTER_CONFIG)? & CONFIG_OS_SINGLE == 0 {
+            if Instant::now() >= deadline {
+                return Err(SensorError::Timeout { what: "ADS1115 conversion", after: CONVERSION_TIMEOUT });
+            }
+            thread::sleep(CONVERSION_POLL_INTERVAL);
+        }
+
+        let raw = self.read_register(REGISTER_CONVERSION)? as i16;
+        Ok(raw as f32 * self.gain.full_scale_volts() / 32768.0)
+    }
+
+    fn read_register(&mut self, register: u8) -> Result<u16, SensorError> {
+        let mut raw = [0u8; 2];
+        with_retry(READ_ATTEMPTS, || self.i2c.write_read(&[register], &mut raw))?;
+        Ok(u16::from_be_bytes(raw))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 16-bit register file; conversions complete instantly with a fixed result
+    struct FakeAds {
+        config: u16,
+        conversion: i16,
+        writes: Vec<u16>,
+    }
+
+    impl I2cBus for FakeAds {
+        fn set_slave_address(&mut self, _address: u16) -> Result<(), SensorError> {
+            Ok(())
+        }
+
+        fn write(&mut self, buffer: &[u8]) -> Result<(), SensorError> {
+            assert_eq!(buffer[0], REGISTER_CONFIG);
+            self.config = u16::from_be_bytes([buffer[1], buffer[2]]);
+            self.writes.push(self.config);
+            Ok(())
+        }
+
+        fn write_read(&mut self, write_buffer: &[u8], read_buffer: &mut [u8]) -> Result<(), SensorError> {
+            let value = match write_buffer[0] {
+                REGISTER_CONVERSION => self.conversion as u16,
+                _ => self.config | CONFIG_OS_SINGLE,
+            };
+            read_buffer.copy_from_slice(&value.to_be_bytes());
+            Ok(())
+        }
+    }
+
+    fn adc(conversion: i16, channel: u8, gain: Gain) -> ADS1115<FakeAds> {
+        let bus = FakeAds { config: 0x8583, conversion, writes: Vec::new() };
+        ADS1115::new(bus, ADS1115_ADDRESS, channel, gain).unwrap()
+    }
+
+    #[test]
+    fn single_shot_read_scales_by_gain() {
+        let mut sensor = adc(16384, 2, Gain::PGA_4_096V);
+        assert!((sensor.read_voltage().unwrap() - 2.048).abs() < 1e-4);
+
+        // OS | AIN2 vs GND | ±4.096V | single-shot | 128SPS | comparator off
+        assert_eq!(sensor.i2c.writes, vec![0xE383]);
+
+        sensor.set_gain(Gain::PGA_0_256V);
+        assert!((sensor.read_voltage().unwrap() - 0.128).abs() < 1e-5);
+    }
+
+    #[test]
+    fn negative_codes_read_below_ground() {
+        let mut sensor = adc(-32768, 0, Gain::PGA_2_048V);
+        assert!((sensor.read_voltage().unwrap() + 2.048).abs() < 1e-4);
+    }
+
+    #[test]
+    fn rejects_bad_channel_and_address() {
+        let bus = || FakeAds { config: 0x8583, conversion: 0, writes: Vec::new() };
+        assert!(ADS1115::new(bus(), ADS1115_ADDRESS, 4, Gain::PGA_4_096V).is_err());
+        assert!(ADS1115::new(bus(), 0x60, 0, Gain::PGA_4_096V).is_err());
+        assert_eq!(Gain::from_millivolts(512), Some(Gain::PGA_0_512V));
+        assert_eq!(Gain::from_millivolts(5000), None);
+    }
+}
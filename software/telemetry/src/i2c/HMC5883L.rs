@@ -0,0 +1,220 @@
+// HMC5883L I2C driver: three-axis magnetometer, the absolute heading reference the
+// integrated gyro lacks. Many GY-271 breakouts sold as HMC5883L carry the
+// pin-compatible QMC5883L instead, which has its own address and register map; both
+// are handled here, the chip chosen at construction.
+
+use super::sensor::{with_retry, SensorError, READ_ATTEMPTS};
+use super::I2cBus;
+use tracing::info;
+
+pub const HMC5883L_ADDRESS: u8 = 0x1E;
+pub const QMC5883L_ADDRESS: u8 = 0x0D;
+
+// HMC5883L registers. Data is X, Z, Y (not X, Y, Z), each MSB first.
+const HMC_REGISTER_CONFIG_A: u8 = 0x00;
+const HMC_REGISTER_CONFIG_B: u8 = 0x01;
+const HMC_REGISTER_MODE: u8 = 0x02;
+const HMC_REGISTER_DATA: u8 = 0x03;
+const HMC_REGISTER_IDENTITY: u8 = 0x0A; // Three bytes, "H43"
+const HMC_IDENTITY: [u8; 3] = *b"H43";
+
+const HMC_CONFIG_A_8_AVERAGES_15HZ: u8 = 0x70;
+const HMC_CONFIG_B_GAIN_1_3_GAUSS: u8 = 0x20; // ±1.3 Ga, 1090 LSB/Ga: the Earth's field is 0.25-0.65 Ga
+const HMC_MODE_CONTINUOUS: u8 = 0x00;
+const HMC_OVERFLOW: i16 = -4096; // Written to an axis whose ADC overflowed
+
+// QMC5883L registers. Data is X, Y, Z, each LSB first.
+const QMC_REGISTER_DATA: u8 = 0x00;
+const QMC_REGISTER_STATUS: u8 = 0x06;
+const QMC_REGISTER_CONTROL_1: u8 = 0x09;
+const QMC_REGISTER_SET_RESET: u8 = 0x0B;
+const QMC_REGISTER_CHIP_ID: u8 = 0x0D;
+const QMC_CHIP_ID: u8 = 0xFF;
+
+const QMC_CONTROL_1_CONTINUOUS_50HZ_2G: u8 = 0x05; // 512x oversampling, ±2 G, 12000 LSB/G
+const QMC_SET_RESET_PERIOD: u8 = 0x01; // The datasheet's recommended value
+const QMC_STATUS_OVERFLOW: u8 = 0x02;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip {
+    Hmc5883l,
+    Qmc5883l,
+}
+
+impl Chip {
+    pub fn name(self) -> &'static str {
+        match self {
+            Chip::Hmc5883l => "HMC5883L",
+            Chip::Qmc5883l => "QMC5883L",
+        }
+    }
+
+    pub fn address(self) -> u8 {
+        match self {
+            Chip::Hmc5883l => HMC5883L_ADDRESS,
+            Chip::Qmc5883l => QMC5883L_ADDRESS,
+        }
+    }
+}
+
+// Compass heading in degrees clockwise from north (0-360), from the horizontal field
+// components with X forward, Y to the left and Z up: the MPU6050's axes, which the
+// magnetometer's are mounted to match. Facing east, north is to the left, on +Y, so
+// the heading is atan2(y, x). Declination (east positive) turns magnetic north into
+// true north. Only valid with the sensor near level; the payload hangs that way under
+// the balloon, but not while tumbling after burst.
+pub fn compass_heading(x: f32, y: f32, declination_deg: f32) -> f32 {
+    (y.atan2(x).to_degrees() + declination_deg).rem_euclid(360.0)
+}
+
+pub struct HMC5883L<B: I2cBus> {
+    i2c: B,
+    chip: Chip,
+    declination_deg: f32,
+}
+
+impl<B: I2cBus> HMC5883L<B> {
+    pub fn new(mut i2c: B, chip: Chip, declination_deg: f32) -> Result<Self, SensorError> {
+        if !(-180.0..=180.0).contains(&declination_deg) {
+            return Err(SensorError::InvalidConfig(format!("Magnetic declination must be within ±180°, got {}", declination_deg)));
+        }
+        i2c.set_slave_address(chip.address() as u16)?;
+
+        let mut sensor = Self { i2c, chip, declination_deg };
+        match chip {
+            Chip::Hmc5883l => {
+                let mut identity = [0u8; 3];
+                sensor.read_registers(HMC_REGISTER_IDENTITY, &mut identity)?;
+                if let Some((&found, &expected)) = identity.iter().zip(&HMC_IDENTITY).find(|(found, expected)| found != expected) {
+                    return Err(SensorError::IdentityMismatch { expected, found });
+                }
+                sensor.i2c.write(&[HMC_REGISTER_CONFIG_A, HMC_CONFIG_A_8_AVERAGES_15HZ])?;
+                sensor.i2c.write(&[HMC_REGISTER_CONFIG_B, HMC_CONFIG_B_GAIN_1_3_GAUSS])?;
+                sensor.i2c.write(&[HMC_REGISTER_MODE, HMC_MODE_CONTINUOUS])?;
+                sensor.verify(HMC_REGISTER_CONFIG_A, HMC_CONFIG_A_8_AVERAGES_15HZ)?;
+            }
+            Chip::Qmc5883l => {
+                let mut id = [0u8];
+                sensor.read_registers(QMC_REGISTER_CHIP_ID, &mut id)?;
+                if id[0] != QMC_CHIP_ID {
+                    return Err(SensorError::IdentityMismatch { expected: QMC_CHIP_ID, found: id[0] });
+                }
+                sensor.i2c.write(&[QMC_REGISTER_SET_RESET, QMC_SET_RESET_PERIOD])?;
+                sensor.i2c.write(&[QMC_REGISTER_CONTROL_1, QMC_CONTROL_1_CONTINUOUS_50HZ_2G])?;
+                sensor.verify(QMC_REGISTER_CONTROL_1, QMC_CONTROL_1_CONTINUOUS_50HZ_2G)?;
+            }
+        }
+        info!("{} initialized successfully (declination {:+.1}°)", chip.name(), declination_deg);
+
+        Ok(sensor)
+    }
+
+    pub fn chip(&self) -> Chip {
+        self.chip
+    }
+
+    // The latest X, Y, Z measurement in raw counts, whichever order the chip stores it
+    pub fn read_raw(&mut self) -> Result<[i16; 3], SensorError> {
+        match self.chip {
+            Chip::Hmc5883l => {
+                let mut raw = [0u8; 6];
+                self.read_registers(HMC_REGISTER_DATA, &mut raw)?;
+                let axis = |i: usize| i16::from_be_bytes([raw[i], raw[i + 1]]);
+                let (x, z, y) = (axis(0), axis(2), axis(4));
+                if [x, y, z].contains(&HMC_OVERFLOW) {
+                    return Err(SensorError::OutOfRange("HMC5883L measurement overflowed its range".to_string()));
+                }
+                Ok([x, y, z])
+            }
+            Chip::Qmc5883l => {
+                // Data and status in one transfer, so the overflow bit matches the data
+                let mut raw = [0u8; 7];
+                self.read_registers(QMC_REGISTER_DATA, &mut raw)?;
+                if raw[QMC_REGISTER_STATUS as usize] & QMC_STATUS_OVERFLOW != 0 {
+                    return Err(SensorError::OutOfRange("QMC5883L measurement overflowed its range".to_string()));
+                }
+                let axis = |i: usize| i16::from_le_bytes([raw[i], raw[i + 1]]);
+                Ok([axis(0), axis(2), axis(4)])
+            }
+        }
+    }
+
+    // Degrees clockwise from true north, 0-360 (see compass_heading)
+    pub fn read_heading(&mut self) -> Result<f32, SensorError> {
+        let [x, y, _] = self.read_raw()?;
+        Ok(compass_heading(x as f32, y as f32, self.declination_deg))
+    }
+
+    fn read_registers(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), SensorError> {
+        with_retry(READ_ATTEMPTS, || self.i2c.write_read(&[register], buffer))
+    }
+
+    fn verify(&mut self, register: u8, wrote: u8) -> Result<(), SensorError> {
+        let mut found = [0u8];
+        self.read_registers(register, &mut found)?;
+        if found[0] != wrote {
+            return Err(SensorError::VerifyFailed { register, wrote: wrote as u16, found: found[0] as u16 });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i2c::mock::MockI2c;
+
+    fn assert_heading(x: f32, y: f32, declination: f32, expected: f32) {
+        let heading = compass_heading(x, y, declination);
+        assert!((heading - expected).abs() < 1e-3, "({}, {}) with {}° declination: {}°, expected {}°", x, y, declination, heading, expected);
+    }
+
+    #[test]
+    fn heading_from_horizontal_field() {
+        assert_heading(0.3, 0.0, 0.0, 0.0);   // Facing magnetic north
+        assert_heading(0.0, 0.3, 0.0, 90.0);
+        assert_heading(-0.3, 0.0, 0.0, 180.0);
+        assert_heading(0.0, -0.3, 0.0, 270.0);
+        assert_heading(0.2, 0.2, 0.0, 45.0);
+        assert_heading(-0.1, -3.0f32.sqrt() * 0.1, 0.0, 240.0);
+
+        // Declination wraps through north in both directions
+        assert_heading(0.3, 0.0, 15.0, 15.0);
+        assert_heading(0.3, 0.0, -10.0, 350.0);
+        assert_heading(0.0, -0.3, 100.0, 10.0);
+    }
+
+    #[test]
+    fn reads_each_chips_axis_order() {
+        // HMC5883L: X, Z, Y big-endian; X = 200, Z = -300, Y = 200 (heading 45°)
+        let mut hmc_bus = MockI2c::new();
+        hmc_bus.load(HMC_REGISTER_IDENTITY, &HMC_IDENTITY);
+        hmc_bus.load(HMC_REGISTER_DATA, &[0x00, 0xC8, 0xFE, 0xD4, 0x00, 0xC8]);
+        let mut hmc = HMC5883L::new(hmc_bus, Chip::Hmc5883l, 0.0).unwrap();
+        assert_eq!(hmc.read_raw().unwrap(), [200, 200, -300]);
+        assert!((hmc.read_heading().unwrap() - 45.0).abs() < 1e-3);
+        assert_eq!(hmc.i2c.slave_address, Some(HMC5883L_ADDRESS as u16));
+        assert!(hmc.i2c.writes.contains(&(HMC_REGISTER_MODE, HMC_MODE_CONTINUOUS)));
+
+        hmc.i2c.load(HMC_REGISTER_DATA, &HMC_OVERFLOW.to_be_bytes());
+        assert!(matches!(hmc.read_raw(), Err(SensorError::OutOfRange(_))));
+
+        // QMC5883L: X, Y, Z little-endian; X = -1000, Y = 0 (due south)
+        let mut qmc_bus = MockI2c::new();
+        qmc_bus.load(QMC_REGISTER_CHIP_ID, &[QMC_CHIP_ID]);
+        qmc_bus.load(QMC_REGISTER_DATA, &[0x18, 0xFC, 0x00, 0x00, 0x10, 0x27, 0x01]);
+        let mut qmc = HMC5883L::new(qmc_bus, Chip::Qmc5883l, 0.0).unwrap();
+        assert_eq!(qmc.read_raw().unwrap(), [-1000, 0, 10000]);
+        assert!((qmc.read_heading().unwrap() - 180.0).abs() < 1e-3);
+
+        qmc.i2c.load(QMC_REGISTER_STATUS, &[QMC_STATUS_OVERFLOW]);
+        assert!(qmc.read_raw().is_err());
+
+        // The wrong chip at the address
+        let mut wrong = MockI2c::new();
+        wrong.load(HMC_REGISTER_IDENTITY, b"H44");
+        assert!(matches!(HMC5883L::new(wrong, Chip::Hmc5883l, 0.0), Err(SensorError::IdentityMismatch { expected: b'3', found: b'4' })));
+        assert!(HMC5883L::new(MockI2c::new(), Chip::Qmc5883l, 0.0).is_err());
+        assert!(HMC5883L::new(MockI2c::new(), Chip::Hmc5883l, 200.0).is_err());
+    }
+}
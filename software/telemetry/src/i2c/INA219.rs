@@ -0,0 +1,168 @@
+// INA219 I2C driver: high-side bus voltage and shunt current (battery monitoring without
+// a resistor divider)
+
+use super::sensor::{with_retry, SensorError, READ_ATTEMPTS};
+use super::I2cBus;
+use tracing::info;
+
+// A0 and A1 to GND; the other pin combinations select 0x41-0x4F
+pub const INA219_ADDRESS: u8 = 0x40;
+
+// Common breakout boards fit a 0.1 Ω shunt
+pub const DEFAULT_SHUNT_OHMS: f32 = 0.1;
+
+// INA219 register pointers (16-bit registers, MSB first)
+const REGISTER_CONFIG: u8 = 0x00;
+const REGISTER_SHUNT_VOLTAGE: u8 = 0x01;
+const REGISTER_BUS_VOLTAGE: u8 = 0x02;
+
+// Config register: 32V bus range, ±320mV shunt range (PGA /8), 12-bit bus and shunt
+// conversions, both measured continuously. The power-on default.
+const CONFIG_CONTINUOUS_32V_320MV: u16 = 0x399F;
+const CONFIG_RESET: u16 = 0x8000;
+
+const SHUNT_VOLTS_PER_LSB: f32 = 10e-6;
+const BUS_VOLTS_PER_LSB: f32 = 4e-3; // Of the value in bits 15:3
+const BUS_MATH_OVERFLOW: u16 = 0x0001;
+
+// Current through the shunt, in amps; positive flowing from IN+ to IN-
+pub fn shunt_current(shunt_volts: f32, shunt_ohms: f32) -> f32 {
+    shunt_volts / shunt_ohms
+}
+
+pub struct INA219<B: I2cBus> {
+    i2c: B,
+    shunt_ohms: f32,
+}
+
+impl<B: I2cBus> INA219<B> {
+    pub fn new(mut i2c: B, address: u8, shunt_ohms: f32) -> Result<Self, SensorError> {
+        if !(INA219_ADDRESS..=INA219_ADDRESS + 0x0F).contains(&address) {
+            return Err(SensorError::InvalidConfig(format!("Invalid INA219 address 0x{:02X}", address)));
+        }
+        if !(shunt_ohms > 0.0 && shunt_ohms.is_finite()) {
+            return Err(SensorError::InvalidConfig(format!("INA219 shunt resistance must be positive, got {} Ω", shunt_ohms)));
+        }
+        i2c.set_slave_address(address as u16)?;
+
+        let mut sensor = Self { i2c, shunt_ohms };
+        sensor.write_register(REGISTER_CONFIG, CONFIG_RESET)?;
+        sensor.write_register(REGISTER_CONFIG, CONFIG_CONTINUOUS_32V_320MV)?;
+
+        // No identity register either; reading the config back confirms the device answers
+        let config = sensor.read_register(REGISTER_CONFIG)?;
+        if config != CONFIG_CONTINUOUS_32V_320MV {
+            return Err(SensorError::VerifyFailed { register: REGISTER_CONFIG, wrote: CONFIG_CONTINUOUS_32V_320MV, found: config });
+        }
+        info!("INA219 initialized successfully ({} Ω shunt)", shunt_ohms);
+
+        Ok(sensor)
+    }
+
+    pub fn shunt_ohms(&self) -> f32 {
+        self.shunt_ohms
+    }
+
+    // Volts from IN- to GND, i.e. on the load side of the shunt
+    pub fn read_bus_voltage(&mut self) -> Result<f32, SensorError> {
+        let raw = self.read_register(REGISTER_BUS_VOLTAGE)?;
+        if raw & BUS_MATH_OVERFLOW != 0 {
+            return Err(SensorError::OutOfRange("INA219 bus/current math overflow".to_string()));
+        }
+        Ok((raw >> 3) as f32 * BUS_VOLTS_PER_LSB)
+    }
+
+    // Volts across the shunt, signed
+    pub fn read_shunt_voltage(&mut self) -> Result<f32, SensorError> {
+        let raw = self.read_register(REGISTER_SHUNT_VOLTAGE)? as i16;
+        Ok(raw as f32 * SHUNT_VOLTS_PER_LSB)
+    }
+
+    // Amps, from the shunt voltage rather than the calibration-dependent current register
+    pub fn read_current(&mut self) -> Result<f32, SensorError> {
+        Ok(shunt_current(self.read_shunt_voltage()?, self.shunt_ohms))
+    }
+
+    // Volts at the battery terminal: the bus voltage plus the drop across the shunt
+    pub fn read_supply_voltage(&mut self) -> Result<f32, SensorError> {
+        Ok(self.read_bus_voltage()? + self.read_shunt_voltage()?)
+    }
+
+    fn read_register(&mut self, register: u8) -> Result<u16, SensorError> {
+        let mut raw = [0u8; 2];
+        with_retry(READ_ATTEMPTS, || self.i2c.write_read(&[register], &mut raw))?;
+        Ok(u16::from_be_bytes(raw))
+    }
+
+    fn write_register(&mut self, register: u8, value: u16) -> Result<(), SensorError> {
+        let [msb, lsb] = value.to_be_bytes();
+        self.i2c.write(&[register, msb, lsb])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Config, shunt voltage and bus voltage registers
+    struct FakeIna {
+        registers: [u16; 3],
+    }
+
+    impl I2cBus for FakeIna {
+        fn set_slave_address(&mut self, _address: u16) -> Result<(), SensorError> {
+            Ok(())
+        }
+
+        fn write(&mut self, buffer: &[u8]) -> Result<(), SensorError> {
+            assert_eq!(buffer[0], REGISTER_CONFIG);
+            let value = u16::from_be_bytes([buffer[1], buffer[2]]);
+            // Reset self-clears back to the power-on default
+            self.registers[0] = if value & CONFIG_RESET != 0 { CONFIG_CONTINUOUS_32V_320MV } else { value };
+            Ok(())
+        }
+
+        fn write_read(&mut self, write_buffer: &[u8], read_buffer: &mut [u8]) -> Result<(), SensorError> {
+            read_buffer.copy_from_slice(&self.registers[write_buffer[0] as usize].to_be_bytes());
+            Ok(())
+        }
+    }
+
+    fn monitor(shunt: i16, bus: u16) -> INA219<FakeIna> {
+        let bus = FakeIna { registers: [0, shunt as u16, bus] };
+        INA219::new(bus, INA219_ADDRESS, DEFAULT_SHUNT_OHMS).unwrap()
+    }
+
+    #[test]
+    fn converts_shunt_voltage_to_current() {
+        // 5000 LSB = 50 mV across 0.1 Ω
+        let mut sensor = monitor(5000, 0);
+        assert!((sensor.read_shunt_voltage().unwrap() - 0.05).abs() < 1e-6);
+        assert!((sensor.read_current().unwrap() - 0.5).abs() < 1e-5);
+
+        // Charging current flows the other way
+        let mut sensor = monitor(-1200, 0);
+        assert!((sensor.read_current().unwrap() + 0.12).abs() < 1e-5);
+
+        assert!((shunt_current(0.032, 0.01) - 3.2).abs() < 1e-5);
+    }
+
+    #[test]
+    fn reads_bus_and_supply_voltage() {
+        // 3.7 V = 925 LSB in bits 15:3, with the conversion-ready bit set
+        let mut sensor = monitor(1000, 925 << 3 | 0x0002);
+        assert!((sensor.read_bus_voltage().unwrap() - 3.7).abs() < 1e-5);
+        assert!((sensor.read_supply_voltage().unwrap() - 3.71).abs() < 1e-5);
+
+        let mut overflowed = monitor(0, 925 << 3 | BUS_MATH_OVERFLOW);
+        assert!(overflowed.read_bus_voltage().is_err());
+    }
+
+    #[test]
+    fn rejects_bad_address_and_shunt() {
+        let bus = || FakeIna { registers: [0; 3] };
+        assert!(INA219::new(bus(), 0x50, DEFAULT_SHUNT_OHMS).is_err());
+        assert!(INA219::new(bus(), INA219_ADDRESS, 0.0).is_err());
+        assert!(INA219::new(bus(), INA219_ADDRESS + 5, 0.05).is_ok());
+    }
+}
@@ -0,0 +1,342 @@
+// MPL115A2 I2C driver for barometric pressure (50-115 kPa) with die temperature
+
+use super::sensor::{with_retry, Sensor, SensorError, READ_ATTEMPTS};
+use super::I2cBus;
+use crate::altitude;
+use std::collections::VecDeque;
+use std::thread;
+use std::time::Duration;
+use tracing::info;
+
+const MPL115A2_ADDRESS: u8 = 0x60;
+
+// MPL115A2 register addresses
+const REGISTER_PADC_MSB: u8 = 0x00; // Padc, Tadc follow: 10-bit values, left-justified
+const REGISTER_A0_MSB: u8 = 0x04;   // a0, b1, b2, c12 coefficients follow
+const REGISTER_CONVERT: u8 = 0x12;
+
+// Conservative wait after starting a conversion. The datasheet gives 3ms typical.
+const DEFAULT_CONVERSION_DELAY: Duration = Duration::from_millis(5);
+
+// Readings averaged by read_pressure_filtered(); at 10 Hz this smooths over 0.8 s
+pub const DEFAULT_FILTER_WINDOW: usize = 8;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PressureReading {
+    pub pressure_hpa: f32,
+    pub temperature: f32, // °C
+}
+
+impl PressureReading {
+    // Barometric altitude in meters, 44330 * (1 - (p/p0)^0.1903); pass
+    // altitude::STANDARD_SEA_LEVEL_HPA without a local QNH
+    pub fn altitude_m(&self, sea_level_hpa: f32) -> f32 {
+        altitude::pressure_to_altitude(self.pressure_hpa, sea_level_hpa)
+    }
+}
+
+// Factory-trimmed compensation coefficients read from the device at startup
+#[derive(Debug, Clone, Copy)]
+struct Coefficients {
+    a0: f32,
+    b1: f32,
+    b2: f32,
+    c12: f32,
+}
+
+pub struct MPL115A2<B: I2cBus> {
+    i2c: B,
+    coefficients: Coefficients,
+    conversion_delay: Duration,
+    oversampling: usize, // Conversions averaged per reading by the sensor loop
+    sea_level_hpa: f32,  // Reference for altitude_m()
+    filter_window: usize,
+    recent: VecDeque<PressureReading>, // Last filter_window readings, oldest first
+}
+
+impl<B: I2cBus> MPL115A2<B> {
+    // `filter_window` readings are averaged by read_pressure_filtered(); at least 1
+    pub fn new(mut i2c: B, filter_window: usize) -> Result<Self, SensorError> {
+        i2c.set_slave_address(MPL115A2_ADDRESS as u16)?;
+
+        let mut raw = [0u8; 8];
+        with_retry(READ_ATTEMPTS, || i2c.write_read(&[REGISTER_A0_MSB], &mut raw))?;
+        let word = |i: usize| i16::from_be_bytes([raw[i], raw[i + 1]]);
+
+        let coefficients = Coefficients {
+            a0: word(0) as f32 / 8.0,
+            b1: word(2) as f32 / 8192.0,
+            b2: word(4) as f32 / 16384.0,
+            c12: (word(6) >> 2) as f32 / 4_194_304.0,
+        };
+
+        info!("MPL115A2 initialized successfully ({:?})", coefficients);
+
+        Ok(Self {
+            i2c,
+            coefficients,
+            conversion_delay: DEFAULT_CONVERSION_DELAY,
+            oversampling: 1,
+            sea_level_hpa: altitude::STANDARD_SEA_LEVEL_HPA,
+            filter_window: filter_window.max(1),
+            recent: VecDeque::with_capacity(filter_window.max(1)),
+        })
+    }
+
+    // Time to wait between starting a conversion and reading the result. The MPL115A2
+    // has no ready flag, so this is a blind wait: too short and the read returns the
+    // previous conversion (stale data, with nothing to indicate it); too long only adds
+    // latency. The datasheet gives 3ms typical.
+    pub fn set_conversion_delay(&mut self, delay: Duration) {
+        self.conversion_delay = delay;
+    }
+
+    pub fn conversion_delay(&self) -> Duration {
+        self.conversion_delay
+    }
+
+    // Conversions to average per reading in read_pressure_oversampled(); at least 1
+    pub fn set_oversampling(&mut self, conversions: usize) {
+        self.oversampling = conversions.max(1);
+    }
+
+    pub fn oversampling(&self) -> usize {
+        self.oversampling
+    }
+
+    // Rejected outside altitude::SEA_LEVEL_MIN_HPA-SEA_LEVEL_MAX_HPA
+    pub fn set_sea_level_hpa(&mut self, hpa: f32) -> Result<(), String> {
+        self.sea_level_hpa = altitude::validate_sea_level(hpa)?;
+        Ok(())
+    }
+
+    pub fn sea_level_hpa(&self) -> f32 {
+        self.sea_level_hpa
+    }
+
+    // `reading` converted with the stored sea-level reference
+    pub fn altitude_m(&self, reading: &PressureReading) -> f32 {
+        reading.altitude_m(self.sea_level_hpa)
+    }
+
+    // Averages `samples` conversions with the sensor at a known elevation (m above mean
+    // sea level, e.g. surveyed field elevation) and keeps the sea-level reference that
+    // makes them read it. An implausible result leaves the reference unchanged, since it
+    // means the elevation or the sensor is wrong.
+    pub fn calibrate_sea_level(&mut self, known_altitude_m: f32, samples: usize) -> Result<f32, SensorError> {
+        let reading = self.read_pressure_oversampled(samples)?;
+        let sea_level = altitude::sea_level_from_altitude(reading.pressure_hpa, known_altitude_m);
+        self.set_sea_level_hpa(sea_level)
+            .map_err(|e| SensorError::Calibration(format!("{} ({:.2} hPa at {} m)", e, reading.pressure_hpa, known_altitude_m)))?;
+        info!("MPL115A2 sea-level reference {:.2} hPa from {:.2} hPa at {} m", sea_level, reading.pressure_hpa, known_altitude_m);
+        Ok(sea_level)
+    }
+
+    pub fn read_pressure(&mut self) -> Result<PressureReading, SensorError> {
+        self.i2c.write(&[REGISTER_CONVERT, 0x00])?;
+        thread::sleep(self.conversion_delay);
+
+        let mut raw = [0u8; 4];
+        with_retry(READ_ATTEMPTS, || self.i2c.write_read(&[REGISTER_PADC_MSB], &mut raw))?;
+        let padc = (u16::from_be_bytes([raw[0], raw[1]]) >> 6) as f32;
+        let tadc = (u16::from_be_bytes([raw[2], raw[3]]) >> 6) as f32;
+
+        Ok(self.compensate(padc, tadc))
+    }
+
+    // Mean of `n` complete conversions, each with its own start and settle. One 10-bit
+    // conversion resolves ~0.64 hPa (~5 m of altitude near sea level); averaging cuts the
+    // noise by about √n, at n times the latency of read_pressure() (n × the conversion
+    // delay plus the bus transfers), so keep n × delay inside the sensor read budget.
+    pub fn read_pressure_oversampled(&mut self, n: usize) -> Result<PressureReading, SensorError> {
+        if n == 0 {
+            return Err(SensorError::InvalidConfig("Oversampling needs at least one conversion".to_string()));
+        }
+
+        let (mut pressure_sum, mut temperature_sum) = (0.0f64, 0.0f64);
+        for _ in 0..n {
+            let reading = self.read_pressure()?;
+            pressure_sum += reading.pressure_hpa as f64;
+            temperature_sum += reading.temperature as f64;
+        }
+
+        Ok(PressureReading {
+            pressure_hpa: (pressure_sum / n as f64) as f32,
+            temperature: (temperature_sum / n as f64) as f32,
+        })
+    }
+
+    // Takes one reading (read_pressure_oversampled() with the configured conversions) and
+    // returns the moving average of it and the previous filter_window - 1. Until the
+    // window fills, the average is over the readings so far. Trades the single-sample
+    // jitter for lag: a steady climb reads (filter_window - 1) / 2 readings behind.
+    pub fn read_pressure_filtered(&mut self) -> Result<PressureReading, SensorError> {
+        let reading = self.read_pressure_oversampled(self.oversampling)?;
+        if self.recent.len() == self.filter_window {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(reading);
+
+        let n = self.recent.len() as f64;
+        let (pressure_sum, temperature_sum) = self.recent.iter()
+            .fold((0.0f64, 0.0f64), |(p, t), r| (p + r.pressure_hpa as f64, t + r.temperature as f64));
+        Ok(PressureReading {
+            pressure_hpa: (pressure_sum / n) as f32,
+            temperature: (temperature_sum / n) as f32,
+        })
+    }
+
+    pub fn filter_window(&self) -> usize {
+        self.filter_window
+    }
+
+    // Forget the averaged history, e.g. after a sensor fault
+    pub fn clear_filter(&mut self) {
+        self.recent.clear();
+    }
+
+    fn compensate(&self, padc: f32, tadc: f32) -> PressureReading {
+        let c = self.coefficients;
+        let pcomp = c.a0 + (c.b1 + c.c12 * tadc) * padc + c.b2 * tadc;
+        let pressure_kpa = pcomp * (115.0 - 50.0) / 1023.0 + 50.0;
+
+        PressureReading {
+            pressure_hpa: pressure_kpa * 10.0,
+            temperature: (tadc - 498.0) / -5.35 + 25.0,
+        }
+    }
+}
+
+impl<B: I2cBus> Sensor for MPL115A2<B> {
+    type Reading = PressureReading;
+
+    // The configured conversions averaged, without the moving-average history
+    fn read(&mut self) -> Result<PressureReading, SensorError> {
+        self.read_pressure_oversampled(self.oversampling)
+    }
+
+    fn who_am_i(&mut self) -> Result<u8, SensorError> {
+        Err(SensorError::Unsupported("the MPL115A2 has no identity register"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i2c::mock::MockI2c;
+    use std::time::Instant;
+
+    // Datasheet example: a0 = 2009.75, b1 = -2.37585, b2 = -0.92047, c12 = 0.000790,
+    // Padc = 410, Tadc = 507 -> 96.59 kPa
+    const SAMPLE_COEFFICIENTS: [u8; 8] = [0x3E, 0xCE, 0xB3, 0xF9, 0xC5, 0x17, 0x33, 0xC8];
+    const SAMPLE_ADC: [u8; 4] = [0x66, 0x80, 0x7E, 0xC0];
+
+    fn sensor() -> MPL115A2<MockI2c> {
+        let mut bus = MockI2c::new();
+        bus.load(REGISTER_A0_MSB, &SAMPLE_COEFFICIENTS);
+        bus.load(REGISTER_PADC_MSB, &SAMPLE_ADC);
+        MPL115A2::new(bus, DEFAULT_FILTER_WINDOW).unwrap()
+    }
+
+    #[test]
+    fn compensates_datasheet_example() {
+        let mut sensor = sensor();
+        assert_eq!(sensor.i2c.slave_address, Some(MPL115A2_ADDRESS as u16));
+
+        let reading = sensor.read_pressure().unwrap();
+        assert!((reading.pressure_hpa - 965.9).abs() < 0.1, "pressure {}", reading.pressure_hpa);
+        assert!((reading.temperature - 23.32).abs() < 0.01, "temperature {}", reading.temperature);
+        assert_eq!(sensor.i2c.writes, vec![(REGISTER_CONVERT, 0x00)]);
+    }
+
+    #[test]
+    fn oversampling_averages_separate_conversions() {
+        let mut sensor = sensor();
+        let single = sensor.read_pressure().unwrap();
+        sensor.i2c.writes.clear();
+
+        let averaged = sensor.read_pressure_oversampled(4).unwrap();
+        assert!((averaged.pressure_hpa - single.pressure_hpa).abs() < 1e-3);
+        assert!((averaged.temperature - single.temperature).abs() < 1e-3);
+        assert_eq!(sensor.i2c.writes, vec![(REGISTER_CONVERT, 0x00); 4]);
+
+        assert!(sensor.read_pressure_oversampled(0).is_err());
+        sensor.set_oversampling(0);
+        assert_eq!(sensor.oversampling(), 1);
+    }
+
+    #[test]
+    fn conversion_delay_is_configurable() {
+        let mut sensor = sensor();
+        assert_eq!(sensor.conversion_delay(), DEFAULT_CONVERSION_DELAY);
+
+        sensor.set_conversion_delay(Duration::from_millis(30));
+        let start = Instant::now();
+        sensor.read_pressure().unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(30));
+    }
+
+    #[test]
+    fn altitude_from_pressure() {
+        let at = |pressure_hpa| PressureReading { pressure_hpa, temperature: 15.0 };
+        assert!(at(1013.25).altitude_m(altitude::STANDARD_SEA_LEVEL_HPA).abs() < 0.01);
+
+        // Half the sea-level pressure is about 5.5 km up
+        let half = at(506.625).altitude_m(altitude::STANDARD_SEA_LEVEL_HPA);
+        assert!((5_400.0..5_700.0).contains(&half), "altitude {}", half);
+
+        // The local reference shifts the zero
+        assert!(at(1020.0).altitude_m(1020.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn calibration_recovers_sea_level_reference() {
+        let mut sensor = sensor();
+        let pressure = sensor.read_pressure().unwrap().pressure_hpa; // ~965.9 hPa
+        let reading = PressureReading { pressure_hpa: pressure, temperature: 20.0 };
+        assert_eq!(sensor.sea_level_hpa(), altitude::STANDARD_SEA_LEVEL_HPA);
+
+        // A field at 350 m reading 965.9 hPa means a low of about 1006.6 hPa
+        let sea_level = sensor.calibrate_sea_level(350.0, 4).unwrap();
+        let expected = pressure / (1.0 - 350.0 / 44_330.0f32).powf(5.255);
+        assert!((sea_level - expected).abs() < 0.01, "p0 {} vs {}", sea_level, expected);
+        assert!((sea_level - 1006.6).abs() < 0.5, "p0 {}", sea_level);
+        assert_eq!(sensor.sea_level_hpa(), sea_level);
+        assert!((sensor.altitude_m(&reading) - 350.0).abs() < 0.1);
+
+        // 965.9 hPa can't be 3 km up on any weather day
+        assert!(sensor.calibrate_sea_level(3000.0, 1).is_err());
+        assert_eq!(sensor.sea_level_hpa(), sea_level);
+    }
+
+    #[test]
+    fn moving_average_smooths_a_spike() {
+        let mut sensor = sensor();
+        let steady = sensor.read_pressure().unwrap();
+
+        // Padc 410 for three readings, then one at 600 (over 100 hPa off), then 410 again
+        let port = sensor.i2c.ports.entry(REGISTER_PADC_MSB).or_default();
+        for padc in [410u16, 410, 410, 600, 410, 410] {
+            port.extend((padc << 6).to_be_bytes());
+            port.extend(&SAMPLE_ADC[2..]);
+        }
+
+        // Partial window: the average of what's there
+        for _ in 0..3 {
+            let filtered = sensor.read_pressure_filtered().unwrap();
+            assert!((filtered.pressure_hpa - steady.pressure_hpa).abs() < 1e-3);
+            assert!((filtered.temperature - steady.temperature).abs() < 1e-3);
+        }
+        let spiked = sensor.read_pressure_filtered().unwrap();
+        let spike = sensor.compensate(600.0, 507.0).pressure_hpa - steady.pressure_hpa;
+        assert!(spike.abs() > 100.0);
+        assert!((spiked.pressure_hpa - steady.pressure_hpa - spike / 4.0).abs() < 0.01);
+
+        // The spike keeps its 1/n weight until it leaves the window
+        sensor.read_pressure_filtered().unwrap();
+        let later = sensor.read_pressure_filtered().unwrap();
+        assert!((later.pressure_hpa - steady.pressure_hpa - spike / 6.0).abs() < 0.01);
+        assert_eq!(sensor.filter_window(), DEFAULT_FILTER_WINDOW);
+    }
+}
@@ -1,17 +1,28 @@
 // MPU6050 I2C driver for 6-axis motion tracking (3-axis gyroscope + 3-axis accelerometer)
 
-use rppal::i2c::I2c;
+use super::sensor::{with_retry, Sensor, SensorError, READ_ATTEMPTS};
+use super::I2cBus;
+use std::fs;
+use std::io;
+use std::path::Path;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
 
 const MPU6050_ADDRESS: u8 = 0x68; // Default I2C address (AD0 = 0)
 const MPU6050_ADDRESS_ALT: u8 = 0x69; // Alternative I2C address (AD0 = 1)
 
 // MPU6050 register addresses
+const REGISTER_SELF_TEST_X: u8 = 0x0D; // X/Y/Z: XA_TEST[4:2] in bits 7:5, XG_TEST in bits 4:0
+const REGISTER_SELF_TEST_A: u8 = 0x10; // XA_TEST[1:0], YA_TEST[1:0], ZA_TEST[1:0] in bits 5:0
 const REGISTER_SMPLRT_DIV: u8 = 0x19;
 const REGISTER_CONFIG: u8 = 0x1A;
 const REGISTER_GYRO_CONFIG: u8 = 0x1B;
 const REGISTER_ACCEL_CONFIG: u8 = 0x1C;
+const REGISTER_FIFO_EN: u8 = 0x23;
+const REGISTER_INT_ENABLE: u8 = 0x38;
+const REGISTER_INT_STATUS: u8 = 0x3A;
 const REGISTER_ACCEL_XOUT_H: u8 = 0x3B;
 const REGISTER_ACCEL_YOUT_H: u8 = 0x3D;
 const REGISTER_ACCEL_ZOUT_H: u8 = 0x3F;
@@ -19,12 +30,65 @@ const REGISTER_TEMP_OUT_H: u8 = 0x41;
 const REGISTER_GYRO_XOUT_H: u8 = 0x43;
 const REGISTER_GYRO_YOUT_H: u8 = 0x45;
 const REGISTER_GYRO_ZOUT_H: u8 = 0x47;
+const REGISTER_USER_CTRL: u8 = 0x6A;
 const REGISTER_PWR_MGMT_1: u8 = 0x6B;
+const REGISTER_PWR_MGMT_2: u8 = 0x6C;
+const REGISTER_FIFO_COUNT_H: u8 = 0x72;
+const REGISTER_FIFO_R_W: u8 = 0x74;
 const REGISTER_WHO_AM_I: u8 = 0x75;
 
+// ACCEL_XOUT_H..GYRO_ZOUT_H: accel X/Y/Z, temperature, gyro X/Y/Z, each 16-bit MSB first
+const BURST_LEN: usize = 14;
+
+// Documented register map range covered by dump_registers()
+pub const REGISTER_DUMP_START: u8 = 0x0D;
+pub const REGISTER_DUMP_END: u8 = 0x75;
+pub const REGISTER_DUMP_LEN: usize = (REGISTER_DUMP_END - REGISTER_DUMP_START) as usize + 1;
+
 // Configuration values
+const SMPLRT_DIV_125HZ: u8 = 0x07;
+const GYRO_OUTPUT_RATE_HZ: u16 = 1000; // With the DLPF enabled
+const GYRO_OUTPUT_RATE_UNFILTERED_HZ: u16 = 8000; // DLPF_CFG 0
 const PWR_MGMT_1_RESET: u8 = 0x80;
 const PWR_MGMT_1_CLKSEL_PLL_X: u8 = 0x01;
+const PWR_MGMT_1_SLEEP: u8 = 0x40;
+const PWR_MGMT_1_CYCLE: u8 = 0x20;
+const PWR_MGMT_1_TEMP_DIS: u8 = 0x08;
+const PWR_MGMT_2_STBY_XYZG: u8 = 0x07;
+const PWR_MGMT_2_LP_WAKE_CTRL_SHIFT: u8 = 6;
+const INT_ENABLE_DATA_RDY_EN: u8 = 0x01;
+const INT_STATUS_DATA_RDY_INT: u8 = 0x01;
+const INT_STATUS_FIFO_OFLOW_INT: u8 = 0x10;
+
+// FIFO: with temperature, gyro and accel enabled each sample is the same 14 bytes as a
+// burst read, in register order. The FIFO holds 1024 bytes, ~0.6s at 125Hz.
+const FIFO_EN_TEMP_XYZG_ACCEL: u8 = 0xF8; // TEMP_FIFO_EN, XG/YG/ZG_FIFO_EN, ACCEL_FIFO_EN
+const USER_CTRL_FIFO_EN: u8 = 0x40;
+const USER_CTRL_FIFO_RESET: u8 = 0x04; // Self-clearing
+pub const FIFO_SIZE: usize = 1024;
+
+// Samples between convergence checks in calibrate_until_stable()
+const CALIBRATION_WINDOW: usize = 20;
+
+// Calibration quality limits. At rest the accelerometer noise is a few mg and the gyro
+// well under 0.1°/s, so variances above these mean the payload moved while sampling.
+const CALIBRATION_ACCEL_VARIANCE_WARN: f32 = 4e-4; // g² (0.02g standard deviation)
+const CALIBRATION_ACCEL_VARIANCE_FAIL: f32 = 1e-2; // g² (0.1g)
+const CALIBRATION_GYRO_VARIANCE_WARN: f32 = 1.0;   // (°/s)²
+const CALIBRATION_GYRO_VARIANCE_FAIL: f32 = 25.0;  // (°/s)²
+// Angle between the mean gravity vector and +Z; offsets assume the device lies flat
+const CALIBRATION_TILT_WARN_DEG: f32 = 5.0;
+const CALIBRATION_TILT_FAIL_DEG: f32 = 15.0;
+
+// Factory self-test: the change in output with the self-test actuation enabled must be
+// within 14% of the factory trim value stored in SELF_TEST_X..SELF_TEST_A
+pub const SELF_TEST_TOLERANCE: f32 = 0.14;
+const SELF_TEST_SAMPLES: usize = 10;
+const SELF_TEST_SETTLE: Duration = Duration::from_millis(250);
+const CONFIG_SELF_TEST_XYZ: u8 = 0xE0; // XA_ST/YA_ST/ZA_ST or XG_ST/YG_ST/ZG_ST
+
+// Polling interval while waiting for DATA_RDY (sample period is ~8ms at 125Hz)
+const DATA_READY_POLL_INTERVAL: Duration = Duration::from_millis(1);
 
 // Accelerometer sensitivity settings (LSB/g)
 const ACCEL_SENSITIVITY_2G: f32 = 16384.0;
@@ -38,14 +102,19 @@ const GYRO_SENSITIVITY_500DPS: f32 = 65.5;
 const GYRO_SENSITIVITY_1000DPS: f32 = 32.8;
 const GYRO_SENSITIVITY_2000DPS: f32 = 16.4;
 
-#[derive(Debug, Clone)]
+// Standard gravity (m/s² per g), the default conversion for accelerometer output
+pub const STANDARD_GRAVITY: f32 = 9.80665;
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AccelerometerReading {
-    pub x: f32, // m/s²
-    pub y: f32, // m/s²
-    pub z: f32, // m/s²
+    pub x: f32, // m/s² (or g, see AccelUnits)
+    pub y: f32, // m/s² (or g, see AccelUnits)
+    pub z: f32, // m/s² (or g, see AccelUnits)
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GyroscopeReading {
     pub x: f32, // °/s
     pub y: f32, // °/s
@@ -53,12 +122,78 @@ pub struct GyroscopeReading {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MotionReading {
     pub accelerometer: AccelerometerReading,
     pub gyroscope: GyroscopeReading,
     pub temperature: f32, // °C
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct FifoDrain {
+    pub samples: Vec<MotionReading>,
+    pub overflowed: bool, // Samples were lost; `samples` is empty and the FIFO was reset
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CalibrationQuality {
+    Pass,
+    Warn, // Offsets usable but noisier or more tilted than expected
+    Fail, // The device moved or wasn't flat; the offsets shouldn't be trusted
+}
+
+#[derive(Debug, Clone)]
+pub struct CalibrationReport {
+    pub samples: usize,
+    pub accel_variance: f32, // Largest per-axis variance, g²
+    pub gyro_variance: f32,  // Largest per-axis variance, (°/s)²
+    pub tilt_deg: f32,       // Mean gravity vector's angle from +Z
+    pub quality: CalibrationQuality,
+}
+
+// Self-test response per axis (X, Y, Z) as a fraction away from the factory trim; NaN
+// where the trim is unprogrammed
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfTestResult {
+    pub accel_deviation: [f32; 3],
+    pub gyro_deviation: [f32; 3],
+}
+
+impl SelfTestResult {
+    pub fn passed(&self) -> bool {
+        self.accel_deviation
+            .iter()
+            .chain(&self.gyro_deviation)
+            .all(|&deviation| within_tolerance(deviation))
+    }
+
+    // Axes outside the tolerance or without a trim, named e.g. "gyro Z"
+    pub fn failed_axes(&self) -> Vec<&'static str> {
+        const NAMES: [&str; 6] = ["accel X", "accel Y", "accel Z", "gyro X", "gyro Y", "gyro Z"];
+        self.accel_deviation
+            .iter()
+            .chain(&self.gyro_deviation)
+            .zip(NAMES)
+            .filter(|(&deviation, _)| !within_tolerance(deviation))
+            .map(|(_, name)| name)
+            .collect()
+    }
+
+    // Largest deviation on any axis (NaN if any trim is missing)
+    pub fn worst_deviation(&self) -> f32 {
+        self.accel_deviation
+            .iter()
+            .chain(&self.gyro_deviation)
+            .fold(0.0, |worst: f32, &deviation| if deviation.is_nan() { f32::NAN } else { worst.max(deviation.abs()) })
+    }
+}
+
+// False for NaN, an axis without a trim
+fn within_tolerance(deviation: f32) -> bool {
+    deviation.abs() <= SELF_TEST_TOLERANCE
+}
+
+#[allow(non_camel_case_types)]
 #[derive(Debug, Clone, Copy)]
 pub enum AccelSensitivity {
     AFS_SEL_2G = 0x00,
@@ -67,6 +202,7 @@ pub enum AccelSensitivity {
     AFS_SEL_16G = 0x18,
 }
 
+#[allow(non_camel_case_types)]
 #[derive(Debug, Clone, Copy)]
 pub enum GyroSensitivity {
     FS_SEL_250DPS = 0x00,
@@ -75,40 +211,251 @@ pub enum GyroSensitivity {
     FS_SEL_2000DPS = 0x18,
 }
 
-pub struct MPU6050 {
-    i2c: I2c,
+impl AccelSensitivity {
+    pub fn range_g(self) -> u8 {
+        match self {
+            AccelSensitivity::AFS_SEL_2G => 2,
+            AccelSensitivity::AFS_SEL_4G => 4,
+            AccelSensitivity::AFS_SEL_8G => 8,
+            AccelSensitivity::AFS_SEL_16G => 16,
+        }
+    }
+}
+
+impl GyroSensitivity {
+    pub fn range_dps(self) -> u16 {
+        match self {
+            GyroSensitivity::FS_SEL_250DPS => 250,
+            GyroSensitivity::FS_SEL_500DPS => 500,
+            GyroSensitivity::FS_SEL_1000DPS => 1000,
+            GyroSensitivity::FS_SEL_2000DPS => 2000,
+        }
+    }
+}
+
+// Digital low-pass filter setting (DLPF_CFG in CONFIG), named by the accelerometer's
+// bandwidth; the gyro's is within a few Hz. Narrower bands add delay (19 ms at 5 Hz) and
+// smooth away the vibration of burst and descent. To avoid aliasing the bandwidth
+// should stay below half the sample rate, 62 Hz at the 125 Hz default.
+//
+// Hz260 turns the filter off, which also raises the gyro output rate the sample rate
+// divider works from to 8 kHz. The accelerometer still outputs at 1 kHz, so at sample
+// rates above that its samples repeat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DlpfBandwidth {
+    Hz260 = 0,
+    Hz184 = 1,
+    Hz94 = 2,
+    #[default]
+    Hz44 = 3,
+    Hz21 = 4,
+    Hz10 = 5,
+    Hz5 = 6,
+}
+
+impl DlpfBandwidth {
+    pub const ALL: [DlpfBandwidth; 7] = [
+        DlpfBandwidth::Hz260, DlpfBandwidth::Hz184, DlpfBandwidth::Hz94, DlpfBandwidth::Hz44,
+        DlpfBandwidth::Hz21, DlpfBandwidth::Hz10, DlpfBandwidth::Hz5,
+    ];
+
+    pub fn bandwidth_hz(self) -> u16 {
+        match self {
+            DlpfBandwidth::Hz260 => 260,
+            DlpfBandwidth::Hz184 => 184,
+            DlpfBandwidth::Hz94 => 94,
+            DlpfBandwidth::Hz44 => 44,
+            DlpfBandwidth::Hz21 => 21,
+            DlpfBandwidth::Hz10 => 10,
+            DlpfBandwidth::Hz5 => 5,
+        }
+    }
+
+    // Rate the sample rate divider divides down
+    pub fn gyro_output_rate_hz(self) -> u16 {
+        match self {
+            DlpfBandwidth::Hz260 => GYRO_OUTPUT_RATE_UNFILTERED_HZ,
+            _ => GYRO_OUTPUT_RATE_HZ,
+        }
+    }
+}
+
+// How often the accelerometer wakes for a sample in cycle mode (LP_WAKE_CTRL)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WakeRate {
+    Hz1_25 = 0,
+    Hz5 = 1,
+    Hz20 = 2,
+    Hz40 = 3,
+}
+
+// Sleep draws about 5 µA and keeps the configuration, so wake() resumes without
+// reinitializing. Cycle mode samples only the accelerometer, at about 10-110 µA
+// depending on the wake rate, against 3.9 mA with everything running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerMode {
+    Awake,
+    Asleep,
+    Cycle(WakeRate), // Gyro and temperature sensor powered down
+}
+
+// A bandwidth in Hz, one of 260, 184, 94, 44, 21, 10 or 5
+impl std::str::FromStr for DlpfBandwidth {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hz: u16 = s.trim().trim_end_matches("Hz").parse().map_err(|_| format!("Invalid bandwidth '{}'", s))?;
+        Self::ALL.into_iter().find(|bw| bw.bandwidth_hz() == hz).ok_or_else(|| {
+            let choices: Vec<String> = Self::ALL.iter().map(|bw| bw.bandwidth_hz().to_string()).collect();
+            format!("No {} Hz filter setting (one of {})", hz, choices.join(", "))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+// Mounting correction from the sensor's axes to the vehicle frame: vehicle axis i reads
+// sign * sensor axis axes[i].0. Applied to both accelerometer and gyroscope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AxisMap {
+    axes: [(Axis, i8); 3],
+}
+
+impl AxisMap {
+    pub const IDENTITY: AxisMap = AxisMap { axes: [(Axis::X, 1), (Axis::Y, 1), (Axis::Z, 1)] };
+
+    // Each sensor axis must be used exactly once, with a sign of +1 or -1
+    pub fn new(axes: [(Axis, i8); 3]) -> Result<Self, String> {
+        for (i, &(axis, sign)) in axes.iter().enumerate() {
+            if sign != 1 && sign != -1 {
+                return Err(format!("Axis sign must be 1 or -1, got {}", sign));
+            }
+            if axes[..i].iter().any(|&(other, _)| other == axis) {
+                return Err(format!("Sensor axis {:?} is mapped more than once", axis));
+            }
+        }
+        Ok(Self { axes })
+    }
+
+    pub fn axes(&self) -> [(Axis, i8); 3] {
+        self.axes
+    }
+
+    pub fn apply(&self, sensor: [f32; 3]) -> [f32; 3] {
+        self.axes.map(|(axis, sign)| sign as f32 * sensor[axis as usize])
+    }
+}
+
+impl Default for AxisMap {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+// Vehicle X, Y and Z in terms of sensor axes, e.g. "x,y,z" (identity) or "-y,x,z"
+impl std::str::FromStr for AxisMap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+        if parts.len() != 3 {
+            return Err(format!("Expected three comma-separated axes, got '{}'", s));
+        }
+
+        let mut axes = [(Axis::X, 1); 3];
+        for (slot, part) in axes.iter_mut().zip(&parts) {
+            let (sign, name) = match part.strip_prefix('-') {
+                Some(name) => (-1, name),
+                None => (1, part.strip_prefix('+').unwrap_or(part)),
+            };
+            let axis = match name.to_ascii_lowercase().as_str() {
+                "x" => Axis::X,
+                "y" => Axis::Y,
+                "z" => Axis::Z,
+                _ => return Err(format!("Unknown axis '{}'", part)),
+            };
+            *slot = (axis, sign);
+        }
+        Self::new(axes)
+    }
+}
+
+// Default smoothing factor for read_accelerometer_smoothed
+const DEFAULT_EMA_ALPHA: f32 = 0.2;
+
+// Accelerometer output units. The sensor natively measures in g; readings in m/s²
+// are g multiplied by the configured gravity constant.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AccelUnits {
+    MetersPerSecondSquared,
+    G,
+}
+
+pub struct MPU6050<B: I2cBus> {
+    i2c: B,
     accel_sensitivity: AccelSensitivity,
     gyro_sensitivity: GyroSensitivity,
+    dlpf: DlpfBandwidth,
+    power: PowerMode,
     accel_scale: f32,
     gyro_scale: f32,
+    accel_units: AccelUnits,
+    gravity: f32, // m/s² per g
+    ema_alpha: f32,
+    accel_ema: Option<AccelerometerReading>,
+    axis_map: AxisMap,
+    accel_offset: AccelerometerReading, // Subtracted from every reading, in the mapped frame
+    gyro_offset: GyroscopeReading,
+    fifo_overflowed: bool, // Since the FIFO was last reset
 }
 
-impl MPU6050 {
-    pub fn new(mut i2c: I2c, use_alt_address: bool) -> Result<Self, Box<dyn std::error::Error>> {
+impl<B: I2cBus> MPU6050<B> {
+    pub fn new(i2c: B, use_alt_address: bool) -> Result<Self, SensorError> {
+        let mut sensor = Self::attach(i2c, use_alt_address)?;
+        
+        // Initialize the sensor
+        sensor.initialize()?;
+        
+        Ok(sensor)
+    }
+    
+    // Address the sensor without resetting or reconfiguring it, e.g. to inspect its
+    // current state. Scaling assumes the power-on default ranges.
+    pub fn attach(mut i2c: B, use_alt_address: bool) -> Result<Self, SensorError> {
         let address = if use_alt_address { MPU6050_ADDRESS_ALT } else { MPU6050_ADDRESS };
         i2c.set_slave_address(address as u16)?;
         
-        let mut sensor = Self {
+        Ok(Self {
             i2c,
             accel_sensitivity: AccelSensitivity::AFS_SEL_2G,
             gyro_sensitivity: GyroSensitivity::FS_SEL_250DPS,
+            dlpf: DlpfBandwidth::default(),
+            power: PowerMode::Awake,
             accel_scale: ACCEL_SENSITIVITY_2G,
             gyro_scale: GYRO_SENSITIVITY_250DPS,
-        };
-        
-        // Initialize the sensor
-        sensor.initialize()?;
-        
-        Ok(sensor)
+            accel_units: AccelUnits::MetersPerSecondSquared,
+            gravity: STANDARD_GRAVITY,
+            ema_alpha: DEFAULT_EMA_ALPHA,
+            accel_ema: None,
+            axis_map: AxisMap::IDENTITY,
+            accel_offset: AccelerometerReading::default(),
+            gyro_offset: GyroscopeReading::default(),
+            fifo_overflowed: false,
+        })
     }
     
-    fn initialize(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn initialize(&mut self) -> Result<(), SensorError> {
         // Reset the device
         self.write_register(REGISTER_PWR_MGMT_1, PWR_MGMT_1_RESET)?;
         thread::sleep(Duration::from_millis(100));
         
         // Wake up the device and set clock source to PLL with X-axis gyroscope reference
-        self.write_register(REGISTER_PWR_MGMT_1, PWR_MGMT_1_CLKSEL_PLL_X)?;
+        self.write_register_verified(REGISTER_PWR_MGMT_1, PWR_MGMT_1_CLKSEL_PLL_X)?;
         
         // Configure gyroscope sensitivity
         self.set_gyro_sensitivity(self.gyro_sensitivity)?;
@@ -117,23 +464,23 @@ impl MPU6050 {
         self.set_accel_sensitivity(self.accel_sensitivity)?;
         
         // Set sample rate divider (1kHz / (1 + SMPLRT_DIV))
-        self.write_register(REGISTER_SMPLRT_DIV, 0x07)?; // ~125Hz
+        self.write_register_verified(REGISTER_SMPLRT_DIV, SMPLRT_DIV_125HZ)?; // ~125Hz
         
         // Configure digital low-pass filter
-        self.write_register(REGISTER_CONFIG, 0x06)?; // ~5Hz cutoff
+        self.set_dlpf(self.dlpf)?;
         
         // Verify device identity
         let who_am_i = self.read_register(REGISTER_WHO_AM_I)?;
         if who_am_i != 0x68 {
-            return Err(format!("Invalid WHO_AM_I value: 0x{:02X}, expected 0x68", who_am_i).into());
+            return Err(SensorError::IdentityMismatch { expected: 0x68, found: who_am_i });
         }
         
-        println!("MPU6050 initialized successfully (WHO_AM_I: 0x{:02X})", who_am_i);
+        info!("MPU6050 initialized successfully (WHO_AM_I: 0x{:02X})", who_am_i);
         
         Ok(())
     }
     
-    pub fn set_accel_sensitivity(&mut self, sensitivity: AccelSensitivity) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn set_accel_sensitivity(&mut self, sensitivity: AccelSensitivity) -> Result<(), SensorError> {
         self.accel_sensitivity = sensitivity;
         
         // Update scale factor
@@ -145,13 +492,72 @@ impl MPU6050 {
         };
         
         // Write configuration
-        self.write_register(REGISTER_ACCEL_CONFIG, sensitivity as u8)?;
+        self.write_register_verified(REGISTER_ACCEL_CONFIG, sensitivity as u8)?;
         
-        println!("Accelerometer sensitivity set to {:?}", sensitivity);
+        info!("Accelerometer sensitivity set to {:?}", sensitivity);
         Ok(())
     }
     
-    pub fn set_gyro_sensitivity(&mut self, sensitivity: GyroSensitivity) -> Result<(), Box<dyn std::error::Error>> {
+    // Also changes the sample rate if the filter is switched on or off (see DlpfBandwidth)
+    pub fn set_dlpf(&mut self, bandwidth: DlpfBandwidth) -> Result<(), SensorError> {
+        self.dlpf = bandwidth;
+        self.write_register_verified(REGISTER_CONFIG, bandwidth as u8)?;
+
+        info!("MPU6050 low-pass filter set to {} Hz ({} Hz sample rate)", bandwidth.bandwidth_hz(), self.sample_rate_hz());
+        Ok(())
+    }
+
+    pub fn dlpf(&self) -> DlpfBandwidth {
+        self.dlpf
+    }
+
+    pub fn sleep(&mut self) -> Result<(), SensorError> {
+        self.write_register_verified(REGISTER_PWR_MGMT_1, PWR_MGMT_1_SLEEP | PWR_MGMT_1_CLKSEL_PLL_X)?;
+        self.power = PowerMode::Asleep;
+        info!("MPU6050 asleep");
+        Ok(())
+    }
+
+    // Back to full operation from sleep or cycle mode. The gyro takes ~30 ms to settle.
+    pub fn wake(&mut self) -> Result<(), SensorError> {
+        self.write_register_verified(REGISTER_PWR_MGMT_1, PWR_MGMT_1_CLKSEL_PLL_X)?;
+        self.write_register_verified(REGISTER_PWR_MGMT_2, 0)?;
+        self.power = PowerMode::Awake;
+        info!("MPU6050 awake");
+        Ok(())
+    }
+
+    // Accelerometer-only low-power mode: the device sleeps between samples taken at
+    // `rate`. The gyro the PLL locks to is in standby, so it runs from the internal oscillator.
+    pub fn set_cycle_mode(&mut self, rate: WakeRate) -> Result<(), SensorError> {
+        let standby = (rate as u8) << PWR_MGMT_2_LP_WAKE_CTRL_SHIFT | PWR_MGMT_2_STBY_XYZG;
+        self.write_register_verified(REGISTER_PWR_MGMT_2, standby)?;
+        self.write_register_verified(REGISTER_PWR_MGMT_1, PWR_MGMT_1_CYCLE | PWR_MGMT_1_TEMP_DIS)?;
+        self.power = PowerMode::Cycle(rate);
+        info!("MPU6050 in accelerometer-only cycle mode ({:?})", rate);
+        Ok(())
+    }
+
+    pub fn power_mode(&self) -> PowerMode {
+        self.power
+    }
+
+    // Accelerometer outputs update in cycle mode too; everything else needs the device awake
+    fn check_accel_powered(&self) -> Result<(), SensorError> {
+        match self.power {
+            PowerMode::Asleep => Err(SensorError::Asleep),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_awake(&self) -> Result<(), SensorError> {
+        match self.power {
+            PowerMode::Awake => Ok(()),
+            _ => Err(SensorError::Asleep),
+        }
+    }
+
+    pub fn set_gyro_sensitivity(&mut self, sensitivity: GyroSensitivity) -> Result<(), SensorError> {
         self.gyro_sensitivity = sensitivity;
         
         // Update scale factor
@@ -163,60 +569,267 @@ impl MPU6050 {
         };
         
         // Write configuration
-        self.write_register(REGISTER_GYRO_CONFIG, sensitivity as u8)?;
+        self.write_register_verified(REGISTER_GYRO_CONFIG, sensitivity as u8)?;
         
-        println!("Gyroscope sensitivity set to {:?}", sensitivity);
+        info!("Gyroscope sensitivity set to {:?}", sensitivity);
+        Ok(())
+    }
+    
+    pub fn enable_data_ready_interrupt(&mut self) -> Result<(), SensorError> {
+        self.write_register_verified(REGISTER_INT_ENABLE, INT_ENABLE_DATA_RDY_EN)?;
+        info!("MPU6050 data ready interrupt enabled");
+        Ok(())
+    }
+    
+    // Reading INT_STATUS clears it, so each `true` corresponds to a new sample
+    pub fn data_ready(&mut self) -> Result<bool, SensorError> {
+        let status = self.read_int_status()?;
+        Ok(status & INT_STATUS_DATA_RDY_INT != 0)
+    }
+    
+    // Keeps a FIFO overflow seen by any INT_STATUS read for the next drain_fifo()
+    fn read_int_status(&mut self) -> Result<u8, SensorError> {
+        let status = self.read_register(REGISTER_INT_STATUS)?;
+        self.fifo_overflowed |= status & INT_STATUS_FIFO_OFLOW_INT != 0;
+        Ok(status)
+    }
+    
+    // Buffers every sample at the sample rate in the hardware FIFO, starting empty, so a
+    // busy caller can collect them later with drain_fifo()
+    pub fn enable_fifo(&mut self) -> Result<(), SensorError> {
+        self.write_register_verified(REGISTER_FIFO_EN, FIFO_EN_TEMP_XYZG_ACCEL)?;
+        self.reset_fifo()?;
+        info!("MPU6050 FIFO enabled ({} bytes per sample)", BURST_LEN);
         Ok(())
     }
     
-    fn write_register(&mut self, register: u8, value: u8) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn disable_fifo(&mut self) -> Result<(), SensorError> {
+        self.write_register(REGISTER_USER_CTRL, 0)?;
+        self.write_register_verified(REGISTER_FIFO_EN, 0)
+    }
+    
+    fn reset_fifo(&mut self) -> Result<(), SensorError> {
+        self.write_register(REGISTER_USER_CTRL, USER_CTRL_FIFO_EN | USER_CTRL_FIFO_RESET)?;
+        self.fifo_overflowed = false;
+        Ok(())
+    }
+    
+    // Bytes waiting in the FIFO
+    pub fn fifo_count(&mut self) -> Result<u16, SensorError> {
+        Ok(self.read_register_16(REGISTER_FIFO_COUNT_H)? as u16)
+    }
+    
+    // Every whole sample in the FIFO, oldest first. After an overflow the oldest samples
+    // are gone and the rest may no longer start on a sample boundary, so the FIFO is
+    // reset and the drain comes back empty with `overflowed` set.
+    pub fn drain_fifo(&mut self) -> Result<FifoDrain, SensorError> {
+        self.read_int_status()?;
+        let count = self.fifo_count()? as usize;
+        if self.fifo_overflowed || count >= FIFO_SIZE {
+            warn!("MPU6050 FIFO overflowed - resetting it; samples were lost");
+            self.reset_fifo()?;
+            return Ok(FifoDrain { samples: Vec::new(), overflowed: true });
+        }
+        
+        // A partly written sample stays for the next drain
+        let mut buffer = vec![0u8; count / BURST_LEN * BURST_LEN];
+        if !buffer.is_empty() {
+            self.i2c.write_read(&[REGISTER_FIFO_R_W], &mut buffer)?;
+        }
+        let samples = buffer.chunks_exact(BURST_LEN)
+            .map(|sample| self.parse_burst(sample.try_into().expect("chunks are BURST_LEN long")))
+            .collect();
+        Ok(FifoDrain { samples, overflowed: false })
+    }
+    
+    pub fn set_accel_units(&mut self, units: AccelUnits) {
+        self.accel_units = units;
+    }
+    
+    // Local gravity in m/s², used when converting g to m/s²
+    pub fn set_gravity(&mut self, gravity: f32) {
+        self.gravity = gravity;
+    }
+    
+    // Smoothing factor for read_accelerometer_smoothed, in (0, 1]. Alpha near 1 follows
+    // the raw readings closely (little smoothing); near 0 smooths heavily but lags.
+    pub fn set_ema_alpha(&mut self, alpha: f32) -> Result<(), SensorError> {
+        if !(alpha > 0.0 && alpha <= 1.0) {
+            return Err(SensorError::InvalidConfig(format!("EMA alpha must be in (0, 1], got {}", alpha)));
+        }
+        self.ema_alpha = alpha;
+        Ok(())
+    }
+    
+    pub fn ema_alpha(&self) -> f32 {
+        self.ema_alpha
+    }
+    
+    // Forget the smoothing state so the next smoothed read starts from the raw value
+    pub fn reset_ema(&mut self) {
+        self.accel_ema = None;
+    }
+    
+    // Rotates readings into the vehicle frame. Calibrate after setting this: the offsets
+    // are in the mapped frame, with vehicle +Z assumed up.
+    pub fn set_axis_map(&mut self, map: AxisMap) {
+        self.axis_map = map;
+        self.accel_ema = None;
+    }
+    
+    pub fn axis_map(&self) -> AxisMap {
+        self.axis_map
+    }
+    
+    // Offsets to subtract from every reading, e.g. from an earlier calibrate(). They are
+    // in the mapped frame and the accelerometer units configured when they were measured.
+    pub fn set_offsets(&mut self, accel: AccelerometerReading, gyro: GyroscopeReading) {
+        self.accel_offset = accel;
+        self.gyro_offset = gyro;
+        self.accel_ema = None;
+    }
+    
+    // Back to uncorrected readings
+    pub fn clear_offsets(&mut self) {
+        self.set_offsets(AccelerometerReading::default(), GyroscopeReading::default());
+    }
+    
+    pub fn offsets(&self) -> (&AccelerometerReading, &GyroscopeReading) {
+        (&self.accel_offset, &self.gyro_offset)
+    }
+    
+    // Writes the current offsets as TOML, so the next boot can skip calibrate():
+    //   accel_units = "m/s2"
+    //   accel_offset = [0.05, -0.12, 0.31]
+    //   gyro_offset = [-1.2, 0.4, 0.03]
+    pub fn save_calibration(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, format_calibration(&self.accel_offset, &self.gyro_offset, self.accel_units))
+    }
+    
+    // Applies offsets written by save_calibration(). An io::ErrorKind::NotFound error
+    // means there is no saved calibration; a file that doesn't parse, or was saved in
+    // other accelerometer units, is InvalidData. The offsets are unchanged on error.
+    pub fn load_calibration(&mut self, path: &Path) -> io::Result<()> {
+        let text = fs::read_to_string(path)?;
+        let (accel, gyro) = parse_calibration(&text, self.accel_units)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))?;
+        info!("Loaded MPU6050 calibration from {}", path.display());
+        self.set_offsets(accel, gyro);
+        Ok(())
+    }
+    
+    // Magnitude of 1g in the configured accelerometer units
+    fn one_g(&self) -> f32 {
+        match self.accel_units {
+            AccelUnits::MetersPerSecondSquared => self.gravity,
+            AccelUnits::G => 1.0,
+        }
+    }
+    
+    pub fn accel_sensitivity(&self) -> AccelSensitivity {
+        self.accel_sensitivity
+    }
+    
+    pub fn gyro_sensitivity(&self) -> GyroSensitivity {
+        self.gyro_sensitivity
+    }
+    
+    pub fn sample_rate_hz(&self) -> u16 {
+        self.dlpf.gyro_output_rate_hz() / (1 + SMPLRT_DIV_125HZ as u16)
+    }
+    
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), SensorError> {
         self.i2c.write(&[register, value])?;
         Ok(())
     }
     
-    fn read_register(&mut self, register: u8) -> Result<u8, Box<dyn std::error::Error>> {
+    // Writes, then reads the register back so a write corrupted on the bus is caught
+    // instead of silently mis-scaling every reading. Only bits in verify_mask() are compared.
+    fn write_register_verified(&mut self, register: u8, value: u8) -> Result<(), SensorError> {
+        self.write_register(register, value)?;
+        let read_back = self.read_register(register)?;
+        let mask = verify_mask(register);
+        if read_back & mask != value & mask {
+            return Err(SensorError::VerifyFailed { register, wrote: value as u16, found: read_back as u16 });
+        }
+        Ok(())
+    }
+    
+    fn read_register(&mut self, register: u8) -> Result<u8, SensorError> {
         let mut buffer = [0u8; 1];
-        self.i2c.write_read(&[register], &mut buffer)?;
+        with_retry(READ_ATTEMPTS, || self.i2c.write_read(&[register], &mut buffer))?;
         Ok(buffer[0])
     }
     
-    fn read_register_16(&mut self, register: u8) -> Result<i16, Box<dyn std::error::Error>> {
+    fn read_register_16(&mut self, register: u8) -> Result<i16, SensorError> {
         let mut buffer = [0u8; 2];
-        self.i2c.write_read(&[register], &mut buffer)?;
+        with_retry(READ_ATTEMPTS, || self.i2c.write_read(&[register], &mut buffer))?;
         Ok(((buffer[0] as i16) << 8) | (buffer[1] as i16))
     }
     
-    pub fn read_accelerometer(&mut self) -> Result<AccelerometerReading, Box<dyn std::error::Error>> {
+    pub fn read_accelerometer(&mut self) -> Result<AccelerometerReading, SensorError> {
+        self.check_accel_powered()?;
         let x_raw = self.read_register_16(REGISTER_ACCEL_XOUT_H)?;
         let y_raw = self.read_register_16(REGISTER_ACCEL_YOUT_H)?;
         let z_raw = self.read_register_16(REGISTER_ACCEL_ZOUT_H)?;
+        Ok(self.scale_accelerometer(x_raw, y_raw, z_raw))
+    }
+    
+    fn scale_accelerometer(&self, x_raw: i16, y_raw: i16, z_raw: i16) -> AccelerometerReading {
+        let one_g = self.one_g(); // Convert to configured units
+        let x = (x_raw as f32 / self.accel_scale) * one_g;
+        let y = (y_raw as f32 / self.accel_scale) * one_g;
+        let z = (z_raw as f32 / self.accel_scale) * one_g;
+        
+        let [x, y, z] = self.axis_map.apply([x, y, z]);
+        let offset = &self.accel_offset;
+        AccelerometerReading { x: x - offset.x, y: y - offset.y, z: z - offset.z }
+    }
+    
+    // Per-axis exponential moving average of the accelerometer, seeded by the first read.
+    // read_accelerometer stays unfiltered.
+    pub fn read_accelerometer_smoothed(&mut self) -> Result<AccelerometerReading, SensorError> {
+        let raw = self.read_accelerometer()?;
+        let alpha = self.ema_alpha;
         
-        let x = (x_raw as f32 / self.accel_scale) * 9.80665; // Convert to m/s²
-        let y = (y_raw as f32 / self.accel_scale) * 9.80665;
-        let z = (z_raw as f32 / self.accel_scale) * 9.80665;
+        let smoothed = match self.accel_ema.take() {
+            Some(prev) => AccelerometerReading {
+                x: prev.x + alpha * (raw.x - prev.x),
+                y: prev.y + alpha * (raw.y - prev.y),
+                z: prev.z + alpha * (raw.z - prev.z),
+            },
+            None => raw,
+        };
         
-        Ok(AccelerometerReading { x, y, z })
+        self.accel_ema = Some(smoothed.clone());
+        Ok(smoothed)
     }
     
-    pub fn read_gyroscope(&mut self) -> Result<GyroscopeReading, Box<dyn std::error::Error>> {
+    pub fn read_gyroscope(&mut self) -> Result<GyroscopeReading, SensorError> {
+        self.check_awake()?;
         let x_raw = self.read_register_16(REGISTER_GYRO_XOUT_H)?;
         let y_raw = self.read_register_16(REGISTER_GYRO_YOUT_H)?;
         let z_raw = self.read_register_16(REGISTER_GYRO_ZOUT_H)?;
-        
+        Ok(self.scale_gyroscope(x_raw, y_raw, z_raw))
+    }
+    
+    fn scale_gyroscope(&self, x_raw: i16, y_raw: i16, z_raw: i16) -> GyroscopeReading {
         let x = x_raw as f32 / self.gyro_scale;
         let y = y_raw as f32 / self.gyro_scale;
         let z = z_raw as f32 / self.gyro_scale;
         
-        Ok(GyroscopeReading { x, y, z })
+        let [x, y, z] = self.axis_map.apply([x, y, z]);
+        let offset = &self.gyro_offset;
+        GyroscopeReading { x: x - offset.x, y: y - offset.y, z: z - offset.z }
     }
     
-    pub fn read_temperature(&mut self) -> Result<f32, Box<dyn std::error::Error>> {
+    pub fn read_temperature(&mut self) -> Result<f32, SensorError> {
+        self.check_awake()?;
         let temp_raw = self.read_register_16(REGISTER_TEMP_OUT_H)?;
-        let temperature = (temp_raw as f32 / 340.0) + 36.53; // Convert to °C
-        Ok(temperature)
+        Ok(scale_temperature(temp_raw))
     }
     
-    pub fn read_all(&mut self) -> Result<MotionReading, Box<dyn std::error::Error>> {
+    pub fn read_all(&mut self) -> Result<MotionReading, SensorError> {
         let accelerometer = self.read_accelerometer()?;
         let gyroscope = self.read_gyroscope()?;
         let temperature = self.read_temperature()?;
@@ -228,47 +841,884 @@ impl MPU6050 {
         })
     }
     
-    pub fn calibrate(&mut self, samples: usize) -> Result<(AccelerometerReading, GyroscopeReading), Box<dyn std::error::Error>> {
-        println!("Calibrating MPU6050 with {} samples...", samples);
+    // Every output register in one transaction: a single sample, where read_all() can mix
+    // axes from consecutive samples, for a seventh of the bus traffic
+    pub fn read_all_burst(&mut self) -> Result<MotionReading, SensorError> {
+        self.check_awake()?;
+        let mut buffer = [0u8; BURST_LEN];
+        with_retry(READ_ATTEMPTS, || self.i2c.write_read(&[REGISTER_ACCEL_XOUT_H], &mut buffer))?;
+        Ok(self.parse_burst(&buffer))
+    }
+    
+    fn parse_burst(&self, buffer: &[u8; BURST_LEN]) -> MotionReading {
+        let raw: [i16; BURST_LEN / 2] = std::array::from_fn(|i| i16::from_be_bytes([buffer[2 * i], buffer[2 * i + 1]]));
+        MotionReading {
+            accelerometer: self.scale_accelerometer(raw[0], raw[1], raw[2]),
+            temperature: scale_temperature(raw[3]),
+            gyroscope: self.scale_gyroscope(raw[4], raw[5], raw[6]),
+        }
+    }
+    
+    // Blocks until the sensor reports a fresh sample, then burst-reads it
+    pub fn read_all_when_ready(&mut self, timeout: Duration) -> Result<MotionReading, SensorError> {
+        self.check_awake()?; // A sleeping device never reports data ready
+        let deadline = Instant::now() + timeout;
+        
+        while !self.data_ready()? {
+            if Instant::now() >= deadline {
+                return Err(SensorError::Timeout { what: "MPU6050 data ready", after: timeout });
+            }
+            thread::sleep(DATA_READY_POLL_INTERVAL);
+        }
+        
+        self.read_all_burst()
+    }
+    
+    // Runs the factory self-test at the ranges it is specified for (±8g, ±250°/s), then
+    // restores the configured ranges. The device must be still while it runs.
+    pub fn self_test(&mut self) -> Result<SelfTestResult, SensorError> {
+        self.check_awake()?;
+        let mut trim = [0u8; 4];
+        for (value, register) in trim.iter_mut().zip(REGISTER_SELF_TEST_X..=REGISTER_SELF_TEST_A) {
+            *value = self.read_register(register)?;
+        }
+        
+        let (accel, gyro) = (self.accel_sensitivity, self.gyro_sensitivity);
+        let accel_range = AccelSensitivity::AFS_SEL_8G as u8;
+        let gyro_range = GyroSensitivity::FS_SEL_250DPS as u8;
+        
+        let responses = self.write_register_verified(REGISTER_ACCEL_CONFIG, accel_range)
+            .and_then(|_| self.write_register_verified(REGISTER_GYRO_CONFIG, gyro_range))
+            .and_then(|_| self.average_raw_after_settling())
+            .and_then(|without| {
+                self.write_register_verified(REGISTER_ACCEL_CONFIG, CONFIG_SELF_TEST_XYZ | accel_range)?;
+                self.write_register_verified(REGISTER_GYRO_CONFIG, CONFIG_SELF_TEST_XYZ | gyro_range)?;
+                Ok((without, self.average_raw_after_settling()?))
+            });
         
-        let mut accel_offset = AccelerometerReading { x: 0.0, y: 0.0, z: 0.0 };
-        let mut gyro_offset = GyroscopeReading { x: 0.0, y: 0.0, z: 0.0 };
+        // Leave self-test mode even if a read failed part way
+        let restored = self.set_accel_sensitivity(accel).and_then(|_| self.set_gyro_sensitivity(gyro));
+        let (without, with) = responses?;
+        restored?;
         
-        for i in 0..samples {
+        let result = self_test_deviation(trim, without, with);
+        info!("MPU6050 self-test: accel deviation X={:+.1}% Y={:+.1}% Z={:+.1}%, gyro deviation X={:+.1}% Y={:+.1}% Z={:+.1}%",
+                 result.accel_deviation[0] * 100.0, result.accel_deviation[1] * 100.0, result.accel_deviation[2] * 100.0,
+                 result.gyro_deviation[0] * 100.0, result.gyro_deviation[1] * 100.0, result.gyro_deviation[2] * 100.0);
+        Ok(result)
+    }
+    
+    // Mean raw accel X/Y/Z and gyro X/Y/Z once the output has settled after a config change
+    fn average_raw_after_settling(&mut self) -> Result<[f32; 6], SensorError> {
+        thread::sleep(SELF_TEST_SETTLE);
+        let mut sum = [0.0f32; 6];
+        for _ in 0..SELF_TEST_SAMPLES {
+            let registers = [REGISTER_ACCEL_XOUT_H, REGISTER_ACCEL_YOUT_H, REGISTER_ACCEL_ZOUT_H,
+                             REGISTER_GYRO_XOUT_H, REGISTER_GYRO_YOUT_H, REGISTER_GYRO_ZOUT_H];
+            for (total, register) in sum.iter_mut().zip(registers) {
+                *total += self.read_register_16(register)? as f32;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        Ok(sum.map(|total| total / SELF_TEST_SAMPLES as f32))
+    }
+    
+    // Read-only snapshot of the register map from REGISTER_DUMP_START to REGISTER_DUMP_END.
+    // FIFO_R_W is reported as 0 because reading it would pop a byte from the FIFO.
+    pub fn dump_registers(&mut self) -> Result<[u8; REGISTER_DUMP_LEN], SensorError> {
+        let mut dump = [0u8; REGISTER_DUMP_LEN];
+        
+        for (offset, value) in dump.iter_mut().enumerate() {
+            let register = REGISTER_DUMP_START + offset as u8;
+            if register != REGISTER_FIFO_R_W {
+                *value = self.read_register(register)?;
+            }
+        }
+        
+        Ok(dump)
+    }
+    
+    // Measures the offsets with the device still and flat, and applies them to every
+    // reading from then on. Any earlier offsets are cleared first, and stay cleared if
+    // a read fails.
+    pub fn calibrate(&mut self, samples: usize) -> Result<(AccelerometerReading, GyroscopeReading, CalibrationReport), SensorError> {
+        if samples == 0 {
+            return Err(SensorError::Calibration("needs at least one sample".to_string()));
+        }
+        
+        info!("Calibrating MPU6050 with {} samples...", samples);
+        
+        self.clear_offsets();
+        let mut sums = CalibrationSums::default();
+        
+        for _ in 0..samples {
             let reading = self.read_all()?;
+            sums.add(&reading);
             
-            accel_offset.x += reading.accelerometer.x;
-            accel_offset.y += reading.accelerometer.y;
-            accel_offset.z += reading.accelerometer.z;
-            
-            gyro_offset.x += reading.gyroscope.x;
-            gyro_offset.y += reading.gyroscope.y;
-            gyro_offset.z += reading.gyroscope.z;
+            thread::sleep(Duration::from_millis(10));
+        }
+        
+        Ok(self.finish_calibration(&sums))
+    }
+    
+    // Like calibrate(), but stops early once the running mean of every axis moves by less
+    // than `tolerance` over a CALIBRATION_WINDOW-sample window. The report's sample count
+    // reaching `max_samples` means the estimate never settled.
+    pub fn calibrate_until_stable(&mut self, max_samples: usize, tolerance: f32) -> Result<(AccelerometerReading, GyroscopeReading, CalibrationReport), SensorError> {
+        if max_samples == 0 {
+            return Err(SensorError::Calibration("needs at least one sample".to_string()));
+        }
+        
+        info!("Calibrating MPU6050 until stable (tolerance {}, at most {} samples)...", tolerance, max_samples);
+        
+        self.clear_offsets();
+        let mut sums = CalibrationSums::default();
+        let mut checkpoint: Option<[f32; 6]> = None;
+        
+        while sums.samples < max_samples {
+            let reading = self.read_all()?;
+            sums.add(&reading);
             
-            if i % 50 == 0 {
-                print!(".");
+            if sums.samples % CALIBRATION_WINDOW == 0 {
+                let mean = sums.mean();
+                
+                let settled = checkpoint.is_some_and(|previous| {
+                    previous.iter().zip(mean.iter()).all(|(a, b)| (a - b).abs() < tolerance)
+                });
+                if settled {
+                    break;
+                }
+                
+                checkpoint = Some(mean);
             }
             
             thread::sleep(Duration::from_millis(10));
         }
         
-        accel_offset.x /= samples as f32;
-        accel_offset.y /= samples as f32;
-        accel_offset.z /= samples as f32;
+        let calibration = self.finish_calibration(&sums);
+        info!("Used {} of {} samples", sums.samples, max_samples);
+        
+        Ok(calibration)
+    }
+    
+    // Turns per-axis sums into offsets, applies them and judges whether the device held
+    // still and flat
+    fn finish_calibration(&mut self, sums: &CalibrationSums) -> (AccelerometerReading, GyroscopeReading, CalibrationReport) {
+        let mean = sums.mean();
+        let variance = sums.variance();
         
-        gyro_offset.x /= samples as f32;
-        gyro_offset.y /= samples as f32;
-        gyro_offset.z /= samples as f32;
+        let mut accel_offset = AccelerometerReading { x: mean[0], y: mean[1], z: mean[2] };
+        let gyro_offset = GyroscopeReading { x: mean[3], y: mean[4], z: mean[5] };
         
         // For accelerometer, subtract gravity from Z-axis if device is stationary
-        accel_offset.z -= 9.80665; // Assume device is flat during calibration
+        accel_offset.z -= self.one_g(); // Assume device is flat during calibration
         
-        println!("\nCalibration complete!");
-        println!("Accelerometer offsets: X={:.3}, Y={:.3}, Z={:.3}", 
+        let one_g_squared = self.one_g() * self.one_g();
+        let accel_variance = variance[..3].iter().fold(0.0f32, |max, &v| max.max(v)) / one_g_squared;
+        let gyro_variance = variance[3..].iter().fold(0.0f32, |max, &v| max.max(v));
+        let report = CalibrationReport {
+            samples: sums.samples,
+            accel_variance,
+            gyro_variance,
+            tilt_deg: tilt_from_z(mean[0], mean[1], mean[2]),
+            quality: CalibrationQuality::Pass,
+        };
+        let report = CalibrationReport { quality: assess_calibration(&report), ..report };
+        
+        info!("Calibration complete");
+        info!("Accelerometer offsets: X={:.3}, Y={:.3}, Z={:.3}", 
                  accel_offset.x, accel_offset.y, accel_offset.z);
-        println!("Gyroscope offsets: X={:.3}, Y={:.3}, Z={:.3}", 
+        info!("Gyroscope offsets: X={:.3}, Y={:.3}, Z={:.3}", 
                  gyro_offset.x, gyro_offset.y, gyro_offset.z);
+        info!("Variance: accel {:.2e} g², gyro {:.2e} (°/s)², tilt {:.1}°",
+                 report.accel_variance, report.gyro_variance, report.tilt_deg);
+        
+        match report.quality {
+            CalibrationQuality::Pass => {}
+            CalibrationQuality::Warn => warn!("Calibration was noisy or tilted - check the payload was still and flat"),
+            CalibrationQuality::Fail => error!("Calibration failed - the payload moved or wasn't flat; the offsets are unreliable"),
+        }
         
-        Ok((accel_offset, gyro_offset))
+        self.set_offsets(accel_offset.clone(), gyro_offset.clone());
+        (accel_offset, gyro_offset, report)
+    }
+}
+
+impl<B: I2cBus> Sensor for MPU6050<B> {
+    type Reading = MotionReading;
+
+    fn read(&mut self) -> Result<MotionReading, SensorError> {
+        self.read_all_burst()
+    }
+
+    fn who_am_i(&mut self) -> Result<u8, SensorError> {
+        self.read_register(REGISTER_WHO_AM_I)
+    }
+}
+
+fn scale_temperature(raw: i16) -> f32 {
+    raw as f32 / 340.0 + 36.53 // °C
+}
+
+fn units_name(units: AccelUnits) -> &'static str {
+    match units {
+        AccelUnits::MetersPerSecondSquared => "m/s2",
+        AccelUnits::G => "g",
+    }
+}
+
+// The file save_calibration() writes
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CalibrationFile {
+    accel_units: String,
+    accel_offset: [f64; 3],
+    gyro_offset: [f64; 3],
+}
+
+// Each axis widened through its shortest decimal, so the file says 0.05 rather than the
+// 0.05000000074505806 a plain `as f64` would; narrowing back gives the same f32
+fn decimal_axes(axes: [f32; 3]) -> [f64; 3] {
+    axes.map(|axis| axis.to_string().parse().expect("an f32 prints as a valid f64"))
+}
+
+fn format_calibration(accel: &AccelerometerReading, gyro: &GyroscopeReading, units: AccelUnits) -> String {
+    let file = CalibrationFile {
+        accel_units: units_name(units).to_string(),
+        accel_offset: decimal_axes([accel.x, accel.y, accel.z]),
+        gyro_offset: decimal_axes([gyro.x, gyro.y, gyro.z]),
+    };
+    let body = toml::to_string(&file).expect("a string and arrays of floats always serialize");
+    format!("# MPU6050 offsets, subtracted from every reading (vehicle frame)\n{}", body)
+}
+
+// Inverse of format_calibration(), for accelerometer readings in `units`
+fn parse_calibration(text: &str, units: AccelUnits) -> Result<(AccelerometerReading, GyroscopeReading), String> {
+    let file: CalibrationFile = toml::from_str(text).map_err(|e| e.to_string())?;
+    if file.accel_units != units_name(units) {
+        return Err(format!("offsets were saved in {}, readings are in {}", file.accel_units, units_name(units)));
+    }
+    let [ax, ay, az] = file.accel_offset.map(|axis| axis as f32);
+    let [gx, gy, gz] = file.gyro_offset.map(|axis| axis as f32);
+    Ok((AccelerometerReading { x: ax, y: ay, z: az }, GyroscopeReading { x: gx, y: gy, z: gz }))
+}
+
+// Per-axis running sums over calibration samples: accel X/Y/Z, then gyro X/Y/Z.
+// Accumulated in f64 so variance survives thousands of samples.
+#[derive(Default)]
+struct CalibrationSums {
+    samples: usize,
+    sum: [f64; 6],
+    sum_squares: [f64; 6],
+}
+
+impl CalibrationSums {
+    fn add(&mut self, reading: &MotionReading) {
+        let (a, g) = (&reading.accelerometer, &reading.gyroscope);
+        for (axis, value) in [a.x, a.y, a.z, g.x, g.y, g.z].into_iter().enumerate() {
+            self.sum[axis] += value as f64;
+            self.sum_squares[axis] += value as f64 * value as f64;
+        }
+        self.samples += 1;
+    }
+
+    fn mean(&self) -> [f32; 6] {
+        let n = self.samples as f64;
+        self.sum.map(|sum| (sum / n) as f32)
+    }
+
+    fn variance(&self) -> [f32; 6] {
+        let n = self.samples as f64;
+        let mut variance = [0.0; 6];
+        for (axis, v) in variance.iter_mut().enumerate() {
+            let mean = self.sum[axis] / n;
+            *v = (self.sum_squares[axis] / n - mean * mean).max(0.0) as f32;
+        }
+        variance
+    }
+}
+
+// Compares the self-test response (raw output with minus without actuation) against the
+// factory trim, using the trim formulas from the MPU-6000/6050 register map
+fn self_test_deviation(trim: [u8; 4], without: [f32; 6], with: [f32; 6]) -> SelfTestResult {
+    let accel_codes = [
+        (trim[0] >> 3) & 0x1C | (trim[3] >> 4) & 0x03,
+        (trim[1] >> 3) & 0x1C | (trim[3] >> 2) & 0x03,
+        (trim[2] >> 3) & 0x1C | trim[3] & 0x03,
+    ];
+    let gyro_codes = [trim[0] & 0x1F, trim[1] & 0x1F, trim[2] & 0x1F];
+    
+    // An unprogrammed (zero) code has no trim to compare against
+    let accel_trim = |code: u8| if code == 0 { f32::NAN } else { 4096.0 * 0.34 * (0.92f32 / 0.34).powf((code as f32 - 1.0) / 30.0) };
+    let gyro_trim = |code: u8| if code == 0 { f32::NAN } else { 25.0 * 131.0 * 1.046f32.powf(code as f32 - 1.0) };
+    let deviation = |axis: usize, factory: f32| (with[axis] - without[axis] - factory) / factory;
+    
+    SelfTestResult {
+        accel_deviation: [0, 1, 2].map(|axis| deviation(axis, accel_trim(accel_codes[axis]))),
+        // The Y gyro's self-test actuation is in the negative direction
+        gyro_deviation: [0, 1, 2].map(|axis| {
+            let factory = gyro_trim(gyro_codes[axis]);
+            deviation(axis + 3, if axis == 1 { -factory } else { factory })
+        }),
+    }
+}
+
+// Bits that read back as written; reserved bits read as zero and DEVICE_RESET clears itself
+fn verify_mask(register: u8) -> u8 {
+    match register {
+        REGISTER_CONFIG => 0x3F,                              // Bits 7:6 reserved
+        REGISTER_GYRO_CONFIG | REGISTER_ACCEL_CONFIG => 0xF8, // Bits 2:0 reserved
+        REGISTER_INT_ENABLE => 0x59,                          // MOT_EN, FIFO_OFLOW_EN, I2C_MST_INT_EN, DATA_RDY_EN
+        REGISTER_PWR_MGMT_1 => 0x7F,                          // DEVICE_RESET
+        _ => 0xFF,
+    }
+}
+
+// Angle in degrees between (x, y, z) and +Z
+fn tilt_from_z(x: f32, y: f32, z: f32) -> f32 {
+    let magnitude = (x * x + y * y + z * z).sqrt();
+    if magnitude == 0.0 {
+        return 180.0; // No gravity at all: certainly not flat
+    }
+    (z / magnitude).clamp(-1.0, 1.0).acos().to_degrees()
+}
+
+// The worst of the motion and flatness checks
+fn assess_calibration(report: &CalibrationReport) -> CalibrationQuality {
+    let grade = |value: f32, warn: f32, fail: f32| {
+        if value > fail {
+            CalibrationQuality::Fail
+        } else if value > warn {
+            CalibrationQuality::Warn
+        } else {
+            CalibrationQuality::Pass
+        }
+    };
+
+    grade(report.accel_variance, CALIBRATION_ACCEL_VARIANCE_WARN, CALIBRATION_ACCEL_VARIANCE_FAIL)
+        .max(grade(report.gyro_variance, CALIBRATION_GYRO_VARIANCE_WARN, CALIBRATION_GYRO_VARIANCE_FAIL))
+        .max(grade(report.tilt_deg, CALIBRATION_TILT_WARN_DEG, CALIBRATION_TILT_FAIL_DEG))
+}
+
+pub fn register_name(register: u8) -> Option<&'static str> {
+    let name = match register {
+        0x0D => "SELF_TEST_X",
+        0x0E => "SELF_TEST_Y",
+        0x0F => "SELF_TEST_Z",
+        0x10 => "SELF_TEST_A",
+        0x19 => "SMPLRT_DIV",
+        0x1A => "CONFIG",
+        0x1B => "GYRO_CONFIG",
+        0x1C => "ACCEL_CONFIG",
+        0x23 => "FIFO_EN",
+        0x24 => "I2C_MST_CTRL",
+        0x25..=0x35 => "I2C_SLV0-4 (aux I2C master)",
+        0x36 => "I2C_MST_STATUS",
+        0x37 => "INT_PIN_CFG",
+        0x38 => "INT_ENABLE",
+        0x3A => "INT_STATUS",
+        0x3B => "ACCEL_XOUT_H",
+        0x3C => "ACCEL_XOUT_L",
+        0x3D => "ACCEL_YOUT_H",
+        0x3E => "ACCEL_YOUT_L",
+        0x3F => "ACCEL_ZOUT_H",
+        0x40 => "ACCEL_ZOUT_L",
+        0x41 => "TEMP_OUT_H",
+        0x42 => "TEMP_OUT_L",
+        0x43 => "GYRO_XOUT_H",
+        0x44 => "GYRO_XOUT_L",
+        0x45 => "GYRO_YOUT_H",
+        0x46 => "GYRO_YOUT_L",
+        0x47 => "GYRO_ZOUT_H",
+        0x48 => "GYRO_ZOUT_L",
+        0x49..=0x60 => "EXT_SENS_DATA",
+        0x63..=0x66 => "I2C_SLV0-3_DO",
+        0x67 => "I2C_MST_DELAY_CTRL",
+        0x68 => "SIGNAL_PATH_RESET",
+        0x6A => "USER_CTRL",
+        0x6B => "PWR_MGMT_1",
+        0x6C => "PWR_MGMT_2",
+        0x72 => "FIFO_COUNTH",
+        0x73 => "FIFO_COUNTL",
+        0x74 => "FIFO_R_W (not read)",
+        0x75 => "WHO_AM_I",
+        _ => return None,
+    };
+    Some(name)
+}
+
+// One "0xRR  0xVV  NAME" line per register in a dump
+pub fn format_register_dump(dump: &[u8; REGISTER_DUMP_LEN]) -> String {
+    let mut output = String::from("Reg   Value  Name\n");
+    for (offset, value) in dump.iter().enumerate() {
+        let register = REGISTER_DUMP_START + offset as u8;
+        output.push_str(&format!("0x{:02X}  0x{:02X}   {}\n", register, value, register_name(register).unwrap_or("")));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i2c::mock::MockI2c;
+
+    // ACCEL_XOUT_H..GYRO_ZOUT_H: accel (1g, -0.5g, 0), temp raw 0, gyro (1, -2, 0) at default ranges
+    const SAMPLE_DUMP: [u8; 14] = [
+        0x40, 0x00, 0xE0, 0x00, 0x00, 0x00,
+        0x00, 0x00,
+        0x00, 0x83, 0xFE, 0xFA, 0x00, 0x00,
+    ];
+
+    fn sensor_with_dump(dump: &[u8]) -> MPU6050<MockI2c> {
+        let mut bus = MockI2c::new();
+        bus.load(REGISTER_WHO_AM_I, &[0x68]);
+        bus.load(REGISTER_ACCEL_XOUT_H, dump);
+        MPU6050::new(bus, false).unwrap()
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-4, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn initialize_configures_registers() {
+        let sensor = sensor_with_dump(&SAMPLE_DUMP);
+        assert_eq!(sensor.i2c.slave_address, Some(MPU6050_ADDRESS as u16));
+        assert_eq!(sensor.i2c.writes[0], (REGISTER_PWR_MGMT_1, PWR_MGMT_1_RESET));
+        assert_eq!(sensor.i2c.registers[REGISTER_PWR_MGMT_1 as usize], PWR_MGMT_1_CLKSEL_PLL_X);
+        assert_eq!(sensor.i2c.registers[REGISTER_SMPLRT_DIV as usize], SMPLRT_DIV_125HZ);
+        assert_eq!(sensor.i2c.registers[REGISTER_CONFIG as usize], DlpfBandwidth::Hz44 as u8);
+        assert_eq!(sensor.sample_rate_hz(), 125);
+    }
+
+    #[test]
+    fn power_modes_set_pwr_mgmt_bits() {
+        let mut sensor = sensor_with_dump(&SAMPLE_DUMP);
+        let power = |sensor: &MPU6050<MockI2c>| {
+            (sensor.i2c.registers[REGISTER_PWR_MGMT_1 as usize], sensor.i2c.registers[REGISTER_PWR_MGMT_2 as usize])
+        };
+
+        sensor.sleep().unwrap();
+        assert_eq!(power(&sensor), (0x41, 0x00)); // SLEEP, PLL clock kept
+        assert!(matches!(sensor.read_all_burst(), Err(SensorError::Asleep)));
+        assert!(matches!(sensor.read_accelerometer(), Err(SensorError::Asleep)));
+        assert!(!SensorError::Asleep.is_transient());
+
+        sensor.set_cycle_mode(WakeRate::Hz20).unwrap();
+        assert_eq!(power(&sensor), (0x28, 0x87)); // CYCLE | TEMP_DIS; LP_WAKE_CTRL 2, gyros in standby
+        assert_eq!(sensor.power_mode(), PowerMode::Cycle(WakeRate::Hz20));
+        assert!(sensor.read_accelerometer().is_ok());
+        assert!(matches!(sensor.read_gyroscope(), Err(SensorError::Asleep)));
+        assert!(matches!(sensor.read_all(), Err(SensorError::Asleep)));
+
+        sensor.wake().unwrap();
+        assert_eq!(power(&sensor), (PWR_MGMT_1_CLKSEL_PLL_X, 0x00));
+        assert!(sensor.read_all().is_ok());
+    }
+
+    #[test]
+    fn dlpf_settings_map_to_config_bytes() {
+        let mut sensor = sensor_with_dump(&SAMPLE_DUMP);
+        let expected = [(260, 0x00), (184, 0x01), (94, 0x02), (44, 0x03), (21, 0x04), (10, 0x05), (5, 0x06)];
+        for (bandwidth, (hz, byte)) in DlpfBandwidth::ALL.into_iter().zip(expected) {
+            assert_eq!(bandwidth.bandwidth_hz(), hz);
+            assert_eq!(format!("{}", hz).parse(), Ok(bandwidth));
+            sensor.set_dlpf(bandwidth).unwrap();
+            assert_eq!(sensor.i2c.registers[REGISTER_CONFIG as usize], byte);
+        }
+        // Only with the filter off does the divider work from 8 kHz
+        assert_eq!(sensor.sample_rate_hz(), 125);
+        sensor.set_dlpf(DlpfBandwidth::Hz260).unwrap();
+        assert_eq!(sensor.sample_rate_hz(), 1000);
+
+        assert!("43".parse::<DlpfBandwidth>().is_err());
+        assert_eq!("10Hz".parse(), Ok(DlpfBandwidth::Hz10));
+    }
+
+    #[test]
+    fn reads_ride_out_bus_nacks() {
+        let mut sensor = sensor_with_dump(&SAMPLE_DUMP);
+        let expected = sensor.read_all_burst().unwrap();
+        sensor.i2c.fail_reads = 2;
+        assert_eq!(sensor.read_all_burst().unwrap().temperature, expected.temperature);
+        assert_eq!(sensor.read_register(REGISTER_WHO_AM_I).unwrap(), 0x68);
+
+        sensor.i2c.fail_reads = READ_ATTEMPTS;
+        assert!(sensor.read_register(REGISTER_WHO_AM_I).unwrap_err().is_transient());
+    }
+
+    #[test]
+    fn config_writes_are_read_back() {
+        let mut sensor = sensor_with_dump(&SAMPLE_DUMP);
+        sensor.i2c.stuck.push(REGISTER_ACCEL_CONFIG);
+        let err = sensor.set_accel_sensitivity(AccelSensitivity::AFS_SEL_8G).unwrap_err();
+        assert!(err.to_string().contains("register 0x1C reads back 0x00 after writing 0x10"), "{}", err);
+
+        // Reserved bits reading as zero aren't a mismatch
+        sensor.i2c.registers[REGISTER_CONFIG as usize] = 0x06;
+        sensor.i2c.stuck.push(REGISTER_CONFIG);
+        assert!(sensor.write_register_verified(REGISTER_CONFIG, 0xC6).is_ok());
+
+        let mut bus = MockI2c::new();
+        bus.load(REGISTER_WHO_AM_I, &[0x68]);
+        bus.stuck.push(REGISTER_SMPLRT_DIV);
+        assert!(MPU6050::new(bus, false).is_err());
+    }
+
+    #[test]
+    fn self_test_compares_response_to_factory_trim() {
+        // Codes of 1 on every axis: accel trim 4096 * 0.34, gyro trim 25 * 131 (Y negative)
+        let trim = [0x01, 0x01, 0x01, 0x15];
+        let (accel_trim, gyro_trim) = (4096.0 * 0.34, 25.0 * 131.0);
+        let without = [100.0, -50.0, 8000.0, 3.0, -2.0, 1.0];
+        let mut with = without;
+        for value in &mut with[..3] {
+            *value += accel_trim * 1.05;
+        }
+        with[3] += gyro_trim * 0.9;
+        with[4] -= gyro_trim;
+        with[5] += gyro_trim * 1.2;
+
+        let result = self_test_deviation(trim, without, with);
+        for deviation in result.accel_deviation {
+            assert_close(deviation, 0.05);
+        }
+        assert_close(result.gyro_deviation[0], -0.1);
+        assert_close(result.gyro_deviation[1], 0.0);
+        assert_close(result.gyro_deviation[2], 0.2);
+        assert!(!result.passed());
+        assert_eq!(result.failed_axes(), ["gyro Z"]);
+        assert_close(result.worst_deviation(), 0.2);
+
+        with[5] -= gyro_trim * 0.2;
+        assert!(self_test_deviation(trim, without, with).passed());
+        assert!(self_test_deviation(trim, without, with).failed_axes().is_empty());
+        assert!(!self_test_deviation([0; 4], without, with).passed());
+        assert_eq!(self_test_deviation([0; 4], without, with).failed_axes().len(), 6);
+    }
+
+    #[test]
+    fn self_test_restores_ranges() {
+        let mut sensor = sensor_with_dump(&SAMPLE_DUMP);
+        sensor.set_accel_sensitivity(AccelSensitivity::AFS_SEL_4G).unwrap();
+        sensor.i2c.load(REGISTER_SELF_TEST_X, &[0x01, 0x01, 0x01, 0x15]);
+
+        // A static register map shows no response to the actuation at all
+        let result = sensor.self_test().unwrap();
+        assert!(!result.passed());
+        assert_close(result.accel_deviation[0], -1.0);
+        assert!(sensor.i2c.writes.contains(&(REGISTER_ACCEL_CONFIG, 0xF0)));
+        assert_eq!(sensor.i2c.registers[REGISTER_ACCEL_CONFIG as usize], AccelSensitivity::AFS_SEL_4G as u8);
+        assert_eq!(sensor.i2c.registers[REGISTER_GYRO_CONFIG as usize], GyroSensitivity::FS_SEL_250DPS as u8);
+    }
+
+    #[test]
+    fn rejects_wrong_identity() {
+        let mut bus = MockI2c::new();
+        bus.load(REGISTER_WHO_AM_I, &[0x70]);
+        assert!(MPU6050::new(bus, true).is_err());
+    }
+
+    #[test]
+    fn scales_recorded_dump() {
+        let mut sensor = sensor_with_dump(&SAMPLE_DUMP);
+        let reading = sensor.read_all().unwrap();
+
+        assert_close(reading.accelerometer.x, STANDARD_GRAVITY);
+        assert_close(reading.accelerometer.y, -4.903325);
+        assert_close(reading.accelerometer.z, 0.0);
+        assert_close(reading.temperature, 36.53);
+        assert_close(reading.gyroscope.x, 1.0);
+        assert_close(reading.gyroscope.y, -2.0);
+        assert_close(reading.gyroscope.z, 0.0);
+    }
+
+    #[test]
+    fn burst_read_parses_one_sample() {
+        let mut sensor = sensor_with_dump(&SAMPLE_DUMP);
+        let expected = sensor.read_all().unwrap();
+        let reading = sensor.parse_burst(&SAMPLE_DUMP);
+        assert_eq!(format!("{:?}", reading), format!("{:?}", expected));
+        assert_close(reading.accelerometer.y, -4.903325);
+        assert_close(reading.gyroscope.y, -2.0);
+        assert_close(reading.temperature, 36.53);
+
+        // Temperature sits between the accel and gyro blocks
+        let mut dump = SAMPLE_DUMP;
+        dump[6..8].copy_from_slice(&(-3400i16).to_be_bytes());
+        sensor.i2c.load(REGISTER_ACCEL_XOUT_H, &dump);
+        let reading = sensor.read_all_burst().unwrap();
+        assert_close(reading.temperature, 26.53);
+        assert_close(reading.accelerometer.x, STANDARD_GRAVITY);
+        assert_close(reading.gyroscope.x, 1.0);
+    }
+
+    #[test]
+    fn fifo_drains_whole_samples() {
+        let mut sensor = sensor_with_dump(&SAMPLE_DUMP);
+        sensor.enable_fifo().unwrap();
+        assert_eq!(sensor.i2c.registers[REGISTER_FIFO_EN as usize], 0xF8);
+        assert_eq!(sensor.i2c.writes.last(), Some(&(REGISTER_USER_CTRL, USER_CTRL_FIFO_EN | USER_CTRL_FIFO_RESET)));
+
+        // Two samples and the start of a third
+        let mut second = SAMPLE_DUMP;
+        second[0] = 0xC0; // -1g on X
+        let fifo: Vec<u8> = SAMPLE_DUMP.iter().chain(&second).chain(&SAMPLE_DUMP[..5]).copied().collect();
+        sensor.i2c.load(REGISTER_FIFO_COUNT_H, &(fifo.len() as u16).to_be_bytes());
+        sensor.i2c.ports.insert(REGISTER_FIFO_R_W, fifo.into());
+        assert_eq!(sensor.fifo_count().unwrap(), 33);
+
+        let drain = sensor.drain_fifo().unwrap();
+        assert!(!drain.overflowed);
+        assert_eq!(drain.samples.len(), 2);
+        assert_close(drain.samples[0].accelerometer.x, STANDARD_GRAVITY);
+        assert_close(drain.samples[1].accelerometer.x, -STANDARD_GRAVITY);
+        assert_close(drain.samples[1].gyroscope.y, -2.0);
+        assert_eq!(sensor.i2c.ports[&REGISTER_FIFO_R_W].len(), 5);
+    }
+
+    #[test]
+    fn fifo_overflow_resets_and_is_reported() {
+        let mut sensor = sensor_with_dump(&SAMPLE_DUMP);
+        sensor.enable_fifo().unwrap();
+        sensor.i2c.load(REGISTER_FIFO_COUNT_H, &(14u16).to_be_bytes());
+
+        // Seen by data_ready(), which clears INT_STATUS before the drain reads it
+        sensor.i2c.load(REGISTER_INT_STATUS, &[INT_STATUS_FIFO_OFLOW_INT | INT_STATUS_DATA_RDY_INT]);
+        assert!(sensor.data_ready().unwrap());
+        sensor.i2c.load(REGISTER_INT_STATUS, &[0]);
+        sensor.i2c.writes.clear();
+
+        let drain = sensor.drain_fifo().unwrap();
+        assert!(drain.overflowed && drain.samples.is_empty());
+        assert_eq!(sensor.i2c.writes, [(REGISTER_USER_CTRL, USER_CTRL_FIFO_EN | USER_CTRL_FIFO_RESET)]);
+
+        // A full FIFO has overflowed too, or is about to
+        sensor.i2c.load(REGISTER_FIFO_COUNT_H, &(FIFO_SIZE as u16).to_be_bytes());
+        assert!(sensor.drain_fifo().unwrap().overflowed);
+        sensor.i2c.load(REGISTER_FIFO_COUNT_H, &[0, 0]);
+        let drain = sensor.drain_fifo().unwrap();
+        assert!(!drain.overflowed && drain.samples.is_empty());
+    }
+
+    #[test]
+    fn axis_map_swaps_and_negates_axes() {
+        let mut sensor = sensor_with_dump(&SAMPLE_DUMP);
+        // Board mounted rotated 90° about Z: vehicle X = sensor -Y, vehicle Y = sensor X
+        sensor.set_axis_map("-y,x,z".parse().unwrap());
+        let reading = sensor.read_all().unwrap();
+
+        assert_close(reading.accelerometer.x, 4.903325);
+        assert_close(reading.accelerometer.y, STANDARD_GRAVITY);
+        assert_close(reading.accelerometer.z, 0.0);
+        assert_close(reading.gyroscope.x, 2.0);
+        assert_close(reading.gyroscope.y, 1.0);
+        assert_close(reading.gyroscope.z, 0.0);
+    }
+
+    #[test]
+    fn axis_map_must_be_a_signed_permutation() {
+        assert_eq!("x,y,z".parse::<AxisMap>(), Ok(AxisMap::IDENTITY));
+        assert_eq!("+X, -Z, Y".parse::<AxisMap>().unwrap().axes(), [(Axis::X, 1), (Axis::Z, -1), (Axis::Y, 1)]);
+        assert!("x,x,z".parse::<AxisMap>().is_err());
+        assert!("x,y".parse::<AxisMap>().is_err());
+        assert!("x,y,w".parse::<AxisMap>().is_err());
+        assert!(AxisMap::new([(Axis::X, 2), (Axis::Y, 1), (Axis::Z, 1)]).is_err());
+    }
+
+    #[test]
+    fn dump_registers_covers_map_without_touching_fifo() {
+        let mut bus = MockI2c::new();
+        for register in REGISTER_DUMP_START..=REGISTER_DUMP_END {
+            bus.registers[register as usize] = register;
+        }
+        let mut sensor = MPU6050::attach(bus, false).unwrap();
+        let dump = sensor.dump_registers().unwrap();
+
+        assert!(sensor.i2c.writes.is_empty());
+        assert_eq!(dump[0], REGISTER_DUMP_START);
+        assert_eq!(dump[(REGISTER_PWR_MGMT_1 - REGISTER_DUMP_START) as usize], REGISTER_PWR_MGMT_1);
+        assert_eq!(dump[(REGISTER_FIFO_R_W - REGISTER_DUMP_START) as usize], 0);
+        assert_eq!(dump[REGISTER_DUMP_LEN - 1], REGISTER_WHO_AM_I);
+
+        let text = format_register_dump(&dump);
+        assert!(text.contains("0x6B  0x6B   PWR_MGMT_1"));
+        assert!(text.contains("0x1B  0x1B   GYRO_CONFIG"));
+    }
+
+    #[test]
+    fn stable_calibration_stops_after_two_windows() {
+        // Flat and still: 1g on Z, no rotation
+        let mut sensor = sensor_with_dump(&[0, 0, 0, 0, 0x40, 0x00, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let (accel, gyro, report) = sensor.calibrate_until_stable(500, 0.01).unwrap();
+
+        assert_eq!(report.samples, 2 * CALIBRATION_WINDOW);
+        assert_close(accel.z, 0.0);
+        assert_close(gyro.x, 0.0);
+        assert_close(report.tilt_deg, 0.0);
+        assert_eq!(report.quality, CalibrationQuality::Pass);
+    }
+
+    #[test]
+    fn offsets_are_subtracted_from_readings() {
+        let mut sensor = sensor_with_dump(&SAMPLE_DUMP);
+        sensor.set_offsets(AccelerometerReading { x: 0.5, y: -1.0, z: 0.25 }, GyroscopeReading { x: 1.5, y: 0.0, z: -0.5 });
+        let reading = sensor.read_all().unwrap();
+
+        // Raw (1g, -0.5g, 0) m/s² and (1, -2, 0) °/s, less the offsets
+        assert_close(reading.accelerometer.x, STANDARD_GRAVITY - 0.5);
+        assert_close(reading.accelerometer.y, -4.903325 + 1.0);
+        assert_close(reading.accelerometer.z, -0.25);
+        assert_close(reading.gyroscope.x, -0.5);
+        assert_close(reading.gyroscope.y, -2.0);
+        assert_close(reading.gyroscope.z, 0.5);
+
+        sensor.clear_offsets();
+        assert_close(sensor.read_accelerometer().unwrap().x, STANDARD_GRAVITY);
+
+        // Calibrating measures from uncorrected readings and applies the result
+        sensor.set_offsets(AccelerometerReading { x: 3.0, y: 3.0, z: 3.0 }, GyroscopeReading::default());
+        let (accel, gyro, _) = sensor.calibrate(3).unwrap();
+        assert_close(accel.x, STANDARD_GRAVITY);
+        assert_close(gyro.y, -2.0);
+        let reading = sensor.read_all().unwrap();
+        assert_close(reading.accelerometer.x, 0.0);
+        assert_close(reading.accelerometer.z, STANDARD_GRAVITY);
+        assert_close(reading.gyroscope.y, 0.0);
+    }
+
+    #[test]
+    fn calibration_round_trips_through_a_file() {
+        let path = std::env::temp_dir().join(format!("balloon-imu-calibration-{}.toml", std::process::id()));
+        let mut sensor = sensor_with_dump(&SAMPLE_DUMP);
+        sensor.set_offsets(AccelerometerReading { x: 0.123_456_7, y: -9.75, z: 1e-6 }, GyroscopeReading { x: -1.5, y: 0.0, z: 250.0 });
+        sensor.save_calibration(&path).unwrap();
+
+        let mut restored = sensor_with_dump(&SAMPLE_DUMP);
+        restored.load_calibration(&path).unwrap();
+        let (accel, gyro) = restored.offsets();
+        assert_eq!((accel.x, accel.y, accel.z), (0.123_456_7, -9.75, 1e-6));
+        assert_eq!((gyro.x, gyro.y, gyro.z), (-1.5, 0.0, 250.0));
+        assert_close(restored.read_all().unwrap().gyroscope.z, -250.0);
+
+        // Offsets in m/s² are meaningless to a sensor reading in g
+        restored.set_accel_units(AccelUnits::G);
+        let err = restored.load_calibration(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("saved in m/s2"), "{}", err);
+
+        fs::write(&path, "accel_units = \"m/s2\"\naccel_offset = [1, 2]\ngyro_offset = [0, 0, 0]\n").unwrap();
+        assert_eq!(sensor.load_calibration(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(sensor.offsets().1.z, 250.0);
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(sensor.load_calibration(&path).unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn stable_calibration_respects_max_samples() {
+        let mut sensor = sensor_with_dump(&SAMPLE_DUMP);
+        let (_, gyro, report) = sensor.calibrate_until_stable(5, 0.01).unwrap();
+
+        assert_eq!(report.samples, 5);
+        assert_close(gyro.y, -2.0);
+        assert!(sensor.calibrate_until_stable(0, 0.01).is_err());
+
+        // Gravity lies in the X/Y plane: the device was on its side
+        assert!(report.tilt_deg > 80.0);
+        assert_eq!(report.quality, CalibrationQuality::Fail);
+    }
+
+    #[test]
+    fn calibration_variance_flags_motion() {
+        let still = MotionReading {
+            accelerometer: AccelerometerReading { x: 0.0, y: 0.0, z: STANDARD_GRAVITY },
+            gyroscope: GyroscopeReading { x: 0.1, y: 0.0, z: 0.0 },
+            temperature: 20.0,
+        };
+        let mut bumped = still.clone();
+        bumped.accelerometer.x = 0.5 * STANDARD_GRAVITY;
+
+        let mut sums = CalibrationSums::default();
+        for i in 0..100 {
+            sums.add(if i == 50 { &bumped } else { &still });
+        }
+        let variance = sums.variance();
+        let mut sensor = sensor_with_dump(&SAMPLE_DUMP);
+        let (_, _, report) = sensor.finish_calibration(&sums);
+
+        // One 0.5g bump in 100 samples: variance 0.25 * 0.01 * 0.99 g²
+        assert_close(variance[0] / (STANDARD_GRAVITY * STANDARD_GRAVITY), 0.002_475);
+        assert_close(variance[3], 0.0);
+        assert_eq!(report.quality, CalibrationQuality::Warn);
+
+        for _ in 0..10 {
+            sums.add(&bumped);
+        }
+        assert_eq!(sensor.finish_calibration(&sums).2.quality, CalibrationQuality::Fail);
+    }
+
+    #[test]
+    fn accel_units_select_output_scale() {
+        let mut sensor = sensor_with_dump(&SAMPLE_DUMP);
+        assert_close(sensor.read_accelerometer().unwrap().x, 9.80665);
+
+        sensor.set_accel_units(AccelUnits::G);
+        let accel = sensor.read_accelerometer().unwrap();
+        assert_close(accel.x, 1.0);
+        assert_close(accel.y, -0.5);
+
+        sensor.set_accel_units(AccelUnits::MetersPerSecondSquared);
+        sensor.set_gravity(9.78);
+        assert_close(sensor.read_accelerometer().unwrap().x, 9.78);
+    }
+
+    #[test]
+    fn sensitivity_changes_rescale_readings() {
+        let mut sensor = sensor_with_dump(&SAMPLE_DUMP);
+        sensor.set_accel_sensitivity(AccelSensitivity::AFS_SEL_16G).unwrap();
+        sensor.set_gyro_sensitivity(GyroSensitivity::FS_SEL_2000DPS).unwrap();
+
+        assert_eq!(sensor.i2c.registers[REGISTER_ACCEL_CONFIG as usize], 0x18);
+        assert_eq!(sensor.i2c.registers[REGISTER_GYRO_CONFIG as usize], 0x18);
+
+        let accel = sensor.read_accelerometer().unwrap();
+        let gyro = sensor.read_gyroscope().unwrap();
+        assert_close(accel.x, 8.0 * STANDARD_GRAVITY);
+        assert_close(gyro.x, 131.0 / 16.4);
+    }
+
+    #[test]
+    fn smoothed_accelerometer_follows_step_at_alpha_rate() {
+        let mut sensor = sensor_with_dump(&[0; 14]);
+        sensor.set_ema_alpha(0.25).unwrap();
+        assert_close(sensor.read_accelerometer_smoothed().unwrap().x, 0.0);
+
+        // Step to 1g on X: after n samples the EMA has covered 1 - (1 - alpha)^n of the step
+        sensor.i2c.load(REGISTER_ACCEL_XOUT_H, &[0x40, 0x00]);
+        for n in 1..=10 {
+            let expected = STANDARD_GRAVITY * (1.0 - 0.75f32.powi(n));
+            assert_close(sensor.read_accelerometer_smoothed().unwrap().x, expected);
+        }
+        assert_close(sensor.read_accelerometer().unwrap().x, STANDARD_GRAVITY);
+
+        sensor.reset_ema();
+        assert_close(sensor.read_accelerometer_smoothed().unwrap().x, STANDARD_GRAVITY);
+        assert!(sensor.set_ema_alpha(0.0).is_err());
+        assert!(sensor.set_ema_alpha(1.5).is_err());
+    }
+
+    #[test]
+    fn data_ready_reflects_int_status() {
+        let mut sensor = sensor_with_dump(&SAMPLE_DUMP);
+        sensor.enable_data_ready_interrupt().unwrap();
+        assert_eq!(sensor.i2c.registers[REGISTER_INT_ENABLE as usize], INT_ENABLE_DATA_RDY_EN);
+
+        assert!(!sensor.data_ready().unwrap());
+        assert!(sensor.read_all_when_ready(Duration::from_millis(5)).is_err());
+
+        sensor.i2c.load(REGISTER_INT_STATUS, &[INT_STATUS_DATA_RDY_INT]);
+        assert!(sensor.data_ready().unwrap());
+        assert!(sensor.read_all_when_ready(Duration::from_millis(5)).is_ok());
     }
 }
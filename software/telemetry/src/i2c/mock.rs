@@ -0,0 +1,80 @@
+// Mock I2C bus backed by a programmable register map, for exercising the
+// drivers without hardware
+
+use std::collections::{HashMap, VecDeque};
+
+use super::sensor::SensorError;
+use super::I2cBus;
+
+pub struct MockI2c {
+    pub registers: [u8; 256],
+    pub writes: Vec<(u8, u8)>, // (register, value) in the order they were written
+    pub slave_address: Option<u16>,
+    pub stuck: Vec<u8>, // Registers that keep their value whatever is written (a corrupted write)
+    pub ports: HashMap<u8, VecDeque<u8>>, // Data ports (a FIFO): reads pop bytes instead of auto-incrementing
+    pub fail_reads: u32, // Upcoming reads that fail as a NACK would
+}
+
+impl MockI2c {
+    pub fn new() -> Self {
+        Self {
+            registers: [0; 256],
+            writes: Vec::new(),
+            slave_address: None,
+            stuck: Vec::new(),
+            ports: HashMap::new(),
+            fail_reads: 0,
+        }
+    }
+
+    // Load a recorded register dump starting at `start`
+    pub fn load(&mut self, start: u8, bytes: &[u8]) {
+        let start = start as usize;
+        self.registers[start..start + bytes.len()].copy_from_slice(bytes);
+    }
+}
+
+impl Default for MockI2c {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl I2cBus for MockI2c {
+    fn set_slave_address(&mut self, address: u16) -> Result<(), SensorError> {
+        self.slave_address = Some(address);
+        Ok(())
+    }
+
+    // A write is a register address followed by data bytes for consecutive registers
+    fn write(&mut self, buffer: &[u8]) -> Result<(), SensorError> {
+        let (&register, data) = buffer.split_first().ok_or_else(|| SensorError::Bus("empty I2C write".to_string()))?;
+        for (offset, &value) in data.iter().enumerate() {
+            let address = register.wrapping_add(offset as u8);
+            if !self.stuck.contains(&address) {
+                self.registers[address as usize] = value;
+            }
+            self.writes.push((address, value));
+        }
+        Ok(())
+    }
+
+    // Reads auto-increment from the register address in `write_buffer`
+    fn write_read(&mut self, write_buffer: &[u8], read_buffer: &mut [u8]) -> Result<(), SensorError> {
+        let register = *write_buffer.first().ok_or_else(|| SensorError::Bus("empty I2C write_read".to_string()))?;
+        if self.fail_reads > 0 {
+            self.fail_reads -= 1;
+            return Err(SensorError::Bus(format!("NACK reading register 0x{:02X}", register)));
+        }
+        if let Some(port) = self.ports.get_mut(&register) {
+            for byte in read_buffer.iter_mut() {
+                *byte = port.pop_front().ok_or_else(|| SensorError::Bus("read past the end of a mock data port".to_string()))?;
+            }
+            return Ok(());
+        }
+        for (offset, byte) in read_buffer.iter_mut().enumerate() {
+            *byte = self.registers[register.wrapping_add(offset as u8) as usize];
+        }
+        Ok(())
+    }
+}
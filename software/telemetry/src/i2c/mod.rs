@@ -1 +1,40 @@
+#[allow(non_snake_case)]
+pub mod ADS1115;
+#[allow(non_snake_case)]
+pub mod HMC5883L;
+#[allow(non_snake_case)]
+pub mod INA219;
+#[allow(non_snake_case)]
+pub mod MPL115A2;
+#[allow(non_snake_case)]
 pub mod MPU6050;
+pub mod mock;
+pub mod sensor;
+
+use sensor::SensorError;
+
+// Minimal I2C bus interface used by the sensor drivers, so they can run against
+// real hardware or recorded register contents
+pub trait I2cBus {
+    fn set_slave_address(&mut self, address: u16) -> Result<(), SensorError>;
+    fn write(&mut self, buffer: &[u8]) -> Result<(), SensorError>;
+    fn write_read(&mut self, write_buffer: &[u8], read_buffer: &mut [u8]) -> Result<(), SensorError>;
+}
+
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+impl I2cBus for rppal::i2c::I2c {
+    fn set_slave_address(&mut self, address: u16) -> Result<(), SensorError> {
+        rppal::i2c::I2c::set_slave_address(self, address)?;
+        Ok(())
+    }
+
+    fn write(&mut self, buffer: &[u8]) -> Result<(), SensorError> {
+        rppal::i2c::I2c::write(self, buffer)?;
+        Ok(())
+    }
+
+    fn write_read(&mut self, write_buffer: &[u8], read_buffer: &mut [u8]) -> Result<(), SensorError> {
+        rppal::i2c::I2c::write_read(self, write_buffer, read_buffer)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+// Common interface over the I2C sensor drivers, for code that handles any sensor the
+// same way. Each driver keeps its own richer methods; this is only the shared subset.
+//
+// The reading type differs per device, so a collection of sensors is of one reading
+// type, e.g. Vec<Box<dyn Sensor<Reading = PressureReading>>>.
+
+use std::thread;
+use std::time::Duration;
+
+// Tries of each register read in the drivers; 1 + 2 + 4 ms of backoff at most, well
+// inside a sensor read budget
+pub const READ_ATTEMPTS: u32 = 4;
+
+// Wait before the first retry, doubling for each one after
+const FIRST_RETRY_DELAY: Duration = Duration::from_millis(1);
+
+// Every way a driver (or the bus under it) can fail
+#[derive(Debug, thiserror::Error)]
+pub enum SensorError {
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    #[error("I2C error: {0}")]
+    I2c(#[from] rppal::i2c::Error),
+    // Any other I2cBus implementation (the mock in tests)
+    #[error("I2C error: {0}")]
+    Bus(String),
+    #[error("timed out after {after:?} waiting for {what}")]
+    Timeout { what: &'static str, after: Duration },
+    #[error("wrong device: identity register reads 0x{found:02X}, expected 0x{expected:02X}")]
+    IdentityMismatch { expected: u8, found: u8 },
+    // A register read back different from what was written: a corrupted write
+    #[error("register 0x{register:02X} reads back 0x{found:02X} after writing 0x{wrote:02X}")]
+    VerifyFailed { register: u8, wrote: u16, found: u16 },
+    // The measurement overflowed what the device can represent
+    #[error("out of range: {0}")]
+    OutOfRange(String),
+    #[error("calibration failed: {0}")]
+    Calibration(String),
+    // A bad address, channel or setting handed to the driver
+    #[error("{0}")]
+    InvalidConfig(String),
+    #[error("unsupported: {0}")]
+    Unsupported(&'static str),
+    // Read while sleeping, or an output powered down by a low-power mode
+    #[error("sensor is asleep")]
+    Asleep,
+}
+
+impl SensorError {
+    // Worth retrying: the next transfer may well succeed. Any other error will recur.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+            SensorError::I2c(_) => true,
+            SensorError::Bus(_) | SensorError::Timeout { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+// Calls `f` up to `attempts` times while it fails with a transient error (on the balloon,
+// mostly NACKs from EMI and the cold), waiting 1 ms, 2 ms, 4 ms... in between. Any other
+// error, or the last transient one, is returned.
+pub fn with_retry<T>(attempts: u32, mut f: impl FnMut() -> Result<T, SensorError>) -> Result<T, SensorError> {
+    let mut delay = FIRST_RETRY_DELAY;
+    for _ in 1..attempts.max(1) {
+        match f() {
+            Err(e) if e.is_transient() => {
+                thread::sleep(delay);
+                delay *= 2;
+            }
+            result => return result,
+        }
+    }
+    f()
+}
+
+pub trait Sensor {
+    type Reading;
+
+    // One reading, as the sensor loop would take it
+    fn read(&mut self) -> Result<Self::Reading, SensorError>;
+
+    // The device's identity register
+    fn who_am_i(&mut self) -> Result<u8, SensorError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i2c::mock::MockI2c;
+    use crate::i2c::MPU6050::MPU6050;
+
+    // Counts up from `next`, failing once it passes `fail_after`
+    struct Counter {
+        next: u32,
+        fail_after: u32,
+    }
+
+    impl Sensor for Counter {
+        type Reading = u32;
+
+        fn read(&mut self) -> Result<u32, SensorError> {
+            if self.next > self.fail_after {
+                return Err(SensorError::Bus("counter overflowed".to_string()));
+            }
+            self.next += 1;
+            Ok(self.next - 1)
+        }
+
+        fn who_am_i(&mut self) -> Result<u8, SensorError> {
+            Ok(0x42)
+        }
+    }
+
+    fn read_all<R>(sensors: &mut [Box<dyn Sensor<Reading = R>>]) -> Vec<Result<R, SensorError>> {
+        sensors.iter_mut().map(|sensor| sensor.read()).collect()
+    }
+
+    #[test]
+    fn sensors_are_interchangeable_behind_the_trait() {
+        let mut sensors: Vec<Box<dyn Sensor<Reading = u32>>> = vec![
+            Box::new(Counter { next: 7, fail_after: 10 }),
+            Box::new(Counter { next: 11, fail_after: 10 }),
+        ];
+        let readings = read_all(&mut sensors);
+        assert_eq!(readings[0].as_ref().ok(), Some(&7));
+        assert!(readings[1].as_ref().is_err_and(SensorError::is_transient));
+        assert_eq!(sensors[0].who_am_i().ok(), Some(0x42));
+
+        // A real driver on the mock bus
+        let mut bus = MockI2c::new();
+        bus.load(0x75, &[0x68]); // WHO_AM_I
+        bus.load(0x3B, &[0x40, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]); // Positive X
+        let mut imu = MPU6050::new(bus, false).unwrap();
+        assert_eq!(Sensor::who_am_i(&mut imu).ok(), Some(0x68));
+        assert!(Sensor::read(&mut imu).unwrap().accelerometer.x > 0.0);
+
+        assert!(!SensorError::IdentityMismatch { expected: 0x68, found: 0x70 }.is_transient());
+        assert!(SensorError::Timeout { what: "data ready", after: Duration::from_millis(20) }.is_transient());
+    }
+
+    #[test]
+    fn retries_only_transient_errors() {
+        let mut calls = 0;
+        let value = with_retry(READ_ATTEMPTS, || {
+            calls += 1;
+            if calls <= 2 { Err(SensorError::Bus("NACK".to_string())) } else { Ok(0x5A) }
+        });
+        assert_eq!(value.ok(), Some(0x5A));
+        assert_eq!(calls, 3);
+
+        let mut calls = 0;
+        let failed: Result<(), _> = with_retry(READ_ATTEMPTS, || {
+            calls += 1;
+            Err(SensorError::IdentityMismatch { expected: 0x68, found: 0x00 })
+        });
+        assert!(matches!(failed, Err(SensorError::IdentityMismatch { .. })));
+        assert_eq!(calls, 1);
+    }
+}
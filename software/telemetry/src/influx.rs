@@ -0,0 +1,90 @@
+// Background sink POSTing line-protocol telemetry to an InfluxDB v2 write endpoint,
+// e.g. http://localhost:8086/api/v2/write?org=balloon&bucket=flight&precision=ns
+
+use tokio::sync::mpsc;
+use tracing::warn;
+
+// Lines buffered while a write is in flight; beyond this new lines are dropped so a
+// slow or unreachable database never backs up the telemetry loop
+const QUEUE_CAPACITY: usize = 1024;
+
+// Lines combined into one POST once a backlog has built up
+const MAX_BATCH_LINES: usize = 100;
+
+pub struct InfluxSink {
+    lines: mpsc::Sender<String>,
+    measurement: String,
+}
+
+impl InfluxSink {
+    // Must be called from within the tokio runtime
+    pub fn spawn(url: String, token: Option<String>, measurement: String) -> Self {
+        let (lines, rx) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(write_lines(url, token, rx));
+        Self { lines, measurement }
+    }
+
+    pub fn submit(&self, packet: &crate::packet::TelemetryPacket) {
+        if self.lines.try_send(packet.to_line_protocol(&self.measurement)).is_err() {
+            warn!("InfluxDB queue full - dropping telemetry line");
+        }
+    }
+}
+
+async fn write_lines(url: String, token: Option<String>, mut rx: mpsc::Receiver<String>) {
+    let client = reqwest::Client::new();
+
+    while let Some(first) = rx.recv().await {
+        let mut batch = vec![first];
+        while batch.len() < MAX_BATCH_LINES {
+            match rx.try_recv() {
+                Ok(line) => batch.push(line),
+                Err(_) => break,
+            }
+        }
+
+        let mut request = client.post(&url).body(batch.join("\n"));
+        if let Some(token) = &token {
+            request = request.header("Authorization", format!("Token {}", token));
+        }
+
+        match request.send().await {
+            Ok(response) if response.status().is_success() => {}
+            Ok(response) => warn!("InfluxDB write rejected ({}), dropped {} lines", response.status(), batch.len()),
+            Err(e) => warn!("InfluxDB write failed, dropped {} lines: {}", batch.len(), e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::TelemetryPacket;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn posts_lines_with_token() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/api/v2/write?bucket=test", listener.local_addr().unwrap());
+
+        let sink = InfluxSink::spawn(url, Some("secret".to_string()), "balloon".to_string());
+        let packet = TelemetryPacket { timestamp_ms: 42_000, ..TelemetryPacket::new(0) };
+        sink.submit(&packet);
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0u8; 4096];
+        while !String::from_utf8_lossy(&request).contains(" 42000000000") {
+            let n = stream.read(&mut buf).await.unwrap();
+            assert!(n > 0, "connection closed early");
+            request.extend_from_slice(&buf[..n]);
+        }
+        stream.write_all(b"HTTP/1.1 204 No Content\r\ncontent-length: 0\r\n\r\n").await.unwrap();
+
+        let request = String::from_utf8_lossy(&request).to_lowercase();
+        assert!(request.starts_with("post /api/v2/write?bucket=test http/1.1"));
+        assert!(request.contains("authorization: token secret"));
+        assert!(request.contains("balloon,source=simulated "));
+    }
+}
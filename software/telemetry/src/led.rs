@@ -0,0 +1,131 @@
+// "Still alive" indicator on a GPIO pin for field debugging without a console.
+//
+// Patterns, evaluated once per loop iteration:
+//   toggling each successful send    - link up, sensors healthy
+//   short flash every PULSE_PERIOD   - link up, sensor fault
+//   steady on                        - sends failing (link down)
+
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+use rppal::gpio::{Gpio, OutputPin};
+use tracing::info;
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+use tracing::warn;
+
+// Iterations per flash in the sensor-fault pattern
+const PULSE_PERIOD: u32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkState {
+    Sent,
+    SendFailed,
+    Skipped, // Nothing was due this iteration (on-change mode)
+}
+
+#[derive(Debug, Default)]
+pub struct HeartbeatPattern {
+    level: bool,
+    tick: u32,
+}
+
+impl HeartbeatPattern {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Returns the LED level for this iteration
+    pub fn update(&mut self, link: LinkState, sensor_fault: bool) -> bool {
+        self.level = match link {
+            LinkState::SendFailed => true,
+            LinkState::Sent | LinkState::Skipped if sensor_fault => {
+                self.tick = (self.tick + 1) % PULSE_PERIOD;
+                self.tick == 0
+            }
+            LinkState::Sent => !self.level,
+            LinkState::Skipped => self.level,
+        };
+        self.level
+    }
+}
+
+pub struct StatusLed {
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    pin: Option<OutputPin>,
+    pattern: HeartbeatPattern,
+}
+
+impl StatusLed {
+    // BCM pin number; None disables the indicator
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    pub fn new(pin: Option<u8>) -> Self {
+        let pin = pin.and_then(|number| {
+            match Gpio::new().and_then(|gpio| gpio.get(number)) {
+                Ok(pin) => {
+                    info!("Status LED on GPIO {}", number);
+                    Some(pin.into_output_low())
+                }
+                Err(e) => {
+                    warn!("Failed to set up status LED on GPIO {}: {}", number, e);
+                    None
+                }
+            }
+        });
+
+        Self { pin, pattern: HeartbeatPattern::new() }
+    }
+
+    #[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
+    pub fn new(pin: Option<u8>) -> Self {
+        if let Some(number) = pin {
+            info!("Not running on ARM Linux - status LED on GPIO {} disabled", number);
+        }
+
+        Self { pattern: HeartbeatPattern::new() }
+    }
+
+    pub fn update(&mut self, link: LinkState, sensor_fault: bool) {
+        let level = self.pattern.update(link, sensor_fault);
+
+        #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+        if let Some(pin) = self.pin.as_mut() {
+            if level {
+                pin.set_high();
+            } else {
+                pin.set_low();
+            }
+        }
+
+        #[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
+        let _ = level;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggles_on_each_successful_send() {
+        let mut pattern = HeartbeatPattern::new();
+        let levels: Vec<bool> = (0..4).map(|_| pattern.update(LinkState::Sent, false)).collect();
+        assert_eq!(levels, vec![true, false, true, false]);
+
+        // Skipped iterations hold the current level
+        assert!(!pattern.update(LinkState::Skipped, false));
+    }
+
+    #[test]
+    fn holds_steady_while_sends_fail() {
+        let mut pattern = HeartbeatPattern::new();
+        for _ in 0..10 {
+            assert!(pattern.update(LinkState::SendFailed, false));
+            assert!(pattern.update(LinkState::SendFailed, true));
+        }
+    }
+
+    #[test]
+    fn sensor_fault_flashes_briefly() {
+        let mut pattern = HeartbeatPattern::new();
+        let on = (0..PULSE_PERIOD * 3).filter(|_| pattern.update(LinkState::Sent, true)).count();
+        assert_eq!(on, 3);
+    }
+}
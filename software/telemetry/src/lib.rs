@@ -0,0 +1,42 @@
+pub mod altitude;
+pub mod aprs;
+pub mod attitude;
+pub mod blackbox;
+pub mod cadence;
+pub mod checksum;
+pub mod clock;
+pub mod cobs;
+pub mod coords;
+pub mod command;
+pub mod config;
+pub mod deadline;
+pub mod downlink;
+pub mod fields;
+pub mod flight;
+pub mod fragment;
+pub mod frame;
+pub mod freefall;
+pub mod gps;
+pub mod heading;
+pub mod i2c;
+#[cfg(feature = "influx")]
+pub mod influx;
+pub mod led;
+#[cfg(unix)]
+pub mod local_socket;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+pub mod on_change;
+pub mod onewire;
+pub mod packet;
+pub mod peak;
+pub mod reliable;
+pub mod preflight;
+pub mod sensors;
+pub mod serial;
+pub mod session;
+pub mod shutdown;
+pub mod spin;
+pub mod stats;
+pub mod transport;
+pub mod trend;
@@ -0,0 +1,166 @@
+// Unix domain socket publishing every frame to local consumers (display, logger) on the
+// Pi without going through the network stack. Frames use the same u16 length prefix
+// as the TCP transport. Sends never block: with no consumer connected frames are
+// dropped, and a consumer that falls too far behind is disconnected.
+
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+
+use tracing::{info, warn};
+
+use crate::transport::{length_prefixed, Transport};
+
+// Unsent bytes a consumer may accumulate before it is dropped
+const MAX_PENDING_BYTES: usize = 64 * 1024;
+
+struct Consumer {
+    stream: UnixStream,
+    pending: Vec<u8>,
+}
+
+impl Consumer {
+    // Writes as much pending data as the socket accepts; Err means disconnect
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.pending.is_empty() {
+            match self.stream.write(&self.pending) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(written) => {
+                    self.pending.drain(..written);
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct LocalSocket {
+    path: PathBuf,
+    listener: UnixListener,
+    consumers: Vec<Consumer>,
+}
+
+impl LocalSocket {
+    // Replaces a stale socket left behind by an unclean shutdown
+    pub fn bind(path: &Path) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+            fs::remove_file(path)?;
+        }
+
+        let listener = UnixListener::bind(path)?;
+        listener.set_nonblocking(true)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            listener,
+            consumers: Vec::new(),
+        })
+    }
+
+    pub fn consumers(&self) -> usize {
+        self.consumers.len()
+    }
+
+    fn accept_consumers(&mut self) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    if let Err(e) = stream.set_nonblocking(true) {
+                        warn!("Failed to configure local consumer: {}", e);
+                        continue;
+                    }
+                    info!("Local consumer connected to {}", self.path.display());
+                    self.consumers.push(Consumer { stream, pending: Vec::new() });
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return,
+                Err(e) => {
+                    warn!("Failed to accept local consumer: {}", e);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl Transport for LocalSocket {
+    fn send(&mut self, frame: &[u8]) -> io::Result<usize> {
+        self.accept_consumers();
+
+        let buf = length_prefixed(frame)?;
+        self.consumers.retain_mut(|consumer| {
+            if consumer.pending.len() + buf.len() > MAX_PENDING_BYTES {
+                warn!("Dropping local consumer that stopped reading");
+                return false;
+            }
+            consumer.pending.extend_from_slice(&buf);
+            consumer.flush().is_ok()
+        });
+
+        Ok(frame.len())
+    }
+}
+
+impl Drop for LocalSocket {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn socket_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("balloon-{}-{}", std::process::id(), name)).join("telemetry.sock")
+    }
+
+    #[test]
+    fn publishes_frames_to_connected_consumers() {
+        let path = socket_path("publish");
+        let mut socket = LocalSocket::bind(&path).unwrap();
+
+        // Nobody listening yet: the frame is dropped without blocking
+        assert_eq!(socket.send(&[9, 9]).unwrap(), 2);
+
+        let mut consumer = UnixStream::connect(&path).unwrap();
+        socket.send(&[1, 2, 3]).unwrap();
+        assert_eq!(socket.consumers(), 1);
+
+        let mut received = [0u8; 5];
+        consumer.read_exact(&mut received).unwrap();
+        assert_eq!(received, [3, 0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn removes_socket_on_drop_and_replaces_stale_one() {
+        let path = socket_path("cleanup");
+        let stale = LocalSocket::bind(&path).unwrap();
+        std::mem::forget(stale); // Simulate a crash that skipped cleanup
+
+        let socket = LocalSocket::bind(&path).unwrap();
+        assert!(path.exists());
+        drop(socket);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn drops_consumer_that_stops_reading() {
+        let path = socket_path("slow");
+        let mut socket = LocalSocket::bind(&path).unwrap();
+        let _consumer = UnixStream::connect(&path).unwrap();
+
+        let frame = [0u8; 1000];
+        for _ in 0..(4 * MAX_PENDING_BYTES / frame.len()) {
+            socket.send(&frame).unwrap();
+        }
+        assert_eq!(socket.consumers(), 0);
+    }
+}
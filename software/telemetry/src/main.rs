@@ -1,225 +1,268 @@
-use std::net::UdpSocket;
-use std::time::{SystemTime, UNIX_EPOCH};
-use rand::Rng;
-use std::mem;
-
-// Conditional imports for ARM Linux (Raspberry Pi)
-#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
-use rppal::i2c::I2c;
-
-#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
-mod i2c;
-
-#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
-use i2c::MPU6050::{MPU6050, MotionReading};
-
-
-#[repr(C, packed)]  // C layout, no padding
-#[derive(Debug, Clone, Copy)]
-struct TelemetryPacket {
-    sync: u64,
-    timestamp: u64,
-    temperature: f32,
-    humidity: f32,
-    altitude: f32,
-    latitude: f32,
-    longitude: f32,
-    accel_x: f32,
-    accel_y: f32,
-    accel_z: f32,
-    gyro_x: f32,
-    gyro_y: f32,
-    gyro_z: f32,
-    status: u8,
-}
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use clap::Parser;
 
-impl TelemetryPacket {
-    fn new() -> Self {
-        let mut rng = rand::thread_rng();
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-
-        Self {
-            sync: 0xFF_FF_FF_FF_FF_FF_FF_FF,
-            timestamp: now,
-            temperature: rng.gen_range(-40.0..=60.0), // Temperature in Celsius
-            humidity: rng.gen_range(0.0..=100.0),     // Humidity percentage
-            altitude: rng.gen_range(0.0..=50000.0),   // Altitude in meters
-            latitude: rng.gen_range(-90.0..=90.0),    // Latitude in degrees
-            longitude: rng.gen_range(-180.0..=180.0), // Longitude in degrees
-            accel_x: rng.gen_range(-20.0..=20.0),     // Accelerometer X in m/s²
-            accel_y: rng.gen_range(-20.0..=20.0),     // Accelerometer Y in m/s²
-            accel_z: rng.gen_range(-20.0..=20.0),     // Accelerometer Z in m/s²
-            gyro_x: rng.gen_range(-2000.0..=2000.0),  // Gyroscope X in °/s
-            gyro_y: rng.gen_range(-2000.0..=2000.0),  // Gyroscope Y in °/s
-            gyro_z: rng.gen_range(-2000.0..=2000.0),  // Gyroscope Z in °/s
-            status: rng.gen_range(0..=255),           // Status byte
-        }
-    }
-    
-    
-    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
-    fn new_with_motion_data(temperature_celsius: f32, motion: MotionReading) -> Self {
-        let mut rng = rand::thread_rng();
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-
-        Self {
-            sync: 0xFF_FF_FF_FF_FF_FF_FF_FF,
-            timestamp: now,
-            temperature: temperature_celsius,
-            humidity: rng.gen_range(0.0..=100.0),     // Humidity percentage (still simulated)
-            altitude: rng.gen_range(0.0..=50000.0),   // Altitude in meters (simulated)
-            latitude: rng.gen_range(-90.0..=90.0),    // Latitude in degrees (still simulated)
-            longitude: rng.gen_range(-180.0..=180.0), // Longitude in degrees (still simulated)
-            accel_x: motion.accelerometer.x,
-            accel_y: motion.accelerometer.y,
-            accel_z: motion.accelerometer.z,
-            gyro_x: motion.gyroscope.x,
-            gyro_y: motion.gyroscope.y,
-            gyro_z: motion.gyroscope.z,
-            status: 0x02, // Status byte indicating real temperature and motion data
-        }
-    }
+use tracing::{info, info_span, warn, Instrument};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{reload, EnvFilter, Registry};
 
-    fn as_bytes(&self) -> &[u8] {
-        unsafe {
-            std::slice::from_raw_parts(
-                self as *const Self as *const u8,
-                mem::size_of::<Self>()
-            )
-        }
-    }
-}
+use balloon_software::checksum::Checksum;
+use balloon_software::config::Config;
+use balloon_software::cobs::CobsTransport;
+use balloon_software::frame::{self, Endianness, Framed, Preamble};
+use balloon_software::gps;
+use balloon_software::preflight::{self, PreflightReport};
+use balloon_software::reliable::ReliableSender;
+use balloon_software::i2c::MPU6050::format_register_dump;
+use balloon_software::sensors;
+#[cfg(unix)]
+use balloon_software::local_socket::LocalSocket;
+use balloon_software::serial::{self, SerialTransport};
+use balloon_software::shutdown::Shutdown;
+use balloon_software::transport::{Fanout, Tee, TcpTransport, Transport, UdpTransport, WriterTransport};
 
+mod app;
+mod cli;
 
-#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
-fn init_motion_sensor(i2c: I2c) -> Option<MPU6050> {
-    let motion_sensor: Option<MPU6050> = match MPU6050::new(i2c, false) {
-        Ok(sensor) => {
-            println!("MPU6050 motion sensor initialized successfully");
-            Some(sensor)
-        }
-        Err(e) => {
-            eprintln!("Failed to initialize MPU6050 motion sensor: {}", e);
-            eprintln!("Continuing with simulated motion data...");
-            None
-        }
+use app::AppContext;
+use cli::{Args, TransportKind};
+
+// Longest wait for a TCP target to accept a connection during --preflight
+const PREFLIGHT_CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+// A cold start can take a minute or more; on the pad the receiver has usually been
+// powered long enough to be tracking
+const PREFLIGHT_GPS_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Plain text to stdout, or to stderr when stdout carries frames; colored only on a
+// terminal. RUST_LOG, when valid, overrides --log-filter.
+fn init_logging(default_filter: &str, to_stderr: bool) -> Result<LogControl, Box<dyn std::error::Error>> {
+    let normal = std::env::var(EnvFilter::DEFAULT_ENV)
+        .ok()
+        .filter(|directives| EnvFilter::try_new(directives).is_ok())
+        .unwrap_or_else(|| default_filter.to_string());
+    let (writer, ansi) = if to_stderr {
+        (BoxMakeWriter::new(std::io::stderr), std::io::stderr().is_terminal())
+    } else {
+        (BoxMakeWriter::new(std::io::stdout), std::io::stdout().is_terminal())
     };
+    let (filter, handle) = reload::Layer::new(EnvFilter::try_new(&normal)?);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer().with_ansi(ansi).with_writer(writer))
+        .init();
+    Ok(LogControl { handle, normal })
+}
 
-    return motion_sensor;
+// Runtime switch between the configured filter and full per-iteration detail
+struct LogControl {
+    handle: reload::Handle<EnvFilter, Registry>,
+    normal: String,
 }
 
-#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
-fn read_motion_sensor(motion_sensor: &mut Option<MPU6050>) -> Option<MotionReading> {
-    if let Some(ref mut motion) = motion_sensor {
-        match motion.read_all() {
-            Ok(reading) => {
-                println!("Motion reading: Accel({:.2}, {:.2}, {:.2}) m/s², Gyro({:.2}, {:.2}, {:.2}) °/s, Temp: {:.2}°C", 
-                         reading.accelerometer.x, reading.accelerometer.y, reading.accelerometer.z,
-                         reading.gyroscope.x, reading.gyroscope.y, reading.gyroscope.z,
-                         reading.temperature);
-                Some(reading)
-            },
-            Err(e) => {
-                eprintln!("Failed to read motion sensor: {}", e);
-                None
-            }
+impl LogControl {
+    fn set_full_detail(&self, full: bool) {
+        let directives = if full { format!("{},balloon_software=debug", self.normal) } else { self.normal.clone() };
+        let reloaded = EnvFilter::try_new(&directives)
+            .map_err(|e| e.to_string())
+            .and_then(|filter| self.handle.reload(filter).map_err(|e| e.to_string()));
+        match reloaded {
+            Ok(()) => info!("Log filter now {:?}", directives),
+            Err(e) => warn!("Failed to switch log filter to {:?}: {}", directives, e),
         }
-    } else {
-        None
     }
 }
 
-// Fallback functions for non-ARM Linux systems
+// Everything a launch depends on, as run on the pad before flight
+fn preflight(args: &Args) -> PreflightReport {
+    let mut report = PreflightReport::new();
+    sensors::preflight(&args.sensor_config(), &mut report);
+    match &args.gps_device {
+        Some(device) => report.check("GPS fix", gps::check_fix(device, args.gps_baud, PREFLIGHT_GPS_TIMEOUT)),
+        None => report.skip("GPS fix", "no --gps-device"),
+    }
 
-#[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
-fn init_motion_sensor(_i2c: ()) -> () {
-    println!("Running on non-ARM Linux system - using simulated data");
-    ()
-}
+    let mut dirs: Vec<&Path> = [&args.summary_path, &args.black_box_path]
+        .into_iter()
+        .map(|path| path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new(".")))
+        .collect();
+    dirs.dedup();
+    for dir in dirs {
+        report.check(format!("Output dir {}", dir.display()), preflight::check_writable(dir));
+    }
 
-#[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
-fn read_motion_sensor(_motion_sensor: &mut ()) -> () {
-    ()
+    if args.emit_only {
+        report.skip("Target", "--emit-only: frames go to stdout");
+    } else {
+        for target in &args.target {
+            let outcome = match args.transport {
+                TransportKind::Udp => preflight::check_reachable(target, false, PREFLIGHT_CONNECT_TIMEOUT),
+                TransportKind::Tcp => preflight::check_reachable(target, true, PREFLIGHT_CONNECT_TIMEOUT),
+                TransportKind::Serial => serial::open_port(Path::new(target), args.serial_baud())
+                    .map(|_| format!("{} opens at {} baud", target, args.serial_baud()))
+                    .map_err(|e| e.to_string()),
+                TransportKind::File => {
+                    let dir = Path::new(target).parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+                    preflight::check_writable(dir)
+                }
+            };
+            report.check(format!("Target {}", target), outcome);
+        }
+    }
+    report
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let socket = UdpSocket::bind("0.0.0.0:0")?;
-    let target_addr = "127.0.0.1:3000";
-    
-    println!("Starting telemetry packet generator...");
-    println!("Sending packets to: {}", target_addr);
-    
-    // Check if running on ARM Linux (Raspberry Pi)
-    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
-    {
-        println!("Detected ARM Linux system - attempting to initialize Raspberry Pi sensors...");
-        
-        // Initialize MPU6050 motion sensor
-        let i2c_motion = I2c::new()?;
-        let mut motion_sensor = init_motion_sensor(i2c_motion);
-        
-        loop {
-            let motion_reading = read_motion_sensor(&mut motion_sensor);
-            
-            let packet = match motion_reading {
-                Some(motion) => {
-                    TelemetryPacket::new_with_motion_data(
-                        motion.temperature, 
-                        motion
-                    )
-                },
-                None => TelemetryPacket::new() // Fallback to simulated data
-            };
-            
-            let bytes = packet.as_bytes();
-            
-            match socket.send_to(bytes, target_addr) {
-                Ok(bytes_sent) => {
-                    println!("Sent telemetry packet ({} bytes): {:?}", bytes_sent, packet);
-                    println!("Packet size: {} bytes", mem::size_of::<TelemetryPacket>());
-                }
-                Err(e) => {
-                    eprintln!("Failed to send packet: {}", e);
-                }
-            }
+    let mut args = Args::parse();
+    let emit_format = args.emit_format();
+    let logging = init_logging(&args.log_filter, emit_format.is_some())?;
+
+    if args.dump_registers {
+        let dump = sensors::dump_motion_registers()?;
+        print!("{}", format_register_dump(&dump));
+        return Ok(());
+    }
 
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    let config_path = Config::path();
+    let config = match Config::load(&config_path)? {
+        Some(config) => {
+            info!("Loaded config from {}", config_path.display());
+            config
         }
+        None => Config::default(),
+    };
+    args.apply_config(&config);
+
+    if args.preflight {
+        let report = preflight(&args);
+        print!("{}", report);
+        std::process::exit(if report.go() { 0 } else { 1 });
     }
-    
-    #[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
-    {
-        println!("Not running on ARM Linux - using simulated data only");
-        
-        // Initialize dummy sensor for non-ARM systems
-        let mut motion_sensor = init_motion_sensor(());
-        
-        loop {
-            let _motion_reading = read_motion_sensor(&mut motion_sensor);
-            
-            // Always use simulated data for non-ARM systems
-            let packet = TelemetryPacket::new();
-            
-            let bytes = packet.as_bytes();
-            
-            match socket.send_to(bytes, target_addr) {
-                Ok(bytes_sent) => {
-                    println!("Sent telemetry packet ({} bytes): {:?}", bytes_sent, packet);
-                    println!("Packet size: {} bytes", mem::size_of::<TelemetryPacket>());
-                }
-                Err(e) => {
-                    eprintln!("Failed to send packet: {}", e);
-                }
+
+    let init = info_span!("init").entered();
+
+    info!("Starting telemetry packet generator...");
+
+    let mut target_stats = None;
+    let mut transport: Box<dyn Transport> = match (emit_format, args.emit_only) {
+        (Some(format), true) => Box::new(WriterTransport::stdout(format)),
+        _ => {
+            let mut targets = Vec::with_capacity(args.target.len());
+            for target in &args.target {
+                let downlink: Box<dyn Transport> = match args.transport {
+                    TransportKind::Udp => match (args.multicast_ttl, args.reliable_config()) {
+                        (Some(_), Some(_)) => return Err("--reliable can't be used with --multicast-ttl".into()),
+                        (Some(ttl), None) => Box::new(UdpTransport::multicast(target, ttl)?),
+                        (None, Some(reliable)) => {
+                            Box::new(ReliableSender::new(UdpTransport::bound(target, &config.bind_addr)?, reliable))
+                        }
+                        (None, None) => Box::new(UdpTransport::bound(target, &config.bind_addr)?),
+                    },
+                    _ if args.multicast_ttl.is_some() => return Err("--multicast-ttl requires the UDP transport".into()),
+                    _ if args.reliable => return Err("--reliable requires the UDP transport".into()),
+                    TransportKind::Tcp => Box::new(TcpTransport::new(target)),
+                    TransportKind::Serial => {
+                        if !args.cobs {
+                            warn!("Serial transport without --cobs: the ground can't resync after a corrupted byte");
+                        }
+                        Box::new(SerialTransport::open(target, args.serial_baud())?)
+                    }
+                    TransportKind::File => Box::new(WriterTransport::create(Path::new(target))?),
+                };
+                targets.push((target.clone(), downlink));
+            }
+            let downlink: Box<dyn Transport> = if targets.len() == 1 {
+                targets.remove(0).1
+            } else {
+                let fanout = Fanout::new(targets);
+                target_stats = Some(fanout.stats());
+                Box::new(fanout)
+            };
+            match args.multicast_ttl {
+                Some(ttl) => info!("Sending packets to multicast group {} (TTL {})", args.target.join(", "), ttl),
+                None => info!("Sending packets to: {} ({:?})", args.target.join(", "), args.transport),
             }
+            if let Some(reliable) = args.reliable_config() {
+                info!("Resending unacked frames every {} ms, up to {} times", reliable.ack_timeout.as_millis(),
+                      reliable.max_retransmits);
+            }
+            match emit_format {
+                Some(format) => Box::new(Tee::new(downlink, Box::new(WriterTransport::stdout(format)))),
+                None => downlink,
+            }
+        }
+    };
+    if let Some(format) = emit_format {
+        info!("Writing frames to stdout ({:?})", format);
+    }
 
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    if let Some(path) = &args.local_socket {
+        #[cfg(unix)]
+        {
+            let local = LocalSocket::bind(path)?;
+            info!("Publishing frames to local socket {}", path.display());
+            transport = Box::new(Tee::new(transport, Box::new(local)));
         }
+
+        #[cfg(not(unix))]
+        return Err(format!("--local-socket {} requires a Unix platform", path.display()).into());
+    }
+
+    if args.cobs {
+        transport = Box::new(CobsTransport::new(transport));
+        info!("COBS-encoding frames with 0x00 delimiters");
     }
-}
\ No newline at end of file
+
+    if args.preamble_bytes > 0 {
+        transport = Box::new(Preamble::new(transport, args.preamble_pattern, args.preamble_bytes)?);
+        info!("Sending a {}-byte preamble of 0x{:02X} ahead of every frame", args.preamble_bytes, args.preamble_pattern);
+    }
+
+    // Every frame goes out inside the versioned envelope
+    let byte_order = Endianness::from(args.byte_order);
+    let checksum = Checksum::from(args.checksum);
+    transport = Box::new(Framed::new(transport, byte_order).with_checksum(checksum));
+    info!("Frame format version {}, {:?}-endian packets, {:?} checksum", frame::FORMAT_VERSION, byte_order, checksum);
+
+    let send_interval = args.send_interval();
+    let mut ctx = AppContext::new(args, transport, logging)?;
+
+    let shutdown = Shutdown::new();
+    shutdown.listen_for_signals();
+
+    drop(init);
+
+    let iterations = app::run(&mut ctx, send_interval, &shutdown).instrument(info_span!("main_loop")).await;
+
+    info!("Shutting down after {} iterations...", iterations);
+    let AppContext { args, mut stats, sensors, .. } = ctx;
+    stats.sensor_errors = sensors.read_errors();
+    stats.sensor_timeouts = sensors.read_timeouts();
+    // Stops the motion sampler and GPS reader threads; the downlink stays up until
+    // main returns, so frames already handed to it are delivered first
+    drop(sensors);
+    if let Some(target_stats) = &target_stats {
+        stats.targets = target_stats.snapshot();
+    }
+    let now = Instant::now();
+    // Keep stdout clean for frame consumers
+    let flushed = if emit_format.is_some() {
+        eprint!("{}", stats.summary(now));
+        std::io::stderr().flush()
+    } else {
+        print!("{}", stats.summary(now));
+        std::io::stdout().flush()
+    };
+    if let Err(e) = flushed {
+        warn!("Failed to flush the flight summary: {}", e);
+    }
+    match stats.write_summary(&args.summary_path, now) {
+        Ok(()) => info!("Flight summary written to {}", args.summary_path.display()),
+        Err(e) => warn!("Failed to write flight summary to {}: {}", args.summary_path.display(), e),
+    }
+
+    Ok(())
+}
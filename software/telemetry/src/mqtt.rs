@@ -0,0 +1,234 @@
+// Background sink publishing every packet to an MQTT broker (JSON or MessagePack), for
+// dashboards that subscribe to the flight from anywhere
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, QoS};
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+use crate::packet::TelemetryPacket;
+
+pub const DEFAULT_PORT: u16 = 1883;
+
+// Packets held while the broker is slow or unreachable; beyond this the oldest are
+// dropped so the dashboard catches up with the live flight when the link returns
+const BACKLOG_CAPACITY: usize = 256;
+
+// Requests rumqttc buffers between the publisher and its event loop
+const CLIENT_CAPACITY: usize = 16;
+
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Json,
+    MsgPack,
+}
+
+impl Encoding {
+    pub fn encode(self, packet: &TelemetryPacket) -> Vec<u8> {
+        match self {
+            Encoding::Json => packet.to_json().into_bytes(),
+            Encoding::MsgPack => packet.to_msgpack(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MqttConfig {
+    pub broker: String, // host[:port], optionally prefixed with mqtt://
+    pub topic: String,
+    pub qos: u8,        // 0-2
+    pub encoding: Encoding,
+}
+
+pub struct MqttSink {
+    backlog: Arc<Backlog>,
+    encoding: Encoding,
+}
+
+impl MqttSink {
+    // Must be called from within the tokio runtime
+    pub fn spawn(config: MqttConfig) -> Result<Self, String> {
+        let (host, port) = parse_broker(&config.broker)?;
+        let qos = rumqttc::qos(config.qos).map_err(|_| format!("invalid MQTT QoS {}: expected 0-2", config.qos))?;
+        if !rumqttc::valid_topic(&config.topic) {
+            return Err(format!("invalid MQTT topic {:?}", config.topic));
+        }
+
+        let mut options = MqttOptions::new(format!("balloon-{}", std::process::id()), host, port);
+        options.set_keep_alive(KEEP_ALIVE);
+        let (client, event_loop) = AsyncClient::new(options, CLIENT_CAPACITY);
+
+        let backlog = Arc::new(Backlog::new(BACKLOG_CAPACITY));
+        tokio::spawn(drive_connection(event_loop, config.broker));
+        tokio::spawn(publish_backlog(client, config.topic, qos, backlog.clone()));
+        Ok(Self { backlog, encoding: config.encoding })
+    }
+
+    pub fn submit(&self, packet: &TelemetryPacket) {
+        if self.backlog.push(self.encoding.encode(packet)) {
+            warn!("MQTT backlog full - dropping the oldest packet");
+        }
+    }
+}
+
+// Bounded queue that makes room by discarding its oldest entry
+struct Backlog {
+    payloads: Mutex<VecDeque<Vec<u8>>>,
+    ready: Notify,
+    capacity: usize,
+}
+
+impl Backlog {
+    fn new(capacity: usize) -> Self {
+        Self { payloads: Mutex::new(VecDeque::with_capacity(capacity)), ready: Notify::new(), capacity }
+    }
+
+    // Returns true if the oldest payload was dropped to make room
+    fn push(&self, payload: Vec<u8>) -> bool {
+        let mut payloads = self.payloads.lock().unwrap_or_else(PoisonError::into_inner);
+        let dropped = payloads.len() >= self.capacity && payloads.pop_front().is_some();
+        payloads.push_back(payload);
+        drop(payloads);
+        self.ready.notify_one();
+        dropped
+    }
+
+    fn pop(&self) -> Option<Vec<u8>> {
+        self.payloads.lock().unwrap_or_else(PoisonError::into_inner).pop_front()
+    }
+
+    async fn next(&self) -> Vec<u8> {
+        loop {
+            if let Some(payload) = self.pop() {
+                return payload;
+            }
+            self.ready.notified().await;
+        }
+    }
+}
+
+// Hands payloads to rumqttc, which holds them (up to CLIENT_CAPACITY) while reconnecting
+async fn publish_backlog(client: AsyncClient, topic: String, qos: QoS, backlog: Arc<Backlog>) {
+    loop {
+        let payload = backlog.next().await;
+        if let Err(e) = client.publish(topic.as_str(), qos, false, payload).await {
+            warn!("MQTT publish failed, dropped one packet: {}", e);
+        }
+    }
+}
+
+// Polling the event loop does the network I/O; after an error the next poll reconnects
+async fn drive_connection(mut event_loop: EventLoop, broker: String) {
+    let mut backoff = INITIAL_BACKOFF;
+    loop {
+        match event_loop.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                info!("Connected to MQTT broker {}", broker);
+                backoff = INITIAL_BACKOFF;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                warn!("MQTT broker {} unavailable ({}), retrying in {:?}", broker, e, backoff);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+fn parse_broker(broker: &str) -> Result<(String, u16), String> {
+    let address = broker.strip_prefix("mqtt://").unwrap_or(broker);
+    let (host, port) = match address.rsplit_once(':') {
+        Some((host, port)) => {
+            let port = port.parse().map_err(|_| format!("invalid port in MQTT broker {:?}", broker))?;
+            (host, port)
+        }
+        None => (address, DEFAULT_PORT),
+    };
+    if host.is_empty() {
+        return Err(format!("MQTT broker {:?} has no host", broker));
+    }
+    Ok((host.to_string(), port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // Fixed header type byte and body of one MQTT control packet
+    async fn read_packet(stream: &mut (impl AsyncRead + Unpin)) -> (u8, Vec<u8>) {
+        let kind = stream.read_u8().await.unwrap();
+        let (mut length, mut shift) = (0usize, 0);
+        loop {
+            let byte = stream.read_u8().await.unwrap();
+            length |= ((byte & 0x7F) as usize) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+        }
+        let mut body = vec![0; length];
+        stream.read_exact(&mut body).await.unwrap();
+        (kind, body)
+    }
+
+    #[test]
+    fn parses_broker_addresses() {
+        assert_eq!(parse_broker("mqtt://broker.local:1884"), Ok(("broker.local".to_string(), 1884)));
+        assert_eq!(parse_broker("10.0.0.5"), Ok(("10.0.0.5".to_string(), DEFAULT_PORT)));
+        assert!(parse_broker("host:port").is_err());
+        assert!(parse_broker(":1883").is_err());
+    }
+
+    #[test]
+    fn backlog_drops_the_oldest() {
+        let backlog = Backlog::new(2);
+        assert!(!backlog.push(vec![1]));
+        assert!(!backlog.push(vec![2]));
+        assert!(backlog.push(vec![3]));
+        assert_eq!(backlog.pop(), Some(vec![2]));
+        assert_eq!(backlog.pop(), Some(vec![3]));
+        assert_eq!(backlog.pop(), None);
+    }
+
+    #[tokio::test]
+    async fn publishes_packets_to_the_topic() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let sink = MqttSink::spawn(MqttConfig {
+            broker: listener.local_addr().unwrap().to_string(),
+            topic: "balloon/test".to_string(),
+            qos: 0,
+            encoding: Encoding::MsgPack,
+        })
+        .unwrap();
+        let packet = TelemetryPacket { timestamp_ms: 42_000, ..TelemetryPacket::new(0) };
+        sink.submit(&packet);
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        assert_eq!(read_packet(&mut stream).await.0 >> 4, 1); // CONNECT
+        stream.write_all(&[0x20, 0x02, 0x00, 0x00]).await.unwrap(); // CONNACK, accepted
+
+        let (kind, body) = read_packet(&mut stream).await;
+        assert_eq!(kind >> 4, 3); // PUBLISH
+        let topic_len = u16::from_be_bytes([body[0], body[1]]) as usize;
+        assert_eq!(&body[2..2 + topic_len], b"balloon/test");
+        let decoded = TelemetryPacket::from_msgpack(&body[2 + topic_len..]).unwrap();
+        assert_eq!({ decoded.timestamp_ms }, 42_000);
+    }
+
+    #[test]
+    fn rejects_bad_configuration() {
+        let config = MqttConfig { broker: "localhost".to_string(), topic: "a/b".to_string(), qos: 3, encoding: Encoding::Json };
+        assert!(MqttSink::spawn(config.clone()).is_err());
+        assert!(MqttSink::spawn(MqttConfig { qos: 0, topic: "a/#".to_string(), ..config }).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+// Transmit-on-change gate: suppresses packets that don't differ meaningfully from the
+// last one sent, while still guaranteeing a periodic heartbeat frame.
+
+use std::time::{Duration, Instant};
+
+use crate::packet::TelemetryPacket;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ChangeThresholds {
+    pub temperature: f32, // °C
+    pub humidity: f32,    // %
+    pub altitude: f32,    // m
+    pub position: f32,    // degrees (latitude or longitude)
+    pub accel: f32,       // m/s² on any axis
+    pub gyro: f32,        // °/s on any axis
+}
+
+impl Default for ChangeThresholds {
+    fn default() -> Self {
+        Self {
+            temperature: 0.5,
+            humidity: 1.0,
+            altitude: 1.0,
+            position: 0.0001, // ~11m at the equator
+            accel: 0.5,
+            gyro: 5.0,
+        }
+    }
+}
+
+pub struct ChangeGate {
+    thresholds: ChangeThresholds,
+    max_interval: Duration,
+    last_sent: Option<(TelemetryPacket, Instant)>,
+}
+
+impl ChangeGate {
+    pub fn new(thresholds: ChangeThresholds, max_interval: Duration) -> Self {
+        Self {
+            thresholds,
+            max_interval,
+            last_sent: None,
+        }
+    }
+
+    // A packet is due if nothing has been sent yet, the heartbeat interval has elapsed,
+    // or any field moved beyond its threshold since the last transmitted packet.
+    pub fn should_send(&self, packet: &TelemetryPacket, now: Instant) -> bool {
+        match &self.last_sent {
+            None => true,
+            Some((last, sent_at)) => {
+                now.duration_since(*sent_at) >= self.max_interval || self.has_changed(last, packet)
+            }
+        }
+    }
+
+    pub fn record_sent(&mut self, packet: TelemetryPacket, now: Instant) {
+        self.last_sent = Some((packet, now));
+    }
+
+    fn has_changed(&self, last: &TelemetryPacket, packet: &TelemetryPacket) -> bool {
+        let t = &self.thresholds;
+        let exceeds = |a: f32, b: f32, threshold: f32| (a - b).abs() > threshold;
+
+        last.status != packet.status
+            || last.flight_phase != packet.flight_phase
+            || exceeds(last.temperature, packet.temperature, t.temperature)
+            || exceeds(last.humidity, packet.humidity, t.humidity)
+            || exceeds(last.altitude, packet.altitude, t.altitude)
+            || exceeds(last.latitude, packet.latitude, t.position)
+            || exceeds(last.longitude, packet.longitude, t.position)
+            || exceeds(last.accel_x, packet.accel_x, t.accel)
+            || exceeds(last.accel_y, packet.accel_y, t.accel)
+            || exceeds(last.accel_z, packet.accel_z, t.accel)
+            || exceeds(last.gyro_x, packet.gyro_x, t.gyro)
+            || exceeds(last.gyro_y, packet.gyro_y, t.gyro)
+            || exceeds(last.gyro_z, packet.gyro_z, t.gyro)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flight::FlightPhase;
+    use crate::packet::StatusFlags;
+
+    const HEARTBEAT: Duration = Duration::from_secs(30);
+
+    // A gate that has just sent `packet`
+    fn sent(packet: TelemetryPacket, now: Instant) -> ChangeGate {
+        let mut gate = ChangeGate::new(ChangeThresholds::default(), HEARTBEAT);
+        assert!(gate.should_send(&packet, now), "the first packet always goes");
+        gate.record_sent(packet, now);
+        gate
+    }
+
+    #[test]
+    fn only_changes_beyond_a_threshold_are_sent() {
+        let now = Instant::now();
+        let packet = TelemetryPacket::new(0);
+        let gate = sent(packet, now);
+        assert!(!gate.should_send(&packet, now + Duration::from_secs(1)));
+
+        let changed = |change: fn(&mut TelemetryPacket)| {
+            let mut next = packet;
+            change(&mut next);
+            gate.should_send(&next, now + Duration::from_secs(1))
+        };
+        assert!(!changed(|p| p.altitude += 0.9));
+        assert!(changed(|p| p.altitude += 1.1));
+        assert!(!changed(|p| p.accel_y -= 0.4));
+        assert!(changed(|p| p.accel_y -= 0.6));
+        assert!(changed(|p| p.status ^= StatusFlags::LOW_BATTERY.bits()));
+        assert!(changed(|p| p.flight_phase = FlightPhase::Ascent as u8));
+    }
+
+    #[test]
+    fn heartbeat_forces_a_send() {
+        let now = Instant::now();
+        let packet = TelemetryPacket::new(0);
+        let gate = sent(packet, now);
+        assert!(!gate.should_send(&packet, now + HEARTBEAT - Duration::from_millis(1)));
+        assert!(gate.should_send(&packet, now + HEARTBEAT));
+    }
+}
@@ -0,0 +1,157 @@
+// DS18B20 1-Wire temperature probe read through the Linux w1 sysfs interface
+// (dtoverlay=w1-gpio). Mounted outside the payload it measures ambient air, unlike the
+// self-heated dies of the onboard sensors.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::error;
+
+pub const W1_DEVICES_DIR: &str = "/sys/bus/w1/devices";
+
+// 1-Wire family code prefix of DS18B20 device directories, e.g. 28-3c01d607d5a1
+const DS18B20_FAMILY: &str = "28-";
+
+// Power-on value of the temperature register, reported when a conversion didn't run
+const POWER_ON_RESET_MILLIDEGREES: i32 = 85_000;
+
+pub struct Ds18b20 {
+    device_dir: PathBuf,
+}
+
+impl Ds18b20 {
+    pub fn open(device_dir: impl Into<PathBuf>) -> Self {
+        Self { device_dir: device_dir.into() }
+    }
+
+    // First DS18B20 on the bus
+    pub fn discover() -> Result<Self, Box<dyn std::error::Error>> {
+        Self::discover_in(Path::new(W1_DEVICES_DIR))
+    }
+
+    fn discover_in(devices_dir: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut devices: Vec<PathBuf> = fs::read_dir(devices_dir)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(DS18B20_FAMILY))
+            .map(|entry| entry.path())
+            .collect();
+        devices.sort();
+
+        devices
+            .into_iter()
+            .next()
+            .map(Self::open)
+            .ok_or_else(|| format!("No DS18B20 found in {}", devices_dir.display()).into())
+    }
+
+    pub fn device_dir(&self) -> &Path {
+        &self.device_dir
+    }
+
+    // Triggers a conversion, so this blocks for up to 750ms at 12-bit resolution
+    pub fn read_temperature(&self) -> Result<f32, Box<dyn std::error::Error>> {
+        let contents = fs::read_to_string(self.device_dir.join("w1_slave"))?;
+        Ok(parse_w1_slave(&contents)?)
+    }
+}
+
+// Parses the kernel's w1_slave output:
+//   72 01 4b 46 7f ff 0e 10 57 : crc=57 YES
+//   72 01 4b 46 7f ff 0e 10 57 t=23125
+pub fn parse_w1_slave(contents: &str) -> Result<f32, String> {
+    let mut lines = contents.lines();
+    let crc_line = lines.next().ok_or("Empty w1_slave output")?;
+    if !crc_line.trim_end().ends_with("YES") {
+        return Err("DS18B20 CRC check failed".to_string());
+    }
+
+    let data_line = lines.next().ok_or("Missing w1_slave temperature line")?;
+    let (_, value) = data_line.rsplit_once("t=").ok_or("Missing t= in w1_slave output")?;
+    let millidegrees: i32 = value.trim().parse().map_err(|_| format!("Invalid DS18B20 temperature '{}'", value.trim()))?;
+
+    if millidegrees == POWER_ON_RESET_MILLIDEGREES {
+        return Err("DS18B20 returned its power-on value (conversion didn't run)".to_string());
+    }
+    Ok(millidegrees as f32 / 1000.0)
+}
+
+// Reads the probe on a background thread, since one conversion takes most of a second
+// and would stall the 10Hz telemetry loop
+pub struct AmbientTemperature {
+    latest: Arc<Mutex<Option<(f32, Instant)>>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl AmbientTemperature {
+    pub fn spawn(probe: Ds18b20, interval: Duration) -> std::io::Result<Self> {
+        let latest = Arc::new(Mutex::new(None));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let (thread_latest, thread_stop) = (Arc::clone(&latest), Arc::clone(&stop));
+        thread::Builder::new().name("ds18b20".to_string()).spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                match probe.read_temperature() {
+                    Ok(celsius) => {
+                        *thread_latest.lock().unwrap_or_else(PoisonError::into_inner) = Some((celsius, Instant::now()));
+                    }
+                    Err(e) => error!("Failed to read DS18B20 ambient temperature: {}", e),
+                }
+                thread::sleep(interval);
+            }
+        })?;
+
+        Ok(Self { latest, stop })
+    }
+
+    // Most recent reading, or None if there is none newer than `max_age`
+    pub fn latest(&self, max_age: Duration) -> Option<f32> {
+        let latest = *self.latest.lock().unwrap_or_else(PoisonError::into_inner);
+        latest.filter(|(_, at)| at.elapsed() <= max_age).map(|(celsius, _)| celsius)
+    }
+}
+
+impl Drop for AmbientTemperature {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_valid_and_negative_readings() {
+        let warm = "72 01 4b 46 7f ff 0e 10 57 : crc=57 YES\n72 01 4b 46 7f ff 0e 10 57 t=23125\n";
+        assert_eq!(parse_w1_slave(warm), Ok(23.125));
+
+        // Typical stratosphere reading
+        let cold = "5e fc 4b 46 7f ff 02 10 0a : crc=0a YES\n5e fc 4b 46 7f ff 02 10 0a t=-58125\n";
+        assert_eq!(parse_w1_slave(cold), Ok(-58.125));
+    }
+
+    #[test]
+    fn rejects_bad_crc_and_power_on_value() {
+        assert!(parse_w1_slave("72 01 4b 46 7f ff 0e 10 57 : crc=00 NO\n72 01 4b 46 7f ff 0e 10 57 t=23125\n").is_err());
+        assert!(parse_w1_slave("50 05 4b 46 7f ff 0c 10 1c : crc=1c YES\n50 05 4b 46 7f ff 0c 10 1c t=85000\n").is_err());
+        assert!(parse_w1_slave("").is_err());
+    }
+
+    #[test]
+    fn discovers_probe_in_sysfs_layout() {
+        let dir = std::env::temp_dir().join(format!("balloon-w1-{}", std::process::id()));
+        let device = dir.join("28-3c01d607d5a1");
+        fs::create_dir_all(&device).unwrap();
+        fs::create_dir_all(dir.join("w1_bus_master1")).unwrap();
+        fs::write(device.join("w1_slave"), "72 01 4b 46 7f ff 0e 10 57 : crc=57 YES\n72 01 4b 46 7f ff 0e 10 57 t=-12500\n").unwrap();
+
+        let probe = Ds18b20::discover_in(&dir).unwrap();
+        assert_eq!(probe.device_dir(), device);
+        assert_eq!(probe.read_temperature().unwrap(), -12.5);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,754 @@
+// Telemetry data packet as sent over the wire
+//
+// Wire layout: every field in declaration order at its own width, no padding, in the
+// byte order the frame envelope declares (little-endian unless --byte-order big). Each
+// field is written explicitly, so the host's layout and endianness don't matter.
+//
+// Every version starts with sync u64 then version u8; only what follows may change, so
+// a receiver can tell the layout from those 9 bytes before parsing the rest.
+//   v1: timestamp u64, temperature, humidity, altitude, latitude, longitude, accel_x/y/z,
+//       gyro_x/y/z (f32), status u8, flight_phase u8, peak_accel f32,
+//       peak_accel_age_ms u16, battery_voltage, heading, pressure_hpa (f32),
+//       sequence u32, crc u16 (87 bytes)
+//   v2: imu_temperature f32 inserted after pressure_hpa (91 bytes)
+//   v3: roll, pitch (f32) inserted after imu_temperature (99 bytes)
+//   v4: vertical_speed f32 inserted after pitch (103 bytes)
+//   v5: status widened to u16 (104 bytes)
+//   v6: timestamp in milliseconds (timestamp_ms), wall-clock or since startup per
+//       --clock (104 bytes)
+
+use std::fmt;
+use std::mem;
+use std::time::Duration;
+use rand::Rng;
+
+use crate::checksum;
+use crate::clock::unix_time_ms;
+use crate::flight::FlightPhase;
+use crate::frame::{ByteReader, Endianness};
+use crate::i2c::MPU6050::MotionReading;
+
+// Sync word at the start of every data packet
+pub const PACKET_SYNC: u64 = 0xFF_FF_FF_FF_FF_FF_FF_FF;
+
+// Layout version written into every data packet
+pub const PACKET_VERSION: u8 = 6;
+
+// Versions from_bytes_in() can decode
+pub const SUPPORTED_PACKET_VERSIONS: &[u8] = &[PACKET_VERSION];
+
+// Size of a data packet on the wire (packed, so also the in-memory size)
+pub const PACKET_LEN: usize = mem::size_of::<TelemetryPacket>();
+
+bitflags::bitflags! {
+    // The status word: which subsystems supplied this packet's fields, and the alarms
+    // raised from them. The low byte is as it was in the one-byte status of v1-v4.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct StatusFlags: u16 {
+        // Set when the corresponding fields come from a real sensor
+        const TEMP_REAL = 0x01;
+        const MOTION_REAL = 0x02;
+        const BARO_REAL = 0x04;
+        // The measured battery voltage is below the configured threshold
+        const LOW_BATTERY = 0x08;
+        // |accel| is below the free-fall threshold (see freefall.rs)
+        const FREEFALL = 0x10;
+        // The position was unusable or unreliable and the last valid fix was sent
+        const POSITION_STALE = 0x20;
+        // Latitude and longitude come from a GPS fix rather than simulation
+        const GPS_FIX = 0x40;
+        // No field comes from a real sensor (pure simulation)
+        const SIMULATED = 0x80;
+        // Altitude has fallen well below its peak: the balloon has burst (see ApogeeDetector)
+        const BURST_DETECTED = 0x0100;
+        // Heading comes from the magnetometer rather than the integrated gyro alone
+        const MAG_REAL = 0x0200;
+    }
+}
+
+impl StatusFlags {
+    // Bits that mark real sensor data
+    pub const REAL_MASK: Self = Self::TEMP_REAL.union(Self::MOTION_REAL).union(Self::BARO_REAL).union(Self::GPS_FIX)
+        .union(Self::MAG_REAL);
+
+    pub fn is_real(self) -> bool {
+        self.intersects(Self::REAL_MASK)
+    }
+}
+
+// Flag names separated by spaces, e.g. `TEMP_REAL MOTION_REAL`; `-` when none are set
+impl fmt::Display for StatusFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("-");
+        }
+        for (i, (name, _)) in self.iter_names().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            f.write_str(name)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    WrongLength { expected: usize, actual: usize },
+    BadSync(u64),
+    UnsupportedVersion(u8),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::WrongLength { expected, actual } => {
+                write!(f, "data packet must be {} bytes, got {}", expected, actual)
+            }
+            ParseError::BadSync(sync) => {
+                write!(f, "bad data packet sync word 0x{:016X}, expected 0x{:016X}", sync, PACKET_SYNC)
+            }
+            ParseError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported data packet version {} (this receiver understands {:?})",
+                version, SUPPORTED_PACKET_VERSIONS
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// serde (feature "serde") works on field values, so the packed layout is unaffected. Its
+// derive copies each field out before serializing (they are all Copy), never taking an
+// unaligned reference into the packed struct.
+#[repr(C, packed)]  // C layout, no padding
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TelemetryPacket {
+    pub sync: u64,
+    pub version: u8, // PACKET_VERSION
+    pub timestamp_ms: u64,      // Unix milliseconds, or since startup with a monotonic clock (see clock.rs)
+    pub temperature: f32,
+    pub humidity: f32,
+    pub altitude: f32,
+    pub latitude: f32,
+    pub longitude: f32,
+    pub accel_x: f32,
+    pub accel_y: f32,
+    pub accel_z: f32,
+    pub gyro_x: f32,
+    pub gyro_y: f32,
+    pub gyro_z: f32,
+    pub status: u16,
+    pub flight_phase: u8, // FlightPhase discriminant
+    pub peak_accel: f32,        // Highest |accel| since the previous transmitted packet (m/s²)
+    pub peak_accel_age_ms: u16, // How long before this packet the peak occurred
+    pub battery_voltage: f32,   // Volts, after the divider ratio
+    pub heading: f32,           // 0-360°: from true north with MAG_REAL, else integrated gyro Z relative to startup (drifts, see heading.rs)
+    pub pressure_hpa: f32,      // Raw MPL115A2 pressure; `altitude` is derived from it and the sea-level reference
+    pub imu_temperature: f32,   // MPU6050 die temperature (°C), for gyro drift compensation
+    pub roll: f32,              // Degrees from the complementary filter (see attitude.rs)
+    pub pitch: f32,
+    pub vertical_speed: f32,    // Smoothed climb rate (m/s, negative descending), 0 until two altitudes are in
+    pub sequence: u32,          // Packets transmitted before this one, wrapping; gaps are losses
+    pub crc: u16,               // compute_crc() as of finalize(), 0 before
+}
+
+// Column names of to_csv_row(), one per field in wire order
+pub const CSV_HEADER: &str = "sync,version,timestamp_ms,temperature,humidity,altitude,latitude,longitude,\
+accel_x,accel_y,accel_z,gyro_x,gyro_y,gyro_z,status,flight_phase,peak_accel,peak_accel_age_ms,\
+battery_voltage,heading,pressure_hpa,imu_temperature,roll,pitch,vertical_speed,sequence,crc";
+
+impl TelemetryPacket {
+    // Fully simulated packet; random readings make a `Default` impl misleading
+    pub fn new(sequence: u32) -> Self {
+        let mut rng = rand::thread_rng();
+
+        Self {
+            sync: PACKET_SYNC,
+            version: PACKET_VERSION,
+            timestamp_ms: unix_time_ms(),
+            temperature: rng.gen_range(-40.0..=60.0), // Temperature in Celsius
+            humidity: rng.gen_range(0.0..=100.0),     // Humidity percentage
+            altitude: rng.gen_range(0.0..=50000.0),   // Altitude in meters
+            latitude: rng.gen_range(-90.0..=90.0),    // Latitude in degrees
+            longitude: rng.gen_range(-180.0..=180.0), // Longitude in degrees
+            accel_x: rng.gen_range(-20.0..=20.0),     // Accelerometer X in m/s²
+            accel_y: rng.gen_range(-20.0..=20.0),     // Accelerometer Y in m/s²
+            accel_z: rng.gen_range(-20.0..=20.0),     // Accelerometer Z in m/s²
+            gyro_x: rng.gen_range(-2000.0..=2000.0),  // Gyroscope X in °/s
+            gyro_y: rng.gen_range(-2000.0..=2000.0),  // Gyroscope Y in °/s
+            gyro_z: rng.gen_range(-2000.0..=2000.0),  // Gyroscope Z in °/s
+            status: StatusFlags::SIMULATED.bits(), // No real sensor data
+            flight_phase: FlightPhase::Pad as u8,     // Set by the flight phase tracker
+            peak_accel: 0.0,                          // Set by apply_peak
+            peak_accel_age_ms: 0,
+            battery_voltage: rng.gen_range(3.6..=4.2), // Single Li-ion cell in volts
+            heading: 0.0,                             // Set by the heading tracker
+            pressure_hpa: rng.gen_range(1.0..=1013.25), // Pressure in hPa
+            imu_temperature: rng.gen_range(-40.0..=85.0), // MPU6050 rated range in Celsius
+            roll: rng.gen_range(-180.0..=180.0),      // Roll in degrees
+            pitch: rng.gen_range(-90.0..=90.0),       // Pitch in degrees
+            vertical_speed: 0.0,                      // Set by the climb rate estimator
+            sequence,
+            crc: 0,                                   // Set by finalize
+        }
+    }
+    
+    
+    pub fn new_with_motion_data(sequence: u32, temperature_celsius: f32, motion: MotionReading) -> Self {
+        let mut rng = rand::thread_rng();
+
+        Self {
+            sync: PACKET_SYNC,
+            version: PACKET_VERSION,
+            timestamp_ms: unix_time_ms(),
+            temperature: temperature_celsius,
+            humidity: rng.gen_range(0.0..=100.0),     // Humidity percentage (still simulated)
+            altitude: rng.gen_range(0.0..=50000.0),   // Altitude in meters (simulated)
+            latitude: rng.gen_range(-90.0..=90.0),    // Latitude in degrees (still simulated)
+            longitude: rng.gen_range(-180.0..=180.0), // Longitude in degrees (still simulated)
+            accel_x: motion.accelerometer.x,
+            accel_y: motion.accelerometer.y,
+            accel_z: motion.accelerometer.z,
+            gyro_x: motion.gyroscope.x,
+            gyro_y: motion.gyroscope.y,
+            gyro_z: motion.gyroscope.z,
+            status: (StatusFlags::TEMP_REAL | StatusFlags::MOTION_REAL).bits(),
+            flight_phase: FlightPhase::Pad as u8,
+            peak_accel: 0.0,
+            peak_accel_age_ms: 0,
+            battery_voltage: rng.gen_range(3.6..=4.2), // Still simulated
+            heading: 0.0,
+            pressure_hpa: rng.gen_range(1.0..=1013.25), // Still simulated
+            imu_temperature: motion.temperature,
+            roll: 0.0, // Set by the attitude filter
+            pitch: 0.0,
+            vertical_speed: 0.0, // Set by the climb rate estimator
+            sequence,
+            crc: 0,
+        }
+    }
+
+    pub fn accel_magnitude(&self) -> f32 {
+        let (x, y, z) = (self.accel_x, self.accel_y, self.accel_z);
+        (x * x + y * y + z * z).sqrt()
+    }
+
+    // Peak acceleration from the high-rate latch, or this sample's own magnitude if that
+    // is higher (or nothing was latched)
+    pub fn apply_peak(&mut self, latched: Option<(f32, Duration)>) {
+        let current = self.accel_magnitude();
+        let (peak, age) = match latched {
+            Some((peak, age)) if peak > current => (peak, age),
+            _ => (current, Duration::ZERO),
+        };
+        self.peak_accel = peak;
+        self.peak_accel_age_ms = age.as_millis().min(u16::MAX as u128) as u16;
+    }
+
+    // CRC-16/CCITT-FALSE of every field before `crc`, taken over their little-endian
+    // serialization so the value doesn't depend on the host or the wire byte order
+    pub fn compute_crc(&self) -> u16 {
+        checksum::crc16_ccitt(&self.to_array(Endianness::Little)[..PACKET_LEN - 2])
+    }
+
+    // Call once the packet is complete, just before it leaves
+    pub fn finalize(&mut self) {
+        self.crc = self.compute_crc();
+    }
+
+    pub fn status_flags(&self) -> StatusFlags {
+        StatusFlags::from_bits_retain(self.status)
+    }
+
+    pub fn verify_crc(&self) -> bool {
+        self.crc == self.compute_crc()
+    }
+
+    // InfluxDB line protocol with nanosecond timestamp, e.g.
+    // `balloon,source=flight temperature=21.5,...,status=3i,flight_phase=1i 1700000000000000000`.
+    // Non-finite readings are omitted since line protocol can't represent them.
+    pub fn to_line_protocol(&self, measurement: &str) -> String {
+        let source = if self.status_flags().is_real() { "flight" } else { "simulated" };
+        let floats = [
+            ("temperature", self.temperature),
+            ("humidity", self.humidity),
+            ("altitude", self.altitude),
+            ("latitude", self.latitude),
+            ("longitude", self.longitude),
+            ("accel_x", self.accel_x),
+            ("accel_y", self.accel_y),
+            ("accel_z", self.accel_z),
+            ("gyro_x", self.gyro_x),
+            ("gyro_y", self.gyro_y),
+            ("gyro_z", self.gyro_z),
+            ("peak_accel", self.peak_accel),
+            ("battery_voltage", self.battery_voltage),
+            ("heading", self.heading),
+            ("pressure_hpa", self.pressure_hpa),
+            ("imu_temperature", self.imu_temperature),
+            ("roll", self.roll),
+            ("pitch", self.pitch),
+            ("vertical_speed", self.vertical_speed),
+        ];
+
+        let mut fields: Vec<String> = floats
+            .iter()
+            .filter(|(_, value)| value.is_finite())
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        fields.push(format!("peak_accel_age_ms={}i", { self.peak_accel_age_ms }));
+        fields.push(format!("status={}i", { self.status }));
+        fields.push(format!("flight_phase={}i", self.flight_phase));
+        fields.push(format!("sequence={}i", { self.sequence }));
+
+        let measurement = measurement.replace(',', "\\,").replace(' ', "\\ ");
+        let timestamp_ns = { self.timestamp_ms }.saturating_mul(1_000_000);
+        format!("{},source={} {} {}", measurement, source, fields.join(","), timestamp_ns)
+    }
+
+    // Every field, comma-separated. Floats use the shortest form that parses back to the
+    // same f32 (NaN and inf included), so nothing is lost to rounding.
+    pub fn to_csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            { self.sync }, self.version, { self.timestamp_ms },
+            { self.temperature }, { self.humidity }, { self.altitude }, { self.latitude }, { self.longitude },
+            { self.accel_x }, { self.accel_y }, { self.accel_z }, { self.gyro_x }, { self.gyro_y }, { self.gyro_z },
+            { self.status }, self.flight_phase, { self.peak_accel }, { self.peak_accel_age_ms },
+            { self.battery_voltage }, { self.heading }, { self.pressure_hpa },
+            { self.imu_temperature }, { self.roll }, { self.pitch }, { self.vertical_speed }, { self.sequence }, { self.crc }
+        )
+    }
+
+    pub fn to_bytes(&self, order: Endianness) -> Vec<u8> {
+        self.to_array(order).to_vec()
+    }
+
+    // to_bytes() appended to `out`, so the loop can reuse one buffer instead of
+    // allocating per frame
+    pub fn write_bytes(&self, order: Endianness, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_array(order));
+    }
+
+    // Serialized on the stack; one copy into the destination rather than a capacity
+    // check per field
+    pub fn to_array(&self, order: Endianness) -> [u8; PACKET_LEN] {
+        let mut buf = [0; PACKET_LEN];
+        let mut at = 0;
+        let mut put = |bytes: &[u8]| {
+            buf[at..at + bytes.len()].copy_from_slice(bytes);
+            at += bytes.len();
+        };
+        put(&order.u64_bytes(self.sync));
+        put(&[self.version]);
+        put(&order.u64_bytes(self.timestamp_ms));
+        for value in [self.temperature, self.humidity, self.altitude, self.latitude, self.longitude,
+                      self.accel_x, self.accel_y, self.accel_z, self.gyro_x, self.gyro_y, self.gyro_z] {
+            put(&order.u32_bytes(value.to_bits()));
+        }
+        put(&order.u16_bytes(self.status));
+        put(&[self.flight_phase]);
+        put(&order.u32_bytes({ self.peak_accel }.to_bits()));
+        put(&order.u16_bytes(self.peak_accel_age_ms));
+        put(&order.u32_bytes({ self.battery_voltage }.to_bits()));
+        put(&order.u32_bytes({ self.heading }.to_bits()));
+        put(&order.u32_bytes({ self.pressure_hpa }.to_bits()));
+        put(&order.u32_bytes({ self.imu_temperature }.to_bits()));
+        put(&order.u32_bytes({ self.roll }.to_bits()));
+        put(&order.u32_bytes({ self.pitch }.to_bits()));
+        put(&order.u32_bytes({ self.vertical_speed }.to_bits()));
+        put(&order.u32_bytes(self.sequence));
+        put(&order.u16_bytes(self.crc));
+        buf
+    }
+
+    // Inverse of to_bytes(); the buffer must be exactly one data packet. Sync and
+    // version are checked first, so a newer layout is reported as such rather than as
+    // the wrong length.
+    pub fn from_bytes_in(buf: &[u8], order: Endianness) -> Result<Self, ParseError> {
+        let wrong_length = ParseError::WrongLength { expected: PACKET_LEN, actual: buf.len() };
+        let mut r = ByteReader::new(buf, order);
+        let (sync, version) = r.u64().zip(r.u8()).ok_or(wrong_length.clone())?;
+        if sync != PACKET_SYNC {
+            return Err(ParseError::BadSync(sync));
+        }
+        if !SUPPORTED_PACKET_VERSIONS.contains(&version) {
+            return Err(ParseError::UnsupportedVersion(version));
+        }
+        if buf.len() != PACKET_LEN {
+            return Err(wrong_length);
+        }
+
+        Self::read_fields(&mut ByteReader::new(buf, order)).ok_or(wrong_length)
+    }
+
+    fn read_fields(r: &mut ByteReader) -> Option<Self> {
+        Some(Self {
+            sync: r.u64()?,
+            version: r.u8()?,
+            timestamp_ms: r.u64()?,
+            temperature: r.f32()?,
+            humidity: r.f32()?,
+            altitude: r.f32()?,
+            latitude: r.f32()?,
+            longitude: r.f32()?,
+            accel_x: r.f32()?,
+            accel_y: r.f32()?,
+            accel_z: r.f32()?,
+            gyro_x: r.f32()?,
+            gyro_y: r.f32()?,
+            gyro_z: r.f32()?,
+            status: r.u16()?,
+            flight_phase: r.u8()?,
+            peak_accel: r.f32()?,
+            peak_accel_age_ms: r.u16()?,
+            battery_voltage: r.f32()?,
+            heading: r.f32()?,
+            pressure_hpa: r.f32()?,
+            imu_temperature: r.f32()?,
+            roll: r.f32()?,
+            pitch: r.f32()?,
+            vertical_speed: r.f32()?,
+            sequence: r.u32()?,
+            crc: r.u16()?,
+        })
+    }
+
+    // JSON object keyed by field name, for dashboards and databases. JSON has no NaN or
+    // infinity, so non-finite floats come out as null and won't parse back.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("packet fields always serialize")
+    }
+
+    // MessagePack map keyed by field name: compact like to_bytes() but self-describing,
+    // for message brokers and tools that prefer it
+    #[cfg(feature = "serde")]
+    pub fn to_msgpack(&self) -> Vec<u8> {
+        rmp_serde::to_vec_named(self).expect("packet fields always serialize")
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_msgpack(buf: &[u8]) -> Result<Self, rmp_serde::decode::Error> {
+        rmp_serde::from_slice(buf)
+    }
+
+    // Inverse of to_le_bytes()
+    pub fn from_bytes(buf: &[u8]) -> Result<Self, ParseError> {
+        Self::from_bytes_in(buf, Endianness::Little)
+    }
+
+    // The default wire encoding
+    pub fn to_le_bytes(&self) -> [u8; PACKET_LEN] {
+        self.to_array(Endianness::Little)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, ClockSource};
+
+    fn packet_with(temperature: f32, latitude: f32, longitude: f32) -> TelemetryPacket {
+        TelemetryPacket {
+            sync: PACKET_SYNC,
+            version: PACKET_VERSION,
+            timestamp_ms: 1_700_000_123_456,
+            temperature,
+            humidity: 37.5,
+            altitude: 31_204.25,
+            latitude,
+            longitude,
+            accel_x: 0.125,
+            accel_y: -9.75,
+            accel_z: 19.5,
+            gyro_x: -1999.5,
+            gyro_y: 0.0,
+            gyro_z: 2000.0,
+            status: (StatusFlags::TEMP_REAL | StatusFlags::MOTION_REAL).bits(),
+            flight_phase: FlightPhase::Descent as u8,
+            peak_accel: 61.5,
+            peak_accel_age_ms: 35,
+            battery_voltage: 3.75,
+            heading: 271.25,
+            pressure_hpa: 11.5,
+            imu_temperature: 38.25,
+            roll: -170.5,
+            pitch: 12.75,
+            vertical_speed: -4.5,
+            sequence: 4_000_000_001,
+            crc: 0,
+        }
+    }
+
+    // Compares bitwise so NaN payloads must survive unchanged
+    fn assert_same(actual: &TelemetryPacket, expected: &TelemetryPacket) {
+        let (a, e) = (*actual, *expected);
+        assert_eq!({ a.sync }, { e.sync });
+        assert_eq!(a.version, e.version);
+        assert_eq!({ a.timestamp_ms }, { e.timestamp_ms });
+        let floats = |p: TelemetryPacket| {
+            [p.temperature, p.humidity, p.altitude, p.latitude, p.longitude,
+             p.accel_x, p.accel_y, p.accel_z, p.gyro_x, p.gyro_y, p.gyro_z].map(f32::to_bits)
+        };
+        assert_eq!(floats(a), floats(e));
+        assert_eq!({ a.status }, { e.status });
+        assert_eq!({ a.flight_phase }, { e.flight_phase });
+        assert_eq!({ a.peak_accel }.to_bits(), { e.peak_accel }.to_bits());
+        assert_eq!({ a.peak_accel_age_ms }, { e.peak_accel_age_ms });
+        assert_eq!({ a.battery_voltage }.to_bits(), { e.battery_voltage }.to_bits());
+        assert_eq!({ a.heading }.to_bits(), { e.heading }.to_bits());
+        assert_eq!({ a.pressure_hpa }.to_bits(), { e.pressure_hpa }.to_bits());
+        assert_eq!({ a.imu_temperature }.to_bits(), { e.imu_temperature }.to_bits());
+        assert_eq!(({ a.roll }.to_bits(), { a.pitch }.to_bits()), ({ e.roll }.to_bits(), { e.pitch }.to_bits()));
+        assert_eq!({ a.vertical_speed }.to_bits(), { e.vertical_speed }.to_bits());
+        assert_eq!({ a.sequence }, { e.sequence });
+        assert_eq!({ a.crc }, { e.crc });
+    }
+
+    #[test]
+    fn status_flags_round_trip_through_the_word() {
+        let flags = [
+            (StatusFlags::TEMP_REAL, 0x01),
+            (StatusFlags::MOTION_REAL, 0x02),
+            (StatusFlags::BARO_REAL, 0x04),
+            (StatusFlags::LOW_BATTERY, 0x08),
+            (StatusFlags::FREEFALL, 0x10),
+            (StatusFlags::POSITION_STALE, 0x20),
+            (StatusFlags::GPS_FIX, 0x40),
+            (StatusFlags::SIMULATED, 0x80),
+            (StatusFlags::BURST_DETECTED, 0x100),
+            (StatusFlags::MAG_REAL, 0x200),
+        ];
+        let mut status = StatusFlags::empty();
+        for (flag, bit) in flags {
+            assert_eq!(flag.bits(), bit);
+            status.toggle(flag);
+            assert_eq!(StatusFlags::from_bits(status.bits()), Some(status));
+            status.toggle(flag);
+            assert_eq!(status.bits(), 0);
+        }
+
+        let live = StatusFlags::TEMP_REAL | StatusFlags::GPS_FIX | StatusFlags::LOW_BATTERY;
+        assert_eq!(live.bits(), 0x49);
+        assert_eq!(live.to_string(), "TEMP_REAL LOW_BATTERY GPS_FIX");
+        assert!(live.is_real() && !StatusFlags::SIMULATED.is_real());
+        assert_eq!(StatusFlags::empty().to_string(), "-");
+        assert_eq!(StatusFlags::from_bits(0x3FF), Some(StatusFlags::all()));
+        assert_eq!(StatusFlags::from_bits(0x400), None);
+    }
+
+    #[test]
+    fn simulated_packet_has_defined_status() {
+        for _ in 0..20 {
+            assert_eq!(TelemetryPacket::new(0).status_flags(), StatusFlags::SIMULATED);
+        }
+    }
+
+    #[test]
+    fn timestamp_resolves_milliseconds() {
+        let before = unix_time_ms();
+        let packet = TelemetryPacket::new(0);
+        assert!((before..=unix_time_ms()).contains(&{ packet.timestamp_ms }), "stamped {}", { packet.timestamp_ms });
+
+        // The monotonic clock, as the loop stamps packets, so NTP can't step it mid-test;
+        // only the lower bound is exact, since a loaded machine can oversleep
+        let clock = Clock::new(ClockSource::Monotonic);
+        let first = TelemetryPacket { timestamp_ms: clock.now_ms(), ..TelemetryPacket::new(0) };
+        std::thread::sleep(Duration::from_millis(100));
+        let second = TelemetryPacket { timestamp_ms: clock.now_ms(), ..TelemetryPacket::new(1) };
+        let elapsed = { second.timestamp_ms } - { first.timestamp_ms };
+        assert!((100..5_000).contains(&elapsed), "{} ms apart", elapsed);
+    }
+
+    #[test]
+    fn wire_size_is_stable() {
+        assert_eq!(mem::size_of::<TelemetryPacket>(), 104);
+        assert_eq!(packet_with(0.0, 0.0, 0.0).to_le_bytes().len(), 104);
+    }
+
+    #[test]
+    fn round_trips_known_values() {
+        let packet = packet_with(-56.5, 45.523064, -122.676_48);
+        let decoded = TelemetryPacket::from_bytes(&packet.to_le_bytes()).unwrap();
+        assert_same(&decoded, &packet);
+    }
+
+    #[test]
+    fn round_trips_nan_and_extremes() {
+        for (temperature, latitude, longitude) in [
+            (f32::NAN, 90.0, 180.0),
+            (f32::INFINITY, -90.0, -180.0),
+            (f32::MIN, f32::MIN_POSITIVE, -0.0),
+            (f32::MAX, 89.999_9, 179.999_9),
+        ] {
+            let packet = packet_with(temperature, latitude, longitude);
+            let decoded = TelemetryPacket::from_bytes(&packet.to_le_bytes()).unwrap();
+            assert_same(&decoded, &packet);
+        }
+    }
+
+    #[test]
+    fn field_offsets_match_wire_format() {
+        let mut packet = packet_with(1.5, 2.5, 3.5);
+        packet.finalize();
+        let bytes = packet.to_le_bytes();
+        assert_eq!(&bytes[..8], &[0xFF; 8]); // PACKET_SYNC
+        assert_eq!(bytes[8], PACKET_VERSION);
+        assert_eq!(&bytes[9..17], &1_700_000_123_456u64.to_le_bytes());
+        assert_eq!(&bytes[17..21], &[0x00, 0x00, 0xC0, 0x3F]); // 1.5 = 0x3FC00000
+        assert_eq!(&bytes[29..33], &2.5f32.to_le_bytes());
+        assert_eq!(&bytes[33..37], &3.5f32.to_le_bytes());
+        assert_eq!(&bytes[61..63], &(StatusFlags::TEMP_REAL | StatusFlags::MOTION_REAL).bits().to_le_bytes());
+        assert_eq!(bytes[63], FlightPhase::Descent as u8);
+        assert_eq!(&bytes[64..68], &61.5f32.to_le_bytes());
+        assert_eq!(&bytes[68..70], &35u16.to_le_bytes());
+        assert_eq!(&bytes[70..74], &3.75f32.to_le_bytes());
+        assert_eq!(&bytes[74..78], &271.25f32.to_le_bytes());
+        assert_eq!(&bytes[78..82], &11.5f32.to_le_bytes());
+        assert_eq!(&bytes[82..86], &38.25f32.to_le_bytes());
+        assert_eq!(&bytes[86..90], &(-170.5f32).to_le_bytes());
+        assert_eq!(&bytes[90..94], &12.75f32.to_le_bytes());
+        assert_eq!(&bytes[94..98], &(-4.5f32).to_le_bytes());
+        assert_eq!(&bytes[98..102], &4_000_000_001u32.to_le_bytes());
+        assert_eq!(&bytes[102..104], &packet.compute_crc().to_le_bytes());
+    }
+
+    #[test]
+    fn crc_detects_a_flipped_bit() {
+        let mut packet = packet_with(-56.5, 45.523064, -122.676_48);
+        assert!(!packet.verify_crc());
+        packet.finalize();
+        assert!(packet.verify_crc());
+
+        // Same CRC whichever order the packet travelled in
+        let big = TelemetryPacket::from_bytes_in(&packet.to_bytes(Endianness::Big), Endianness::Big).unwrap();
+        assert!(big.verify_crc());
+
+        for bit in [0, 8 * 20 + 3, 8 * 61, 8 * 84 + 7] {
+            let mut bytes = packet.to_bytes(Endianness::Little);
+            bytes[bit / 8] ^= 1 << (bit % 8);
+            if let Ok(corrupted) = TelemetryPacket::from_bytes_in(&bytes, Endianness::Little) {
+                assert!(!corrupted.verify_crc(), "bit {}", bit);
+            }
+        }
+    }
+
+    #[test]
+    fn apply_peak_keeps_the_larger_magnitude() {
+        let mut packet = TelemetryPacket { accel_x: 0.0, accel_y: 0.0, accel_z: 9.5, ..packet_with(0.0, 0.0, 0.0) };
+        packet.apply_peak(None);
+        assert_eq!(({ packet.peak_accel }, { packet.peak_accel_age_ms }), (9.5, 0));
+
+        packet.apply_peak(Some((48.0, Duration::from_millis(70))));
+        assert_eq!(({ packet.peak_accel }, { packet.peak_accel_age_ms }), (48.0, 70));
+
+        packet.apply_peak(Some((3.0, Duration::from_secs(100))));
+        assert_eq!({ packet.peak_accel }, 9.5);
+    }
+
+    #[test]
+    fn line_protocol_types_fields_and_converts_timestamp() {
+        let line = packet_with(-56.5, 45.5, -122.25).to_line_protocol("balloon");
+        assert!(line.starts_with("balloon,source=flight temperature=-56.5,humidity=37.5,altitude=31204.25,"), "{}", line);
+        assert!(line.contains(",latitude=45.5,longitude=-122.25,"));
+        assert!(line.ends_with(",peak_accel=61.5,battery_voltage=3.75,heading=271.25,pressure_hpa=11.5,imu_temperature=38.25,roll=-170.5,pitch=12.75,vertical_speed=-4.5,peak_accel_age_ms=35i,status=3i,flight_phase=4i,sequence=4000000001i 1700000123456000000"), "{}", line);
+    }
+
+    #[test]
+    fn line_protocol_skips_non_finite_and_escapes_measurement() {
+        let mut packet = packet_with(f32::NAN, 0.0, 0.0);
+        packet.status = StatusFlags::SIMULATED.bits();
+        let line = packet.to_line_protocol("test flight,1");
+
+        assert!(line.starts_with("test\\ flight\\,1,source=simulated humidity="), "{}", line);
+        assert!(!line.contains(" temperature=") && !line.contains(",temperature="));
+    }
+
+    #[test]
+    fn csv_row_has_a_column_per_field_and_exact_floats() {
+        let packet = packet_with(-std::f32::consts::PI * 18.0, 45.523064, f32::NAN);
+        let row = packet.to_csv_row();
+        let values: Vec<&str> = row.split(',').collect();
+        assert_eq!(values.len(), CSV_HEADER.split(',').count());
+        assert_eq!(values.len(), 27);
+
+        let column = |name: &str| values[CSV_HEADER.split(',').position(|c| c == name).unwrap()];
+        assert_eq!(column("temperature").parse::<f32>().unwrap(), -std::f32::consts::PI * 18.0);
+        assert_eq!(column("latitude").parse::<f32>().unwrap(), 45.523064);
+        assert!(column("longitude").parse::<f32>().unwrap().is_nan());
+        assert_eq!(column("sequence"), "4000000001");
+    }
+
+    #[test]
+    fn explicit_byte_order_round_trips() {
+        let packet = packet_with(-56.5, 45.523064, -122.676_48);
+        assert_eq!(packet.to_bytes(Endianness::Little), packet.to_le_bytes());
+
+        let big = packet.to_bytes(Endianness::Big);
+        assert_eq!(&big[17..21], &(-56.5f32).to_be_bytes());
+        assert_same(&TelemetryPacket::from_bytes_in(&big, Endianness::Big).unwrap(), &packet);
+
+        let little = packet.to_bytes(Endianness::Little);
+        assert_same(&TelemetryPacket::from_bytes_in(&little, Endianness::Little).unwrap(), &packet);
+        assert!(TelemetryPacket::from_bytes_in(&big[..86], Endianness::Big).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        let mut bytes = packet_with(0.0, 0.0, 0.0).to_le_bytes().to_vec();
+        bytes[8] = 0xEE;
+        let err = TelemetryPacket::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err, ParseError::UnsupportedVersion(0xEE));
+        assert!(err.to_string().contains("version 238"));
+
+        // A future layout of another length still reports its version
+        bytes.extend_from_slice(&[0; 12]);
+        assert_eq!(TelemetryPacket::from_bytes(&bytes).unwrap_err(), ParseError::UnsupportedVersion(0xEE));
+        bytes[8] = PACKET_VERSION;
+        assert!(matches!(TelemetryPacket::from_bytes(&bytes), Err(ParseError::WrongLength { .. })));
+    }
+
+    #[test]
+    fn from_bytes_rejects_wrong_length_or_sync() {
+        let packet = packet_with(0.0, 0.0, 0.0);
+        let bytes = packet.to_le_bytes();
+        assert_eq!(
+            TelemetryPacket::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err(),
+            ParseError::WrongLength { expected: PACKET_LEN, actual: PACKET_LEN - 1 }
+        );
+        assert!(TelemetryPacket::from_bytes(&[]).is_err());
+
+        let mut corrupted = bytes.to_vec();
+        corrupted[0] = 0x00;
+        let err = TelemetryPacket::from_bytes(&corrupted).unwrap_err();
+        assert_eq!(err, ParseError::BadSync(u64::from_ne_bytes(corrupted[..8].try_into().unwrap())));
+        assert!(err.to_string().contains("sync word"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trips_fields() {
+        let packet = packet_with(-56.5, 45.523064, -122.676_48);
+        let json = packet.to_json();
+        assert!(json.contains("\"peak_accel_age_ms\":35"));
+        assert!(json.starts_with('{') && json.contains("\"sequence\":"));
+
+        let decoded: TelemetryPacket = serde_json::from_str(&json).unwrap();
+        assert_same(&decoded, &packet);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn msgpack_round_trips_with_field_names() {
+        for packet in [packet_with(-56.5, 45.523064, -122.676_48), packet_with(f32::NAN, -90.0, f32::INFINITY)] {
+            let encoded = packet.to_msgpack();
+            assert!(encoded.windows(b"pressure_hpa".len()).any(|w| w == b"pressure_hpa"));
+            assert_same(&TelemetryPacket::from_msgpack(&encoded).unwrap(), &packet);
+        }
+        assert!(TelemetryPacket::from_msgpack(&[0xC1]).is_err());
+    }
+}
@@ -0,0 +1,92 @@
+// Peak acceleration latch fed by a high-rate sampler between telemetry frames, so brief
+// shocks such as balloon burst are captured even though packets go out at 10Hz
+
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PeakLatch {
+    peak: Option<(f32, Instant)>,
+}
+
+impl PeakLatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, magnitude: f32, at: Instant) {
+        if !magnitude.is_finite() {
+            return;
+        }
+        if !self.peak.is_some_and(|(peak, _)| magnitude <= peak) {
+            self.peak = Some((magnitude, at));
+        }
+    }
+
+    // Peak magnitude since the last reset and how long before `now` it occurred
+    pub fn peek(&self, now: Instant) -> Option<(f32, Duration)> {
+        self.peak.map(|(peak, at)| (peak, now.saturating_duration_since(at)))
+    }
+
+    pub fn reset(&mut self) {
+        self.peak = None;
+    }
+}
+
+// Latch shared between the sampler thread and the telemetry loop
+#[derive(Debug, Default, Clone)]
+pub struct SharedPeakLatch(Arc<Mutex<PeakLatch>>);
+
+impl SharedPeakLatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, magnitude: f32, at: Instant) {
+        self.lock().record(magnitude, at);
+    }
+
+    pub fn peek(&self, now: Instant) -> Option<(f32, Duration)> {
+        self.lock().peek(now)
+    }
+
+    pub fn reset(&self) {
+        self.lock().reset();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PeakLatch> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latches_highest_magnitude_and_its_time() {
+        let start = Instant::now();
+        let mut latch = PeakLatch::new();
+        assert!(latch.peek(start).is_none());
+
+        latch.record(9.8, start);
+        latch.record(58.0, start + Duration::from_millis(30)); // Burst shock
+        latch.record(12.0, start + Duration::from_millis(60));
+        latch.record(f32::NAN, start + Duration::from_millis(70));
+
+        let (peak, age) = latch.peek(start + Duration::from_millis(100)).unwrap();
+        assert_eq!(peak, 58.0);
+        assert_eq!(age, Duration::from_millis(70));
+    }
+
+    #[test]
+    fn reset_starts_a_new_window() {
+        let start = Instant::now();
+        let latch = SharedPeakLatch::new();
+        latch.record(40.0, start);
+        latch.reset();
+
+        latch.record(10.0, start + Duration::from_millis(10));
+        assert_eq!(latch.peek(start + Duration::from_millis(10)), Some((10.0, Duration::ZERO)));
+    }
+}
@@ -0,0 +1,188 @@
+// Pad ground test (--preflight): every sensor, the output directories and the downlink
+// checked in one go, reported as a PASS/FAIL table with an overall go/no-go
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+    Skip, // Not applicable to this build or configuration; doesn't block a go
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            CheckStatus::Pass => "PASS",
+            CheckStatus::Fail => "FAIL",
+            CheckStatus::Skip => "SKIP",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PreflightReport {
+    checks: Vec<Check>,
+}
+
+impl PreflightReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, name: impl Into<String>, status: CheckStatus, detail: impl Into<String>) {
+        self.checks.push(Check { name: name.into(), status, detail: detail.into() });
+    }
+
+    pub fn pass(&mut self, name: impl Into<String>, detail: impl Into<String>) {
+        self.record(name, CheckStatus::Pass, detail);
+    }
+
+    pub fn fail(&mut self, name: impl Into<String>, detail: impl Into<String>) {
+        self.record(name, CheckStatus::Fail, detail);
+    }
+
+    pub fn skip(&mut self, name: impl Into<String>, detail: impl Into<String>) {
+        self.record(name, CheckStatus::Skip, detail);
+    }
+
+    // PASS with the Ok detail, FAIL with the error
+    pub fn check(&mut self, name: impl Into<String>, outcome: Result<String, String>) {
+        match outcome {
+            Ok(detail) => self.pass(name, detail),
+            Err(detail) => self.fail(name, detail),
+        }
+    }
+
+    pub fn checks(&self) -> &[Check] {
+        &self.checks
+    }
+
+    fn count(&self, status: CheckStatus) -> usize {
+        self.checks.iter().filter(|check| check.status == status).count()
+    }
+
+    // Go for launch: nothing failed
+    pub fn go(&self) -> bool {
+        self.count(CheckStatus::Fail) == 0
+    }
+}
+
+impl fmt::Display for PreflightReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self.checks.iter().map(|check| check.name.len()).max().unwrap_or(0).max("Check".len());
+        writeln!(f, "{:<width$}  Result  Detail", "Check")?;
+        for check in &self.checks {
+            writeln!(f, "{:<width$}  {:<6}  {}", check.name, check.status, check.detail)?;
+        }
+        let verdict = if self.go() { "GO" } else { "NO-GO" };
+        writeln!(f, "{}: {} passed, {} failed, {} skipped", verdict,
+                 self.count(CheckStatus::Pass), self.count(CheckStatus::Fail), self.count(CheckStatus::Skip))
+    }
+}
+
+// Every sample within `range`; the detail gives the mean and spread
+pub fn check_range(samples: &[f32], range: RangeInclusive<f32>, unit: &str) -> Result<String, String> {
+    if samples.is_empty() {
+        return Err("no samples".to_string());
+    }
+    let min = samples.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = samples.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+
+    if let Some(bad) = samples.iter().find(|value| !range.contains(value)) {
+        return Err(format!("{:.2} {} outside {}..{} {}", bad, unit, range.start(), range.end(), unit));
+    }
+    Ok(format!("mean {:.2} {} ({:.2}..{:.2}, {} samples)", mean, unit, min, max, samples.len()))
+}
+
+// Creates, writes and removes a probe file in `dir`
+pub fn check_writable(dir: &Path) -> Result<String, String> {
+    let probe = dir.join(format!(".preflight-{}", std::process::id()));
+    let written = File::create(&probe).and_then(|mut file| file.write_all(b"preflight\n").and_then(|_| file.sync_all()));
+    let removed = fs::remove_file(&probe);
+    written.and(removed).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    Ok(format!("{} is writable", dir.display()))
+}
+
+// TCP targets must accept a connection. UDP is connectionless, so the best available
+// check is that the address resolves and the kernel has a route to it.
+pub fn check_reachable(target: &str, tcp: bool, timeout: Duration) -> Result<String, String> {
+    let addr = target
+        .to_socket_addrs()
+        .map_err(|e| format!("can't resolve {}: {}", target, e))?
+        .next()
+        .ok_or_else(|| format!("{} resolves to no address", target))?;
+
+    if tcp {
+        TcpStream::connect_timeout(&addr, timeout).map_err(|e| format!("{}: {}", addr, e))?;
+        return Ok(format!("{} accepted a TCP connection", addr));
+    }
+
+    let local = if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+    let socket = UdpSocket::bind(local).map_err(|e| format!("can't open a UDP socket: {}", e))?;
+    socket.connect(addr).map_err(|e| format!("no route to {}: {}", addr, e))?;
+    Ok(format!("route to {} (UDP, delivery not confirmed)", addr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn report_is_go_only_without_failures() {
+        let mut report = PreflightReport::new();
+        report.pass("MPU6050 init", "responding");
+        report.skip("GPS fix", "no GPS receiver");
+        assert!(report.go());
+
+        report.check("Battery", Err("3.10 V, below 3.40 V".to_string()));
+        assert!(!report.go());
+
+        let table = report.to_string();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines[0], "Check         Result  Detail");
+        assert_eq!(lines[3], "Battery       FAIL    3.10 V, below 3.40 V");
+        assert_eq!(lines[4], "NO-GO: 1 passed, 1 failed, 1 skipped");
+    }
+
+    #[test]
+    fn range_check_reports_the_offending_sample() {
+        assert_eq!(check_range(&[0.98, 1.0, 1.02], 0.8..=1.2, "g").unwrap(), "mean 1.00 g (0.98..1.02, 3 samples)");
+        assert_eq!(check_range(&[1.0, 1.5], 0.8..=1.2, "g").unwrap_err(), "1.50 g outside 0.8..1.2 g");
+        assert!(check_range(&[f32::NAN], 0.8..=1.2, "g").is_err());
+        assert!(check_range(&[], 0.8..=1.2, "g").is_err());
+    }
+
+    #[test]
+    fn writable_and_reachable_checks() {
+        let dir = std::env::temp_dir();
+        assert!(check_writable(&dir).is_ok());
+        assert!(fs::read_dir(&dir).unwrap().all(|entry| {
+            !entry.unwrap().file_name().to_string_lossy().starts_with(&format!(".preflight-{}", std::process::id()))
+        }));
+        assert!(check_writable(&dir.join("no-such-preflight-dir")).is_err());
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let target = listener.local_addr().unwrap().to_string();
+        assert!(check_reachable(&target, true, Duration::from_secs(1)).is_ok());
+        assert!(check_reachable(&target, false, Duration::from_secs(1)).is_ok());
+        drop(listener);
+        assert!(check_reachable(&target, true, Duration::from_secs(1)).is_err());
+        assert!(check_reachable("not an address", false, Duration::from_secs(1)).is_err());
+    }
+}
@@ -0,0 +1,358 @@
+// At-least-once delivery over UDP for links where a lost frame matters more than
+// latency. Each frame is sent with a delivery sequence number; the ground station echoes
+// an ack carrying that number, and frames not acked within the timeout are sent again
+// up to a retransmit limit. Off by default: at 10 Hz a lost packet is usually better
+// replaced by the next one than resent.
+//
+// Data layout (little-endian): RELIABLE_SYNC u64 | sequence u32 | frame
+// Ack layout (little-endian):  DELIVERY_ACK_SYNC u64 | sequence u32
+//
+// The header goes in front of whatever the transport chain below Framed produced
+// (preamble, COBS), so a receiver strips it before anything else. Retransmits can
+// arrive after the original's ack was lost, so the receiver drops repeated sequences,
+// keeping one Deduplicator per sender. A restarted sender numbers from 0 again.
+
+use std::collections::VecDeque;
+use std::io;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::transport::{Transport, UdpTransport};
+
+// Sync word identifying a frame sent for acknowledged delivery
+pub const RELIABLE_SYNC: u64 = 0xFF_FF_FF_FF_FF_FF_FF_F9;
+
+// Sync word identifying a delivery ack from the ground
+pub const DELIVERY_ACK_SYNC: u64 = 0xFF_FF_FF_FF_FF_FF_FF_F8;
+
+pub const HEADER_LEN: usize = 12;
+
+// Frames awaiting an ack; beyond this the oldest is given up on, so a ground station
+// that never acks costs bounded memory
+const MAX_PENDING: usize = 64;
+
+// Sequences remembered by the receiver to drop retransmitted duplicates
+const SEEN_WINDOW: usize = 256;
+
+// A UDP socket as the reliable layer uses it: send, and collect acks without blocking
+pub trait DatagramSocket {
+    fn send(&mut self, datagram: &[u8]) -> io::Result<usize>;
+
+    // The next datagram already received, or None
+    fn try_recv(&mut self, buf: &mut [u8]) -> io::Result<Option<usize>>;
+}
+
+impl DatagramSocket for UdpTransport {
+    fn send(&mut self, datagram: &[u8]) -> io::Result<usize> {
+        Transport::send(self, datagram)
+    }
+
+    fn try_recv(&mut self, buf: &mut [u8]) -> io::Result<Option<usize>> {
+        self.recv_from_target(buf)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReliableConfig {
+    pub ack_timeout: Duration,
+    pub max_retransmits: u32, // Sends after the first before the frame is given up
+}
+
+impl Default for ReliableConfig {
+    fn default() -> Self {
+        Self { ack_timeout: Duration::from_millis(500), max_retransmits: 3 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReliableStats {
+    pub acked: u64,
+    pub retransmits: u64,
+    pub given_up: u64, // Out of retransmits, or pushed out of the pending window
+}
+
+struct Pending {
+    sequence: u32,
+    datagram: Vec<u8>,
+    sent_at: Instant,
+    retransmits: u32,
+}
+
+pub struct ReliableSender<S: DatagramSocket> {
+    socket: S,
+    config: ReliableConfig,
+    next_sequence: u32,
+    pending: VecDeque<Pending>,
+    stats: ReliableStats,
+}
+
+impl<S: DatagramSocket> ReliableSender<S> {
+    pub fn new(socket: S, config: ReliableConfig) -> Self {
+        Self { socket, config, next_sequence: 0, pending: VecDeque::new(), stats: ReliableStats::default() }
+    }
+
+    pub fn stats(&self) -> ReliableStats {
+        self.stats
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    // Sends `frame` under the next sequence number and tracks it until acked
+    pub fn send_at(&mut self, frame: &[u8], now: Instant) -> io::Result<usize> {
+        self.poll(now);
+
+        let sequence = self.next_sequence;
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+        let datagram = wrap(sequence, frame);
+
+        if self.pending.len() == MAX_PENDING {
+            if let Some(oldest) = self.pending.pop_front() {
+                warn!("Giving up on frame {}: {} frames awaiting ack", oldest.sequence, MAX_PENDING);
+                self.stats.given_up += 1;
+            }
+        }
+        // A failed first send is tracked like a lost one and retried on the same schedule
+        let result = self.socket.send(&datagram);
+        self.pending.push_back(Pending { sequence, datagram, sent_at: now, retransmits: 0 });
+        result.map(|_| frame.len())
+    }
+
+    // Collects acks that have arrived, then resends what has timed out
+    pub fn poll(&mut self, now: Instant) {
+        let mut buf = [0u8; 64];
+        loop {
+            match self.socket.try_recv(&mut buf) {
+                Ok(Some(len)) => match parse_ack(&buf[..len]) {
+                    Some(sequence) => self.acknowledge(sequence),
+                    None => debug!("Ignoring {}-byte datagram that isn't a delivery ack", len),
+                },
+                Ok(None) => break,
+                Err(e) => {
+                    warn!("Failed to receive delivery acks: {}", e);
+                    break;
+                }
+            }
+        }
+
+        let (timeout, max_retransmits) = (self.config.ack_timeout, self.config.max_retransmits);
+        let due = |pending: &Pending| now.saturating_duration_since(pending.sent_at) >= timeout;
+        let mut given_up = 0;
+        self.pending.retain(|pending| {
+            let exhausted = due(pending) && pending.retransmits >= max_retransmits;
+            if exhausted {
+                warn!("Giving up on frame {} after {} retransmits", pending.sequence, pending.retransmits);
+                given_up += 1;
+            }
+            !exhausted
+        });
+        self.stats.given_up += given_up;
+
+        for pending in self.pending.iter_mut().filter(|pending| due(pending)) {
+            pending.retransmits += 1;
+            pending.sent_at = now;
+            self.stats.retransmits += 1;
+            if let Err(e) = self.socket.send(&pending.datagram) {
+                debug!("Failed to retransmit frame {}: {}", pending.sequence, e);
+            }
+        }
+    }
+
+    fn acknowledge(&mut self, sequence: u32) {
+        // Duplicate acks (for a retransmit of an already-acked frame) match nothing
+        if let Some(index) = self.pending.iter().position(|pending| pending.sequence == sequence) {
+            self.pending.remove(index);
+            self.stats.acked += 1;
+        }
+    }
+}
+
+impl<S: DatagramSocket> Transport for ReliableSender<S> {
+    fn send(&mut self, frame: &[u8]) -> io::Result<usize> {
+        self.send_at(frame, Instant::now())
+    }
+}
+
+pub fn wrap(sequence: u32, frame: &[u8]) -> Vec<u8> {
+    let mut datagram = Vec::with_capacity(HEADER_LEN + frame.len());
+    datagram.extend_from_slice(&RELIABLE_SYNC.to_le_bytes());
+    datagram.extend_from_slice(&sequence.to_le_bytes());
+    datagram.extend_from_slice(frame);
+    datagram
+}
+
+// The sequence and frame of a reliable datagram, or None for any other datagram
+pub fn unwrap(datagram: &[u8]) -> Option<(u32, &[u8])> {
+    if datagram.len() < HEADER_LEN || datagram[..8] != RELIABLE_SYNC.to_le_bytes() {
+        return None;
+    }
+    let sequence = u32::from_le_bytes(datagram[8..12].try_into().ok()?);
+    Some((sequence, &datagram[HEADER_LEN..]))
+}
+
+pub fn ack(sequence: u32) -> [u8; HEADER_LEN] {
+    let mut ack = [0u8; HEADER_LEN];
+    ack[..8].copy_from_slice(&DELIVERY_ACK_SYNC.to_le_bytes());
+    ack[8..].copy_from_slice(&sequence.to_le_bytes());
+    ack
+}
+
+pub fn parse_ack(datagram: &[u8]) -> Option<u32> {
+    if datagram.len() != HEADER_LEN || datagram[..8] != DELIVERY_ACK_SYNC.to_le_bytes() {
+        return None;
+    }
+    Some(u32::from_le_bytes(datagram[8..12].try_into().ok()?))
+}
+
+// Receiver side, for one sender: which sequences have already been delivered, so a
+// retransmit whose original got through (only the ack was lost) can be acked again but
+// not processed twice
+#[derive(Debug, Clone, Default)]
+pub struct Deduplicator {
+    seen: VecDeque<u32>,
+    newest: Option<u32>,
+}
+
+impl Deduplicator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // True the first time a sequence is seen
+    pub fn first_delivery(&mut self, sequence: u32) -> bool {
+        if let Some(newest) = self.newest {
+            let behind = newest.wrapping_sub(sequence);
+            // A retransmit is never further back than the sender keeps frames pending, so
+            // this is the sender starting over after a reboot
+            if behind > MAX_PENDING as u32 && behind <= u32::MAX / 2 {
+                debug!("Sequence {} after {}: sender restarted", sequence, newest);
+                self.reset();
+            }
+        }
+        if self.newest.is_none_or(|newest| sequence.wrapping_sub(newest) <= u32::MAX / 2) {
+            self.newest = Some(sequence);
+        }
+        if self.seen.contains(&sequence) {
+            return false;
+        }
+        if self.seen.len() == SEEN_WINDOW {
+            self.seen.pop_front();
+        }
+        self.seen.push_back(sequence);
+        true
+    }
+
+    // Forget every sequence, e.g. when the sender begins a new session
+    pub fn reset(&mut self) {
+        self.seen.clear();
+        self.newest = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Loops frames back as acks from a ground station, losing the first `drop_acks`
+    #[derive(Default)]
+    struct MockSocket {
+        sent: Vec<Vec<u8>>,
+        acks: VecDeque<Vec<u8>>,
+        drop_acks: usize,
+        ground: Deduplicator,
+        delivered: Vec<Vec<u8>>,
+    }
+
+    impl DatagramSocket for MockSocket {
+        fn send(&mut self, datagram: &[u8]) -> io::Result<usize> {
+            self.sent.push(datagram.to_vec());
+            let (sequence, frame) = unwrap(datagram).expect("reliable datagram");
+            if self.ground.first_delivery(sequence) {
+                self.delivered.push(frame.to_vec());
+            }
+            if self.drop_acks > 0 {
+                self.drop_acks -= 1;
+            } else {
+                self.acks.push_back(ack(sequence).to_vec());
+            }
+            Ok(datagram.len())
+        }
+
+        fn try_recv(&mut self, buf: &mut [u8]) -> io::Result<Option<usize>> {
+            Ok(self.acks.pop_front().map(|ack| {
+                buf[..ack.len()].copy_from_slice(&ack);
+                ack.len()
+            }))
+        }
+    }
+
+    #[test]
+    fn retransmits_until_acked_and_delivers_once() {
+        let config = ReliableConfig { ack_timeout: Duration::from_millis(100), max_retransmits: 3 };
+        let mut sender = ReliableSender::new(MockSocket { drop_acks: 1, ..MockSocket::default() }, config);
+        let start = Instant::now();
+
+        assert_eq!(sender.send_at(&[1, 2, 3], start).unwrap(), 3);
+        assert_eq!(sender.pending(), 1);
+
+        // Not yet due
+        sender.poll(start + Duration::from_millis(50));
+        assert_eq!(sender.socket.sent.len(), 1);
+
+        // The first ack was lost: resent once, and the second ack clears it
+        sender.poll(start + Duration::from_millis(100));
+        assert_eq!(sender.socket.sent.len(), 2);
+        assert_eq!(sender.socket.sent[0], sender.socket.sent[1]);
+        sender.poll(start + Duration::from_millis(150));
+        assert_eq!(sender.pending(), 0);
+        assert_eq!(sender.stats(), ReliableStats { acked: 1, retransmits: 1, given_up: 0 });
+        assert_eq!(sender.socket.delivered, vec![vec![1, 2, 3]]);
+
+        sender.send_at(&[4], start + Duration::from_millis(200)).unwrap();
+        sender.poll(start + Duration::from_millis(210));
+        assert_eq!(unwrap(&sender.socket.sent[2]), Some((1, &[4][..])));
+        assert_eq!(sender.stats().acked, 2);
+    }
+
+    #[test]
+    fn gives_up_after_the_retransmit_limit() {
+        let config = ReliableConfig { ack_timeout: Duration::from_millis(100), max_retransmits: 2 };
+        let mut sender = ReliableSender::new(MockSocket { drop_acks: usize::MAX, ..MockSocket::default() }, config);
+        let start = Instant::now();
+
+        sender.send_at(&[9], start).unwrap();
+        for step in 1..=4 {
+            sender.poll(start + Duration::from_millis(100 * step));
+        }
+        assert_eq!(sender.socket.sent.len(), 3);
+        assert_eq!(sender.pending(), 0);
+        assert_eq!(sender.stats(), ReliableStats { acked: 0, retransmits: 2, given_up: 1 });
+
+        assert_eq!(parse_ack(&ack(0xDEAD_BEEF)), Some(0xDEAD_BEEF));
+        assert_eq!(parse_ack(&wrap(7, &[])), None);
+        assert_eq!(unwrap(&[0xFF; 4]), None);
+    }
+
+    #[test]
+    fn deduplicator_starts_over_with_a_restarted_sender() {
+        let mut ground = Deduplicator::new();
+        for sequence in 0..100 {
+            assert!(ground.first_delivery(sequence));
+        }
+        // Retransmits of frames still pending at the sender
+        assert!(!ground.first_delivery(99));
+        assert!(!ground.first_delivery(50));
+
+        // Rebooted: numbering starts again at 0, still in the window but too far back to
+        // be a retransmit
+        assert!(ground.first_delivery(0));
+        assert!(ground.first_delivery(1));
+        assert!(!ground.first_delivery(0));
+
+        // A restart within the first few frames needs an explicit reset (a session header)
+        assert!(!ground.first_delivery(1));
+        ground.reset();
+        assert!(ground.first_delivery(1));
+    }
+}
@@ -0,0 +1,1098 @@
+// All onboard sensors, each initialized independently so one failing device doesn't
+// prevent the others from contributing real data
+
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+use rppal::i2c::I2c;
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+use crate::altitude::{self, AltitudeFusion, STANDARD_SEA_LEVEL_HPA};
+use crate::blackbox::{BlackBox, BlackBoxSample, SharedBlackBox};
+use crate::freefall::DEFAULT_FREEFALL_THRESHOLD;
+use crate::gps::{FixGate, GpsFix, GpsReceiver};
+use crate::i2c::ADS1115::Gain;
+use crate::i2c::INA219::DEFAULT_SHUNT_OHMS;
+use crate::i2c::MPL115A2::{self as mpl115a2, PressureReading};
+use crate::i2c::MPU6050::{AxisMap, DlpfBandwidth, MotionReading, REGISTER_DUMP_LEN};
+use crate::i2c::sensor::SensorError;
+use crate::packet::{StatusFlags, TelemetryPacket};
+use crate::peak::SharedPeakLatch;
+use crate::preflight::PreflightReport;
+use crate::serial::DEFAULT_BAUD_RATE;
+use crate::session::SessionHeader;
+
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+use std::sync::{Arc, Mutex};
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+use tracing::debug;
+
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+use crate::deadline::{TimedDevice, TimedRead};
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+use crate::freefall::is_freefall;
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+use crate::i2c::ADS1115::{ADS1115, ADS1115_ADDRESS};
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+use crate::i2c::HMC5883L::{Chip as CompassChip, HMC5883L};
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+use crate::i2c::INA219::{INA219, INA219_ADDRESS};
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+use crate::i2c::MPL115A2::MPL115A2;
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+use crate::i2c::MPU6050::{MPU6050, SELF_TEST_TOLERANCE, STANDARD_GRAVITY};
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+use crate::preflight;
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+use crate::onewire::{AmbientTemperature, Ds18b20};
+
+// Longest wait for a fresh MPU6050 sample before giving up on this iteration
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+const MOTION_READY_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(20);
+
+// Pause between DS18B20 conversions (each takes up to 750ms itself)
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+const AMBIENT_SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+
+// Older ambient readings are dropped in favour of the onboard sensors' temperature
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+const AMBIENT_MAX_AGE: Duration = Duration::from_secs(5);
+
+// Receivers send a fix each second; one missing for longer has lost fix or the UART
+const GPS_MAX_AGE: Duration = Duration::from_secs(3);
+
+// Readings taken from each sensor by the preflight check
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+const PREFLIGHT_SAMPLES: usize = 10;
+
+// MPL115A2 conversions averaged by the --field-elevation-m calibration (~0.2 s)
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+const SEA_LEVEL_CALIBRATION_SAMPLES: usize = 32;
+
+#[derive(Debug, Clone)]
+pub struct SensorConfig {
+    pub read_budget: Duration,            // Reads taking longer are abandoned (see deadline.rs)
+    pub baro_conversion_delay: Duration,  // MPL115A2 wait between starting and reading a conversion
+    pub baro_oversampling: usize,         // MPL115A2 conversions averaged per reading
+    pub baro_filter_window: usize,        // MPL115A2 readings in the moving average
+    pub peak_sample_interval: Option<Duration>, // High-rate motion polling for the peak latch and black box
+    pub black_box_window: Duration,       // Full-rate motion history kept for a post-burst dump
+    pub battery: BatteryConfig,
+    pub axis_map: AxisMap,                // MPU6050 mounting orientation
+    pub dlpf: DlpfBandwidth,              // MPU6050 low-pass filter
+    pub motion_init_attempts: u32,        // MPU6050 initialization tries before falling back to simulation
+    pub motion_init_retry_delay: Duration,
+    pub freefall_threshold: f32,          // |accel| below which FREEFALL is set (m/s²)
+    pub magnetic_declination: f32,        // Compass correction from magnetic to true north (°, east positive)
+    pub sea_level_hpa: f32,               // Reference for barometric altitude, until changed by command
+    pub field_elevation_m: Option<f32>,   // Calibrate sea_level_hpa from the MPL115A2 at this elevation
+    pub gps_device: Option<PathBuf>,      // NMEA receiver's serial port; simulated position without one
+    pub gps_baud: u32,
+    pub simulate: bool,                   // Leave the hardware alone and simulate every sensor
+}
+
+impl SensorConfig {
+    fn black_box(&self) -> BlackBox {
+        self.peak_sample_interval
+            .map_or_else(|| BlackBox::new(1), |interval| BlackBox::with_window(self.black_box_window, interval))
+    }
+}
+
+impl Default for SensorConfig {
+    fn default() -> Self {
+        Self {
+            read_budget: Duration::from_millis(50),
+            baro_conversion_delay: Duration::from_millis(5),
+            baro_oversampling: 1,
+            baro_filter_window: mpl115a2::DEFAULT_FILTER_WINDOW,
+            peak_sample_interval: Some(Duration::from_millis(8)), // ~MPU6050 output rate
+            black_box_window: Duration::from_secs(30),
+            battery: BatteryConfig::default(),
+            axis_map: AxisMap::IDENTITY,
+            dlpf: DlpfBandwidth::default(),
+            motion_init_attempts: 5,
+            motion_init_retry_delay: Duration::from_secs(1),
+            freefall_threshold: DEFAULT_FREEFALL_THRESHOLD,
+            magnetic_declination: 0.0,
+            sea_level_hpa: STANDARD_SEA_LEVEL_HPA,
+            field_elevation_m: None,
+            gps_device: None,
+            gps_baud: DEFAULT_BAUD_RATE,
+            simulate: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatteryMonitor {
+    Ads1115, // Resistor divider into an ADC input
+    Ina219,  // High-side shunt, measuring the battery directly
+}
+
+impl BatteryMonitor {
+    pub fn name(self) -> &'static str {
+        match self {
+            BatteryMonitor::Ads1115 => "ADS1115",
+            BatteryMonitor::Ina219 => "INA219",
+        }
+    }
+}
+
+// Battery voltage through a resistor divider into an ADS1115 input, or from an INA219
+#[derive(Debug, Clone, Copy)]
+pub struct BatteryConfig {
+    pub monitor: BatteryMonitor,
+    pub channel: u8,        // ADS1115 input, 0-3
+    pub gain: Gain,
+    pub divider_ratio: f32, // Battery volts per volt at the ADC pin
+    pub shunt_ohms: f32,    // INA219 shunt resistor
+    pub low_voltage: f32,   // LOW_BATTERY below this, in battery volts
+}
+
+impl Default for BatteryConfig {
+    // Single Li-ion cell through an equal-resistor divider (4.2V full -> 2.1V at the pin)
+    fn default() -> Self {
+        Self {
+            monitor: BatteryMonitor::Ads1115,
+            channel: 0,
+            gain: Gain::PGA_4_096V,
+            divider_ratio: 2.0,
+            shunt_ohms: DEFAULT_SHUNT_OHMS,
+            low_voltage: 3.4,
+        }
+    }
+}
+
+pub struct Sensors {
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    motion: Option<TimedDevice<MPU6050<I2c>>>,
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    motion_header: Option<SessionHeader>, // Configuration is fixed once initialized
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    last_motion: Option<MotionReading>,
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    pressure: Option<TimedDevice<MPL115A2<I2c>>>,
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    last_pressure: Option<(PressureReading, f32)>, // With its altitude from the driver's reference
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    battery: Option<TimedDevice<BatteryDevice>>,
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    last_battery: Option<f32>,
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    compass: Option<TimedDevice<HMC5883L<I2c>>>,
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    last_compass: Option<f32>,
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    ambient: Option<AmbientTemperature>,
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    sampler_stop: Arc<AtomicBool>,
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    battery_config: BatteryConfig,
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    freefall_threshold: f32,
+    gps: Option<GpsReceiver>,
+    fix_gate: FixGate,
+    altitude_fusion: AltitudeFusion,
+    peak: SharedPeakLatch,
+    black_box: SharedBlackBox,
+    sea_level_hpa: f32, // The MPL115A2's reference, for the session header; the reference itself without one
+    read_errors: u64,
+    read_timeouts: u64,
+}
+
+// One reading per sensor; None where the device is unavailable or the read failed
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SensorReadings {
+    pub motion: Option<MotionReading>,
+    pub pressure: Option<PressureReading>,
+    pub altitude: Option<f32>, // Barometric (relative to the sea-level reference), fused with GPS when it has a fix
+    pub gps: Option<GpsFix>,   // Passed by the fix gate; held over from an earlier fix when position_stale
+    pub position_stale: bool,
+    pub ambient_temperature: Option<f32>, // External DS18B20 probe, °C
+    pub compass_heading: Option<f32>,     // Magnetometer, degrees from true north
+    pub battery_voltage: Option<f32>,     // Battery volts, after the divider ratio
+    pub low_battery: bool,
+    pub freefall: bool,
+    pub peak: Option<(f32, Duration)>, // Latched peak |accel| and its age
+}
+
+impl Sensors {
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    pub fn init(config: SensorConfig) -> Self {
+        if config.simulate {
+            info!("Simulation forced - not touching the Raspberry Pi sensors");
+        } else {
+            info!("Detected ARM Linux system - attempting to initialize Raspberry Pi sensors...");
+        }
+
+        let real = !config.simulate;
+        let motion = real.then(|| init_motion_sensor(&config)).flatten();
+        let pressure = real.then(|| init_pressure_sensor(&config)).flatten();
+        let sea_level_hpa = pressure.as_ref().map_or(config.sea_level_hpa, MPL115A2::sea_level_hpa);
+        let sensors = Self {
+            motion_header: motion.as_ref().map(SessionHeader::from_sensor),
+            motion: motion.map(|sensor| TimedDevice::new(sensor, config.read_budget)),
+            last_motion: None,
+            pressure: pressure.map(|sensor| TimedDevice::new(sensor, config.read_budget)),
+            last_pressure: None,
+            battery: real.then(|| init_battery_monitor(&config.battery)).flatten()
+                .map(|device| TimedDevice::new(device, config.read_budget)),
+            last_battery: None,
+            compass: real.then(|| init_compass(config.magnetic_declination)).flatten()
+                .map(|sensor| TimedDevice::new(sensor, config.read_budget)),
+            last_compass: None,
+            ambient: real.then(init_ambient_probe).flatten(),
+            gps: config.gps_device.as_deref().filter(|_| real).and_then(|device| init_gps(device, config.gps_baud)),
+            fix_gate: FixGate::default(),
+            altitude_fusion: AltitudeFusion::default(),
+            sampler_stop: Arc::new(AtomicBool::new(false)),
+            peak: SharedPeakLatch::new(),
+            black_box: SharedBlackBox::new(config.black_box()),
+            battery_config: config.battery,
+            freefall_threshold: config.freefall_threshold,
+            sea_level_hpa,
+            read_errors: 0,
+            read_timeouts: 0,
+        };
+        sensors.log_availability();
+
+        if let (Some(motion), Some(interval)) = (&sensors.motion, config.peak_sample_interval) {
+            spawn_motion_sampler(motion.shared(), sensors.peak.clone(), sensors.black_box.clone(), interval,
+                                 Arc::clone(&sensors.sampler_stop));
+        }
+        sensors
+    }
+
+    #[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
+    pub fn init(config: SensorConfig) -> Self {
+        info!("Not running on ARM Linux - using simulated data only");
+
+        let sensors = Self {
+            gps: config.gps_device.as_deref().filter(|_| !config.simulate).and_then(|device| init_gps(device, config.gps_baud)),
+            fix_gate: FixGate::default(),
+            altitude_fusion: AltitudeFusion::default(),
+            peak: SharedPeakLatch::new(),
+            black_box: SharedBlackBox::new(config.black_box()),
+            sea_level_hpa: config.sea_level_hpa,
+            read_errors: 0,
+            read_timeouts: 0,
+        };
+        sensors.log_availability();
+        sensors
+    }
+
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    pub fn has_motion(&self) -> bool {
+        self.motion.is_some()
+    }
+
+    #[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
+    pub fn has_motion(&self) -> bool {
+        false
+    }
+
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    pub fn has_pressure(&self) -> bool {
+        self.pressure.is_some()
+    }
+
+    #[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
+    pub fn has_pressure(&self) -> bool {
+        false
+    }
+
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    pub fn has_battery_monitor(&self) -> bool {
+        self.battery.is_some()
+    }
+
+    #[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
+    pub fn has_battery_monitor(&self) -> bool {
+        false
+    }
+
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    pub fn has_compass(&self) -> bool {
+        self.compass.is_some()
+    }
+
+    #[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
+    pub fn has_compass(&self) -> bool {
+        false
+    }
+
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    pub fn has_ambient_probe(&self) -> bool {
+        self.ambient.is_some()
+    }
+
+    #[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
+    pub fn has_ambient_probe(&self) -> bool {
+        false
+    }
+
+    // Failed reads from sensors that initialized successfully
+    pub fn read_errors(&self) -> u64 {
+        self.read_errors
+    }
+
+    // Sea-level reference (QNH) for barometric altitude, applied by the MPL115A2 driver;
+    // rejected outside 950-1050 hPa, or while a stuck read holds the sensor
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    pub fn set_sea_level_pressure(&mut self, hpa: f32) -> Result<(), String> {
+        if let Some(pressure) = &self.pressure {
+            pressure.try_with(|sensor| sensor.set_sea_level_hpa(hpa))
+                .unwrap_or_else(|| Err("MPL115A2 is busy with a stuck read".to_string()))?;
+        }
+        self.sea_level_hpa = altitude::validate_sea_level(hpa)?;
+        Ok(())
+    }
+
+    #[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
+    pub fn set_sea_level_pressure(&mut self, hpa: f32) -> Result<(), String> {
+        self.sea_level_hpa = altitude::validate_sea_level(hpa)?;
+        Ok(())
+    }
+
+    pub fn sea_level_pressure(&self) -> f32 {
+        self.sea_level_hpa
+    }
+
+    // Start a new peak-acceleration window once a packet carrying the peak has gone out
+    pub fn reset_peak(&self) {
+        self.peak.reset();
+    }
+
+    // Full-rate motion history, oldest first; empty without a running motion sampler
+    pub fn black_box_snapshot(&self) -> Vec<BlackBoxSample> {
+        self.black_box.snapshot()
+    }
+
+    // Reads abandoned for exceeding the time budget
+    pub fn read_timeouts(&self) -> u64 {
+        self.read_timeouts
+    }
+
+    fn log_availability(&self) {
+        let state = |available: bool| if available { "real" } else { "simulated" };
+        info!("Sensor availability: MPU6050 motion = {}, MPL115A2 pressure = {}, battery monitor = {}, DS18B20 ambient = {}",
+                 state(self.has_motion()), state(self.has_pressure()), state(self.has_battery_monitor()),
+                 if self.has_ambient_probe() { "real" } else { "absent" });
+        info!("GPS position = {}, magnetometer heading = {}", state(self.gps.is_some()),
+                 if self.has_compass() { "real" } else { "absent" });
+    }
+
+    // The gated GPS fix and whether it is held over (lost or unreliable since), with
+    // altitude fused from the barometer's and a current fix's
+    fn read_position(&mut self, baro_altitude: Option<f32>) -> (Option<GpsFix>, bool, Option<f32>) {
+        let latest = self.gps.as_ref().and_then(|gps| gps.latest(GPS_MAX_AGE));
+        let fix = match &latest {
+            Some(fix) => self.fix_gate.apply(fix),
+            None => self.fix_gate.last_good(),
+        };
+        let stale = fix.is_some() && !(latest.is_some() && self.fix_gate.is_reliable());
+        let gps_altitude = fix.filter(|_| !stale).map(|fix| fix.altitude());
+        let altitude = self.altitude_fusion.fuse(baro_altitude, gps_altitude).map(|fused| fused.altitude);
+        (fix, stale, altitude)
+    }
+
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    pub async fn read(&mut self) -> SensorReadings {
+        let motion = read_timed(self.motion.as_mut(), read_motion_sensor, &mut self.last_motion,
+                                &mut self.read_errors, &mut self.read_timeouts, "MPU6050").await;
+        let pressure = read_timed(self.pressure.as_mut(), read_pressure_sensor, &mut self.last_pressure,
+                                  &mut self.read_errors, &mut self.read_timeouts, "MPL115A2").await;
+        let battery_voltage = read_timed(self.battery.as_mut(), read_battery_voltage, &mut self.last_battery,
+                                         &mut self.read_errors, &mut self.read_timeouts,
+                                         self.battery_config.monitor.name()).await;
+        let compass_heading = read_timed(self.compass.as_mut(), read_compass_heading, &mut self.last_compass,
+                                         &mut self.read_errors, &mut self.read_timeouts, "magnetometer").await;
+        let (pressure, pressure_altitude) = pressure.unzip();
+        let (gps, position_stale, altitude) = self.read_position(pressure_altitude);
+
+        SensorReadings {
+            battery_voltage,
+            low_battery: battery_voltage.is_some_and(|volts| volts < self.battery_config.low_voltage),
+            altitude,
+            gps,
+            position_stale,
+            freefall: motion.as_ref().is_some_and(|m| is_freefall(&m.accelerometer, self.freefall_threshold)),
+            motion,
+            pressure,
+            ambient_temperature: self.ambient.as_ref().and_then(|ambient| ambient.latest(AMBIENT_MAX_AGE)),
+            compass_heading,
+            peak: self.peak.peek(Instant::now()),
+        }
+    }
+
+    #[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
+    pub async fn read(&mut self) -> SensorReadings {
+        let (gps, position_stale, altitude) = self.read_position(None);
+        SensorReadings {
+            altitude,
+            gps,
+            position_stale,
+            peak: self.peak.peek(Instant::now()),
+            ..SensorReadings::default()
+        }
+    }
+
+    // Register map of the running MPU6050, for the diagnostic downlink
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    pub fn motion_register_dump(&mut self) -> Option<[u8; REGISTER_DUMP_LEN]> {
+        let motion = self.motion.as_ref()?;
+        match motion.try_with(|sensor| sensor.dump_registers())? {
+            Ok(dump) => Some(dump),
+            Err(e) => {
+                warn!("Failed to dump motion sensor registers: {}", e);
+                None
+            }
+        }
+    }
+
+    #[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
+    pub fn motion_register_dump(&mut self) -> Option<[u8; REGISTER_DUMP_LEN]> {
+        None
+    }
+
+    // Configuration the ground needs to interpret the data packets
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    pub fn session_header(&self) -> SessionHeader {
+        self.motion_header
+            .unwrap_or_else(SessionHeader::simulated)
+            .with_sea_level(self.sea_level_hpa)
+    }
+
+    #[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
+    pub fn session_header(&self) -> SessionHeader {
+        SessionHeader::simulated().with_sea_level(self.sea_level_hpa)
+    }
+}
+
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+impl Drop for Sensors {
+    fn drop(&mut self) {
+        self.sampler_stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl SensorReadings {
+    // Status bits recording which fields carry real sensor data, or SIMULATED if none do
+    pub fn status(&self) -> StatusFlags {
+        let mut status = StatusFlags::empty();
+        if self.temperature().is_some() {
+            status |= StatusFlags::TEMP_REAL;
+        }
+        if self.motion.is_some() {
+            status |= StatusFlags::MOTION_REAL;
+        }
+        if self.pressure.is_some() {
+            status |= StatusFlags::BARO_REAL;
+        }
+        if self.gps.is_some() {
+            status |= StatusFlags::GPS_FIX;
+        }
+        if self.position_stale {
+            status |= StatusFlags::POSITION_STALE;
+        }
+        if self.low_battery {
+            status |= StatusFlags::LOW_BATTERY;
+        }
+        if self.freefall {
+            status |= StatusFlags::FREEFALL;
+        }
+        if self.compass_heading.is_some() {
+            status |= StatusFlags::MAG_REAL;
+        }
+        if !status.is_real() {
+            status |= StatusFlags::SIMULATED;
+        }
+        status
+    }
+
+    // Best available temperature: the external probe measures ambient air, the IMU and
+    // barometer only their own (self-heated) dies
+    pub fn temperature(&self) -> Option<f32> {
+        self.ambient_temperature
+            .or_else(|| self.motion.as_ref().map(|motion| motion.temperature))
+            .or_else(|| self.pressure.as_ref().map(|pressure| pressure.temperature))
+    }
+
+    // Real data where available, simulated elsewhere
+    pub fn to_packet(&self, sequence: u32) -> TelemetryPacket {
+        let mut packet = match &self.motion {
+            Some(motion) => TelemetryPacket::new_with_motion_data(sequence, motion.temperature, motion.clone()),
+            None => TelemetryPacket::new(sequence),
+        };
+        if let Some(temperature) = self.temperature() {
+            packet.temperature = temperature;
+        }
+        if let Some(altitude) = self.altitude {
+            packet.altitude = altitude;
+        }
+        if let Some(pressure) = &self.pressure {
+            packet.pressure_hpa = pressure.pressure_hpa;
+        }
+        if let Some(fix) = &self.gps {
+            packet.latitude = fix.latitude;
+            packet.longitude = fix.longitude;
+        }
+        if let Some(heading) = self.compass_heading {
+            packet.heading = heading;
+        }
+        if let Some(volts) = self.battery_voltage {
+            packet.battery_voltage = volts;
+        }
+        packet.apply_peak(self.peak);
+        packet.status = self.status().bits();
+        packet
+    }
+}
+
+// Reads the MPU6050 register map without resetting or reconfiguring the sensor
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+pub fn dump_motion_registers() -> Result<[u8; REGISTER_DUMP_LEN], SensorError> {
+    let mut sensor = MPU6050::attach(I2c::new()?, false)?;
+    sensor.dump_registers()
+}
+
+#[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
+pub fn dump_motion_registers() -> Result<[u8; REGISTER_DUMP_LEN], SensorError> {
+    Err(SensorError::Unsupported("register dump requires the MPU6050 on a Raspberry Pi"))
+}
+
+// Initializes each sensor on its own, runs the MPU6050 self-test and checks a few
+// readings from each against what a payload sitting on the pad should see
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+pub fn preflight(config: &SensorConfig, report: &mut PreflightReport) {
+    match init_motion_sensor(config) {
+        Some(mut sensor) => {
+            report.pass("MPU6050 init", format!("{} Hz sample rate", sensor.sample_rate_hz()));
+            match sensor.self_test() {
+                Ok(result) if result.passed() => report.pass("MPU6050 self-test",
+                    format!("worst axis {:.1}% from factory trim", result.worst_deviation() * 100.0)),
+                Ok(result) => report.fail("MPU6050 self-test",
+                    format!("{} outside {:.0}% of factory trim (worst {:.1}%)", result.failed_axes().join(", "),
+                            SELF_TEST_TOLERANCE * 100.0, result.worst_deviation() * 100.0)),
+                Err(e) => report.fail("MPU6050 self-test", e.to_string()),
+            }
+            match take_samples(|| sensor.read_all()) {
+                Ok(samples) => {
+                    let accel_g: Vec<f32> = samples.iter().map(|reading| {
+                        let a = &reading.accelerometer;
+                        (a.x * a.x + a.y * a.y + a.z * a.z).sqrt() / STANDARD_GRAVITY
+                    }).collect();
+                    let gyro_dps: Vec<f32> = samples.iter().map(|reading| {
+                        let g = &reading.gyroscope;
+                        (g.x * g.x + g.y * g.y + g.z * g.z).sqrt()
+                    }).collect();
+                    let temperatures: Vec<f32> = samples.iter().map(|reading| reading.temperature).collect();
+                    report.check("MPU6050 gravity", preflight::check_range(&accel_g, 0.8..=1.2, "g"));
+                    report.check("MPU6050 at rest", preflight::check_range(&gyro_dps, 0.0..=10.0, "°/s"));
+                    report.check("MPU6050 temperature", preflight::check_range(&temperatures, -40.0..=85.0, "°C"));
+                }
+                Err(e) => report.fail("MPU6050 readings", e.to_string()),
+            }
+        }
+        None => report.fail("MPU6050 init", "not responding, see log"),
+    }
+
+    match init_pressure_sensor(config) {
+        Some(mut sensor) => match take_samples(|| sensor.read_pressure()) {
+            Ok(samples) => {
+                let pressures: Vec<f32> = samples.iter().map(|reading| reading.pressure_hpa).collect();
+                report.check("MPL115A2 pressure", preflight::check_range(&pressures, 500.0..=1100.0, "hPa"));
+            }
+            Err(e) => report.fail("MPL115A2 pressure", e.to_string()),
+        },
+        None => report.fail("MPL115A2 pressure", "not responding, see log"),
+    }
+
+    let battery = &config.battery;
+    let name = format!("{} battery", battery.monitor.name());
+    match init_battery_monitor(battery) {
+        Some(mut device) => match take_samples(|| device.read_battery_voltage()) {
+            Ok(samples) => {
+                let volts = samples.iter().sum::<f32>() / samples.len() as f32;
+                if volts >= battery.low_voltage {
+                    report.pass(&name, format!("{:.2} V", volts));
+                } else {
+                    report.fail(&name, format!("{:.2} V, below the {:.2} V low threshold", volts, battery.low_voltage));
+                }
+            }
+            Err(e) => report.fail(&name, e.to_string()),
+        },
+        None => report.fail(&name, "not responding, see log"),
+    }
+
+    // Optional, like in flight
+    match init_compass(config.magnetic_declination) {
+        Some(mut sensor) => {
+            let name = format!("{} compass", sensor.chip().name());
+            match take_samples(|| sensor.read_heading()) {
+                Ok(samples) => report.pass(&name, format!("{:.0}° from true north", samples[samples.len() - 1])),
+                Err(e) => report.fail(&name, e.to_string()),
+            }
+        }
+        None => report.skip("Compass", "no magnetometer, see log"),
+    }
+    match Ds18b20::discover() {
+        Ok(probe) => {
+            let reading = probe.read_temperature().map_err(|e| e.to_string());
+            report.check("DS18B20 ambient", reading.and_then(|celsius| preflight::check_range(&[celsius], -60.0..=60.0, "°C")));
+        }
+        Err(e) => report.skip("DS18B20 ambient", format!("no probe ({})", e)),
+    }
+}
+
+#[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
+pub fn preflight(config: &SensorConfig, report: &mut PreflightReport) {
+    let battery = format!("{} battery", config.battery.monitor.name());
+    for sensor in ["MPU6050", "MPL115A2 pressure", &battery, "Compass", "DS18B20 ambient"] {
+        report.skip(sensor, "needs the Raspberry Pi flight computer");
+    }
+}
+
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+fn take_samples<T>(mut read: impl FnMut() -> Result<T, SensorError>) -> Result<Vec<T>, SensorError> {
+    let mut samples = Vec::with_capacity(PREFLIGHT_SAMPLES);
+    for _ in 0..PREFLIGHT_SAMPLES {
+        samples.push(read()?);
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    Ok(samples)
+}
+
+// Calls `init` up to `attempts` times, sleeping `delay` between transient failures, and
+// returns the last error if none succeed. Errors that would only recur (the wrong
+// device, a bad setting) are returned at once.
+pub fn retry_init<T>(name: &str, attempts: u32, delay: Duration,
+                     mut init: impl FnMut() -> Result<T, SensorError>) -> Result<T, SensorError> {
+    let attempts = attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        match init() {
+            Ok(device) => {
+                if attempt > 1 {
+                    info!("{} initialized on attempt {}/{}", name, attempt, attempts);
+                }
+                return Ok(device);
+            }
+            Err(e) if attempt < attempts && e.is_transient() => {
+                warn!("{} initialization attempt {}/{} failed: {} - retrying in {:?}", name, attempt, attempts, e, delay);
+                std::thread::sleep(delay);
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+fn init_motion_sensor(config: &SensorConfig) -> Option<MPU6050<I2c>> {
+    // A sensor still powering up or a noisy bus at cold boot often succeeds a moment later
+    let sensor = retry_init("MPU6050", config.motion_init_attempts, config.motion_init_retry_delay, || {
+        let mut sensor = MPU6050::new(I2c::new()?, false)?;
+        sensor.set_axis_map(config.axis_map);
+        sensor.set_dlpf(config.dlpf)?;
+        sensor.enable_data_ready_interrupt()?;
+        Ok(sensor)
+    });
+
+    match sensor {
+        Ok(sensor) => {
+            info!("MPU6050 motion sensor initialized successfully");
+            Some(sensor)
+        }
+        Err(e) => {
+            error!("Failed to initialize MPU6050 motion sensor: {}", e);
+            warn!("Continuing with simulated motion data...");
+            None
+        }
+    }
+}
+
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+fn init_pressure_sensor(config: &SensorConfig) -> Option<MPL115A2<I2c>> {
+    let sensor = I2c::new()
+        .map_err(SensorError::from)
+        .and_then(|i2c| MPL115A2::new(i2c, config.baro_filter_window));
+
+    match sensor {
+        Ok(mut sensor) => {
+            sensor.set_conversion_delay(config.baro_conversion_delay);
+            sensor.set_oversampling(config.baro_oversampling);
+            info!("MPL115A2 pressure sensor initialized successfully ({} conversion(s) per reading, {}-reading average)",
+                     sensor.oversampling(), sensor.filter_window());
+            if let Err(e) = sensor.set_sea_level_hpa(config.sea_level_hpa) {
+                warn!("Ignoring sea-level reference: {}", e);
+            }
+            if let Some(elevation) = config.field_elevation_m {
+                if let Err(e) = sensor.calibrate_sea_level(elevation, SEA_LEVEL_CALIBRATION_SAMPLES) {
+                    warn!("Sea-level calibration at {} m failed, keeping {:.2} hPa: {}", elevation, sensor.sea_level_hpa(), e);
+                }
+            }
+            Some(sensor)
+        }
+        Err(e) => {
+            error!("Failed to initialize MPL115A2 pressure sensor: {}", e);
+            warn!("Continuing with simulated altitude...");
+            None
+        }
+    }
+}
+
+// Either battery monitor, reading volts at the battery terminal
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+enum BatteryDevice {
+    Ads1115 { adc: ADS1115<I2c>, divider_ratio: f32 },
+    Ina219(INA219<I2c>),
+}
+
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+impl BatteryDevice {
+    fn read_battery_voltage(&mut self) -> Result<f32, SensorError> {
+        match self {
+            BatteryDevice::Ads1115 { adc, divider_ratio } => Ok(adc.read_voltage()? * *divider_ratio),
+            BatteryDevice::Ina219(ina) => {
+                let volts = ina.read_supply_voltage()?;
+                debug!("Battery current: {:.3} A", ina.read_current()?);
+                Ok(volts)
+            }
+        }
+    }
+}
+
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+fn init_battery_monitor(config: &BatteryConfig) -> Option<BatteryDevice> {
+    let device = I2c::new()
+        .map_err(SensorError::from)
+        .and_then(|i2c| match config.monitor {
+            BatteryMonitor::Ads1115 => ADS1115::new(i2c, ADS1115_ADDRESS, config.channel, config.gain)
+                .map(|adc| BatteryDevice::Ads1115 { adc, divider_ratio: config.divider_ratio }),
+            BatteryMonitor::Ina219 => INA219::new(i2c, INA219_ADDRESS, config.shunt_ohms).map(BatteryDevice::Ina219),
+        });
+
+    let name = config.monitor.name();
+    match device {
+        Ok(device) => {
+            match &device {
+                BatteryDevice::Ads1115 { .. } => info!("ADS1115 battery monitor initialized (divider ratio {}, low below {:.2} V)",
+                                                       config.divider_ratio, config.low_voltage),
+                BatteryDevice::Ina219(_) => info!("INA219 battery monitor initialized ({} Ω shunt, low below {:.2} V)",
+                                                  config.shunt_ohms, config.low_voltage),
+            }
+            Some(device)
+        }
+        Err(e) => {
+            error!("Failed to initialize {} battery monitor: {}", name, e);
+            warn!("Continuing with simulated battery voltage...");
+            None
+        }
+    }
+}
+
+// Without a receiver the packet keeps its simulated position
+fn init_gps(device: &Path, baud: u32) -> Option<GpsReceiver> {
+    match GpsReceiver::open(device, baud) {
+        Ok(receiver) => {
+            info!("Reading NMEA from GPS receiver on {} at {} baud", device.display(), baud);
+            Some(receiver)
+        }
+        Err(e) => {
+            error!("Failed to open GPS receiver on {}: {} - continuing with simulated position", device.display(), e);
+            None
+        }
+    }
+}
+
+// The magnetometer is optional; a GY-271 breakout may carry either chip
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+fn init_compass(declination_deg: f32) -> Option<HMC5883L<I2c>> {
+    let mut failures = Vec::new();
+    for chip in [CompassChip::Hmc5883l, CompassChip::Qmc5883l] {
+        match I2c::new().map_err(SensorError::from).and_then(|i2c| HMC5883L::new(i2c, chip, declination_deg)) {
+            Ok(sensor) => return Some(sensor),
+            Err(e) => failures.push(format!("{}: {}", chip.name(), e)),
+        }
+    }
+    info!("No magnetometer ({}) - heading from the gyro alone", failures.join("; "));
+    None
+}
+
+// The external probe is optional: most ground tests run without one
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+fn init_ambient_probe() -> Option<AmbientTemperature> {
+    let probe = match Ds18b20::discover() {
+        Ok(probe) => probe,
+        Err(e) => {
+            info!("No DS18B20 ambient probe ({}) - using onboard sensor temperature", e);
+            return None;
+        }
+    };
+
+    info!("DS18B20 ambient probe found at {}", probe.device_dir().display());
+    match AmbientTemperature::spawn(probe, AMBIENT_SAMPLE_INTERVAL) {
+        Ok(ambient) => Some(ambient),
+        Err(e) => {
+            error!("Failed to start DS18B20 reader: {}", e);
+            None
+        }
+    }
+}
+
+// Polls the MPU6050 between telemetry frames so short shocks reach the peak latch and
+// the black box keeps a full-rate history
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+fn spawn_motion_sampler(device: Arc<Mutex<MPU6050<I2c>>>, latch: SharedPeakLatch, black_box: SharedBlackBox,
+                        interval: Duration, stop: Arc<AtomicBool>) {
+    let sampler = std::thread::Builder::new().name("motion-sampler".to_string()).spawn(move || {
+        while !stop.load(Ordering::Relaxed) {
+            // Skip a sample rather than queue behind the main loop (or a stuck read)
+            if let Ok(mut sensor) = device.try_lock() {
+                if let Ok(reading) = sensor.read_all_burst() {
+                    let now = Instant::now();
+                    let accel = &reading.accelerometer;
+                    let magnitude = (accel.x * accel.x + accel.y * accel.y + accel.z * accel.z).sqrt();
+                    latch.record(magnitude, now);
+                    black_box.record(reading, now);
+                }
+            }
+            std::thread::sleep(interval);
+        }
+    });
+
+    match sampler {
+        Ok(_) => info!("Motion sampler running every {:?}", interval),
+        Err(e) => error!("Failed to start motion sampler: {}", e),
+    }
+}
+
+// Reads within the time budget, falling back to the last good reading on a timeout
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+async fn read_timed<T, R>(device: Option<&mut TimedDevice<T>>, read: fn(&mut T) -> Option<R>, last: &mut Option<R>,
+                          read_errors: &mut u64, read_timeouts: &mut u64, name: &str) -> Option<R>
+where
+    T: Send + 'static,
+    R: Clone + Send + 'static,
+{
+    match device?.read(read).await {
+        TimedRead::Done(Some(reading)) => {
+            *last = Some(reading.clone());
+            Some(reading)
+        }
+        TimedRead::Done(None) => {
+            *read_errors += 1;
+            None
+        }
+        TimedRead::TimedOut => {
+            *read_timeouts += 1;
+            warn!("{} read exceeded its time budget (I2C clock stretching?) - using last good reading", name);
+            last.clone()
+        }
+        TimedRead::Busy => last.clone(),
+    }
+}
+
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+fn read_pressure_sensor(pressure: &mut MPL115A2<I2c>) -> Option<(PressureReading, f32)> {
+    match pressure.read_pressure_filtered() {
+        Ok(reading) => {
+            debug!("Pressure reading: {:.2} hPa, Temp: {:.2}°C", reading.pressure_hpa, reading.temperature);
+            let altitude = pressure.altitude_m(&reading);
+            Some((reading, altitude))
+        }
+        Err(e) => {
+            error!("Failed to read pressure sensor: {}", e);
+            None
+        }
+    }
+}
+
+// Battery volts, after the ADS1115 divider ratio
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+fn read_battery_voltage(device: &mut BatteryDevice) -> Option<f32> {
+    match device.read_battery_voltage() {
+        Ok(volts) => Some(volts),
+        Err(e) => {
+            error!("Failed to read battery voltage: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+fn read_compass_heading(compass: &mut HMC5883L<I2c>) -> Option<f32> {
+    match compass.read_heading() {
+        Ok(heading) => {
+            debug!("Compass heading: {:.1}°", heading);
+            Some(heading)
+        }
+        Err(e) => {
+            error!("Failed to read magnetometer: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+fn read_motion_sensor(motion: &mut MPU6050<I2c>) -> Option<MotionReading> {
+    match motion.read_all_when_ready(MOTION_READY_TIMEOUT) {
+        Ok(reading) => {
+            debug!("Motion reading: Accel({:.2}, {:.2}, {:.2}) m/s², Gyro({:.2}, {:.2}, {:.2}) °/s, Temp: {:.2}°C",
+                     reading.accelerometer.x, reading.accelerometer.y, reading.accelerometer.z,
+                     reading.gyroscope.x, reading.gyroscope.y, reading.gyroscope.z,
+                     reading.temperature);
+            Some(reading)
+        },
+        Err(e) => {
+            error!("Failed to read motion sensor: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i2c::MPU6050::{AccelerometerReading, GyroscopeReading};
+    use crate::gps;
+
+    #[test]
+    fn retry_init_succeeds_on_a_later_attempt() {
+        let mut calls = 0;
+        let device = retry_init("test", 3, Duration::ZERO, || {
+            calls += 1;
+            if calls < 3 { Err(SensorError::Bus("not ready".to_string())) } else { Ok(calls) }
+        });
+        assert_eq!(device.unwrap(), 3);
+
+        let mut calls = 0;
+        let failed: Result<(), _> = retry_init("test", 2, Duration::ZERO, || {
+            calls += 1;
+            Err(SensorError::Bus(format!("attempt {}", calls)))
+        });
+        assert_eq!(failed.unwrap_err().to_string(), "I2C error: attempt 2");
+        assert_eq!(calls, 2);
+
+        // The wrong chip won't turn into the right one
+        let mut calls = 0;
+        let failed: Result<(), _> = retry_init("test", 5, Duration::ZERO, || {
+            calls += 1;
+            Err(SensorError::IdentityMismatch { expected: 0x68, found: 0x70 })
+        });
+        assert!(matches!(failed, Err(SensorError::IdentityMismatch { .. })));
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn status_marks_simulated_only_without_real_data() {
+        assert_eq!(SensorReadings::default().status(), StatusFlags::SIMULATED);
+
+        let readings = SensorReadings {
+            motion: Some(MotionReading {
+                accelerometer: AccelerometerReading { x: 0.0, y: 0.0, z: 9.8 },
+                gyroscope: GyroscopeReading { x: 0.0, y: 0.0, z: 0.0 },
+                temperature: 21.0,
+            }),
+            ..SensorReadings::default()
+        };
+        assert_eq!(readings.status(), StatusFlags::TEMP_REAL | StatusFlags::MOTION_REAL);
+        assert_eq!(readings.to_packet(0).status_flags(), readings.status());
+        assert_eq!({ readings.to_packet(0).imu_temperature }, 21.0);
+    }
+
+    #[test]
+    fn compass_heading_is_real_heading_data() {
+        let readings = SensorReadings { compass_heading: Some(123.5), ..SensorReadings::default() };
+        assert_eq!(readings.status(), StatusFlags::MAG_REAL);
+        assert_eq!({ readings.to_packet(0).heading }, 123.5);
+    }
+
+    #[test]
+    fn barometric_altitude_replaces_simulated_value() {
+        let readings = SensorReadings {
+            pressure: Some(PressureReading { pressure_hpa: 900.0, temperature: 5.0 }),
+            altitude: Some(988.5),
+            ..SensorReadings::default()
+        };
+        assert_eq!(readings.status(), StatusFlags::TEMP_REAL | StatusFlags::BARO_REAL);
+
+        let packet = readings.to_packet(0);
+        assert_eq!({ packet.altitude }, 988.5);
+        assert_eq!({ packet.pressure_hpa }, 900.0);
+        assert_eq!({ packet.temperature }, 5.0);
+    }
+
+    #[test]
+    fn low_battery_sets_status_bit_without_marking_data_real() {
+        let readings = SensorReadings {
+            battery_voltage: Some(3.1),
+            low_battery: true,
+            ..SensorReadings::default()
+        };
+        assert_eq!(readings.status(), StatusFlags::LOW_BATTERY | StatusFlags::SIMULATED);
+        assert_eq!({ readings.to_packet(0).battery_voltage }, 3.1);
+    }
+
+    #[test]
+    fn freefall_sets_status_bit() {
+        let readings = SensorReadings {
+            motion: Some(MotionReading {
+                accelerometer: AccelerometerReading { x: 0.1, y: 0.2, z: -0.3 },
+                gyroscope: GyroscopeReading { x: 0.0, y: 0.0, z: 0.0 },
+                temperature: 20.0,
+            }),
+            freefall: true,
+            ..SensorReadings::default()
+        };
+        assert_eq!(readings.status(), StatusFlags::TEMP_REAL | StatusFlags::MOTION_REAL | StatusFlags::FREEFALL);
+        assert!(readings.to_packet(0).status_flags().contains(StatusFlags::FREEFALL));
+    }
+
+    #[test]
+    fn ambient_probe_is_preferred_temperature_source() {
+        let motion = MotionReading {
+            accelerometer: AccelerometerReading { x: 0.0, y: 0.0, z: 9.8 },
+            gyroscope: GyroscopeReading { x: 0.0, y: 0.0, z: 0.0 },
+            temperature: 31.0,
+        };
+        let pressure = PressureReading { pressure_hpa: 300.0, temperature: 12.0 };
+
+        let mut readings = SensorReadings {
+            motion: Some(motion),
+            pressure: Some(pressure),
+            ambient_temperature: Some(-48.5),
+            ..SensorReadings::default()
+        };
+        assert_eq!({ readings.to_packet(0).temperature }, -48.5);
+
+        readings.ambient_temperature = None;
+        assert_eq!({ readings.to_packet(0).temperature }, 31.0);
+
+        readings.motion = None;
+        assert_eq!({ readings.to_packet(0).temperature }, 12.0);
+
+        readings.pressure = None;
+        assert!(!readings.status().contains(StatusFlags::TEMP_REAL));
+    }
+
+    #[test]
+    fn gps_fix_replaces_simulated_position() {
+        let fix = gps::parse_gga("$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47").unwrap();
+        let mut readings = SensorReadings { gps: Some(fix), altitude: Some(545.4), ..SensorReadings::default() };
+        assert_eq!(readings.status(), StatusFlags::GPS_FIX);
+
+        let packet = readings.to_packet(0);
+        assert!(({ packet.latitude } - 48.1173).abs() < 1e-4);
+        assert_eq!({ packet.altitude }, 545.4);
+
+        readings.position_stale = true;
+        assert_eq!(readings.status(), StatusFlags::GPS_FIX | StatusFlags::POSITION_STALE);
+        assert!(!SensorReadings::default().to_packet(0).status_flags().contains(StatusFlags::GPS_FIX));
+    }
+}
@@ -0,0 +1,162 @@
+// Serial-port transport for a UART radio modem (LoRa, RFD900...), the usual downlink of
+// a high-altitude balloon. The port is opened at startup through the serialport crate,
+// in raw 8N1 mode at the configured baud rate, so nothing in the tty layer rewrites or
+// echoes frame bytes. The GPS receiver's port is opened the same way.
+//
+// A byte stream carries no boundaries beyond each envelope's length field; run with
+// --cobs so the ground can resynchronize after a corrupted or dropped byte.
+
+use serialport::{DataBits, FlowControl, Parity, StopBits};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::transport::Transport;
+
+pub const DEFAULT_SERIAL_DEVICE: &str = "/dev/serial0";
+pub const DEFAULT_BAUD_RATE: u32 = 9600;
+
+// The standard rates; most radio modems default to 9600 or 57600
+pub const SUPPORTED_BAUD_RATES: &[u32] = &[1200, 2400, 4800, 9600, 19200, 38400, 57600, 115200, 230400];
+
+// Pause before reopening a device whose write failed (a USB radio unplugged)
+const REOPEN_INTERVAL: Duration = Duration::from_secs(1);
+
+// Longest a write waits on a stalled port, or a read on a silent one
+const PORT_TIMEOUT: Duration = Duration::from_secs(1);
+
+pub type SerialPort = Box<dyn serialport::SerialPort>;
+
+pub struct SerialTransport {
+    device: PathBuf,
+    baud: u32,
+    port: Option<SerialPort>,
+    next_attempt: Instant,
+}
+
+impl SerialTransport {
+    // Fails with the device named if it is missing or can't be configured; after
+    // startup a failed write reopens it on a later send
+    pub fn open(device: impl Into<PathBuf>, baud: u32) -> io::Result<Self> {
+        let device = device.into();
+        let port = open_port(&device, baud)?;
+        info!("Serial transport on {} at {} baud", device.display(), baud);
+        Ok(Self { device, baud, port: Some(port), next_attempt: Instant::now() })
+    }
+}
+
+impl Transport for SerialTransport {
+    fn send(&mut self, frame: &[u8]) -> io::Result<usize> {
+        let port = match self.port.as_mut() {
+            Some(port) => port,
+            None => {
+                let now = Instant::now();
+                if now < self.next_attempt {
+                    return Err(io::Error::new(io::ErrorKind::NotConnected, "Waiting to reopen serial device"));
+                }
+                self.next_attempt = now + REOPEN_INTERVAL;
+                let port = open_port(&self.device, self.baud)?;
+                info!("Serial transport reopened {}", self.device.display());
+                self.port.insert(port)
+            }
+        };
+
+        if let Err(e) = port.write_all(frame).and_then(|_| port.flush()) {
+            warn!("Serial transport lost {}: {}", self.device.display(), e);
+            self.port = None;
+            self.next_attempt = Instant::now() + REOPEN_INTERVAL;
+            return Err(e);
+        }
+        Ok(frame.len())
+    }
+}
+
+pub fn parse_baud_rate(value: &str) -> Result<u32, String> {
+    validate_baud_rate(value.parse().map_err(|_| format!("{:?} is not a baud rate", value))?)
+}
+
+pub fn validate_baud_rate(baud: u32) -> Result<u32, String> {
+    if !SUPPORTED_BAUD_RATES.contains(&baud) {
+        return Err(format!("unsupported baud rate {} (one of {:?})", baud, SUPPORTED_BAUD_RATES));
+    }
+    Ok(baud)
+}
+
+// Opens the device raw (nothing rewritten or echoed), 8N1 without flow control, at `baud`
+pub fn open_port(device: &Path, baud: u32) -> io::Result<SerialPort> {
+    let named = |e: io::Error| io::Error::new(e.kind(), format!("serial device {}: {}", device.display(), e));
+    let path = device.to_str()
+        .ok_or_else(|| named(io::Error::new(io::ErrorKind::InvalidInput, "path is not UTF-8")))?;
+    serialport::new(path, baud)
+        .data_bits(DataBits::Eight)
+        .parity(Parity::None)
+        .stop_bits(StopBits::One)
+        .flow_control(FlowControl::None)
+        .timeout(PORT_TIMEOUT)
+        .open()
+        .map_err(|e| named(e.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cobs::{CobsTransport, StreamDecoder};
+    use std::fs::File;
+    use std::io::Read;
+
+    // Both ends of a pseudo-terminal: the master as the radio would see the line, and
+    // the path of the slave for the transport to open as its device
+    #[cfg(target_os = "linux")]
+    fn pty() -> (File, PathBuf) {
+        use std::ffi::CStr;
+        use std::os::unix::io::FromRawFd;
+
+        let mut name = [0 as libc::c_char; 64];
+        // SAFETY: the master fd is checked before use and owned by the returned File;
+        // ptsname_r writes a NUL-terminated path into `name`, within its length
+        unsafe {
+            let master = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+            assert!(master >= 0, "posix_openpt: {}", io::Error::last_os_error());
+            assert_eq!(libc::grantpt(master), 0);
+            assert_eq!(libc::unlockpt(master), 0);
+            assert_eq!(libc::ptsname_r(master, name.as_mut_ptr(), name.len()), 0);
+            let path = CStr::from_ptr(name.as_ptr()).to_str().unwrap().into();
+            (File::from_raw_fd(master), path)
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn frames_arrive_intact_over_a_pty() {
+        let (mut master, device) = pty();
+        let serial = SerialTransport::open(&device, 57600).unwrap();
+        let mut transport = CobsTransport::new(Box::new(serial));
+
+        // Bytes a cooked tty would rewrite: newline, carriage return, ^C, ^D, DEL
+        let frames: [&[u8]; 3] = [&[0xB7, 0x0A, 0x00, 0x0D], &[0x03, 0x04, 0x7F, 0x11, 0x13], &[0xFF; 300]];
+        for frame in frames {
+            assert_eq!(transport.send(frame).unwrap(), frame.len());
+        }
+
+        let mut decoder = StreamDecoder::new();
+        let mut received = Vec::new();
+        let mut buf = [0u8; 512];
+        while received.len() < frames.len() {
+            let len = master.read(&mut buf).unwrap();
+            received.extend(decoder.push(&buf[..len]).into_iter().map(Result::unwrap));
+        }
+        assert_eq!(received, frames.map(<[u8]>::to_vec));
+    }
+
+    #[test]
+    fn missing_device_and_bad_baud_are_errors() {
+        let err = SerialTransport::open("/dev/does-not-exist", DEFAULT_BAUD_RATE).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(err.to_string().contains("/dev/does-not-exist"), "{}", err);
+
+        assert_eq!(parse_baud_rate("115200"), Ok(115200));
+        assert!(parse_baud_rate("115201").is_err());
+        assert!(parse_baud_rate("fast").is_err());
+    }
+}
@@ -0,0 +1,167 @@
+// Session header packet describing how to interpret the data packets that follow.
+// Sent at startup and whenever the sensor configuration changes.
+
+use std::mem;
+
+use crate::altitude::STANDARD_SEA_LEVEL_HPA;
+use crate::checksum::Checksum;
+use crate::fields::{FieldMask, MASKED_PACKET_SYNC};
+use crate::frame::{ByteReader, Endianness};
+use crate::packet::{TelemetryPacket, PACKET_SYNC};
+
+use crate::i2c::I2cBus;
+use crate::i2c::MPU6050::{MPU6050, STANDARD_GRAVITY};
+
+// Sync word identifying a session header (distinct from PACKET_SYNC)
+pub const SESSION_HEADER_SYNC: u64 = 0xFF_FF_FF_FF_FF_FF_FF_FE;
+
+// Fraction of the full-scale range above which a reading is taken as clipped. The
+// MPU6050's largest count is 32767 of 32768, so a clipped axis reads just under range.
+const SATURATION_FRACTION: f32 = 0.99;
+
+#[repr(C, packed)]  // C layout, no padding
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SessionHeader {
+    pub sync: u64,
+    pub data_sync: u64,      // Sync word used by the data packets of this session
+    pub accel_range_g: u8,   // Accelerometer full-scale range (±g)
+    pub gyro_range_dps: u16, // Gyroscope full-scale range (±°/s)
+    pub sample_rate_hz: u16, // Sensor output data rate
+    pub field_mask: u32,     // FieldMask bits of the fields present in each data frame
+    pub sea_level_hpa: f32,  // Sea-level reference used for barometric altitude
+    pub checksum: u8,        // Checksum::id() of the frame trailers
+}
+
+impl SessionHeader {
+    pub fn new(accel_range_g: u8, gyro_range_dps: u16, sample_rate_hz: u16) -> Self {
+        Self {
+            sync: SESSION_HEADER_SYNC,
+            data_sync: PACKET_SYNC,
+            accel_range_g,
+            gyro_range_dps,
+            sample_rate_hz,
+            field_mask: FieldMask::ALL.bits(),
+            sea_level_hpa: STANDARD_SEA_LEVEL_HPA,
+            checksum: Checksum::default().id(),
+        }
+    }
+
+    pub fn with_sea_level(mut self, hpa: f32) -> Self {
+        self.sea_level_hpa = hpa;
+        self
+    }
+
+    pub fn with_checksum(mut self, checksum: Checksum) -> Self {
+        self.checksum = checksum.id();
+        self
+    }
+
+    // Announces trimmed data frames carrying only the fields in `mask`
+    pub fn with_field_mask(mut self, mask: FieldMask) -> Self {
+        self.data_sync = if mask == FieldMask::ALL { PACKET_SYNC } else { MASKED_PACKET_SYNC };
+        self.field_mask = mask.bits();
+        self
+    }
+
+    pub fn fields(&self) -> Option<FieldMask> {
+        FieldMask::from_bits(self.field_mask)
+    }
+
+    pub fn from_sensor<B: I2cBus>(sensor: &MPU6050<B>) -> Self {
+        Self::new(
+            sensor.accel_sensitivity().range_g(),
+            sensor.gyro_sensitivity().range_dps(),
+            sensor.sample_rate_hz(),
+        )
+    }
+
+    // Ranges covered by the simulated generator, one sample per packet at 10Hz
+    pub fn simulated() -> Self {
+        Self::new(4, 2000, 10)
+    }
+
+    // Fields in declaration order, no padding
+    pub fn to_bytes(&self, order: Endianness) -> Vec<u8> {
+        let mut out = Vec::with_capacity(mem::size_of::<Self>());
+        order.put_u64(&mut out, self.sync);
+        order.put_u64(&mut out, self.data_sync);
+        out.push(self.accel_range_g);
+        order.put_u16(&mut out, self.gyro_range_dps);
+        order.put_u16(&mut out, self.sample_rate_hz);
+        order.put_u32(&mut out, self.field_mask);
+        order.put_f32(&mut out, self.sea_level_hpa);
+        out.push(self.checksum);
+        out
+    }
+
+    // Inverse of to_bytes(); None unless the buffer is exactly one session header
+    pub fn from_bytes_in(buf: &[u8], order: Endianness) -> Option<Self> {
+        if buf.len() != mem::size_of::<Self>() {
+            return None;
+        }
+
+        let mut r = ByteReader::new(buf, order);
+        let header = Self {
+            sync: r.u64()?,
+            data_sync: r.u64()?,
+            accel_range_g: r.u8()?,
+            gyro_range_dps: r.u16()?,
+            sample_rate_hz: r.u16()?,
+            field_mask: r.u32()?,
+            sea_level_hpa: r.f32()?,
+            checksum: r.u8()?,
+        };
+        (header.sync == SESSION_HEADER_SYNC).then_some(header)
+    }
+
+    // Little-endian from_bytes_in()
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        Self::from_bytes_in(buf, Endianness::Little)
+    }
+}
+
+// Receiver-side cache of a sender's most recent session header: the field mask its
+// trimmed frames need, and the IMU ranges its readings were taken at
+#[derive(Debug, Default)]
+pub struct SessionCache {
+    header: Option<SessionHeader>,
+}
+
+impl SessionCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn store(&mut self, header: SessionHeader) {
+        self.header = Some(header);
+    }
+
+    pub fn current(&self) -> Option<&SessionHeader> {
+        self.header.as_ref()
+    }
+
+    // None before any header, or if the header's mask has unknown bits
+    pub fn fields(&self) -> Option<FieldMask> {
+        self.header.and_then(|header| header.fields())
+    }
+
+    pub fn accel_range_g(&self) -> Option<u8> {
+        self.header.map(|header| header.accel_range_g)
+    }
+
+    pub fn gyro_range_dps(&self) -> Option<u16> {
+        self.header.map(|header| header.gyro_range_dps)
+    }
+
+    // Whether an accelerometer or gyroscope axis sat at the session's full-scale range,
+    // so its true value may have been larger; false before any header
+    pub fn saturated(&self, packet: &TelemetryPacket) -> bool {
+        let (Some(accel_g), Some(gyro_dps)) = (self.accel_range_g(), self.gyro_range_dps()) else {
+            return false;
+        };
+        let accel_limit = accel_g as f32 * STANDARD_GRAVITY * SATURATION_FRACTION;
+        let gyro_limit = gyro_dps as f32 * SATURATION_FRACTION;
+        [packet.accel_x, packet.accel_y, packet.accel_z].iter().any(|axis| axis.abs() >= accel_limit)
+            || [packet.gyro_x, packet.gyro_y, packet.gyro_z].iter().any(|axis| axis.abs() >= gyro_limit)
+    }
+}
@@ -0,0 +1,164 @@
+// Clean exit from the main loop. Ctrl-C, or SIGTERM from systemd stopping the service,
+// sets a flag that the loop checks between iterations, so the packet being built still
+// goes out and the flight summary is written. A second signal exits at once, for a
+// loop stuck in a bus read. One arriving within DUPLICATE_WINDOW of the first is taken
+// as the same request: `timeout`, and kills aimed at the whole process group, deliver
+// SIGTERM to the process twice.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tracing::{debug, info, warn};
+
+// Exit status after a forced second signal, as a shell reports death by SIGINT
+const FORCED_EXIT_STATUS: i32 = 130;
+
+// Too soon after the first signal for anyone to have decided the loop is stuck
+const DUPLICATE_WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Default)]
+struct Inner {
+    requested: AtomicBool,
+    notify: Notify,
+}
+
+// Shared between the signal listener and the loop; clones refer to the same flag
+#[derive(Debug, Clone, Default)]
+pub struct Shutdown {
+    inner: Arc<Inner>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn request(&self) {
+        self.inner.requested.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.inner.requested.load(Ordering::SeqCst)
+    }
+
+    // Completes once shutdown has been requested, immediately if it already has
+    pub async fn requested(&self) {
+        loop {
+            // Registered before the check, so a request in between still wakes it
+            let notified = self.inner.notify.notified();
+            if self.is_requested() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    // Requests shutdown on the first Ctrl-C or SIGTERM, and exits the process on the second.
+    // Listening starts before this returns, so a signal straight afterwards isn't missed.
+    pub fn listen_for_signals(&self) {
+        let mut signals = match Signals::new() {
+            Ok(signals) => signals,
+            Err(e) => {
+                warn!("Failed to listen for shutdown signals: {}", e);
+                return;
+            }
+        };
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            let signal = signals.next().await;
+            info!("{} received, stopping after this iteration", signal);
+            shutdown.request();
+            let first = Instant::now();
+            loop {
+                let signal = signals.next().await;
+                if first.elapsed() < DUPLICATE_WINDOW {
+                    debug!("{} received again straight away, taken as the same request", signal);
+                    continue;
+                }
+                warn!("{} received again, exiting without a summary", signal);
+                std::process::exit(FORCED_EXIT_STATUS);
+            }
+        });
+    }
+}
+
+// Registered once for both waits: a listener created after the first signal can be
+// woken by that same signal, which would make one SIGTERM look like two
+#[cfg(unix)]
+struct Signals {
+    interrupt: tokio::signal::unix::Signal,
+    terminate: tokio::signal::unix::Signal,
+}
+
+#[cfg(unix)]
+impl Signals {
+    fn new() -> std::io::Result<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        Ok(Self { interrupt: signal(SignalKind::interrupt())?, terminate: signal(SignalKind::terminate())? })
+    }
+
+    async fn next(&mut self) -> &'static str {
+        tokio::select! {
+            _ = self.interrupt.recv() => "Ctrl-C",
+            _ = self.terminate.recv() => "SIGTERM",
+        }
+    }
+}
+
+#[cfg(not(unix))]
+struct Signals(tokio::signal::windows::CtrlC);
+
+#[cfg(not(unix))]
+impl Signals {
+    fn new() -> std::io::Result<Self> {
+        tokio::signal::windows::ctrl_c().map(Self)
+    }
+
+    async fn next(&mut self) -> &'static str {
+        self.0.recv().await;
+        "Ctrl-C"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn request_wakes_every_clone() {
+        let shutdown = Shutdown::new();
+        assert!(!shutdown.is_requested());
+
+        let waiter = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.requested().await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        shutdown.clone().request();
+        tokio::time::timeout(Duration::from_secs(2), waiter).await.unwrap().unwrap();
+
+        // Already requested: no wait
+        assert!(shutdown.is_requested());
+        tokio::time::timeout(Duration::from_millis(10), shutdown.requested()).await.unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn sigterm_stops_the_loop_without_forcing_an_exit() {
+        let shutdown = Shutdown::new();
+        shutdown.listen_for_signals();
+        // Twice, as `timeout` delivers it
+        for _ in 0..2 {
+            // SAFETY: kill() on our own pid has no memory-safety preconditions
+            assert_eq!(unsafe { libc::kill(libc::getpid(), libc::SIGTERM) }, 0);
+        }
+        tokio::time::timeout(Duration::from_secs(2), shutdown.requested()).await.unwrap();
+
+        // A forced exit would take the test process with it, summary and all
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(shutdown.is_requested());
+    }
+}
@@ -0,0 +1,80 @@
+// Payload spin rate about the vertical, in rotations per minute. The accelerometer's
+// reaction to gravity gives the vertical; the gyro rate is projected onto it.
+
+use crate::i2c::MPU6050::{MotionReading, STANDARD_GRAVITY};
+
+// |accel| must be within this fraction of 1g for the gravity direction to be trusted.
+// Outside it the payload is tumbling, swinging hard or falling.
+const VERTICAL_TOLERANCE: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpinRate {
+    pub rpm: f32,
+    // False when the vertical couldn't be determined and `rpm` is the total angular
+    // speed (always positive) rather than the signed rate about the vertical
+    pub about_vertical: bool,
+}
+
+// Signed rate about the vertical (positive counter-clockwise seen from above), or the
+// total angular speed when the vertical is ambiguous. Expects accel in m/s².
+pub fn spin_rate(motion: &MotionReading) -> SpinRate {
+    let a = &motion.accelerometer;
+    let g = &motion.gyroscope;
+    let accel_magnitude = (a.x * a.x + a.y * a.y + a.z * a.z).sqrt();
+
+    if (accel_magnitude / STANDARD_GRAVITY - 1.0).abs() <= VERTICAL_TOLERANCE {
+        let about_up = (g.x * a.x + g.y * a.y + g.z * a.z) / accel_magnitude;
+        SpinRate { rpm: dps_to_rpm(about_up), about_vertical: true }
+    } else {
+        let total = (g.x * g.x + g.y * g.y + g.z * g.z).sqrt();
+        SpinRate { rpm: dps_to_rpm(total), about_vertical: false }
+    }
+}
+
+pub fn spin_rate_rpm(motion: &MotionReading) -> f32 {
+    spin_rate(motion).rpm
+}
+
+fn dps_to_rpm(degrees_per_second: f32) -> f32 {
+    degrees_per_second * 60.0 / 360.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i2c::MPU6050::{AccelerometerReading, GyroscopeReading};
+
+    fn motion(accel: [f32; 3], gyro: [f32; 3]) -> MotionReading {
+        MotionReading {
+            accelerometer: AccelerometerReading { x: accel[0], y: accel[1], z: accel[2] },
+            gyroscope: GyroscopeReading { x: gyro[0], y: gyro[1], z: gyro[2] },
+            temperature: 20.0,
+        }
+    }
+
+    #[test]
+    fn pure_z_rotation_upright_and_upside_down() {
+        // 36°/s about Z with Z up: 6 RPM counter-clockwise
+        let upright = spin_rate(&motion([0.0, 0.0, STANDARD_GRAVITY], [0.0, 0.0, 36.0]));
+        assert_eq!(upright, SpinRate { rpm: 6.0, about_vertical: true });
+
+        // Same sensor rotation with the board flipped turns the other way relative to up
+        let flipped = spin_rate(&motion([0.0, 0.0, -STANDARD_GRAVITY], [0.0, 0.0, 36.0]));
+        assert_eq!(flipped, SpinRate { rpm: -6.0, about_vertical: true });
+    }
+
+    #[test]
+    fn tilted_payload_projects_onto_gravity() {
+        // Hanging 60° off vertical in the X-Z plane, spinning about the real vertical
+        let (sin, cos) = 60f32.to_radians().sin_cos();
+        let reading = motion([STANDARD_GRAVITY * sin, 0.0, STANDARD_GRAVITY * cos], [120.0 * sin, 25.0, 120.0 * cos]);
+        assert!((spin_rate_rpm(&reading) - 20.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn tumbling_falls_back_to_total_speed() {
+        let rate = spin_rate(&motion([2.0, 0.0, 1.0], [0.0, -180.0, 240.0]));
+        assert!(!rate.about_vertical);
+        assert!((rate.rpm - 50.0).abs() < 1e-3);
+    }
+}
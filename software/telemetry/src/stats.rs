@@ -0,0 +1,182 @@
+// Running flight statistics, accumulated in O(1) per packet and written out as a
+// human-readable summary at shutdown
+
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::packet::TelemetryPacket;
+use crate::transport::TargetStats;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunningStat {
+    count: u64,
+    min: f32,
+    max: f32,
+    sum: f64,
+}
+
+impl RunningStat {
+    pub fn update(&mut self, value: f32) {
+        if !value.is_finite() {
+            return;
+        }
+
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.sum += value as f64;
+        self.count += 1;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn min(&self) -> Option<f32> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    pub fn max(&self) -> Option<f32> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    pub fn mean(&self) -> Option<f32> {
+        (self.count > 0).then(|| (self.sum / self.count as f64) as f32)
+    }
+
+    fn describe(&self, units: &str) -> String {
+        match (self.min(), self.max(), self.mean()) {
+            (Some(min), Some(max), Some(mean)) => {
+                format!("min {:.2}  max {:.2}  mean {:.2} {}", min, max, mean, units)
+            }
+            _ => "no data".to_string(),
+        }
+    }
+}
+
+pub struct FlightStats {
+    start: Instant,
+    pub temperature: RunningStat,
+    pub altitude: RunningStat,
+    pub climb_rate: RunningStat,
+    pub temperature_rate: RunningStat,
+    pub loop_jitter: RunningStat, // |actual - target| loop interval, ms
+    pub peak_accel: f32,
+    pub apogee: Option<f32>, // Peak altitude, once burst has been detected (m)
+    pub packets_sent: u64,
+    pub send_errors: u64,
+    pub sensor_errors: u64,
+    pub sensor_timeouts: u64,
+    pub targets: Vec<TargetStats>, // Per ground station, when sending to more than one
+}
+
+impl FlightStats {
+    pub fn new(start: Instant) -> Self {
+        Self {
+            start,
+            temperature: RunningStat::default(),
+            altitude: RunningStat::default(),
+            climb_rate: RunningStat::default(),
+            temperature_rate: RunningStat::default(),
+            loop_jitter: RunningStat::default(),
+            peak_accel: 0.0,
+            apogee: None,
+            packets_sent: 0,
+            send_errors: 0,
+            sensor_errors: 0,
+            sensor_timeouts: 0,
+            targets: Vec::new(),
+        }
+    }
+
+    // `climb_rate` is None for a packet without a measured altitude
+    pub fn record_packet(&mut self, packet: &TelemetryPacket, climb_rate: Option<f32>) {
+        self.temperature.update(packet.temperature);
+        self.altitude.update(packet.altitude);
+        if let Some(climb_rate) = climb_rate {
+            self.climb_rate.update(climb_rate);
+        }
+        self.peak_accel = self.peak_accel.max(packet.accel_magnitude()).max(packet.peak_accel);
+    }
+
+    pub fn uptime(&self, now: Instant) -> Duration {
+        now.duration_since(self.start)
+    }
+
+    pub fn summary(&self, now: Instant) -> String {
+        let duration = now.duration_since(self.start).as_secs_f32();
+
+        let mut summary = String::from("Flight summary\n==============\n");
+        summary.push_str(&format!("Duration:      {:.1} s\n", duration));
+        summary.push_str(&format!("Packets sent:  {}\n", self.packets_sent));
+        summary.push_str(&format!("Errors:        {} (send {}, sensor {})\n",
+                                  self.send_errors + self.sensor_errors, self.send_errors, self.sensor_errors));
+        summary.push_str(&format!("I2C timeouts:  {}\n", self.sensor_timeouts));
+        for target in &self.targets {
+            summary.push_str(&format!("Target {}: sent {}, failed {}\n", target.target, target.sent, target.failed));
+        }
+        summary.push_str(&format!("Temperature:   {}\n", self.temperature.describe("°C")));
+        summary.push_str(&format!("Altitude:      {}\n", self.altitude.describe("m")));
+        match self.apogee {
+            Some(apogee) => summary.push_str(&format!("Apogee:        {:.1} m (burst detected)\n", apogee)),
+            None => summary.push_str("Apogee:        no burst detected\n"),
+        }
+        summary.push_str(&format!("Climb rate:    {}\n", self.climb_rate.describe("m/s")));
+        summary.push_str(&format!("Temp rate:     {}\n", self.temperature_rate.describe("°C/s")));
+        summary.push_str(&format!("Loop jitter:   {}\n", self.loop_jitter.describe("ms")));
+        summary.push_str(&format!("Peak accel:    {:.2} m/s²\n", self.peak_accel));
+        summary
+    }
+
+    pub fn write_summary(&self, path: &Path, now: Instant) -> std::io::Result<()> {
+        fs::write(path, self.summary(now))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn running_stat_tracks_min_max_mean() {
+        let mut stat = RunningStat::default();
+        assert_eq!(stat.mean(), None);
+
+        for value in [3.0, -1.0, 4.0, f32::NAN] {
+            stat.update(value);
+        }
+        assert_eq!(stat.min(), Some(-1.0));
+        assert_eq!(stat.max(), Some(4.0));
+        assert_eq!(stat.mean(), Some(2.0));
+    }
+
+    #[test]
+    fn summary_reports_counts_and_duration() {
+        let start = Instant::now();
+        let mut stats = FlightStats::new(start);
+        stats.packets_sent = 42;
+        stats.send_errors = 2;
+        stats.sensor_errors = 1;
+        stats.altitude.update(100.0);
+
+        stats.targets = vec![TargetStats { target: "gs1:3000".to_string(), sent: 40, failed: 2, failing: false }];
+
+        let summary = stats.summary(start + Duration::from_secs(90));
+        assert!(summary.contains("Duration:      90.0 s"));
+        assert!(summary.contains("Target gs1:3000: sent 40, failed 2"));
+        assert!(summary.contains("Packets sent:  42"));
+        assert!(summary.contains("Errors:        3 (send 2, sensor 1)"));
+        assert!(summary.contains("Altitude:      min 100.00  max 100.00  mean 100.00 m"));
+        assert!(summary.contains("Temperature:   no data"));
+        assert!(summary.contains("Apogee:        no burst detected"));
+
+        stats.apogee = Some(30_115.0);
+        assert!(stats.summary(start).contains("Apogee:        30115.0 m (burst detected)"));
+    }
+}
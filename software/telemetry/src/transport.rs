@@ -0,0 +1,559 @@
+// Frame transports for the downlink. UDP is the default; TCP trades latency for
+// reliability on tethered/line-of-sight ground tests, and a file keeps a recording for
+// replay. The serial transport for radio modems is in serial.rs.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::net::{SocketAddr, SocketAddrV4, TcpStream, ToSocketAddrs, UdpSocket};
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+pub trait Transport {
+    // Sends one complete frame, returning the number of frame bytes sent
+    fn send(&mut self, frame: &[u8]) -> io::Result<usize>;
+}
+
+// How often a resolved UDP target is looked up again, in case the ground station's
+// address changes (DHCP); an unresolved target is retried sooner
+const UDP_RESOLVE_INTERVAL: Duration = Duration::from_secs(60);
+const UDP_RETRY_RESOLVE_INTERVAL: Duration = Duration::from_secs(5);
+
+// UDP sender to a host:port target, which may be a hostname. The address is resolved
+// up front and cached, so a ground station that isn't up yet (or a failed lookup)
+// doesn't prevent startup.
+pub struct UdpTransport {
+    socket: UdpSocket,
+    target: String,
+    resolved: Option<SocketAddr>,
+    next_resolve: Instant,
+}
+
+impl UdpTransport {
+    pub fn new(target: &str) -> io::Result<Self> {
+        Self::bound(target, "0.0.0.0:0")
+    }
+
+    // Sends from a chosen local address, e.g. to pick the interface facing the ground
+    // station on a multi-homed flight computer
+    pub fn bound(target: &str, bind: &str) -> io::Result<Self> {
+        let mut transport = Self {
+            socket: UdpSocket::bind(bind)?,
+            target: target.to_string(),
+            resolved: None,
+            next_resolve: Instant::now(),
+        };
+        transport.refresh(Instant::now());
+        Ok(transport)
+    }
+
+    // Sends to an IPv4 multicast group (224.0.0.0/4) so every receiver on the LAN that
+    // joins it gets the feed without being configured as a target. A TTL of 1 keeps
+    // datagrams on the local subnet; each router hop needs one more. Receivers bind the
+    // group's port and join the group, e.g.
+    //   UdpSocket::bind("0.0.0.0:3000")?.join_multicast_v4(&group, &Ipv4Addr::UNSPECIFIED)
+    //   socat -u UDP4-RECV:3000,ip-add-membership=239.1.2.3:0.0.0.0 -
+    pub fn multicast(group: &str, ttl: u32) -> io::Result<Self> {
+        let addr: SocketAddrV4 = group.parse().map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("Multicast target {} must be an IPv4 address:port", group))
+        })?;
+        if !addr.ip().is_multicast() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      format!("{} is not an IPv4 multicast group (224.0.0.0/4)", addr.ip())));
+        }
+
+        let transport = Self::new(group)?;
+        transport.socket.set_multicast_ttl_v4(ttl)?;
+        Ok(transport)
+    }
+
+    pub fn resolved_addr(&self) -> Option<SocketAddr> {
+        self.resolved
+    }
+
+    // A datagram the target has sent back to this socket, without blocking. Anything
+    // from another address is discarded.
+    pub fn recv_from_target(&mut self, buf: &mut [u8]) -> io::Result<Option<usize>> {
+        // Sends stay blocking; only this read is switched over
+        self.socket.set_nonblocking(true)?;
+        let received = loop {
+            match self.socket.recv_from(buf) {
+                Ok((len, from)) if Some(from) == self.resolved => break Ok(Some(len)),
+                Ok(_) => continue,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break Ok(None),
+                Err(e) => break Err(e),
+            }
+        };
+        self.socket.set_nonblocking(false)?;
+        received
+    }
+
+    // Re-resolves the target, keeping the cached address if the lookup fails
+    fn refresh(&mut self, now: Instant) {
+        match resolve_ipv4(&self.target) {
+            Ok(addr) => {
+                if self.resolved != Some(addr) {
+                    info!("UDP target {} resolved to {}", self.target, addr);
+                    if addr.ip().is_unspecified() || addr.port() == 0 {
+                        warn!("UDP target {} doesn't look like a reachable ground station", addr);
+                    }
+                }
+                self.resolved = Some(addr);
+                self.next_resolve = now + UDP_RESOLVE_INTERVAL;
+            }
+            Err(e) => {
+                match self.resolved {
+                    Some(addr) => warn!("Failed to re-resolve UDP target {} ({}) - still sending to {}", self.target, e, addr),
+                    None => warn!("Failed to resolve UDP target {} ({}) - will keep trying", self.target, e),
+                }
+                self.next_resolve = now + UDP_RETRY_RESOLVE_INTERVAL;
+            }
+        }
+    }
+}
+
+impl Transport for UdpTransport {
+    fn send(&mut self, frame: &[u8]) -> io::Result<usize> {
+        let now = Instant::now();
+        if now >= self.next_resolve {
+            self.refresh(now);
+        }
+
+        match self.resolved {
+            Some(addr) => self.socket.send_to(frame, addr),
+            None => Err(io::Error::new(io::ErrorKind::NotConnected, format!("UDP target {} is unresolved", self.target))),
+        }
+    }
+}
+
+const TCP_CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+const TCP_INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+const TCP_MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+// TCP client that reconnects on failure. The stream has no datagram boundaries, so every
+// frame is prefixed with its length as a little-endian u16.
+pub struct TcpTransport {
+    target: String,
+    stream: Option<TcpStream>,
+    backoff: Duration,
+    next_attempt: Instant,
+}
+
+impl TcpTransport {
+    // Connection is deferred to the first send so a missing ground station doesn't
+    // prevent startup
+    pub fn new(target: &str) -> Self {
+        Self {
+            target: target.to_string(),
+            stream: None,
+            backoff: TCP_INITIAL_BACKOFF,
+            next_attempt: Instant::now(),
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    fn connect(&mut self) -> io::Result<&mut TcpStream> {
+        if let Some(ref mut stream) = self.stream {
+            return Ok(stream);
+        }
+
+        let now = Instant::now();
+        if now < self.next_attempt {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, "Waiting to reconnect"));
+        }
+
+        match resolve(&self.target).and_then(|addr| TcpStream::connect_timeout(&addr, TCP_CONNECT_TIMEOUT)) {
+            Ok(stream) => {
+                stream.set_nodelay(true)?;
+                info!("TCP transport connected to {}", self.target);
+                self.backoff = TCP_INITIAL_BACKOFF;
+                Ok(self.stream.insert(stream))
+            }
+            Err(e) => {
+                self.schedule_reconnect(now);
+                Err(e)
+            }
+        }
+    }
+
+    fn schedule_reconnect(&mut self, now: Instant) {
+        self.next_attempt = now + self.backoff;
+        self.backoff = (self.backoff * 2).min(TCP_MAX_BACKOFF);
+    }
+}
+
+impl Transport for TcpTransport {
+    fn send(&mut self, frame: &[u8]) -> io::Result<usize> {
+        let buf = length_prefixed(frame)?;
+        let result = self.connect()?.write_all(&buf);
+        if let Err(e) = result {
+            warn!("TCP transport lost connection to {}: {}", self.target, e);
+            self.stream = None;
+            self.schedule_reconnect(Instant::now());
+            return Err(e);
+        }
+
+        Ok(frame.len())
+    }
+}
+
+// Sends every frame on the primary transport and mirrors it to a secondary sink whose
+// failures are logged but never affect the primary result
+pub struct Tee {
+    primary: Box<dyn Transport>,
+    secondary: Box<dyn Transport>,
+}
+
+impl Tee {
+    pub fn new(primary: Box<dyn Transport>, secondary: Box<dyn Transport>) -> Self {
+        Self { primary, secondary }
+    }
+}
+
+impl Transport for Tee {
+    fn send(&mut self, frame: &[u8]) -> io::Result<usize> {
+        if let Err(e) = self.secondary.send(frame) {
+            warn!("Failed to mirror frame: {}", e);
+        }
+        self.primary.send(frame)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetStats {
+    pub target: String,
+    pub sent: u64,
+    pub failed: u64,
+    pub failing: bool, // The most recent send to this target failed
+}
+
+// Per-target counters, readable after the Fanout has been boxed into the transport chain
+#[derive(Debug, Clone)]
+pub struct FanoutStats(Arc<Mutex<Vec<TargetStats>>>);
+
+impl FanoutStats {
+    pub fn snapshot(&self) -> Vec<TargetStats> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
+// Sends every frame to each of several ground stations in turn. Each target succeeds
+// or fails on its own; the send as a whole fails only if no target took the frame.
+pub struct Fanout {
+    targets: Vec<Box<dyn Transport>>,
+    stats: FanoutStats,
+}
+
+impl Fanout {
+    pub fn new(targets: Vec<(String, Box<dyn Transport>)>) -> Self {
+        let (names, targets): (Vec<String>, Vec<Box<dyn Transport>>) = targets.into_iter().unzip();
+        let stats = names.into_iter().map(|target| TargetStats { target, sent: 0, failed: 0, failing: false }).collect();
+        Self { targets, stats: FanoutStats(Arc::new(Mutex::new(stats))) }
+    }
+
+    pub fn stats(&self) -> FanoutStats {
+        self.stats.clone()
+    }
+}
+
+impl Transport for Fanout {
+    fn send(&mut self, frame: &[u8]) -> io::Result<usize> {
+        let mut stats = self.stats.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut sent = None;
+        let mut last_error = None;
+
+        for (transport, target) in self.targets.iter_mut().zip(stats.iter_mut()) {
+            match transport.send(frame) {
+                Ok(bytes) => {
+                    if target.failing {
+                        info!("Ground station {} is reachable again", target.target);
+                    }
+                    target.sent += 1;
+                    target.failing = false;
+                    sent = Some(bytes);
+                }
+                Err(e) => {
+                    // Warn once per outage rather than on every frame
+                    if !target.failing {
+                        warn!("Failed to send to ground station {}: {}", target.target, e);
+                    }
+                    target.failed += 1;
+                    target.failing = true;
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        match (sent, last_error) {
+            (Some(bytes), _) => Ok(bytes),
+            (None, Some(e)) => Err(io::Error::new(e.kind(), format!("all {} targets failed, last: {}", self.targets.len(), e))),
+            (None, None) => Err(io::Error::new(io::ErrorKind::NotConnected, "no targets")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmitFormat {
+    Hex, // One line of lowercase hex per frame
+    Raw, // Frame bytes back to back; the envelope's length field delimits them
+}
+
+// Writes frames to a byte stream (normally stdout) for piping into other tools,
+// flushing after each so a reader sees every frame as soon as it is sent
+pub struct WriterTransport<W: Write> {
+    writer: W,
+    format: EmitFormat,
+}
+
+impl<W: Write> WriterTransport<W> {
+    pub fn new(writer: W, format: EmitFormat) -> Self {
+        Self { writer, format }
+    }
+}
+
+impl WriterTransport<io::Stdout> {
+    pub fn stdout(format: EmitFormat) -> Self {
+        Self::new(io::stdout(), format)
+    }
+}
+
+impl WriterTransport<File> {
+    // A recording for replay: raw frames back to back, as a receiver reading the file
+    // would see them arrive
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self::new(File::create(path)?, EmitFormat::Raw))
+    }
+}
+
+impl<W: Write> Transport for WriterTransport<W> {
+    fn send(&mut self, frame: &[u8]) -> io::Result<usize> {
+        match self.format {
+            EmitFormat::Hex => {
+                let hex: String = frame.iter().map(|byte| format!("{:02x}", byte)).collect();
+                writeln!(self.writer, "{}", hex)?;
+            }
+            EmitFormat::Raw => self.writer.write_all(frame)?,
+        }
+        self.writer.flush()?;
+        Ok(frame.len())
+    }
+}
+
+// Frame with a little-endian u16 length prefix, for stream transports
+pub(crate) fn length_prefixed(frame: &[u8]) -> io::Result<Vec<u8>> {
+    let len = u16::try_from(frame.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Frame too large for length prefix"))?;
+
+    let mut buf = Vec::with_capacity(2 + frame.len());
+    buf.extend_from_slice(&len.to_le_bytes());
+    buf.extend_from_slice(frame);
+    Ok(buf)
+}
+
+fn resolve(target: &str) -> io::Result<SocketAddr> {
+    target
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("No address for {}", target)))
+}
+
+// The UDP socket is bound to an IPv4 address, so skip any IPv6 results
+fn resolve_ipv4(target: &str) -> io::Result<SocketAddr> {
+    target
+        .to_socket_addrs()?
+        .find(SocketAddr::is_ipv4)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("No IPv4 address for {}", target)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::TelemetryPacket;
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    #[test]
+    fn udp_resolves_hostname_once_and_sends() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = receiver.local_addr().unwrap().port();
+        let mut transport = UdpTransport::new(&format!("localhost:{}", port)).unwrap();
+
+        assert_eq!(transport.resolved_addr(), Some(receiver.local_addr().unwrap()));
+        assert_eq!(transport.send(&[7, 8]).unwrap(), 2);
+
+        let mut buf = [0u8; 8];
+        assert_eq!(receiver.recv(&mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], &[7, 8]);
+    }
+
+    #[test]
+    fn multicast_requires_a_group_address() {
+        let transport = UdpTransport::multicast("239.1.2.3:3000", 2).unwrap();
+        assert_eq!(transport.resolved_addr(), Some("239.1.2.3:3000".parse().unwrap()));
+        assert_eq!(transport.socket.multicast_ttl_v4().unwrap(), 2);
+
+        assert!(UdpTransport::multicast("192.168.1.10:3000", 1).is_err());
+        assert!(UdpTransport::multicast("localhost:3000", 1).is_err());
+    }
+
+    #[test]
+    fn udp_unresolvable_target_fails_sends_without_panicking() {
+        let mut transport = UdpTransport::new("no-port-given").unwrap();
+        assert_eq!(transport.resolved_addr(), None);
+        assert_eq!(transport.send(&[0]).unwrap_err().kind(), io::ErrorKind::NotConnected);
+    }
+
+    #[test]
+    fn writer_emits_hex_lines_or_raw_bytes() {
+        let mut hex = WriterTransport::new(Vec::new(), EmitFormat::Hex);
+        assert_eq!(hex.send(&[0xB7, 0x02, 0x0A]).unwrap(), 3);
+        hex.send(&[0xFF]).unwrap();
+        assert_eq!(String::from_utf8(hex.writer).unwrap(), "b7020a\nff\n");
+
+        let mut raw = WriterTransport::new(Vec::new(), EmitFormat::Raw);
+        raw.send(&[1, 2]).unwrap();
+        raw.send(&[3]).unwrap();
+        assert_eq!(raw.writer, vec![1, 2, 3]);
+    }
+
+    // Keeps every frame it is handed, for checking what a transport chain sends
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<Vec<u8>>>>);
+
+    impl Transport for Capture {
+        fn send(&mut self, frame: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().push(frame.to_vec());
+            Ok(frame.len())
+        }
+    }
+
+    #[test]
+    fn transports_are_interchangeable_behind_the_trait() {
+        let capture = Capture::default();
+        let dir = std::env::temp_dir().join(format!("transport-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let recording_path = dir.join("flight.bin");
+
+        let mut transports: Vec<Box<dyn Transport>> = vec![
+            Box::new(capture.clone()),
+            Box::new(WriterTransport::create(&recording_path).unwrap()),
+            Box::new(WriterTransport::new(Vec::new(), EmitFormat::Hex)),
+        ];
+        for transport in &mut transports {
+            for frame in [&[0xB7, 1, 2][..], &[3]] {
+                assert_eq!(transport.send(frame).unwrap(), frame.len());
+            }
+        }
+        drop(transports);
+
+        assert_eq!(*capture.0.lock().unwrap(), vec![vec![0xB7, 1, 2], vec![3]]);
+        assert_eq!(std::fs::read(&recording_path).unwrap(), [0xB7, 1, 2, 3]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    struct Flaky {
+        up: bool,
+    }
+
+    impl Transport for Flaky {
+        fn send(&mut self, frame: &[u8]) -> io::Result<usize> {
+            self.up = !self.up;
+            if self.up { Ok(frame.len()) } else { Err(io::ErrorKind::ConnectionRefused.into()) }
+        }
+    }
+
+    #[test]
+    fn fanout_counts_each_target_independently() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let good = UdpTransport::new(&receiver.local_addr().unwrap().to_string()).unwrap();
+        let mut fanout = Fanout::new(vec![
+            ("good".to_string(), Box::new(good) as Box<dyn Transport>),
+            ("flaky".to_string(), Box::new(Flaky { up: false })),
+        ]);
+        let stats = fanout.stats();
+
+        for _ in 0..4 {
+            assert_eq!(fanout.send(&[1, 2, 3]).unwrap(), 3);
+        }
+        let snapshot = stats.snapshot();
+        assert_eq!((snapshot[0].sent, snapshot[0].failed), (4, 0));
+        assert_eq!((snapshot[1].sent, snapshot[1].failed), (2, 2));
+        assert!(snapshot[1].failing);
+
+        let mut buf = [0u8; 8];
+        assert_eq!(receiver.recv(&mut buf).unwrap(), 3);
+    }
+
+    #[test]
+    fn fanout_sends_each_packet_to_every_target() {
+        let stations: Vec<Capture> = (0..3).map(|_| Capture::default()).collect();
+        let targets = ["base", "chase", "relay"].iter().zip(&stations)
+            .map(|(name, capture)| (name.to_string(), Box::new(capture.clone()) as Box<dyn Transport>))
+            .collect();
+        let mut fanout = Fanout::new(targets);
+
+        let mut packet = TelemetryPacket::new(1);
+        packet.finalize();
+        fanout.send(&packet.to_le_bytes()).unwrap();
+        for capture in &stations {
+            assert_eq!(*capture.0.lock().unwrap(), vec![packet.to_le_bytes().to_vec()]);
+        }
+        assert!(fanout.stats().snapshot().iter().all(|target| (target.sent, target.failed) == (1, 0)));
+
+        // A dead station in the middle doesn't hold up the one after it
+        let last = Capture::default();
+        let mut fanout = Fanout::new(vec![
+            ("base".to_string(), Box::new(Capture::default()) as Box<dyn Transport>),
+            ("chase".to_string(), Box::new(Flaky { up: true })),
+            ("relay".to_string(), Box::new(last.clone())),
+        ]);
+        fanout.send(&[1]).unwrap();
+        assert_eq!(last.0.lock().unwrap().len(), 1);
+        assert_eq!(fanout.stats().snapshot()[1].failed, 1);
+    }
+
+    #[test]
+    fn fanout_fails_only_when_every_target_does() {
+        let mut fanout = Fanout::new(vec![
+            ("a".to_string(), Box::new(Flaky { up: true }) as Box<dyn Transport>),
+            ("b".to_string(), Box::new(Flaky { up: true })),
+        ]);
+        let err = fanout.send(&[0]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+        assert!(err.to_string().contains("all 2 targets failed"));
+        assert!(fanout.send(&[0]).is_ok());
+    }
+
+    #[test]
+    fn tcp_frames_are_length_prefixed() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut transport = TcpTransport::new(&listener.local_addr().unwrap().to_string());
+
+        assert_eq!(transport.send(&[1, 2, 3]).unwrap(), 3);
+        assert_eq!(transport.send(&[4]).unwrap(), 1);
+
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut received = [0u8; 8];
+        stream.read_exact(&mut received).unwrap();
+        assert_eq!(received, [3, 0, 1, 2, 3, 1, 0, 4]);
+    }
+
+    #[test]
+    fn tcp_backs_off_after_failed_connect() {
+        // Bind then drop to get a port nothing is listening on
+        let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let mut transport = TcpTransport::new(&addr.to_string());
+
+        assert!(transport.send(&[0]).is_err());
+        assert!(!transport.is_connected());
+
+        // The immediate retry is refused without attempting a connection
+        let err = transport.send(&[0]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+        assert_eq!(transport.backoff, TCP_INITIAL_BACKOFF * 2);
+    }
+}
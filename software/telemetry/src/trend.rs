@@ -0,0 +1,109 @@
+// Derived rate-of-change signals computed from successive packets
+
+use std::collections::VecDeque;
+
+use crate::packet::TelemetryPacket;
+
+// Rate of temperature change over a sliding window, using each packet's timestamp rather
+// than an assumed send interval. The gradient reverses sign at the tropopause.
+pub struct TemperatureRate {
+    window_ms: u64,
+    samples: VecDeque<(u64, f32)>, // (timestamp_ms, °C)
+}
+
+impl TemperatureRate {
+    pub fn new(window_secs: u64) -> Self {
+        Self {
+            window_ms: window_secs * 1000,
+            samples: VecDeque::new(),
+        }
+    }
+
+    // Returns d(temperature)/dt in °C/s, or None until the window spans a nonzero time
+    pub fn update(&mut self, packet: &TelemetryPacket) -> Option<f32> {
+        let (timestamp, temperature) = (packet.timestamp_ms, packet.temperature);
+        if !temperature.is_finite() {
+            return self.rate();
+        }
+
+        // A clock step backwards invalidates the window
+        if self.samples.back().is_some_and(|&(last, _)| timestamp < last) {
+            self.samples.clear();
+        }
+
+        self.samples.push_back((timestamp, temperature));
+        while self.samples.front().is_some_and(|&(t, _)| timestamp - t > self.window_ms) {
+            self.samples.pop_front();
+        }
+
+        self.rate()
+    }
+
+    // Least-squares slope over the window, which smooths sensor noise and tolerates
+    // several packets sharing one timestamp
+    pub fn rate(&self) -> Option<f32> {
+        let &(t0, _) = self.samples.front()?;
+        let n = self.samples.len() as f64;
+        let mean_t = self.samples.iter().map(|&(t, _)| (t - t0) as f64).sum::<f64>() / n;
+        let mean_y = self.samples.iter().map(|&(_, y)| y as f64).sum::<f64>() / n;
+
+        let (mut cov, mut var) = (0.0, 0.0);
+        for &(t, y) in &self.samples {
+            let dt = (t - t0) as f64 - mean_t;
+            cov += dt * (y as f64 - mean_y);
+            var += dt * dt;
+        }
+
+        // Per millisecond to per second
+        (var > 0.0).then(|| (cov / var * 1000.0) as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(secs: u64, temperature: f32) -> TelemetryPacket {
+        TelemetryPacket { timestamp_ms: secs * 1000, temperature, ..TelemetryPacket::new(0) }
+    }
+
+    #[test]
+    fn first_sample_and_zero_elapsed_time_have_no_rate() {
+        let mut rate = TemperatureRate::new(10);
+        assert_eq!(rate.update(&packet(100, 15.0)), None);
+        assert_eq!(rate.update(&packet(100, 14.0)), None);
+    }
+
+    #[test]
+    fn uses_actual_elapsed_time() {
+        let mut rate = TemperatureRate::new(10);
+        rate.update(&packet(100, 15.0));
+        // Irregular spacing: 1 s, then 3 s, cooling at a steady 0.5 °C/s
+        rate.update(&packet(101, 14.5));
+        let slope = rate.update(&packet(104, 13.0)).unwrap();
+        assert!((slope + 0.5).abs() < 1e-6, "slope {}", slope);
+    }
+
+    #[test]
+    fn window_follows_gradient_reversal() {
+        let mut rate = TemperatureRate::new(4);
+        for t in 0..10 {
+            rate.update(&packet(t, -0.1 * t as f32));
+        }
+        assert!(rate.rate().unwrap() < 0.0);
+
+        // Above the tropopause the temperature starts rising; old samples age out
+        for t in 10..20 {
+            rate.update(&packet(t, -1.0 + 0.2 * (t - 10) as f32));
+        }
+        assert!((rate.rate().unwrap() - 0.2).abs() < 1e-5);
+    }
+
+    #[test]
+    fn clock_step_backwards_resets_window() {
+        let mut rate = TemperatureRate::new(10);
+        rate.update(&packet(100, 10.0));
+        rate.update(&packet(102, 12.0));
+        assert_eq!(rate.update(&packet(50, 0.0)), None);
+    }
+}